@@ -0,0 +1,311 @@
+use std::cmp::min;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::future::Future;
+use std::ops::Bound;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+
+use openraft::error::CheckIsLeaderError;
+use openraft::error::ClientWriteError;
+use openraft::error::ForwardToLeader;
+use openraft::error::NetworkError;
+use openraft::error::RPCError;
+use openraft::raft::ClientWriteResponse;
+use openraft::Node;
+use openraft::NodeId;
+use openraft::RaftMetrics;
+use openraft::RaftTypeConfig;
+use openraft::TryAsRef;
+
+use crate::ClientConfig;
+use crate::ClientError;
+use crate::ClientTransport;
+
+/// How a failed request is resent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Retry {
+    /// The request may have been applied if no reply is received, e.g., a write: it is resent only
+    /// if it is rejected by a node that is not the leader.
+    IfRejected,
+
+    /// The request can be applied more than once: it is resent after any network error.
+    Always,
+}
+
+/// The nodes a client knows about, and the one it believes to be the leader.
+struct Targets<NID, N> {
+    leader: Option<NID>,
+
+    nodes: BTreeMap<NID, N>,
+
+    /// The last node a request is sent to when the leader is unknown, to rotate from.
+    last_tried: Option<NID>,
+}
+
+impl<NID, N> Targets<NID, N>
+where
+    NID: NodeId,
+    N: Node,
+{
+    /// The node to send the next request to: the leader if it is known, otherwise the node after
+    /// the last tried one.
+    fn next(&mut self) -> (NID, N) {
+        if let Some(id) = self.leader {
+            if let Some(node) = self.nodes.get(&id) {
+                return (id, node.clone());
+            }
+        }
+
+        let after = self.last_tried.and_then(|last| self.nodes.range((Bound::Excluded(last), Bound::Unbounded)).next());
+        let (id, node) = after.or_else(|| self.nodes.iter().next()).expect("a client knows at least one node");
+
+        let (id, node) = (*id, node.clone());
+        self.last_tried = Some(id);
+        (id, node)
+    }
+
+    /// Follow a `ForwardToLeader` returned by node `from`.
+    ///
+    /// It returns `true` if the request should be resent to a new leader at once.
+    fn follow(&mut self, from: NID, fwd: &ForwardToLeader<NID, N>) -> bool {
+        if let (Some(id), Some(node)) = (fwd.leader_id, &fwd.leader_node) {
+            self.nodes.insert(id, node.clone());
+        }
+
+        match fwd.leader_id {
+            Some(id) if id != from && self.nodes.contains_key(&id) => {
+                self.leader = Some(id);
+                true
+            }
+            _ => {
+                self.forget(from);
+                false
+            }
+        }
+    }
+
+    /// `id` is not reachable or is not the leader: the next request goes to another node.
+    fn forget(&mut self, id: NID) {
+        if self.leader == Some(id) {
+            self.leader = None;
+        }
+        self.last_tried = Some(id);
+    }
+}
+
+/// A client that sends requests to the leader of a cluster through a [`ClientTransport`].
+///
+/// It starts with a few known nodes and discovers the leader from the `ForwardToLeader` errors
+/// returned by the others. When the leader is unknown, e.g., during an election or when the last
+/// known leader is unreachable, it tries the known nodes in turn, with exponential backoff.
+///
+/// Every request is sent with a timeout. Requests that can be applied more than once, such as
+/// [`linearizable_read()`](Self::linearizable_read), [`add_learner()`](Self::add_learner),
+/// [`change_membership()`](Self::change_membership) and [`metrics()`](Self::metrics), are resent
+/// after a network error. A [`write()`](Self::write) is not: it may have been applied even though
+/// the reply is lost.
+pub struct RaftClient<C, T>
+where
+    C: RaftTypeConfig,
+    T: ClientTransport<C>,
+{
+    transport: T,
+    config: ClientConfig,
+    targets: Mutex<Targets<C::NodeId, C::Node>>,
+}
+
+impl<C, T> RaftClient<C, T>
+where
+    C: RaftTypeConfig,
+    T: ClientTransport<C>,
+{
+    /// Create a client that knows about `nodes` at first.
+    ///
+    /// # Panics
+    ///
+    /// It panics if `nodes` is empty.
+    pub fn new(transport: T, nodes: BTreeMap<C::NodeId, C::Node>, config: ClientConfig) -> Self {
+        assert!(!nodes.is_empty(), "a client requires at least one node to start with");
+
+        Self {
+            transport,
+            config,
+            targets: Mutex::new(Targets {
+                leader: None,
+                nodes,
+                last_tried: None,
+            }),
+        }
+    }
+
+    /// The transport this client sends requests with.
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// The node this client believes to be the leader.
+    pub fn leader(&self) -> Option<(C::NodeId, C::Node)> {
+        let targets = self.targets();
+        let id = targets.leader?;
+        targets.nodes.get(&id).map(|node| (id, node.clone()))
+    }
+
+    /// All of the nodes this client knows about.
+    pub fn nodes(&self) -> BTreeMap<C::NodeId, C::Node> {
+        self.targets().nodes.clone()
+    }
+
+    // --- Application API
+
+    /// Submit a write request to the leader and return the result of applying it.
+    pub async fn write(
+        &self,
+        req: C::D,
+    ) -> Result<ClientWriteResponse<C>, ClientError<C, ClientWriteError<C::NodeId, C::Node>>> {
+        let req = &req;
+        self.send_to_leader(Retry::IfRejected, |id, node| async move {
+            self.transport.write(id, &node, req).await
+        })
+        .await
+    }
+
+    /// Read on the leader, after it confirms its leadership.
+    pub async fn linearizable_read(
+        &self,
+        req: T::ReadRequest,
+    ) -> Result<T::ReadResponse, ClientError<C, CheckIsLeaderError<C::NodeId, C::Node>>> {
+        let req = &req;
+        self.send_to_leader(Retry::Always, |id, node| async move {
+            self.transport.linearizable_read(id, &node, req).await
+        })
+        .await
+    }
+
+    // --- Cluster management API
+
+    /// Add a node as a learner, and wait for it to catch up with the leader.
+    pub async fn add_learner(
+        &self,
+        id: C::NodeId,
+        learner: C::Node,
+    ) -> Result<ClientWriteResponse<C>, ClientError<C, ClientWriteError<C::NodeId, C::Node>>> {
+        let learner = &learner;
+        self.send_to_leader(Retry::Always, |target, node| async move {
+            self.transport.add_learner(target, &node, id, learner).await
+        })
+        .await
+    }
+
+    /// Change the voters of the cluster to `members`.
+    pub async fn change_membership(
+        &self,
+        members: BTreeSet<C::NodeId>,
+    ) -> Result<ClientWriteResponse<C>, ClientError<C, ClientWriteError<C::NodeId, C::Node>>> {
+        let members = &members;
+        self.send_to_leader(Retry::Always, |id, node| async move {
+            self.transport.change_membership(id, &node, members).await
+        })
+        .await
+    }
+
+    /// Get the metrics of the leader, or of any node if the leader is unknown.
+    ///
+    /// The nodes in the membership config and the leader the metrics report are learned by this
+    /// client.
+    pub async fn metrics(&self) -> Result<RaftMetrics<C::NodeId, C::Node>, ClientError<C>> {
+        let (_target, metrics) = self
+            .send(Retry::Always, |id, node| async move {
+                self.transport.metrics(id, &node).await
+            })
+            .await?;
+
+        let mut targets = self.targets();
+        for (id, node) in metrics.membership_config.nodes() {
+            targets.nodes.insert(*id, node.clone());
+        }
+        if let Some(leader) = metrics.current_leader {
+            if targets.nodes.contains_key(&leader) {
+                targets.leader = Some(leader);
+            }
+        }
+
+        Ok(metrics)
+    }
+
+    // --- Internal methods
+
+    /// Send a request that only the leader accepts, and remember the leader when it succeeds.
+    async fn send_to_leader<Resp, E, F, Fut>(&self, retry: Retry, f: F) -> Result<Resp, ClientError<C, E>>
+    where
+        E: Error + TryAsRef<ForwardToLeader<C::NodeId, C::Node>>,
+        F: Fn(C::NodeId, C::Node) -> Fut,
+        Fut: Future<Output = Result<Resp, ClientError<C, E>>>,
+    {
+        let (target, resp) = self.send(retry, f).await?;
+        self.targets().leader = Some(target);
+        Ok(resp)
+    }
+
+    /// Send a request with `f` to the leader, or to the next known node if the leader is unknown,
+    /// until it succeeds, fails with an error that is not a `ForwardToLeader`, or runs out of
+    /// retries.
+    ///
+    /// It returns the node that handled the request along with the reply.
+    async fn send<Resp, E, F, Fut>(&self, retry: Retry, f: F) -> Result<(C::NodeId, Resp), ClientError<C, E>>
+    where
+        E: Error + TryAsRef<ForwardToLeader<C::NodeId, C::Node>>,
+        F: Fn(C::NodeId, C::Node) -> Fut,
+        Fut: Future<Output = Result<Resp, ClientError<C, E>>>,
+    {
+        let mut retries = 0;
+        let mut backoff = self.config.min_backoff;
+
+        loop {
+            let (target, node) = self.targets().next();
+
+            tracing::debug!("client send request to {}", target);
+
+            let res = match tokio::time::timeout(self.config.request_timeout, f(target, node)).await {
+                Ok(x) => x,
+                Err(elapsed) => Err(RPCError::Network(NetworkError::new(&elapsed))),
+            };
+
+            let err = match res {
+                Ok(resp) => return Ok((target, resp)),
+                Err(e) => e,
+            };
+
+            tracing::debug!("client request to {} failed: {}", target, err);
+
+            let redirected = match &err {
+                RPCError::RemoteError(_) => match err.forward_to_leader() {
+                    Some(fwd) => self.targets().follow(target, fwd),
+                    None => return Err(err),
+                },
+                RPCError::Network(_) | RPCError::Timeout(_) => {
+                    self.targets().forget(target);
+                    if retry == Retry::IfRejected {
+                        return Err(err);
+                    }
+                    false
+                }
+            };
+
+            if retries >= self.config.max_retries {
+                return Err(err);
+            }
+            retries += 1;
+
+            if !redirected {
+                tokio::time::sleep(backoff).await;
+                backoff = min(backoff * 2, self.config.max_backoff);
+            }
+        }
+    }
+
+    fn targets(&self) -> MutexGuard<'_, Targets<C::NodeId, C::Node>> {
+        self.targets.lock().unwrap()
+    }
+}
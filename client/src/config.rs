@@ -0,0 +1,34 @@
+use std::time::Duration;
+
+/// The retry and timeout policy of a [`RaftClient`](crate::RaftClient).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientConfig {
+    /// The time to wait for the reply to one request sent to one node.
+    ///
+    /// A request that times out fails with an `RPCError::Network`.
+    pub request_timeout: Duration,
+
+    /// The max number of times to resend a request after the first attempt fails, including the
+    /// times it is redirected to a new leader.
+    pub max_retries: usize,
+
+    /// The delay before the first retry to a node that is not known to be the leader.
+    ///
+    /// It is doubled after every such retry, up to `max_backoff`. A request redirected to a known
+    /// leader is resent at once.
+    pub min_backoff: Duration,
+
+    /// The max delay between two retries.
+    pub max_backoff: Duration,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            request_timeout: Duration::from_millis(3_000),
+            max_retries: 5,
+            min_backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_millis(1_000),
+        }
+    }
+}
@@ -0,0 +1,36 @@
+#![deny(unused_qualifications)]
+
+//! A client that follows the leader of an openraft cluster.
+//!
+//! [`RaftClient`] sends the requests of an application to the leader of a cluster through a
+//! [`ClientTransport`] implemented by the application, e.g., with HTTP. It discovers the leader
+//! from `ForwardToLeader` errors, resends requests that can be applied more than once with backoff,
+//! times out every request, and tries every known node in turn when the leader is unknown.
+//!
+//! ```ignore
+//! let client = RaftClient::new(HttpTransport::new(), btreemap! {1 => BasicNode::new("127.0.0.1:21001")}, ClientConfig::default());
+//!
+//! client.add_learner(2, BasicNode::new("127.0.0.1:21002")).await?;
+//! client.change_membership(btreeset! {1, 2}).await?;
+//! client.write(Request::Set { key: "foo".to_string(), value: "bar".to_string() }).await?;
+//! ```
+
+mod client;
+mod config;
+mod transport;
+
+#[cfg(test)] mod test;
+
+use openraft::error::Infallible;
+use openraft::error::RPCError;
+use openraft::error::RaftError;
+use openraft::RaftTypeConfig;
+
+pub use crate::client::RaftClient;
+pub use crate::config::ClientConfig;
+pub use crate::transport::ClientTransport;
+
+/// The error of a request sent to a node of a cluster of type config `C`, in which `E` is the error
+/// returned by the remote `Raft` API.
+pub type ClientError<C, E = Infallible> =
+    RPCError<<C as RaftTypeConfig>::NodeId, <C as RaftTypeConfig>::Node, RaftError<<C as RaftTypeConfig>::NodeId, E>>;
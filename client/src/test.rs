@@ -0,0 +1,302 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use maplit::btreemap;
+use maplit::btreeset;
+use openraft::async_trait::async_trait;
+use openraft::error::CheckIsLeaderError;
+use openraft::error::ClientWriteError;
+use openraft::error::ForwardToLeader;
+use openraft::error::NetworkError;
+use openraft::error::RPCError;
+use openraft::error::RaftError;
+use openraft::error::RemoteError;
+use openraft::raft::ClientWriteResponse;
+use openraft::AnyError;
+use openraft::BasicNode;
+use openraft::CommittedLeaderId;
+use openraft::Entry;
+use openraft::LogId;
+use openraft::Membership;
+use openraft::RaftMetrics;
+use openraft::StoredMembership;
+
+use crate::ClientConfig;
+use crate::ClientError;
+use crate::ClientTransport;
+use crate::RaftClient;
+
+openraft::declare_raft_types!(
+    pub TestConfig: D = u64, R = u64, NodeId = u64, Node = BasicNode, Entry = Entry<TestConfig>
+);
+
+fn node(id: u64) -> BasicNode {
+    BasicNode::new(format!("127.0.0.1:{}", 21000 + id))
+}
+
+/// A cluster of nodes `1, 2, 3`, in which every node knows the same leader.
+#[derive(Default)]
+struct Cluster {
+    leader: Option<u64>,
+
+    /// Nodes that can not be reached.
+    down: BTreeSet<u64>,
+
+    /// Nodes that never reply.
+    stuck: BTreeSet<u64>,
+
+    /// The nodes every request is sent to, in order.
+    sent: Mutex<Vec<u64>>,
+}
+
+impl Cluster {
+    async fn recv<E>(
+        &self,
+        target: u64,
+        forward: fn(ForwardToLeader<u64, BasicNode>) -> E,
+    ) -> Result<(), ClientError<TestConfig, E>>
+    where E: std::error::Error {
+        self.sent.lock().unwrap().push(target);
+
+        if self.down.contains(&target) {
+            let e = AnyError::error(format!("node {} is down", target));
+            return Err(RPCError::Network(NetworkError::from(e)));
+        }
+
+        if self.stuck.contains(&target) {
+            tokio::time::sleep(Duration::from_secs(3600)).await;
+        }
+
+        if self.leader == Some(target) {
+            return Ok(());
+        }
+
+        let fwd = ForwardToLeader {
+            leader_id: self.leader,
+            leader_node: self.leader.map(node),
+        };
+        Err(RPCError::RemoteError(RemoteError::new(target, RaftError::APIError(forward(fwd)))))
+    }
+
+    fn sent(&self) -> Vec<u64> {
+        std::mem::take(&mut *self.sent.lock().unwrap())
+    }
+}
+
+#[derive(Clone)]
+struct MockTransport {
+    cluster: Arc<Cluster>,
+}
+
+fn write_resp(leader: u64, data: u64) -> ClientWriteResponse<TestConfig> {
+    ClientWriteResponse {
+        log_id: LogId::new(CommittedLeaderId::new(1, leader), 1),
+        data,
+        membership: None,
+    }
+}
+
+#[async_trait]
+impl ClientTransport<TestConfig> for MockTransport {
+    type ReadRequest = u64;
+    type ReadResponse = u64;
+
+    async fn write(
+        &self,
+        target: u64,
+        _node: &BasicNode,
+        req: &u64,
+    ) -> Result<ClientWriteResponse<TestConfig>, ClientError<TestConfig, ClientWriteError<u64, BasicNode>>> {
+        self.cluster.recv(target, ClientWriteError::ForwardToLeader).await?;
+        Ok(write_resp(target, *req))
+    }
+
+    async fn linearizable_read(
+        &self,
+        target: u64,
+        _node: &BasicNode,
+        req: &u64,
+    ) -> Result<u64, ClientError<TestConfig, CheckIsLeaderError<u64, BasicNode>>> {
+        self.cluster.recv(target, CheckIsLeaderError::ForwardToLeader).await?;
+        Ok(*req * 10)
+    }
+
+    async fn add_learner(
+        &self,
+        target: u64,
+        _node: &BasicNode,
+        id: u64,
+        _learner: &BasicNode,
+    ) -> Result<ClientWriteResponse<TestConfig>, ClientError<TestConfig, ClientWriteError<u64, BasicNode>>> {
+        self.cluster.recv(target, ClientWriteError::ForwardToLeader).await?;
+        Ok(write_resp(target, id))
+    }
+
+    async fn change_membership(
+        &self,
+        target: u64,
+        _node: &BasicNode,
+        members: &BTreeSet<u64>,
+    ) -> Result<ClientWriteResponse<TestConfig>, ClientError<TestConfig, ClientWriteError<u64, BasicNode>>> {
+        self.cluster.recv(target, ClientWriteError::ForwardToLeader).await?;
+        Ok(write_resp(target, members.len() as u64))
+    }
+
+    /// Every node that is up replies with metrics.
+    async fn metrics(
+        &self,
+        target: u64,
+        _node: &BasicNode,
+    ) -> Result<RaftMetrics<u64, BasicNode>, ClientError<TestConfig>> {
+        self.cluster.sent.lock().unwrap().push(target);
+
+        let mut m = RaftMetrics::new_initial(target);
+        m.current_leader = self.cluster.leader;
+        m.membership_config = Arc::new(StoredMembership::new(
+            None,
+            Membership::new(vec![btreeset! {1,2,3}], btreemap! {1=>node(1), 2=>node(2), 3=>node(3)}),
+        ));
+        Ok(m)
+    }
+}
+
+fn config() -> ClientConfig {
+    ClientConfig {
+        request_timeout: Duration::from_millis(100),
+        max_retries: 5,
+        min_backoff: Duration::from_millis(1),
+        max_backoff: Duration::from_millis(10),
+    }
+}
+
+fn new_client(cluster: Cluster, nodes: &[u64]) -> (Arc<Cluster>, RaftClient<TestConfig, MockTransport>) {
+    let cluster = Arc::new(cluster);
+    let nodes = nodes.iter().map(|id| (*id, node(*id))).collect::<BTreeMap<_, _>>();
+    let client = RaftClient::new(
+        MockTransport {
+            cluster: cluster.clone(),
+        },
+        nodes,
+        config(),
+    );
+    (cluster, client)
+}
+
+#[tokio::test]
+async fn test_follow_forward_to_leader() -> anyhow::Result<()> {
+    let (cluster, client) = new_client(
+        Cluster {
+            leader: Some(3),
+            ..Default::default()
+        },
+        &[1],
+    );
+
+    let resp = client.write(5).await?;
+    assert_eq!(5, resp.data);
+    assert_eq!(vec![1, 3], cluster.sent());
+    assert_eq!(Some((3, node(3))), client.leader());
+
+    client.write(6).await?;
+    client.change_membership(btreeset! {1,2,3}).await?;
+    assert_eq!(vec![3, 3], cluster.sent(), "the known leader is used at once");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rotate_when_leader_unknown() -> anyhow::Result<()> {
+    let (cluster, client) = new_client(
+        Cluster {
+            leader: Some(3),
+            down: btreeset! {1},
+            ..Default::default()
+        },
+        &[1, 2],
+    );
+
+    let got = client.linearizable_read(4).await?;
+    assert_eq!(40, got);
+    assert_eq!(vec![1, 2, 3], cluster.sent());
+    assert_eq!(Some((3, node(3))), client.leader());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_write_not_resent_after_network_error() -> anyhow::Result<()> {
+    let (cluster, client) = new_client(
+        Cluster {
+            leader: Some(2),
+            down: btreeset! {1},
+            ..Default::default()
+        },
+        &[1, 2],
+    );
+
+    let res = client.write(5).await;
+    assert!(matches!(res, Err(RPCError::Network(_))));
+    assert_eq!(vec![1], cluster.sent());
+
+    client.write(5).await?;
+    assert_eq!(vec![2], cluster.sent(), "the next request goes to the next node");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_request_timeout() -> anyhow::Result<()> {
+    let (cluster, client) = new_client(
+        Cluster {
+            leader: Some(2),
+            stuck: btreeset! {1},
+            ..Default::default()
+        },
+        &[1, 2],
+    );
+
+    let res = client.write(5).await;
+    assert!(matches!(res, Err(RPCError::Network(_))));
+    assert_eq!(vec![1], cluster.sent());
+
+    let resp = client.add_learner(3, node(3)).await?;
+    assert_eq!(3, resp.data);
+    assert_eq!(vec![2], cluster.sent());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_give_up_after_max_retries() -> anyhow::Result<()> {
+    let (cluster, client) = new_client(Cluster::default(), &[1, 2, 3]);
+
+    let res = client.write(5).await;
+    let fwd = res.unwrap_err().forward_to_leader().cloned();
+    assert_eq!(Some(ForwardToLeader::empty()), fwd);
+    assert_eq!(vec![1, 2, 3, 1, 2, 3], cluster.sent(), "1 attempt and 5 retries");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_metrics_learns_nodes_and_leader() -> anyhow::Result<()> {
+    let (cluster, client) = new_client(
+        Cluster {
+            leader: Some(3),
+            ..Default::default()
+        },
+        &[1],
+    );
+
+    let m = client.metrics().await?;
+    assert_eq!(1, m.id);
+    assert_eq!(vec![1], cluster.sent());
+
+    assert_eq!(Some((3, node(3))), client.leader());
+    assert_eq!(btreemap! {1=>node(1), 2=>node(2), 3=>node(3)}, client.nodes());
+
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use std::collections::BTreeSet;
+
+use openraft::async_trait::async_trait;
+use openraft::error::CheckIsLeaderError;
+use openraft::error::ClientWriteError;
+use openraft::raft::ClientWriteResponse;
+use openraft::RaftMetrics;
+use openraft::RaftTypeConfig;
+
+use crate::ClientError;
+
+/// Sends the requests of a [`RaftClient`](crate::RaftClient) to one node of a cluster.
+///
+/// An implementation only delivers a request to `target`, with the address found in `node`, and
+/// returns what the remote `Raft` returns. It does not retry or follow the leader: the client does.
+///
+/// An error returned by the remote `Raft` must be returned as an `RPCError::RemoteError`, so that
+/// the client can find a `ForwardToLeader` in it. Any failure to deliver a request or to receive
+/// the reply is an `RPCError::Network`.
+#[async_trait]
+pub trait ClientTransport<C: RaftTypeConfig>: Send + Sync + 'static {
+    /// The request of a linearizable read, defined by the application.
+    type ReadRequest: Send + Sync + 'static;
+
+    /// The reply to a linearizable read.
+    type ReadResponse: Send + 'static;
+
+    /// Propose `req` with `Raft::client_write()` on `target`.
+    async fn write(
+        &self,
+        target: C::NodeId,
+        node: &C::Node,
+        req: &C::D,
+    ) -> Result<ClientWriteResponse<C>, ClientError<C, ClientWriteError<C::NodeId, C::Node>>>;
+
+    /// Read from the state machine of `target` after confirming it is the leader, e.g., with
+    /// `Raft::is_leader()`.
+    async fn linearizable_read(
+        &self,
+        target: C::NodeId,
+        node: &C::Node,
+        req: &Self::ReadRequest,
+    ) -> Result<Self::ReadResponse, ClientError<C, CheckIsLeaderError<C::NodeId, C::Node>>>;
+
+    /// Add `id` as a learner with `Raft::add_learner()` on `target`, blocking until the learner
+    /// catches up.
+    async fn add_learner(
+        &self,
+        target: C::NodeId,
+        node: &C::Node,
+        id: C::NodeId,
+        learner: &C::Node,
+    ) -> Result<ClientWriteResponse<C>, ClientError<C, ClientWriteError<C::NodeId, C::Node>>>;
+
+    /// Change the voters to `members` with `Raft::change_membership()` on `target`.
+    async fn change_membership(
+        &self,
+        target: C::NodeId,
+        node: &C::Node,
+        members: &BTreeSet<C::NodeId>,
+    ) -> Result<ClientWriteResponse<C>, ClientError<C, ClientWriteError<C::NodeId, C::Node>>>;
+
+    /// Get the metrics of `target`.
+    async fn metrics(
+        &self,
+        target: C::NodeId,
+        node: &C::Node,
+    ) -> Result<RaftMetrics<C::NodeId, C::Node>, ClientError<C>>;
+}
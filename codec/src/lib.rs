@@ -0,0 +1,77 @@
+#![deny(unused_crate_dependencies)]
+#![deny(unused_qualifications)]
+
+//! Encoding of the values stored by the key-value based `RaftStorage` implementations, e.g.,
+//! `openraft-rocksstore` and `openraft-sledstore`.
+//!
+//! Every encoded value starts with a format byte that identifies the codec it is encoded with, so
+//! that a store can read values written with any codec, regardless of the codec it writes with.
+//!
+//! A value without a known format byte is a legacy value written as plain JSON by an older
+//! version, before the format byte was introduced. A JSON document never starts with a byte
+//! smaller than `0x09`, so legacy values can not be mistaken for values with a format byte.
+
+use openraft::AnyError;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Format byte of values encoded with [`Codec::Json`].
+const FORMAT_JSON: u8 = 1;
+
+/// Format byte of values encoded with [`Codec::Bincode`].
+const FORMAT_BINCODE: u8 = 2;
+
+/// The codec used to encode log entries, vote, membership and snapshot.
+///
+/// The codec only decides how new values are written. Values written with another codec, or
+/// legacy JSON values without a format byte, are still readable and are migrated to this codec
+/// when they are written again, or all at once by an explicit upgrade.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Codec {
+    /// Human readable JSON, by `serde_json`.
+    Json,
+
+    /// Compact binary encoding, by `bincode`.
+    #[default]
+    Bincode,
+}
+
+impl Codec {
+    /// The format byte this codec prepends to every encoded value.
+    pub fn format_byte(&self) -> u8 {
+        match self {
+            Codec::Json => FORMAT_JSON,
+            Codec::Bincode => FORMAT_BINCODE,
+        }
+    }
+
+    /// Returns the codec an encoded value is written with, or `None` for a legacy JSON value.
+    pub fn of(buf: &[u8]) -> Option<Codec> {
+        match buf.first() {
+            Some(&FORMAT_JSON) => Some(Codec::Json),
+            Some(&FORMAT_BINCODE) => Some(Codec::Bincode),
+            _ => None,
+        }
+    }
+
+    /// Encode a value with this codec, prefixed with the format byte.
+    pub fn encode<T: Serialize>(&self, v: &T) -> Result<Vec<u8>, AnyError> {
+        let mut buf = vec![self.format_byte()];
+
+        match self {
+            Codec::Json => serde_json::to_writer(&mut buf, v).map_err(|e| AnyError::new(&e))?,
+            Codec::Bincode => bincode::serialize_into(&mut buf, v).map_err(|e| AnyError::new(&e))?,
+        }
+
+        Ok(buf)
+    }
+
+    /// Decode a value written with any codec, or a legacy JSON value.
+    pub fn decode<T: DeserializeOwned>(buf: &[u8]) -> Result<T, AnyError> {
+        match Codec::of(buf) {
+            Some(Codec::Json) => serde_json::from_slice(&buf[1..]).map_err(|e| AnyError::new(&e)),
+            Some(Codec::Bincode) => bincode::deserialize(&buf[1..]).map_err(|e| AnyError::new(&e)),
+            None => serde_json::from_slice(buf).map_err(|e| AnyError::new(&e)),
+        }
+    }
+}
@@ -1,43 +1,162 @@
+use std::collections::BTreeMap;
 use std::collections::BTreeSet;
-use std::sync::Arc;
-use std::sync::Mutex;
-use std::time::Duration;
 
-use openraft::error::ForwardToLeader;
+use openraft::async_trait::async_trait;
 use openraft::error::NetworkError;
 use openraft::error::RPCError;
 use openraft::error::RemoteError;
 use openraft::BasicNode;
 use openraft::RaftMetrics;
-use openraft::TryAsRef;
+use openraft_client::ClientConfig;
+use openraft_client::ClientTransport;
+use openraft_client::RaftClient;
 use reqwest::Client;
 use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use serde::Serialize;
-use tokio::time::timeout;
 
 use crate::typ;
 use crate::ExampleNodeId;
 use crate::ExampleRequest;
+use crate::ExampleTypeConfig;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Empty {}
 
-pub struct ExampleClient {
-    /// The leader node to send request to.
+/// Sends the requests of a client to a node with HTTP.
+pub struct HttpTransport {
+    pub inner: Client,
+}
+
+impl HttpTransport {
+    pub fn new() -> Self {
+        Self {
+            inner: reqwest::Client::new(),
+        }
+    }
+
+    /// Send RPC to specified node.
     ///
-    /// All traffic should be sent to the leader in a cluster.
-    pub leader: Arc<Mutex<(ExampleNodeId, String)>>,
+    /// It sends out a POST request if `req` is Some. Otherwise a GET request.
+    /// The remote endpoint must respond a reply in form of `Result<T, E>`.
+    /// An `Err` happened on remote will be wrapped in an [`RPCError::RemoteError`].
+    pub async fn send_rpc<Req, Resp, Err>(
+        &self,
+        target: ExampleNodeId,
+        node: &BasicNode,
+        uri: &str,
+        req: Option<&Req>,
+    ) -> Result<Resp, typ::RPCError<Err>>
+    where
+        Req: Serialize + 'static,
+        Resp: Serialize + DeserializeOwned,
+        Err: std::error::Error + Serialize + DeserializeOwned,
+    {
+        let url = format!("http://{}/{}", node.addr, uri);
 
-    pub inner: Client,
+        let resp = if let Some(r) = req {
+            tracing::debug!(
+                ">>> client send request to {}: {}",
+                url,
+                serde_json::to_string_pretty(&r).unwrap()
+            );
+            self.inner.post(url.clone()).json(r)
+        } else {
+            tracing::debug!(">>> client send request to {}", url,);
+            self.inner.get(url.clone())
+        }
+        .send()
+        .await
+        .map_err(|e| RPCError::Network(NetworkError::new(&e)))?;
+
+        let res: Result<Resp, typ::RaftError<Err>> =
+            resp.json().await.map_err(|e| RPCError::Network(NetworkError::new(&e)))?;
+        tracing::debug!(
+            "<<< client recv reply from {}: {}",
+            url,
+            serde_json::to_string_pretty(&res).unwrap()
+        );
+
+        res.map_err(|e| RPCError::RemoteError(RemoteError::new(target, e)))
+    }
+}
+
+impl Default for HttpTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl ClientTransport<ExampleTypeConfig> for HttpTransport {
+    type ReadRequest = String;
+    type ReadResponse = String;
+
+    async fn write(
+        &self,
+        target: ExampleNodeId,
+        node: &BasicNode,
+        req: &ExampleRequest,
+    ) -> Result<typ::ClientWriteResponse, typ::RPCError<typ::ClientWriteError>> {
+        self.send_rpc(target, node, "write", Some(req)).await
+    }
+
+    async fn linearizable_read(
+        &self,
+        target: ExampleNodeId,
+        node: &BasicNode,
+        req: &String,
+    ) -> Result<String, typ::RPCError<typ::CheckIsLeaderError>> {
+        self.send_rpc(target, node, "consistent_read", Some(req)).await
+    }
+
+    async fn add_learner(
+        &self,
+        target: ExampleNodeId,
+        node: &BasicNode,
+        id: ExampleNodeId,
+        learner: &BasicNode,
+    ) -> Result<typ::ClientWriteResponse, typ::RPCError<typ::ClientWriteError>> {
+        self.send_rpc(target, node, "add-learner", Some(&(id, learner.addr.clone()))).await
+    }
+
+    async fn change_membership(
+        &self,
+        target: ExampleNodeId,
+        node: &BasicNode,
+        members: &BTreeSet<ExampleNodeId>,
+    ) -> Result<typ::ClientWriteResponse, typ::RPCError<typ::ClientWriteError>> {
+        self.send_rpc(target, node, "change-membership", Some(members)).await
+    }
+
+    async fn metrics(
+        &self,
+        target: ExampleNodeId,
+        node: &BasicNode,
+    ) -> Result<RaftMetrics<ExampleNodeId, BasicNode>, typ::RPCError> {
+        self.send_rpc(target, node, "metrics", None::<&()>).await
+    }
+}
+
+/// A client of the example cluster, built upon [`RaftClient`], which follows the leader.
+pub struct ExampleClient {
+    /// The node this client is created with.
+    pub node: (ExampleNodeId, BasicNode),
+
+    pub inner: RaftClient<ExampleTypeConfig, HttpTransport>,
 }
 
 impl ExampleClient {
-    /// Create a client with a leader node id and a node manager to get node address by node id.
-    pub fn new(leader_id: ExampleNodeId, leader_addr: String) -> Self {
+    /// Create a client that starts by sending requests to the specified node.
+    ///
+    /// The leader is discovered from the replies.
+    pub fn new(node_id: ExampleNodeId, addr: String) -> Self {
+        let node = BasicNode::new(addr);
+        let nodes = BTreeMap::from([(node_id, node.clone())]);
+
         Self {
-            leader: Arc::new(Mutex::new((leader_id, leader_addr))),
-            inner: reqwest::Client::new(),
+            node: (node_id, node),
+            inner: RaftClient::new(HttpTransport::new(), nodes, ClientConfig::default()),
         }
     }
 
@@ -53,33 +172,36 @@ impl ExampleClient {
         &self,
         req: &ExampleRequest,
     ) -> Result<typ::ClientWriteResponse, typ::RPCError<typ::ClientWriteError>> {
-        self.send_rpc_to_leader("write", Some(req)).await
+        self.inner.write(req.clone()).await
     }
 
     /// Read value by key, in an inconsistent mode.
     ///
     /// This method may return stale value because it does not force to read on a legal leader.
+    /// It reads on the last known leader, or on the node this client is created with.
     pub async fn read(&self, req: &String) -> Result<String, typ::RPCError> {
-        self.do_send_rpc_to_leader("read", Some(req)).await
+        let (id, node) = self.inner.leader().unwrap_or_else(|| self.node.clone());
+        self.inner.transport().send_rpc(id, &node, "read", Some(req)).await
     }
 
-    /// Consistent Read value by key, in an inconsistent mode.
+    /// Consistent Read value by key.
     ///
-    /// This method MUST return consistent value or CheckIsLeaderError.
+    /// It is forwarded to the leader, which MUST return consistent value or CheckIsLeaderError.
     pub async fn consistent_read(&self, req: &String) -> Result<String, typ::RPCError<typ::CheckIsLeaderError>> {
-        self.do_send_rpc_to_leader("consistent_read", Some(req)).await
+        self.inner.linearizable_read(req.clone()).await
     }
 
     // --- Cluster management API
 
-    /// Initialize a cluster of only the node that receives this request.
+    /// Initialize a cluster of only the node this client is created with.
     ///
     /// This is the first step to initialize a cluster.
     /// With a initialized cluster, new node can be added with [`write`].
     /// Then setup replication with [`add_learner`].
     /// Then make the new node a member with [`change_membership`].
     pub async fn init(&self) -> Result<(), typ::RPCError<typ::InitializeError>> {
-        self.do_send_rpc_to_leader("init", Some(&Empty {})).await
+        let (id, node) = &self.node;
+        self.inner.transport().send_rpc(*id, node, "init", Some(&Empty {})).await
     }
 
     /// Add a node as learner.
@@ -89,7 +211,7 @@ impl ExampleClient {
         &self,
         req: (ExampleNodeId, String),
     ) -> Result<typ::ClientWriteResponse, typ::RPCError<typ::ClientWriteError>> {
-        self.send_rpc_to_leader("add-learner", Some(&req)).await
+        self.inner.add_learner(req.0, BasicNode::new(req.1)).await
     }
 
     /// Change membership to the specified set of nodes.
@@ -100,7 +222,7 @@ impl ExampleClient {
         &self,
         req: &BTreeSet<ExampleNodeId>,
     ) -> Result<typ::ClientWriteResponse, typ::RPCError<typ::ClientWriteError>> {
-        self.send_rpc_to_leader("change-membership", Some(req)).await
+        self.inner.change_membership(req.clone()).await
     }
 
     /// Get the metrics about the cluster.
@@ -109,104 +231,6 @@ impl ExampleClient {
     /// membership config, replication status etc.
     /// See [`RaftMetrics`].
     pub async fn metrics(&self) -> Result<RaftMetrics<ExampleNodeId, BasicNode>, typ::RPCError> {
-        self.do_send_rpc_to_leader("metrics", None::<&()>).await
-    }
-
-    // --- Internal methods
-
-    /// Send RPC to specified node.
-    ///
-    /// It sends out a POST request if `req` is Some. Otherwise a GET request.
-    /// The remote endpoint must respond a reply in form of `Result<T, E>`.
-    /// An `Err` happened on remote will be wrapped in an [`RPCError::RemoteError`].
-    async fn do_send_rpc_to_leader<Req, Resp, Err>(
-        &self,
-        uri: &str,
-        req: Option<&Req>,
-    ) -> Result<Resp, typ::RPCError<Err>>
-    where
-        Req: Serialize + 'static,
-        Resp: Serialize + DeserializeOwned,
-        Err: std::error::Error + Serialize + DeserializeOwned,
-    {
-        let (leader_id, url) = {
-            let t = self.leader.lock().unwrap();
-            let target_addr = &t.1;
-            (t.0, format!("http://{}/{}", target_addr, uri))
-        };
-
-        let fu = if let Some(r) = req {
-            tracing::debug!(
-                ">>> client send request to {}: {}",
-                url,
-                serde_json::to_string_pretty(&r).unwrap()
-            );
-            self.inner.post(url.clone()).json(r)
-        } else {
-            tracing::debug!(">>> client send request to {}", url,);
-            self.inner.get(url.clone())
-        }
-        .send();
-
-        let res = timeout(Duration::from_millis(3_000), fu).await;
-        let resp = match res {
-            Ok(x) => x.map_err(|e| RPCError::Network(NetworkError::new(&e)))?,
-            Err(timeout_err) => {
-                tracing::error!("timeout {} to url: {}", timeout_err, url);
-                return Err(RPCError::Network(NetworkError::new(&timeout_err)));
-            }
-        };
-
-        let res: Result<Resp, typ::RaftError<Err>> =
-            resp.json().await.map_err(|e| RPCError::Network(NetworkError::new(&e)))?;
-        tracing::debug!(
-            "<<< client recv reply from {}: {}",
-            url,
-            serde_json::to_string_pretty(&res).unwrap()
-        );
-
-        res.map_err(|e| RPCError::RemoteError(RemoteError::new(leader_id, e)))
-    }
-
-    /// Try the best to send a request to the leader.
-    ///
-    /// If the target node is not a leader, a `ForwardToLeader` error will be
-    /// returned and this client will retry at most 3 times to contact the updated leader.
-    async fn send_rpc_to_leader<Req, Resp, Err>(&self, uri: &str, req: Option<&Req>) -> Result<Resp, typ::RPCError<Err>>
-    where
-        Req: Serialize + 'static,
-        Resp: Serialize + DeserializeOwned,
-        Err: std::error::Error + Serialize + DeserializeOwned + TryAsRef<typ::ForwardToLeader> + Clone,
-    {
-        // Retry at most 3 times to find a valid leader.
-        let mut n_retry = 3;
-
-        loop {
-            let res: Result<Resp, typ::RPCError<Err>> = self.do_send_rpc_to_leader(uri, req).await;
-
-            let rpc_err = match res {
-                Ok(x) => return Ok(x),
-                Err(rpc_err) => rpc_err,
-            };
-
-            if let Some(ForwardToLeader {
-                leader_id: Some(leader_id),
-                leader_node: Some(leader_node),
-            }) = rpc_err.forward_to_leader()
-            {
-                // Update target to the new leader.
-                {
-                    let mut t = self.leader.lock().unwrap();
-                    *t = (*leader_id, leader_node.addr.clone());
-                }
-
-                n_retry -= 1;
-                if n_retry > 0 {
-                    continue;
-                }
-            }
-
-            return Err(rpc_err);
-        }
+        self.inner.metrics().await
     }
 }
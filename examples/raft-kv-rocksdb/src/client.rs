@@ -1,10 +1,9 @@
+use std::collections::BTreeMap;
 use std::collections::BTreeSet;
-use std::sync::Arc;
-use std::sync::Mutex;
 
+use openraft::async_trait::async_trait;
 use openraft::error::CheckIsLeaderError;
 use openraft::error::ClientWriteError;
-use openraft::error::ForwardToLeader;
 use openraft::error::InitializeError;
 use openraft::error::NetworkError;
 use openraft::error::RPCError;
@@ -12,7 +11,10 @@ use openraft::error::RaftError;
 use openraft::error::RemoteError;
 use openraft::raft::ClientWriteResponse;
 use openraft::RaftMetrics;
-use openraft::TryAsRef;
+use openraft_client::ClientConfig;
+use openraft_client::ClientError;
+use openraft_client::ClientTransport;
+use openraft_client::RaftClient;
 use reqwest::Client;
 use serde::de::DeserializeOwned;
 use serde::Deserialize;
@@ -26,21 +28,153 @@ use crate::ExampleTypeConfig;
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Empty {}
 
-pub struct ExampleClient {
-    /// The leader node to send request to.
+/// Sends the requests of a client to the API address of a node with HTTP.
+pub struct HttpTransport {
+    pub inner: Client,
+}
+
+impl HttpTransport {
+    pub fn new() -> Self {
+        Self {
+            inner: reqwest::Client::new(),
+        }
+    }
+
+    /// Send RPC to specified node.
     ///
-    /// All traffic should be sent to the leader in a cluster.
-    pub leader: Arc<Mutex<(ExampleNodeId, String)>>,
+    /// It sends out a POST request if `req` is Some. Otherwise a GET request.
+    /// The remote endpoint must respond a reply in form of `Result<T, E>`.
+    /// An `Err` happened on remote will be wrapped in an [`RPCError::RemoteError`].
+    pub async fn send_rpc<Req, Resp, Err>(
+        &self,
+        target: ExampleNodeId,
+        node: &ExampleNode,
+        uri: &str,
+        req: Option<&Req>,
+    ) -> Result<Resp, RPCError<ExampleNodeId, ExampleNode, Err>>
+    where
+        Req: Serialize + 'static,
+        Resp: Serialize + DeserializeOwned,
+        Err: std::error::Error + Serialize + DeserializeOwned,
+    {
+        let url = format!("http://{}/{}", node.api_addr, uri);
 
-    pub inner: Client,
+        let resp = if let Some(r) = req {
+            println!(
+                ">>> client send request to {}: {}",
+                url,
+                serde_json::to_string_pretty(&r).unwrap()
+            );
+            self.inner.post(url.clone()).json(r)
+        } else {
+            println!(">>> client send request to {}", url,);
+            self.inner.get(url.clone())
+        }
+        .send()
+        .await
+        .map_err(|e| RPCError::Network(NetworkError::new(&e)))?;
+
+        let res: Result<Resp, Err> = resp.json().await.map_err(|e| RPCError::Network(NetworkError::new(&e)))?;
+        println!(
+            "<<< client recv reply from {}: {}",
+            url,
+            serde_json::to_string_pretty(&res).unwrap()
+        );
+
+        res.map_err(|e| RPCError::RemoteError(RemoteError::new(target, e)))
+    }
+}
+
+impl Default for HttpTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl ClientTransport<ExampleTypeConfig> for HttpTransport {
+    type ReadRequest = String;
+    type ReadResponse = String;
+
+    async fn write(
+        &self,
+        target: ExampleNodeId,
+        node: &ExampleNode,
+        req: &ExampleRequest,
+    ) -> Result<
+        ClientWriteResponse<ExampleTypeConfig>,
+        ClientError<ExampleTypeConfig, ClientWriteError<ExampleNodeId, ExampleNode>>,
+    > {
+        self.send_rpc(target, node, "api/write", Some(req)).await
+    }
+
+    async fn linearizable_read(
+        &self,
+        target: ExampleNodeId,
+        node: &ExampleNode,
+        req: &String,
+    ) -> Result<String, ClientError<ExampleTypeConfig, CheckIsLeaderError<ExampleNodeId, ExampleNode>>> {
+        self.send_rpc(target, node, "api/consistent_read", Some(req)).await
+    }
+
+    async fn add_learner(
+        &self,
+        target: ExampleNodeId,
+        node: &ExampleNode,
+        id: ExampleNodeId,
+        learner: &ExampleNode,
+    ) -> Result<
+        ClientWriteResponse<ExampleTypeConfig>,
+        ClientError<ExampleTypeConfig, ClientWriteError<ExampleNodeId, ExampleNode>>,
+    > {
+        let req = (id, learner.api_addr.clone(), learner.rpc_addr.clone());
+        self.send_rpc(target, node, "cluster/add-learner", Some(&req)).await
+    }
+
+    async fn change_membership(
+        &self,
+        target: ExampleNodeId,
+        node: &ExampleNode,
+        members: &BTreeSet<ExampleNodeId>,
+    ) -> Result<
+        ClientWriteResponse<ExampleTypeConfig>,
+        ClientError<ExampleTypeConfig, ClientWriteError<ExampleNodeId, ExampleNode>>,
+    > {
+        self.send_rpc(target, node, "cluster/change-membership", Some(members)).await
+    }
+
+    async fn metrics(
+        &self,
+        target: ExampleNodeId,
+        node: &ExampleNode,
+    ) -> Result<RaftMetrics<ExampleNodeId, ExampleNode>, ClientError<ExampleTypeConfig>> {
+        self.send_rpc(target, node, "cluster/metrics", None::<&()>).await
+    }
+}
+
+/// A client of the example cluster, built upon [`RaftClient`], which follows the leader.
+pub struct ExampleClient {
+    /// The node this client is created with.
+    pub node: (ExampleNodeId, ExampleNode),
+
+    pub inner: RaftClient<ExampleTypeConfig, HttpTransport>,
 }
 
 impl ExampleClient {
-    /// Create a client with a leader node id and a node manager to get node address by node id.
-    pub fn new(leader_id: ExampleNodeId, leader_addr: String) -> Self {
+    /// Create a client that starts by sending requests to the API address of the specified node.
+    ///
+    /// The leader is discovered from the replies. A client does not use the rpc address of a
+    /// node.
+    pub fn new(node_id: ExampleNodeId, api_addr: String) -> Self {
+        let node = ExampleNode {
+            api_addr,
+            ..Default::default()
+        };
+        let nodes = BTreeMap::from([(node_id, node.clone())]);
+
         Self {
-            leader: Arc::new(Mutex::new((leader_id, leader_addr))),
-            inner: reqwest::Client::new(),
+            node: (node_id, node),
+            inner: RaftClient::new(HttpTransport::new(), nodes, ClientConfig::default()),
         }
     }
 
@@ -59,22 +193,24 @@ impl ExampleClient {
         ClientWriteResponse<ExampleTypeConfig>,
         RPCError<ExampleNodeId, ExampleNode, RaftError<ExampleNodeId, ClientWriteError<ExampleNodeId, ExampleNode>>>,
     > {
-        self.send_rpc_to_leader("api/write", Some(req)).await
+        self.inner.write(req.clone()).await
     }
 
     /// Read value by key, in an inconsistent mode.
     ///
     /// This method may return stale value because it does not force to read on a legal leader.
+    /// It reads on the last known leader, or on the node this client is created with.
     pub async fn read(
         &self,
         req: &String,
     ) -> Result<String, RPCError<ExampleNodeId, ExampleNode, RaftError<ExampleNodeId>>> {
-        self.do_send_rpc_to_leader("api/read", Some(req)).await
+        let (id, node) = self.inner.leader().unwrap_or_else(|| self.node.clone());
+        self.inner.transport().send_rpc(id, &node, "api/read", Some(req)).await
     }
 
-    /// Consistent Read value by key, in an inconsistent mode.
+    /// Consistent Read value by key.
     ///
-    /// This method MUST return consistent value or CheckIsLeaderError.
+    /// It is forwarded to the leader, which MUST return consistent value or CheckIsLeaderError.
     pub async fn consistent_read(
         &self,
         req: &String,
@@ -82,12 +218,12 @@ impl ExampleClient {
         String,
         RPCError<ExampleNodeId, ExampleNode, RaftError<ExampleNodeId, CheckIsLeaderError<ExampleNodeId, ExampleNode>>>,
     > {
-        self.do_send_rpc_to_leader("api/consistent_read", Some(req)).await
+        self.inner.linearizable_read(req.clone()).await
     }
 
     // --- Cluster management API
 
-    /// Initialize a cluster of only the node that receives this request.
+    /// Initialize a cluster of only the node this client is created with.
     ///
     /// This is the first step to initialize a cluster.
     /// With a initialized cluster, new node can be added with [`write`].
@@ -99,7 +235,8 @@ impl ExampleClient {
         (),
         RPCError<ExampleNodeId, ExampleNode, RaftError<ExampleNodeId, InitializeError<ExampleNodeId, ExampleNode>>>,
     > {
-        self.do_send_rpc_to_leader("cluster/init", Some(&Empty {})).await
+        let (id, node) = &self.node;
+        self.inner.transport().send_rpc(*id, node, "cluster/init", Some(&Empty {})).await
     }
 
     /// Add a node as learner.
@@ -112,7 +249,8 @@ impl ExampleClient {
         ClientWriteResponse<ExampleTypeConfig>,
         RPCError<ExampleNodeId, ExampleNode, RaftError<ExampleNodeId, ClientWriteError<ExampleNodeId, ExampleNode>>>,
     > {
-        self.send_rpc_to_leader("cluster/add-learner", Some(&req)).await
+        let (id, api_addr, rpc_addr) = req;
+        self.inner.add_learner(id, ExampleNode { rpc_addr, api_addr }).await
     }
 
     /// Change membership to the specified set of nodes.
@@ -126,7 +264,7 @@ impl ExampleClient {
         ClientWriteResponse<ExampleTypeConfig>,
         RPCError<ExampleNodeId, ExampleNode, RaftError<ExampleNodeId, ClientWriteError<ExampleNodeId, ExampleNode>>>,
     > {
-        self.send_rpc_to_leader("cluster/change-membership", Some(req)).await
+        self.inner.change_membership(req.clone()).await
     }
 
     /// Get the metrics about the cluster.
@@ -138,111 +276,6 @@ impl ExampleClient {
         &self,
     ) -> Result<RaftMetrics<ExampleNodeId, ExampleNode>, RPCError<ExampleNodeId, ExampleNode, RaftError<ExampleNodeId>>>
     {
-        self.do_send_rpc_to_leader("cluster/metrics", None::<&()>).await
-    }
-
-    // --- Internal methods
-
-    /// Send RPC to specified node.
-    ///
-    /// It sends out a POST request if `req` is Some. Otherwise a GET request.
-    /// The remote endpoint must respond a reply in form of `Result<T, E>`.
-    /// An `Err` happened on remote will be wrapped in an [`RPCError::RemoteError`].
-    async fn do_send_rpc_to_leader<Req, Resp, Err>(
-        &self,
-        uri: &str,
-        req: Option<&Req>,
-    ) -> Result<Resp, RPCError<ExampleNodeId, ExampleNode, Err>>
-    where
-        Req: Serialize + 'static,
-        Resp: Serialize + DeserializeOwned,
-        Err: std::error::Error + Serialize + DeserializeOwned,
-    {
-        let (leader_id, url) = {
-            let t = self.leader.lock().unwrap();
-            let target_addr = &t.1;
-            (t.0, format!("http://{}/{}", target_addr, uri))
-        };
-
-        let resp = if let Some(r) = req {
-            println!(
-                ">>> client send request to {}: {}",
-                url,
-                serde_json::to_string_pretty(&r).unwrap()
-            );
-            self.inner.post(url.clone()).json(r)
-        } else {
-            println!(">>> client send request to {}", url,);
-            self.inner.get(url.clone())
-        }
-        .send()
-        .await
-        .map_err(|e| RPCError::Network(NetworkError::new(&e)))?;
-
-        let res: Result<Resp, Err> = resp.json().await.map_err(|e| RPCError::Network(NetworkError::new(&e)))?;
-        println!(
-            "<<< client recv reply from {}: {}",
-            url,
-            serde_json::to_string_pretty(&res).unwrap()
-        );
-
-        res.map_err(|e| RPCError::RemoteError(RemoteError::new(leader_id, e)))
-    }
-
-    /// Try the best to send a request to the leader.
-    ///
-    /// If the target node is not a leader, a `ForwardToLeader` error will be
-    /// returned and this client will retry at most 3 times to contact the updated leader.
-    async fn send_rpc_to_leader<Req, Resp, Err>(
-        &self,
-        uri: &str,
-        req: Option<&Req>,
-    ) -> Result<Resp, RPCError<ExampleNodeId, ExampleNode, RaftError<ExampleNodeId, Err>>>
-    where
-        Req: Serialize + 'static,
-        Resp: Serialize + DeserializeOwned,
-        Err: std::error::Error
-            + Serialize
-            + DeserializeOwned
-            + TryAsRef<ForwardToLeader<ExampleNodeId, ExampleNode>>
-            + Clone,
-    {
-        // Retry at most 3 times to find a valid leader.
-        let mut n_retry = 3;
-
-        loop {
-            let res: Result<Resp, RPCError<ExampleNodeId, ExampleNode, RaftError<ExampleNodeId, Err>>> =
-                self.do_send_rpc_to_leader(uri, req).await;
-
-            let rpc_err = match res {
-                Ok(x) => return Ok(x),
-                Err(rpc_err) => rpc_err,
-            };
-
-            if let RPCError::RemoteError(remote_err) = &rpc_err {
-                let raft_err: &RaftError<ExampleNodeId, _> = &remote_err.source;
-
-                if let Some(ForwardToLeader {
-                    leader_id: Some(leader_id),
-                    leader_node: Some(leader_node),
-                    ..
-                }) = raft_err.forward_to_leader()
-                {
-                    // Update target to the new leader.
-                    {
-                        let mut t = self.leader.lock().unwrap();
-                        let api_addr = leader_node.api_addr.clone();
-                        *t = (*leader_id, api_addr);
-                    }
-
-                    n_retry -= 1;
-                    if n_retry > 0 {
-                        continue;
-                    }
-                }
-            }
-
-            return Err(rpc_err);
-        }
+        self.inner.metrics().await
     }
 }
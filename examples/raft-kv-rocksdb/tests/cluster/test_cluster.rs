@@ -222,17 +222,10 @@ async fn test_cluster() -> Result<(), Box<dyn std::error::Error>> {
     let x = leader.consistent_read(&("foo".to_string())).await?;
     assert_eq!("wow", x);
 
-    println!("=== consistent_read `foo` on node 2 MUST return CheckIsLeaderError");
-    let x = client2.consistent_read(&("foo".to_string())).await;
-    match x {
-        Err(e) => {
-            let s = e.to_string();
-            let expect_err:String = "error occur on remote peer 2: has to forward request to: Some(1), Some(ExampleNode { rpc_addr: \"127.0.0.1:22001\", api_addr: \"127.0.0.1:21001\" })".to_string();
-
-            assert_eq!(s, expect_err);
-        }
-        Ok(_) => panic!("MUST return CheckIsLeaderError"),
-    }
+    println!("=== consistent_read `foo` on node 2 is forwarded to the leader");
+    let x = client2.consistent_read(&("foo".to_string())).await?;
+    assert_eq!("wow", x);
+    assert_eq!(Some(1), client2.inner.leader().map(|(id, _)| id));
 
     Ok(())
 }
@@ -0,0 +1,102 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Parser;
+use clap::Subcommand;
+use clap::ValueEnum;
+use openraft::AnyError;
+use openraft::MessageSummary;
+use openraft_inspect::check;
+use openraft_inspect::dump_logs;
+use openraft_inspect::summary;
+use openraft_inspect::RocksReader;
+use openraft_inspect::SledReader;
+use openraft_inspect::StoreReader;
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Backend {
+    Rocksdb,
+    Sled,
+}
+
+/// Inspect the raft log, state and snapshot of an `openraft-rocksstore` or `openraft-sledstore`
+/// store on disk.
+#[derive(Parser, Clone, Debug)]
+#[clap(author, version, about, long_about = None)]
+pub struct Opt {
+    /// The kind of the store.
+    #[clap(long, value_enum, default_value = "rocksdb")]
+    pub store: Backend,
+
+    /// The dir of the store.
+    #[clap(long)]
+    pub path: PathBuf,
+
+    #[clap(subcommand)]
+    pub cmd: Cmd,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum Cmd {
+    /// Print the vote, the log state, the last applied log id and the membership.
+    State,
+
+    /// Print the log entries with index in `[start, end)` as JSON, one entry per line.
+    Logs {
+        #[clap(long, default_value = "0")]
+        start: u64,
+
+        #[clap(long, default_value_t = u64::MAX)]
+        end: u64,
+    },
+
+    /// Print the meta of the current snapshot.
+    Snapshot,
+
+    /// Check the consistency of the log and exit with 2 if a violation is found.
+    Check,
+}
+
+fn main() -> ExitCode {
+    let opt = Opt::parse();
+
+    let res = match opt.store {
+        Backend::Rocksdb => RocksReader::open(&opt.path).and_then(|r| run(&r, &opt.cmd)),
+        Backend::Sled => SledReader::open(&opt.path).and_then(|r| run(&r, &opt.cmd)),
+    };
+
+    match res {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("inspect failed: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run<R: StoreReader>(reader: &R, cmd: &Cmd) -> Result<ExitCode, AnyError> {
+    match cmd {
+        Cmd::State => {
+            print!("{}", summary(reader)?);
+        }
+        Cmd::Logs { start, end } => {
+            dump_logs(reader, *start..*end, &mut std::io::stdout().lock())?;
+        }
+        Cmd::Snapshot => {
+            println!("{}", reader.snapshot_meta()?.summary());
+        }
+        Cmd::Check => {
+            let violations = check(reader)?;
+            if violations.is_empty() {
+                println!("ok");
+            } else {
+                for v in violations.iter() {
+                    println!("{}", v);
+                }
+                return Ok(ExitCode::from(2));
+            }
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
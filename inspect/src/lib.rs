@@ -0,0 +1,276 @@
+#![deny(unused_qualifications)]
+
+//! An offline tool to inspect the data of a store on disk, without starting raft.
+//!
+//! A store is opened read-only through a [`StoreReader`]: [`RocksReader`] reads a store of
+//! `openraft-rocksstore` and [`SledReader`] reads a store of `openraft-sledstore`. With a reader:
+//!
+//! - [`summary()`] collects the vote, the log state, the last applied log id and the effective and
+//!   committed membership.
+//! - [`dump_logs()`] writes the log entries in an index range as JSON, one entry per line.
+//! - [`check()`] runs the defensive checks that `DefensiveCheck` runs on a live store, on all of
+//!   the data, and returns the violations found.
+//!
+//! ```ignore
+//! let reader = RocksReader::open("./db")?;
+//!
+//! println!("{}", summary(&reader)?);
+//! dump_logs(&reader, 10..20, &mut std::io::stdout())?;
+//! assert!(check(&reader)?.is_empty());
+//! ```
+
+mod reader;
+#[cfg(test)] mod test;
+
+use std::fmt;
+use std::io::Write;
+use std::ops::Range;
+
+use openraft::AnyError;
+use openraft::BasicNode;
+use openraft::Entry;
+use openraft::EntryPayload;
+use openraft::LogId;
+use openraft::LogIdOptionExt;
+use openraft::Membership;
+use openraft::MessageSummary;
+use openraft::RaftTypeConfig;
+use openraft::SnapshotMeta;
+use openraft::StoredMembership;
+use openraft::Violation;
+use openraft::Vote;
+
+pub use crate::reader::Records;
+pub use crate::reader::RocksReader;
+pub use crate::reader::SledReader;
+
+/// The node id type of the bundled stores.
+pub type NodeId = u64;
+
+/// Read access to the data of a store.
+///
+/// A reader never writes to the store.
+pub trait StoreReader {
+    type C: RaftTypeConfig<NodeId = NodeId, Node = BasicNode, Entry = Entry<Self::C>>;
+
+    fn vote(&self) -> Result<Option<Vote<NodeId>>, AnyError>;
+
+    fn last_purged_log_id(&self) -> Result<Option<LogId<NodeId>>, AnyError>;
+
+    /// The log entries with the index they are stored at, from index `start`, in index order.
+    fn logs(&self, start: u64) -> Records<'_, Entry<Self::C>>;
+
+    /// The log entry with the greatest index.
+    fn last_log(&self) -> Result<Option<Entry<Self::C>>, AnyError>;
+
+    fn last_applied(&self) -> Result<Option<LogId<NodeId>>, AnyError>;
+
+    /// The committed log id the store persisted.
+    fn committed(&self) -> Result<Option<LogId<NodeId>>, AnyError>;
+
+    /// The log ids of the latest membership entries in the log, or `None` if the store has not
+    /// saved them.
+    fn membership_log_ids(&self) -> Result<Option<Vec<LogId<NodeId>>>, AnyError>;
+
+    /// The last membership applied to the state machine.
+    fn last_membership(&self) -> Result<StoredMembership<NodeId, BasicNode>, AnyError>;
+
+    /// The meta of the current snapshot.
+    fn snapshot_meta(&self) -> Result<Option<SnapshotMeta<NodeId, BasicNode>>, AnyError>;
+}
+
+/// The state of a store.
+#[derive(Debug, Clone, Default)]
+#[derive(PartialEq, Eq)]
+pub struct Summary {
+    pub vote: Option<Vote<NodeId>>,
+
+    pub last_purged_log_id: Option<LogId<NodeId>>,
+
+    /// The last log id in the log, or the last purged log id if there is no log.
+    pub last_log_id: Option<LogId<NodeId>>,
+
+    pub last_applied: Option<LogId<NodeId>>,
+
+    /// The persisted committed log id, or the last applied log id if it is greater.
+    pub committed: Option<LogId<NodeId>>,
+
+    /// The last membership config in the log, or the applied one if there is none after it.
+    pub effective_membership: StoredMembership<NodeId, BasicNode>,
+
+    /// The last membership config that is committed.
+    pub committed_membership: StoredMembership<NodeId, BasicNode>,
+
+    pub snapshot: Option<SnapshotMeta<NodeId, BasicNode>>,
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "vote: {}", display_opt(&self.vote))?;
+        writeln!(f, "last_purged_log_id: {}", display_opt(&self.last_purged_log_id))?;
+        writeln!(f, "last_log_id: {}", display_opt(&self.last_log_id))?;
+        writeln!(f, "last_applied: {}", display_opt(&self.last_applied))?;
+        writeln!(f, "committed: {}", display_opt(&self.committed))?;
+        writeln!(f, "effective_membership: {}", self.effective_membership.summary())?;
+        writeln!(f, "committed_membership: {}", self.committed_membership.summary())?;
+        writeln!(f, "snapshot: {}", self.snapshot.summary())?;
+        Ok(())
+    }
+}
+
+fn display_opt<T: fmt::Display>(v: &Option<T>) -> String {
+    match v {
+        Some(x) => x.to_string(),
+        None => "None".to_string(),
+    }
+}
+
+/// Collect the state of a store.
+pub fn summary<R: StoreReader>(reader: &R) -> Result<Summary, AnyError> {
+    let last_purged_log_id = reader.last_purged_log_id()?;
+    let last_applied = reader.last_applied()?;
+
+    // A store may not save the committed log id, or save it lazily: an applied entry is committed.
+    let committed = std::cmp::max(reader.committed()?, last_applied);
+
+    let last_log_id = match reader.last_log()? {
+        Some(ent) => Some(ent.log_id),
+        None => last_purged_log_id,
+    };
+
+    // Membership configs that are not applied are only in the log.
+    let mut committed_membership = reader.last_membership()?;
+    let mut effective_membership = committed_membership.clone();
+
+    for (log_id, m) in unapplied_memberships(reader, last_applied)? {
+        let stored = StoredMembership::new(Some(log_id), m);
+        if Some(log_id) <= committed {
+            committed_membership = stored.clone();
+        }
+        effective_membership = stored;
+    }
+
+    Ok(Summary {
+        vote: reader.vote()?,
+        last_purged_log_id,
+        last_log_id,
+        last_applied,
+        committed,
+        effective_membership,
+        committed_membership,
+        snapshot: reader.snapshot_meta()?,
+    })
+}
+
+/// The membership configs in the log after `last_applied`, in log order.
+///
+/// If the store saved the membership log ids, only these entries are read, otherwise the log is
+/// scanned. A saved log id that does not match the entry in the log is ignored.
+fn unapplied_memberships<R: StoreReader>(
+    reader: &R,
+    last_applied: Option<LogId<NodeId>>,
+) -> Result<Vec<(LogId<NodeId>, Membership<NodeId, BasicNode>)>, AnyError> {
+    let mut res = vec![];
+
+    let log_ids = match reader.membership_log_ids()? {
+        Some(x) => x,
+        None => {
+            for r in reader.logs(last_applied.next_index()) {
+                let (_index, ent) = r?;
+                if let EntryPayload::Membership(m) = ent.payload {
+                    res.push((ent.log_id, m));
+                }
+            }
+            return Ok(res);
+        }
+    };
+
+    for log_id in log_ids.into_iter().filter(|x| Some(*x) > last_applied) {
+        let ent = match reader.logs(log_id.index).next() {
+            Some(r) => r?.1,
+            None => continue,
+        };
+
+        if ent.log_id != log_id {
+            continue;
+        }
+        if let EntryPayload::Membership(m) = ent.payload {
+            res.push((log_id, m));
+        }
+    }
+
+    Ok(res)
+}
+
+/// Write the log entries with index in `range` to `w` as JSON, one entry per line.
+///
+/// It returns the number of entries written.
+pub fn dump_logs<R: StoreReader>(reader: &R, range: Range<u64>, w: &mut impl Write) -> Result<u64, AnyError> {
+    let mut n = 0;
+
+    for res in reader.logs(range.start) {
+        let (index, ent) = res?;
+        if index >= range.end {
+            break;
+        }
+
+        serde_json::to_writer(&mut *w, &ent).map_err(|e| AnyError::new(&e))?;
+        writeln!(w).map_err(|e| AnyError::new(&e))?;
+        n += 1;
+    }
+
+    Ok(n)
+}
+
+/// Check the consistency of the log of a store, as `DefensiveCheck` does for a live store:
+///
+/// - An entry is stored at the index of its log id.
+/// - The log is consecutive, starting right after the last purged log id.
+/// - There is no dirty log: the log id of an entry is greater than the log id of every entry before
+///   it and greater than the last applied log id, if its index is greater.
+///
+/// It returns every violation found.
+pub fn check<R: StoreReader>(reader: &R) -> Result<Vec<Violation<NodeId>>, AnyError> {
+    let mut violations = vec![];
+
+    let last_applied = reader.last_applied()?;
+    let mut prev = reader.last_purged_log_id()?;
+
+    for res in reader.logs(0) {
+        let (index, ent) = res?;
+        let log_id = ent.log_id;
+
+        if index != log_id.index {
+            violations.push(Violation::LogIndexNotFound {
+                want: index,
+                got: Some(log_id.index),
+            });
+        }
+
+        if log_id.index != prev.next_index() {
+            violations.push(Violation::LogsNonConsecutive { prev, next: log_id });
+        }
+
+        if let Some(p) = prev {
+            if log_id < p {
+                violations.push(Violation::DirtyLog {
+                    higher_index_log_id: log_id,
+                    lower_index_log_id: p,
+                });
+            }
+        }
+
+        if let Some(applied) = last_applied {
+            if log_id.index > applied.index && log_id < applied {
+                violations.push(Violation::DirtyLog {
+                    higher_index_log_id: log_id,
+                    lower_index_log_id: applied,
+                });
+            }
+        }
+
+        prev = Some(log_id);
+    }
+
+    Ok(violations)
+}
@@ -0,0 +1,206 @@
+use std::path::Path;
+
+use openraft::AnyError;
+use openraft::BasicNode;
+use openraft::Entry;
+use openraft::LogId;
+use openraft::SnapshotMeta;
+use openraft::StoredMembership;
+use openraft::Vote;
+use rocksdb::ColumnFamily;
+use rocksdb::Direction;
+use rocksdb::IteratorMode;
+use rocksdb::Options;
+use rocksdb::DB;
+use serde::de::DeserializeOwned;
+
+use crate::NodeId;
+use crate::StoreReader;
+
+/// An iterator of the records of a store, with the index they are stored at.
+pub type Records<'a, T> = Box<dyn Iterator<Item = Result<(u64, T), AnyError>> + 'a>;
+
+/// A store of `openraft-rocksstore`.
+///
+/// The db is opened read-only, it can be inspected while the store is in use.
+pub struct RocksReader {
+    db: DB,
+}
+
+impl RocksReader {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, AnyError> {
+        let cfs = ["meta", "sm_meta", "sm_data", "logs"];
+        let db = DB::open_cf_for_read_only(&Options::default(), path, cfs, false).map_err(|e| AnyError::new(&e))?;
+        Ok(Self { db })
+    }
+
+    fn cf(&self, name: &str) -> Result<&ColumnFamily, AnyError> {
+        self.db.cf_handle(name).ok_or_else(|| AnyError::error(format!("column family not found: {}", name)))
+    }
+
+    fn get<T: DeserializeOwned>(&self, cf: &str, key: &str) -> Result<Option<T>, AnyError> {
+        let v = self.db.get_cf(self.cf(cf)?, key).map_err(|e| AnyError::new(&e))?;
+        let t = v.map(|x| openraft_rocksstore::Codec::decode(&x)).transpose().map_err(|e| e.add_context(|| key))?;
+        Ok(t)
+    }
+}
+
+impl StoreReader for RocksReader {
+    type C = openraft_rocksstore::Config;
+
+    fn vote(&self) -> Result<Option<Vote<NodeId>>, AnyError> {
+        self.get("meta", "vote")
+    }
+
+    fn last_purged_log_id(&self) -> Result<Option<LogId<NodeId>>, AnyError> {
+        self.get("meta", "last_purged_log_id")
+    }
+
+    fn committed(&self) -> Result<Option<LogId<NodeId>>, AnyError> {
+        Ok(self.get::<Option<_>>("meta", "committed")?.flatten())
+    }
+
+    fn membership_log_ids(&self) -> Result<Option<Vec<LogId<NodeId>>>, AnyError> {
+        self.get("meta", "membership_log_ids")
+    }
+
+    fn logs(&self, start: u64) -> Records<'_, Entry<Self::C>> {
+        let cf = match self.cf("logs") {
+            Ok(x) => x,
+            Err(e) => return Box::new(std::iter::once(Err(e))),
+        };
+
+        let key = start.to_be_bytes();
+        let it = self.db.iterator_cf(cf, IteratorMode::From(&key, Direction::Forward));
+        Box::new(it.map(|res| {
+            let (k, v) = res.map_err(|e| AnyError::new(&e))?;
+            decode_log(&k, &v, openraft_rocksstore::Codec::decode)
+        }))
+    }
+
+    fn last_log(&self) -> Result<Option<Entry<Self::C>>, AnyError> {
+        let mut it = self.db.iterator_cf(self.cf("logs")?, IteratorMode::End);
+        let last = match it.next() {
+            None => return Ok(None),
+            Some(res) => res.map_err(|e| AnyError::new(&e))?,
+        };
+
+        let (_index, ent) = decode_log(&last.0, &last.1, openraft_rocksstore::Codec::decode)?;
+        Ok(Some(ent))
+    }
+
+    fn last_applied(&self) -> Result<Option<LogId<NodeId>>, AnyError> {
+        self.get("sm_meta", "last_applied_log")
+    }
+
+    fn last_membership(&self) -> Result<StoredMembership<NodeId, BasicNode>, AnyError> {
+        Ok(self.get("sm_meta", "last_membership")?.unwrap_or_default())
+    }
+
+    fn snapshot_meta(&self) -> Result<Option<SnapshotMeta<NodeId, BasicNode>>, AnyError> {
+        self.get("meta", "snapshot_meta")
+    }
+}
+
+/// A store of `openraft-sledstore`: trees `store`, `logs`, `state_machine` and `data`.
+///
+/// sled can not open a db read-only, and `sled::open()` takes the lock of the db: the node must be
+/// stopped before its store is inspected, otherwise opening fails. The reader itself never writes.
+pub struct SledReader {
+    db: sled::Db,
+}
+
+impl SledReader {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, AnyError> {
+        let path = path.as_ref();
+
+        // sled creates a db if there is none.
+        if !path.exists() {
+            return Err(AnyError::error(format!("sled db not found: {}", path.display())));
+        }
+
+        let db = sled::open(path).map_err(|e| {
+            AnyError::new(&e).add_context(|| format!("open sled db {}, is the node stopped?", path.display()))
+        })?;
+        Ok(Self { db })
+    }
+
+    fn tree(&self, name: &str) -> Result<sled::Tree, AnyError> {
+        if !self.db.tree_names().iter().any(|x| x.as_ref() == name.as_bytes()) {
+            return Err(AnyError::error(format!("tree not found: {}", name)));
+        }
+        self.db.open_tree(name).map_err(|e| AnyError::new(&e))
+    }
+
+    fn get<T: DeserializeOwned>(&self, tree: &str, key: &str) -> Result<Option<T>, AnyError> {
+        let v = self.tree(tree)?.get(key).map_err(|e| AnyError::new(&e))?;
+        let t = v.map(|x| openraft_sledstore::Codec::decode(&x)).transpose().map_err(|e| e.add_context(|| key))?;
+        Ok(t)
+    }
+}
+
+impl StoreReader for SledReader {
+    type C = openraft_sledstore::ExampleTypeConfig;
+
+    fn vote(&self) -> Result<Option<Vote<NodeId>>, AnyError> {
+        self.get("store", "vote")
+    }
+
+    fn last_purged_log_id(&self) -> Result<Option<LogId<NodeId>>, AnyError> {
+        self.get("store", "last_purged_log_id")
+    }
+
+    fn committed(&self) -> Result<Option<LogId<NodeId>>, AnyError> {
+        Ok(self.get::<Option<_>>("store", "committed")?.flatten())
+    }
+
+    fn membership_log_ids(&self) -> Result<Option<Vec<LogId<NodeId>>>, AnyError> {
+        self.get("store", "membership_log_ids")
+    }
+
+    fn logs(&self, start: u64) -> Records<'_, Entry<Self::C>> {
+        let tree = match self.tree("logs") {
+            Ok(x) => x,
+            Err(e) => return Box::new(std::iter::once(Err(e))),
+        };
+
+        Box::new(tree.range(start.to_be_bytes()..).map(|res| {
+            let (k, v) = res.map_err(|e| AnyError::new(&e))?;
+            decode_log(&k, &v, openraft_sledstore::Codec::decode)
+        }))
+    }
+
+    fn last_log(&self) -> Result<Option<Entry<Self::C>>, AnyError> {
+        let last = self.tree("logs")?.last().map_err(|e| AnyError::new(&e))?;
+        match last {
+            None => Ok(None),
+            Some((k, v)) => {
+                let (_index, ent) = decode_log(&k, &v, openraft_sledstore::Codec::decode)?;
+                Ok(Some(ent))
+            }
+        }
+    }
+
+    fn last_applied(&self) -> Result<Option<LogId<NodeId>>, AnyError> {
+        self.get("state_machine", "last_applied_log")
+    }
+
+    fn last_membership(&self) -> Result<StoredMembership<NodeId, BasicNode>, AnyError> {
+        Ok(self.get("state_machine", "last_membership")?.unwrap_or_default())
+    }
+
+    fn snapshot_meta(&self) -> Result<Option<SnapshotMeta<NodeId, BasicNode>>, AnyError> {
+        let snapshot: Option<openraft_sledstore::ExampleSnapshot> = self.get("store", "snapshot")?;
+        Ok(snapshot.map(|x| x.meta))
+    }
+}
+
+/// Decode a log entry and the index it is stored at: log entries are keyed by the big endian
+/// encoded index.
+fn decode_log<T>(key: &[u8], value: &[u8], decode: fn(&[u8]) -> Result<T, AnyError>) -> Result<(u64, T), AnyError> {
+    let b: [u8; 8] = key.try_into().map_err(|_| AnyError::error(format!("invalid log key: {:?}", key)))?;
+    let index = u64::from_be_bytes(b);
+
+    let ent = decode(value).map_err(|e| e.add_context(|| format!("log at index {}", index)))?;
+    Ok((index, ent))
+}
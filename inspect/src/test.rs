@@ -0,0 +1,198 @@
+use std::sync::Arc;
+
+use openraft::BasicNode;
+use openraft::CommittedLeaderId;
+use openraft::Entry;
+use openraft::EntryPayload;
+use openraft::LogId;
+use openraft::Membership;
+use openraft::RaftStorage;
+use openraft::RaftTypeConfig;
+use openraft::StoredMembership;
+use openraft::Violation;
+use openraft::Vote;
+use openraft_rocksstore::RocksRequest;
+use openraft_rocksstore::RocksStore;
+use openraft_sledstore::ExampleRequest;
+use openraft_sledstore::SledStore;
+use tempfile::TempDir;
+
+use crate::check;
+use crate::dump_logs;
+use crate::summary;
+use crate::NodeId;
+use crate::RocksReader;
+use crate::SledReader;
+use crate::StoreReader;
+use crate::Summary;
+
+fn log_id(term: u64, index: u64) -> LogId<NodeId> {
+    LogId::new(CommittedLeaderId::new(term, 0), index)
+}
+
+fn ent<C>(term: u64, index: u64, payload: EntryPayload<C>) -> Entry<C>
+where C: RaftTypeConfig<NodeId = NodeId> {
+    Entry {
+        log_id: log_id(term, index),
+        payload,
+    }
+}
+
+fn membership(ids: &[NodeId]) -> Membership<NodeId, BasicNode> {
+    Membership::new(vec![ids.iter().copied().collect()], ())
+}
+
+/// Write a store: logs `[2, 6]`, of which `[1, 4]` are committed, `[1, 3]` are applied and `[1, 1]`
+/// are purged.
+///
+/// The applied membership is at index 2, the committed one at index 4 and the uncommitted one at
+/// index 6.
+async fn build<C, S>(store: &mut S, req: fn(&str) -> C::D) -> anyhow::Result<()>
+where
+    C: RaftTypeConfig<NodeId = NodeId, Node = BasicNode, Entry = Entry<C>>,
+    S: RaftStorage<C>,
+{
+    store.save_vote(&Vote::new(2, 1)).await?;
+
+    let entries = vec![
+        ent(1, 1, EntryPayload::Blank),
+        ent(1, 2, EntryPayload::Membership(membership(&[1, 2]))),
+        ent(1, 3, EntryPayload::Normal(req("foo"))),
+        ent(2, 4, EntryPayload::Membership(membership(&[1, 2, 3]))),
+        ent(2, 5, EntryPayload::Normal(req("bar"))),
+        ent(2, 6, EntryPayload::Membership(membership(&[1, 2, 3, 4]))),
+    ];
+
+    store.append_to_log(&entries).await?;
+    store.save_committed(Some(log_id(2, 4))).await?;
+    store.apply_to_state_machine(&entries[..3]).await?;
+    store.purge_logs_upto(log_id(1, 1)).await?;
+
+    Ok(())
+}
+
+fn rocks_req(key: &str) -> RocksRequest {
+    RocksRequest::Set {
+        key: key.to_string(),
+        value: "v".to_string(),
+    }
+}
+
+fn sled_req(key: &str) -> ExampleRequest {
+    ExampleRequest::Set {
+        key: key.to_string(),
+        value: "v".to_string(),
+    }
+}
+
+fn assert_store<R: StoreReader>(reader: &R) -> anyhow::Result<()> {
+    let got = summary(reader)?;
+    assert_eq!(
+        Summary {
+            vote: Some(Vote::new(2, 1)),
+            last_purged_log_id: Some(log_id(1, 1)),
+            last_log_id: Some(log_id(2, 6)),
+            last_applied: Some(log_id(1, 3)),
+            committed: Some(log_id(2, 4)),
+            effective_membership: StoredMembership::new(Some(log_id(2, 6)), membership(&[1, 2, 3, 4])),
+            committed_membership: StoredMembership::new(Some(log_id(2, 4)), membership(&[1, 2, 3])),
+            snapshot: None,
+        },
+        got
+    );
+
+    let mut buf = vec![];
+    let n = dump_logs(reader, 3..5, &mut buf)?;
+    assert_eq!(2, n);
+
+    let lines = String::from_utf8(buf)?;
+    let log_ids = lines
+        .lines()
+        .map(|l| serde_json::from_str::<Entry<R::C>>(l).map(|x| x.log_id))
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(vec![log_id(1, 3), log_id(2, 4)], log_ids);
+
+    assert!(check(reader)?.is_empty());
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_inspect_rocksdb() -> anyhow::Result<()> {
+    let td = TempDir::new()?;
+    {
+        let mut store = RocksStore::new(td.path()).await;
+        build(&mut store, rocks_req).await?;
+    }
+
+    let reader = RocksReader::open(td.path())?;
+    assert_store(&reader)?;
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_inspect_sled() -> anyhow::Result<()> {
+    let td = TempDir::new()?;
+    {
+        let db = Arc::new(sled::open(td.path())?);
+        let mut store = SledStore::new(db.clone()).await;
+        build(&mut store, sled_req).await?;
+        db.flush()?;
+    }
+
+    let reader = SledReader::open(td.path())?;
+    assert_store(&reader)?;
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_open_sled_in_use() -> anyhow::Result<()> {
+    let td = TempDir::new()?;
+
+    let db = Arc::new(sled::open(td.path())?);
+    let mut store = SledStore::new(db.clone()).await;
+    build(&mut store, sled_req).await?;
+    db.flush()?;
+
+    // The store still holds the lock of the db.
+    let res = SledReader::open(td.path());
+    assert!(res.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_open_sled_not_exist() -> anyhow::Result<()> {
+    let td = TempDir::new()?;
+    let path = td.path().join("foo");
+
+    let res = SledReader::open(&path);
+    assert!(res.is_err());
+    assert!(!path.exists(), "no db is created");
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_check_non_consecutive_logs() -> anyhow::Result<()> {
+    let td = TempDir::new()?;
+    {
+        let mut store = RocksStore::new(td.path()).await;
+        build(&mut store, rocks_req).await?;
+        store.append_to_log(&[ent(2, 8, EntryPayload::Blank)]).await?;
+    }
+
+    let reader = RocksReader::open(td.path())?;
+    let violations = check(&reader)?;
+    assert_eq!(
+        vec![Violation::LogsNonConsecutive {
+            prev: Some(log_id(2, 6)),
+            next: log_id(2, 8),
+        }],
+        violations
+    );
+
+    Ok(())
+}
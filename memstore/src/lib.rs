@@ -6,10 +6,18 @@
 use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::fmt::Debug;
+use std::io;
 use std::io::Cursor;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
 use std::ops::RangeBounds;
+use std::pin::Pin;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::task::Context;
+use std::task::Poll;
 
 use openraft::async_trait::async_trait;
 use openraft::storage::LogState;
@@ -21,6 +29,9 @@ use openraft::EntryPayload;
 use openraft::LogId;
 use openraft::RaftStorage;
 use openraft::RaftStorageDebug;
+use openraft::SessionResponse;
+use openraft::Sessions;
+use openraft::SnapshotId;
 use openraft::SnapshotMeta;
 use openraft::StorageError;
 use openraft::StorageIOError;
@@ -28,6 +39,10 @@ use openraft::StoredMembership;
 use openraft::Vote;
 use serde::Deserialize;
 use serde::Serialize;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncSeek;
+use tokio::io::AsyncWrite;
+use tokio::io::ReadBuf;
 use tokio::sync::RwLock;
 
 /// The application data request type which the `MemStore` works with.
@@ -64,8 +79,8 @@ impl IntoMemClientRequest<ClientRequest> for ClientRequest {
 }
 
 /// The application data response type which the `MemStore` works with.
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct ClientResponse(Option<String>);
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ClientResponse(pub Option<String>);
 
 pub type MemNodeId = u64;
 
@@ -83,19 +98,127 @@ pub struct MemStoreSnapshot {
     pub data: Vec<u8>,
 }
 
+/// The snapshot data type which the `MemStore` works with: a buffer in memory.
+///
+/// Clones share the buffer, each with its own position. The store keeps a clone of the snapshot
+/// being received, so that the received data outlives a restart of the `Raft` using the store, and
+/// the transfer can be resumed.
+#[derive(Debug, Default, Clone)]
+pub struct MemSnapshotData {
+    buf: Arc<Mutex<Vec<u8>>>,
+    pos: u64,
+}
+
+impl MemSnapshotData {
+    pub fn new(data: Vec<u8>) -> Self {
+        Self {
+            buf: Arc::new(Mutex::new(data)),
+            pos: 0,
+        }
+    }
+
+    /// Returns the size of the data.
+    pub fn len(&self) -> usize {
+        self.buf.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the data, which is copied if the buffer is shared with another clone.
+    pub fn into_inner(self) -> Vec<u8> {
+        match Arc::try_unwrap(self.buf) {
+            Ok(buf) => buf.into_inner().unwrap(),
+            Err(shared) => {
+                let buf = shared.lock().unwrap();
+                buf.clone()
+            }
+        }
+    }
+}
+
+impl AsyncRead for MemSnapshotData {
+    fn poll_read(self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let data = this.buf.lock().unwrap();
+
+        let mut c = Cursor::new(&data[..]);
+        c.set_position(this.pos);
+        let n = c.read(buf.initialize_unfilled())?;
+        buf.advance(n);
+
+        this.pos = c.position();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for MemSnapshotData {
+    fn poll_write(self: Pin<&mut Self>, _cx: &mut Context<'_>, src: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let mut data = this.buf.lock().unwrap();
+
+        let mut c = Cursor::new(&mut *data);
+        c.set_position(this.pos);
+        let n = c.write(src)?;
+
+        this.pos = c.position();
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncSeek for MemSnapshotData {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        let this = self.get_mut();
+        let data = this.buf.lock().unwrap();
+
+        let mut c = Cursor::new(&data[..]);
+        c.set_position(this.pos);
+        this.pos = c.seek(position)?;
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Poll::Ready(Ok(self.pos))
+    }
+}
+
+/// A client session expires if no request of it is applied in this many log entries.
+pub const SESSION_MAX_AGE: u64 = 10_000;
+
 /// The state machine of the `MemStore`.
-#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct MemStoreStateMachine {
     pub last_applied_log: Option<LogId<MemNodeId>>,
 
     pub last_membership: StoredMembership<MemNodeId, ()>,
 
-    /// A mapping of client IDs to their state info.
-    pub client_serial_responses: HashMap<String, (u64, Option<String>)>,
+    /// The sessions of clients by ID, with the response of the last applied request.
+    pub sessions: Sessions<String, Option<String>>,
+
     /// The current status of a client by ID.
     pub client_status: HashMap<String, String>,
 }
 
+impl Default for MemStoreStateMachine {
+    fn default() -> Self {
+        Self {
+            last_applied_log: None,
+            last_membership: StoredMembership::default(),
+            sessions: Sessions::new(SESSION_MAX_AGE),
+            client_status: HashMap::new(),
+        }
+    }
+}
+
 /// An in-memory storage system implementing the `RaftStorage` trait.
 pub struct MemStore {
     last_purged_log_id: RwLock<Option<LogId<MemNodeId>>>,
@@ -109,10 +232,22 @@ pub struct MemStore {
     /// The current hard state.
     vote: RwLock<Option<Vote<MemNodeId>>>,
 
+    /// The last committed log id.
+    committed: RwLock<Option<LogId<MemNodeId>>>,
+
+    /// The log id of the membership that removes this node from the cluster.
+    removed_by: RwLock<Option<LogId<MemNodeId>>>,
+
+    /// Log ids of the membership entries in `log`, keyed by log index.
+    membership_log_ids: RwLock<BTreeMap<u64, LogId<MemNodeId>>>,
+
     snapshot_idx: Arc<Mutex<u64>>,
 
     /// The current snapshot.
     current_snapshot: RwLock<Option<MemStoreSnapshot>>,
+
+    /// The id and the data of the snapshot being received, to resume receiving it.
+    receiving_snapshot: Mutex<Option<(SnapshotId, MemSnapshotData)>>,
 }
 
 impl MemStore {
@@ -127,8 +262,12 @@ impl MemStore {
             log,
             sm,
             vote: RwLock::new(None),
+            committed: RwLock::new(None),
+            removed_by: RwLock::new(None),
+            membership_log_ids: RwLock::new(BTreeMap::new()),
             snapshot_idx: Arc::new(Mutex::new(0)),
             current_snapshot,
+            receiving_snapshot: Mutex::new(None),
         }
     }
 
@@ -184,9 +323,9 @@ impl RaftLogReader<Config> for Arc<MemStore> {
 }
 
 #[async_trait]
-impl RaftSnapshotBuilder<Config, Cursor<Vec<u8>>> for Arc<MemStore> {
+impl RaftSnapshotBuilder<Config, MemSnapshotData> for Arc<MemStore> {
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn build_snapshot(&mut self) -> Result<Snapshot<MemNodeId, (), Cursor<Vec<u8>>>, StorageError<MemNodeId>> {
+    async fn build_snapshot(&mut self) -> Result<Snapshot<MemNodeId, (), MemSnapshotData>, StorageError<MemNodeId>> {
         let data;
         let last_applied_log;
         let last_membership;
@@ -234,14 +373,14 @@ impl RaftSnapshotBuilder<Config, Cursor<Vec<u8>>> for Arc<MemStore> {
 
         Ok(Snapshot {
             meta,
-            snapshot: Box::new(Cursor::new(data)),
+            snapshot: Box::new(MemSnapshotData::new(data)),
         })
     }
 }
 
 #[async_trait]
 impl RaftStorage<Config> for Arc<MemStore> {
-    type SnapshotData = Cursor<Vec<u8>>;
+    type SnapshotData = MemSnapshotData;
 
     #[tracing::instrument(level = "trace", skip(self))]
     async fn save_vote(&mut self, vote: &Vote<MemNodeId>) -> Result<(), StorageError<MemNodeId>> {
@@ -256,6 +395,31 @@ impl RaftStorage<Config> for Arc<MemStore> {
         Ok(*self.vote.read().await)
     }
 
+    async fn save_committed(&mut self, committed: Option<LogId<MemNodeId>>) -> Result<(), StorageError<MemNodeId>> {
+        let mut c = self.committed.write().await;
+        *c = committed;
+        Ok(())
+    }
+
+    async fn read_committed(&mut self) -> Result<Option<LogId<MemNodeId>>, StorageError<MemNodeId>> {
+        Ok(*self.committed.read().await)
+    }
+
+    async fn save_removed_by(&mut self, removed_by: Option<LogId<MemNodeId>>) -> Result<(), StorageError<MemNodeId>> {
+        let mut r = self.removed_by.write().await;
+        *r = removed_by;
+        Ok(())
+    }
+
+    async fn read_removed_by(&mut self) -> Result<Option<LogId<MemNodeId>>, StorageError<MemNodeId>> {
+        Ok(*self.removed_by.read().await)
+    }
+
+    async fn read_membership_log_ids(&mut self) -> Result<Option<Vec<LogId<MemNodeId>>>, StorageError<MemNodeId>> {
+        let ids = self.membership_log_ids.read().await;
+        Ok(Some(ids.values().copied().collect()))
+    }
+
     async fn last_applied_state(
         &mut self,
     ) -> Result<(Option<LogId<MemNodeId>>, StoredMembership<MemNodeId, ()>), StorageError<MemNodeId>> {
@@ -276,6 +440,11 @@ impl RaftStorage<Config> for Arc<MemStore> {
             }
         }
 
+        {
+            let mut ids = self.membership_log_ids.write().await;
+            ids.split_off(&log_id.index);
+        }
+
         Ok(())
     }
 
@@ -298,14 +467,32 @@ impl RaftStorage<Config> for Arc<MemStore> {
             }
         }
 
+        {
+            let mut ids = self.membership_log_ids.write().await;
+            *ids = ids.split_off(&(log_id.index + 1));
+        }
+
         Ok(())
     }
 
     #[tracing::instrument(level = "trace", skip(self, entries))]
     async fn append_to_log(&mut self, entries: &[Entry<Config>]) -> Result<(), StorageError<MemNodeId>> {
         let mut log = self.log.write().await;
+        let mut ids = self.membership_log_ids.write().await;
         for entry in entries {
             log.insert(entry.log_id.index, (*entry).clone());
+
+            if let EntryPayload::Membership(_) = entry.payload {
+                ids.insert(entry.log_id.index, entry.log_id);
+            } else {
+                ids.remove(&entry.log_id.index);
+            }
+        }
+
+        // Only the last 2 are used. Keep one more for the case the last one is deleted by a conflict.
+        while ids.len() > 3 {
+            let first = *ids.keys().next().unwrap();
+            ids.remove(&first);
         }
         Ok(())
     }
@@ -327,14 +514,20 @@ impl RaftStorage<Config> for Arc<MemStore> {
             match entry.payload {
                 EntryPayload::Blank => res.push(ClientResponse(None)),
                 EntryPayload::Normal(ref data) => {
-                    if let Some((serial, r)) = sm.client_serial_responses.get(&data.client) {
-                        if serial == &data.serial {
-                            res.push(ClientResponse(r.clone()));
-                            continue;
-                        }
-                    }
-                    let previous = sm.client_status.insert(data.client.clone(), data.status.clone());
-                    sm.client_serial_responses.insert(data.client.clone(), (data.serial, previous.clone()));
+                    let MemStoreStateMachine {
+                        sessions,
+                        client_status,
+                        ..
+                    } = &mut *sm;
+
+                    let r = sessions.apply(entry.log_id.index, &data.client, data.serial, || {
+                        client_status.insert(data.client.clone(), data.status.clone())
+                    });
+
+                    let previous = match r {
+                        SessionResponse::Applied(previous) => previous,
+                        SessionResponse::Duplicate(previous) => previous,
+                    };
                     res.push(ClientResponse(previous));
                 }
                 EntryPayload::Membership(ref mem) => {
@@ -348,7 +541,30 @@ impl RaftStorage<Config> for Arc<MemStore> {
 
     #[tracing::instrument(level = "trace", skip(self))]
     async fn begin_receiving_snapshot(&mut self) -> Result<Box<Self::SnapshotData>, StorageError<MemNodeId>> {
-        Ok(Box::new(Cursor::new(Vec::new())))
+        Ok(Box::new(MemSnapshotData::default()))
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn resume_receiving_snapshot(
+        &mut self,
+        meta: &SnapshotMeta<MemNodeId, ()>,
+    ) -> Result<(Box<Self::SnapshotData>, u64), StorageError<MemNodeId>> {
+        let mut receiving = self.receiving_snapshot.lock().unwrap();
+
+        match &*receiving {
+            Some((id, data)) if id == &meta.snapshot_id => {
+                let mut data = data.clone();
+                data.pos = data.len() as u64;
+                let received = data.pos;
+                Ok((Box::new(data), received))
+            }
+            _ => {
+                // Only one snapshot is received at a time: the data of another one is discarded.
+                let data = MemSnapshotData::default();
+                *receiving = Some((meta.snapshot_id.clone(), data.clone()));
+                Ok((Box::new(data), 0))
+            }
+        }
     }
 
     #[tracing::instrument(level = "trace", skip(self, snapshot))]
@@ -357,10 +573,9 @@ impl RaftStorage<Config> for Arc<MemStore> {
         meta: &SnapshotMeta<MemNodeId, ()>,
         snapshot: Box<Self::SnapshotData>,
     ) -> Result<(), StorageError<MemNodeId>> {
-        tracing::info!(
-            { snapshot_size = snapshot.get_ref().len() },
-            "decoding snapshot for installation"
-        );
+        tracing::info!({ snapshot_size = snapshot.len() }, "decoding snapshot for installation");
+
+        *self.receiving_snapshot.lock().unwrap() = None;
 
         let new_snapshot = MemStoreSnapshot {
             meta: meta.clone(),
@@ -397,7 +612,7 @@ impl RaftStorage<Config> for Arc<MemStore> {
                 let data = snapshot.data.clone();
                 Ok(Some(Snapshot {
                     meta: snapshot.meta.clone(),
-                    snapshot: Box::new(Cursor::new(data)),
+                    snapshot: Box::new(MemSnapshotData::new(data)),
                 }))
             }
             None => Ok(None),
@@ -3,9 +3,14 @@ use std::sync::Arc;
 use async_trait::async_trait;
 use openraft::testing::StoreBuilder;
 use openraft::testing::Suite;
+use openraft::RaftStorage;
+use openraft::SnapshotMeta;
 use openraft::StorageError;
+use tokio::io::AsyncWriteExt;
 
+use crate::ClientRequest;
 use crate::Config;
+use crate::IntoMemClientRequest;
 use crate::MemNodeId;
 use crate::MemStore;
 
@@ -16,6 +21,10 @@ impl StoreBuilder<Config, Arc<MemStore>> for MemBuilder {
         let store = MemStore::new_async().await;
         Ok(((), store))
     }
+
+    fn sample_app_data(&self, index: u64) -> Option<ClientRequest> {
+        Some(ClientRequest::make_request("foo", index))
+    }
 }
 
 /// To customize a builder:
@@ -45,3 +54,38 @@ pub fn test_mem_store() -> Result<(), StorageError<MemNodeId>> {
     Suite::test_all(MemBuilder {})?;
     Ok(())
 }
+
+/// The data of a snapshot being received is kept by the store, and receiving it can be resumed.
+#[tokio::test]
+async fn test_resume_receiving_snapshot() -> Result<(), StorageError<MemNodeId>> {
+    let mut store = MemStore::new_async().await;
+
+    let meta = |id: &str| SnapshotMeta {
+        snapshot_id: id.to_string(),
+        ..Default::default()
+    };
+
+    let (mut data, received) = store.resume_receiving_snapshot(&meta("ss1")).await?;
+    assert_eq!(0, received);
+    data.write_all(b"foo").await.unwrap();
+    drop(data);
+
+    tracing::info!("--- resume receiving the same snapshot");
+    {
+        let (mut data, received) = store.resume_receiving_snapshot(&meta("ss1")).await?;
+        assert_eq!(3, received);
+        data.write_all(b"bar").await.unwrap();
+        assert_eq!(b"foobar".to_vec(), data.into_inner());
+    }
+
+    tracing::info!("--- receiving another snapshot discards the data");
+    {
+        let (_data, received) = store.resume_receiving_snapshot(&meta("ss2")).await?;
+        assert_eq!(0, received);
+
+        let (_data, received) = store.resume_receiving_snapshot(&meta("ss1")).await?;
+        assert_eq!(0, received);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Parser;
+use clap::ValueEnum;
+use openraft_migrate07::dry_run;
+use openraft_migrate07::migrate;
+use openraft_migrate07::MigrateError;
+use openraft_migrate07::MigrateOptions;
+use openraft_migrate07::Report;
+use openraft_migrate07::RocksSource07;
+use openraft_migrate07::SledSource07;
+use openraft_migrate07::Source07;
+use openraft_rocksstore_compat07::Config;
+use openraft_rocksstore_compat07::RocksStore;
+use tracing_subscriber::EnvFilter;
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Backend {
+    Rocksdb,
+    Sled,
+}
+
+/// Rewrite the data of an openraft-0.7 store in the openraft-0.8 format.
+///
+/// The migrated data is written to a new RocksDB store, in the format of `rocksstore-compat07`.
+#[derive(Parser, Clone, Debug)]
+#[clap(author, version, about, long_about = None)]
+pub struct Opt {
+    /// The kind of the v0.7 store.
+    #[clap(long, value_enum, default_value = "rocksdb")]
+    pub from: Backend,
+
+    /// The dir of the v0.7 store. It is opened read-only.
+    #[clap(long)]
+    pub src: PathBuf,
+
+    /// The dir of the new store. It must be empty. Not required by a dry run.
+    #[clap(long, required_unless_present = "dry-run")]
+    pub dst: Option<PathBuf>,
+
+    /// Read and upgrade every record without writing anything.
+    #[clap(long)]
+    pub dry_run: bool,
+
+    /// The max number of log entries written in one batch.
+    #[clap(long, default_value = "1024")]
+    pub batch_size: usize,
+}
+
+#[async_std::main]
+async fn main() -> ExitCode {
+    tracing_subscriber::fmt().with_ansi(false).with_env_filter(EnvFilter::from_default_env()).init();
+
+    let opt = Opt::parse();
+
+    let res = match opt.from {
+        Backend::Rocksdb => match RocksSource07::open(&opt.src) {
+            Ok(src) => run(&src, &opt).await,
+            Err(e) => Err(e.into()),
+        },
+        Backend::Sled => match SledSource07::open(&opt.src) {
+            Ok(src) => run(&src, &opt).await,
+            Err(e) => Err(e.into()),
+        },
+    };
+
+    match res {
+        Ok(report) => {
+            print!("{}", report);
+            if report.mismatches.is_empty() {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::from(2)
+            }
+        }
+        Err(e) => {
+            eprintln!("migration failed: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run<Src: Source07>(src: &Src, opt: &Opt) -> Result<Report, MigrateError> {
+    let options = MigrateOptions {
+        batch_size: opt.batch_size,
+    };
+
+    if opt.dry_run {
+        return dry_run::<Config, _>(src, &options);
+    }
+
+    // `required_unless_present` ensures it is present.
+    let dst = opt.dst.as_ref().unwrap();
+    let mut target = RocksStore::new(dst).await;
+
+    migrate::<Config, _, _>(src, &mut target, &options).await
+}
@@ -0,0 +1,523 @@
+#![deny(unused_qualifications)]
+
+//! An offline tool to rewrite the on-disk data of an openraft-0.7 application in the
+//! openraft-0.8 format.
+//!
+//! [`rocksstore-compat07`](openraft_rocksstore_compat07) shows how to read v0.7 data at runtime
+//! with the types in [`openraft::compat::compat07`]. This crate does the upgrade once instead:
+//! every record of a v0.7 store is read from a [`Source07`], decoded as a v0.7 type, upgraded with
+//! [`Upgrade`] and written to an empty [`Target`] store. The target store is then verified by
+//! comparing it with the source and by loading it with [`StorageHelper::get_initial_state`].
+//!
+//! A v0.7 snapshot can not be upgraded because its meta does not contain the last membership. It
+//! is discarded, while the state machine data is migrated as is.
+//!
+//! ```ignore
+//! let src = RocksSource07::open("./db07")?;
+//!
+//! // Read and upgrade every record without writing anything.
+//! let report = dry_run::<Config, _>(&src, &MigrateOptions::default())?;
+//!
+//! let mut target = RocksStore::new("./db08").await;
+//! let report = migrate::<Config, _, _>(&src, &mut target, &MigrateOptions::default()).await?;
+//! assert!(report.mismatches.is_empty());
+//! ```
+
+mod source;
+#[cfg(test)] mod test;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Cursor;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use openraft::async_trait::async_trait;
+use openraft::compat::compat07;
+use openraft::compat::Upgrade;
+use openraft::AnyError;
+use openraft::EmptyNode;
+use openraft::Entry;
+use openraft::EntryPayload;
+use openraft::LogId;
+use openraft::RaftLogReader;
+use openraft::RaftStorage;
+use openraft::RaftTypeConfig;
+use openraft::SnapshotMeta;
+use openraft::StorageError;
+use openraft::StorageHelper;
+use openraft::StorageIOError;
+use openraft::StoredMembership;
+use openraft::Vote;
+use openraft_rocksstore_compat07::RocksStore;
+use openraft_rocksstore_compat07::SerializableRocksStateMachine;
+use serde::de::DeserializeOwned;
+
+pub use crate::source::Records;
+pub use crate::source::RocksSource07;
+pub use crate::source::SledSource07;
+pub use crate::source::Source07;
+
+/// The node id type of a v0.7 application.
+pub type NodeId = u64;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MigrateError {
+    #[error("failed to read v0.7 store: {0}")]
+    Source(AnyError),
+
+    #[error("failed to decode {what}: {source}")]
+    Decode { what: String, source: serde_json::Error },
+
+    #[error("target store is not empty: {0}")]
+    TargetNotEmpty(String),
+
+    #[error(transparent)]
+    Storage(#[from] StorageError<NodeId>),
+}
+
+impl From<AnyError> for MigrateError {
+    fn from(e: AnyError) -> Self {
+        Self::Source(e)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MigrateOptions {
+    /// The max number of log entries written to the target store in one `append_to_log()`.
+    pub batch_size: usize,
+}
+
+impl Default for MigrateOptions {
+    fn default() -> Self {
+        Self { batch_size: 1024 }
+    }
+}
+
+/// What a migration found in the v0.7 store and, if it is not a dry run, what it wrote.
+#[derive(Debug, Clone, Default)]
+#[derive(PartialEq, Eq)]
+pub struct Report {
+    pub dry_run: bool,
+
+    pub vote: Option<Vote<NodeId>>,
+
+    pub last_purged_log_id: Option<LogId<NodeId>>,
+
+    pub last_log_id: Option<LogId<NodeId>>,
+
+    /// The number of log entries.
+    pub logs: u64,
+
+    /// The number of membership log entries.
+    pub membership_logs: u64,
+
+    /// The log id of the last membership config, in the logs or in the state machine.
+    pub last_membership_log_id: Option<LogId<NodeId>>,
+
+    pub last_applied: Option<LogId<NodeId>>,
+
+    /// The number of application data keys in the state machine.
+    pub state_machine_keys: u64,
+
+    /// Whether the v0.7 store has a snapshot, which is not migrated.
+    pub snapshot_discarded: bool,
+
+    /// Inconsistencies found in the v0.7 store, or differences between the v0.7 store and the
+    /// target store.
+    pub mismatches: Vec<String>,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "dry_run: {}", self.dry_run)?;
+        writeln!(f, "vote: {}", display_opt(&self.vote))?;
+        writeln!(f, "last_purged_log_id: {}", display_opt(&self.last_purged_log_id))?;
+        writeln!(f, "last_log_id: {}", display_opt(&self.last_log_id))?;
+        writeln!(f, "logs: {}", self.logs)?;
+        writeln!(f, "membership_logs: {}", self.membership_logs)?;
+        writeln!(f, "last_membership_log_id: {}", display_opt(&self.last_membership_log_id))?;
+        writeln!(f, "last_applied: {}", display_opt(&self.last_applied))?;
+        writeln!(f, "state_machine_keys: {}", self.state_machine_keys)?;
+        writeln!(f, "snapshot_discarded: {}", self.snapshot_discarded)?;
+        writeln!(f, "mismatches: {}", self.mismatches.len())?;
+        for m in &self.mismatches {
+            writeln!(f, "  {}", m)?;
+        }
+        Ok(())
+    }
+}
+
+fn display_opt<T: fmt::Display>(v: &Option<T>) -> String {
+    match v {
+        Some(x) => x.to_string(),
+        None => "None".to_string(),
+    }
+}
+
+/// The upgraded state machine of a v0.7 store.
+#[derive(Debug, Clone, Default)]
+pub struct StateMachineData {
+    pub last_applied: Option<LogId<NodeId>>,
+    pub last_membership: StoredMembership<NodeId, EmptyNode>,
+
+    /// The application data, as it is stored in the v0.7 store.
+    pub data: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+/// A store in the openraft-0.8 format a v0.7 store is migrated to.
+///
+/// Vote and logs are written with the [`RaftStorage`] API. The state machine is written with
+/// [`Target::write_state_machine`] since only the application knows how to build it.
+#[async_trait]
+pub trait Target<C>: RaftStorage<C>
+where C: RaftTypeConfig<NodeId = NodeId, Node = EmptyNode>
+{
+    /// Replace the state machine with the one upgraded from a v0.7 store.
+    async fn write_state_machine(&mut self, sm: StateMachineData) -> Result<(), StorageError<NodeId>>;
+}
+
+/// The store of `rocksstore-compat07` writes data in the v0.8 format. The state machine is
+/// installed as a snapshot.
+#[async_trait]
+impl Target<openraft_rocksstore_compat07::Config> for Arc<RocksStore> {
+    async fn write_state_machine(&mut self, sm: StateMachineData) -> Result<(), StorageError<NodeId>> {
+        let mut data = BTreeMap::new();
+        for (k, v) in sm.data {
+            let k = String::from_utf8(k).map_err(|e| StorageIOError::write_state_machine(&e))?;
+            let v = String::from_utf8(v).map_err(|e| StorageIOError::write_state_machine(&e))?;
+            data.insert(k, v);
+        }
+
+        let snapshot_id = match sm.last_applied {
+            Some(last) => format!("{}-{}-migrated-07", last.leader_id, last.index),
+            None => "--migrated-07".to_string(),
+        };
+
+        let meta = SnapshotMeta {
+            last_log_id: sm.last_applied,
+            last_membership: sm.last_membership.clone(),
+            snapshot_id,
+        };
+
+        let state_machine = SerializableRocksStateMachine {
+            last_applied_log: sm.last_applied,
+            last_membership: sm.last_membership,
+            data,
+        };
+        let bytes = serde_json::to_vec(&state_machine).map_err(|e| StorageIOError::write_state_machine(&e))?;
+
+        self.install_snapshot(&meta, Box::new(Cursor::new(bytes))).await
+    }
+}
+
+/// Read and upgrade every record of a v0.7 store, without writing anything.
+pub fn dry_run<C, Src>(src: &Src, options: &MigrateOptions) -> Result<Report, MigrateError>
+where
+    C: RaftTypeConfig<NodeId = NodeId, Node = EmptyNode, Entry = Entry<C>>,
+    Src: Source07,
+{
+    let mut up = Upgrader::<C, Src>::new(src);
+
+    up.vote()?;
+    up.last_purged_log_id()?;
+
+    let mut logs = src.logs();
+    while !up.next_logs(&mut logs, options.batch_size)?.is_empty() {}
+
+    up.state_machine()?;
+    up.snapshot()?;
+
+    let mut report = up.report;
+    report.dry_run = true;
+    Ok(report)
+}
+
+/// Upgrade every record of a v0.7 store, write them to an empty `target` store, and verify the
+/// target store.
+pub async fn migrate<C, Src, T>(src: &Src, target: &mut T, options: &MigrateOptions) -> Result<Report, MigrateError>
+where
+    C: RaftTypeConfig<NodeId = NodeId, Node = EmptyNode, Entry = Entry<C>>,
+    Src: Source07,
+    T: Target<C>,
+{
+    ensure_empty::<C, T>(target).await?;
+
+    let mut up = Upgrader::<C, Src>::new(src);
+
+    if let Some(vote) = up.vote()? {
+        target.save_vote(&vote).await?;
+    }
+
+    if let Some(purged) = up.last_purged_log_id()? {
+        target.purge_logs_upto(purged).await?;
+    }
+
+    let mut logs = src.logs();
+    loop {
+        let entries = up.next_logs(&mut logs, options.batch_size)?;
+        if entries.is_empty() {
+            break;
+        }
+        target.append_to_log(&entries).await?;
+    }
+
+    let sm = up.state_machine()?;
+    if sm.last_applied.is_some() || !sm.data.is_empty() {
+        target.write_state_machine(sm).await?;
+    }
+
+    up.snapshot()?;
+
+    let mut report = up.report;
+    verify::<C, Src, T>(src, target, options, &mut report).await?;
+
+    tracing::info!("migrated v0.7 store: {:?}", report);
+    Ok(report)
+}
+
+async fn ensure_empty<C, T>(target: &mut T) -> Result<(), MigrateError>
+where
+    C: RaftTypeConfig<NodeId = NodeId, Node = EmptyNode>,
+    T: Target<C>,
+{
+    let st = target.get_log_state().await?;
+    if st.last_log_id.is_some() {
+        return Err(MigrateError::TargetNotEmpty(format!("it has logs upto {}", display_opt(&st.last_log_id))));
+    }
+
+    if let Some(vote) = target.read_vote().await? {
+        return Err(MigrateError::TargetNotEmpty(format!("it has vote {}", vote)));
+    }
+
+    let (last_applied, _) = target.last_applied_state().await?;
+    if let Some(last_applied) = last_applied {
+        return Err(MigrateError::TargetNotEmpty(format!("it has applied logs upto {}", last_applied)));
+    }
+
+    Ok(())
+}
+
+/// Compare the migrated target store with the v0.7 store, and check that the target store can be
+/// loaded by raft.
+async fn verify<C, Src, T>(
+    src: &Src,
+    target: &mut T,
+    options: &MigrateOptions,
+    report: &mut Report,
+) -> Result<(), MigrateError>
+where
+    C: RaftTypeConfig<NodeId = NodeId, Node = EmptyNode, Entry = Entry<C>>,
+    Src: Source07,
+    T: Target<C>,
+{
+    let mut mismatches = vec![];
+
+    let mut up = Upgrader::<C, Src>::new(src);
+    let mut logs = src.logs();
+    loop {
+        let want = up.next_logs(&mut logs, options.batch_size)?;
+        let (first, last) = match (want.first(), want.last()) {
+            (Some(f), Some(l)) => (f.log_id.index, l.log_id.index),
+            _ => break,
+        };
+
+        let got = target.try_get_log_entries(first..=last).await?;
+        let got = got.iter().map(|x| (x.log_id.index, x)).collect::<BTreeMap<_, _>>();
+
+        for w in want.iter() {
+            let same = match got.get(&w.log_id.index) {
+                Some(g) => encode(g)? == encode(w)?,
+                None => false,
+            };
+            if !same {
+                mismatches.push(format!("log at index {} is not migrated correctly", w.log_id.index));
+            }
+        }
+    }
+
+    let vote = target.read_vote().await?;
+    if vote != report.vote {
+        mismatches.push(format!("vote: want {}, got {}", display_opt(&report.vote), display_opt(&vote)));
+    }
+
+    let st = target.get_log_state().await?;
+    if st.last_purged_log_id != report.last_purged_log_id {
+        mismatches.push(format!(
+            "last_purged_log_id: want {}, got {}",
+            display_opt(&report.last_purged_log_id),
+            display_opt(&st.last_purged_log_id)
+        ));
+    }
+
+    let want_last = std::cmp::max(report.last_log_id, report.last_purged_log_id);
+    if st.last_log_id != want_last {
+        mismatches.push(format!(
+            "last_log_id: want {}, got {}",
+            display_opt(&want_last),
+            display_opt(&st.last_log_id)
+        ));
+    }
+
+    let state = StorageHelper::new(target).get_initial_state().await?;
+
+    if state.vote_ref() != &report.vote.unwrap_or_default() {
+        mismatches.push(format!("initial vote: got {}", state.vote_ref()));
+    }
+
+    if state.committed < report.last_applied {
+        mismatches.push(format!(
+            "initial committed: want >= {}, got {}",
+            display_opt(&report.last_applied),
+            display_opt(&state.committed)
+        ));
+    }
+
+    let effective = state.membership_state.effective().log_id();
+    if effective != &report.last_membership_log_id {
+        mismatches.push(format!(
+            "initial effective membership: want {}, got {}",
+            display_opt(&report.last_membership_log_id),
+            display_opt(effective)
+        ));
+    }
+
+    report.mismatches.extend(mismatches);
+    Ok(())
+}
+
+fn encode(ent: &Entry<impl RaftTypeConfig>) -> Result<Vec<u8>, MigrateError> {
+    serde_json::to_vec(ent).map_err(|e| MigrateError::Decode {
+        what: format!("log at index {}", ent.log_id.index),
+        source: e,
+    })
+}
+
+fn decode<T: DeserializeOwned>(what: impl fmt::Display, bytes: &[u8]) -> Result<T, MigrateError> {
+    serde_json::from_slice(bytes).map_err(|e| MigrateError::Decode {
+        what: what.to_string(),
+        source: e,
+    })
+}
+
+/// Decodes and upgrades the records of a v0.7 store, and records what it found in a [`Report`].
+struct Upgrader<'a, C, Src> {
+    src: &'a Src,
+
+    /// The log id of the last read log entry.
+    prev: Option<LogId<NodeId>>,
+
+    report: Report,
+
+    _p: PhantomData<C>,
+}
+
+impl<'a, C, Src> Upgrader<'a, C, Src>
+where
+    C: RaftTypeConfig<NodeId = NodeId, Node = EmptyNode, Entry = Entry<C>>,
+    Src: Source07,
+{
+    fn new(src: &'a Src) -> Self {
+        Self {
+            src,
+            prev: None,
+            report: Report::default(),
+            _p: PhantomData,
+        }
+    }
+
+    fn vote(&mut self) -> Result<Option<Vote<NodeId>>, MigrateError> {
+        let vote = match self.src.hard_state()? {
+            None => None,
+            Some(bs) => Some(decode::<compat07::Vote>("hard_state", &bs)?.upgrade()),
+        };
+
+        self.report.vote = vote;
+        Ok(vote)
+    }
+
+    fn last_purged_log_id(&mut self) -> Result<Option<LogId<NodeId>>, MigrateError> {
+        let purged = match self.src.last_purged_log_id()? {
+            None => None,
+            Some(bs) => Some(decode::<compat07::LogId>("last_purged_log_id", &bs)?.upgrade()),
+        };
+
+        self.report.last_purged_log_id = purged;
+        self.prev = purged;
+        Ok(purged)
+    }
+
+    /// Read and upgrade at most `n` log entries. An empty `Vec` is returned when all are read.
+    fn next_logs(&mut self, logs: &mut Records<'_, u64>, n: usize) -> Result<Vec<Entry<C>>, MigrateError> {
+        let mut entries = Vec::with_capacity(n);
+
+        for res in logs.take(n) {
+            let (index, bs) = res?;
+
+            let ent: Entry<C> = decode::<compat07::Entry<C>>(format!("log at index {}", index), &bs)?.upgrade();
+
+            if ent.log_id.index != index {
+                self.report.mismatches.push(format!("log stored at index {} has log id {}", index, ent.log_id));
+            }
+
+            if let Some(prev) = self.prev {
+                if prev.index + 1 != ent.log_id.index {
+                    self.report.mismatches.push(format!("log {} does not follow {}", ent.log_id, prev));
+                }
+            }
+
+            if let EntryPayload::Membership(_) = ent.payload {
+                self.report.membership_logs += 1;
+                self.report.last_membership_log_id = Some(ent.log_id);
+            }
+
+            self.report.logs += 1;
+            self.report.last_log_id = Some(ent.log_id);
+            self.prev = Some(ent.log_id);
+
+            entries.push(ent);
+        }
+
+        Ok(entries)
+    }
+
+    /// Read and upgrade the state machine. It must be called after all logs are read.
+    fn state_machine(&mut self) -> Result<StateMachineData, MigrateError> {
+        let last_applied = match self.src.last_applied_log()? {
+            None => None,
+            Some(bs) => Some(decode::<compat07::LogId>("last_applied_log", &bs)?.upgrade()),
+        };
+
+        let last_membership = match self.src.last_membership()? {
+            None => StoredMembership::default(),
+            Some(bs) => decode::<compat07::StoredMembership>("last_membership", &bs)?.upgrade(),
+        };
+
+        let mut data = vec![];
+        for res in self.src.state_machine_data() {
+            data.push(res?);
+        }
+
+        // The membership in the state machine is effective only if no membership log follows it.
+        if last_membership.log_id() > &self.report.last_membership_log_id {
+            self.report.last_membership_log_id = *last_membership.log_id();
+        }
+
+        self.report.last_applied = last_applied;
+        self.report.state_machine_keys = data.len() as u64;
+
+        Ok(StateMachineData {
+            last_applied,
+            last_membership,
+            data,
+        })
+    }
+
+    fn snapshot(&mut self) -> Result<(), MigrateError> {
+        self.report.snapshot_discarded = self.src.has_snapshot()?;
+        if self.report.snapshot_discarded {
+            tracing::warn!("v0.7 snapshot can not be upgraded and is discarded");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+use std::path::Path;
+
+use openraft::AnyError;
+use rocksdb::ColumnFamily;
+use rocksdb::IteratorMode;
+use rocksdb::Options;
+use rocksdb::DB;
+
+/// An iterator of raw records of a v0.7 store.
+pub type Records<'a, K> = Box<dyn Iterator<Item = Result<(K, Vec<u8>), AnyError>> + 'a>;
+
+/// Read access to the raw records of an openraft-0.7 store.
+///
+/// Values are returned as they are stored: the JSON serialized v0.7 types. They are decoded and
+/// upgraded by the migration.
+pub trait Source07 {
+    /// The serialized `HardState`.
+    fn hard_state(&self) -> Result<Option<Vec<u8>>, AnyError>;
+
+    /// The serialized `LogId` of the last purged log.
+    fn last_purged_log_id(&self) -> Result<Option<Vec<u8>>, AnyError>;
+
+    /// The serialized log entries with the index they are stored at, in index order.
+    fn logs(&self) -> Records<'_, u64>;
+
+    /// The serialized `LogId` of the last applied log.
+    fn last_applied_log(&self) -> Result<Option<Vec<u8>>, AnyError>;
+
+    /// The serialized `EffectiveMembership` of the last applied membership log.
+    fn last_membership(&self) -> Result<Option<Vec<u8>>, AnyError>;
+
+    /// The application data in the state machine, in key order.
+    fn state_machine_data(&self) -> Records<'_, Vec<u8>>;
+
+    /// Whether the store has a snapshot.
+    fn has_snapshot(&self) -> Result<bool, AnyError>;
+}
+
+/// A v0.7 store in RocksDB, with the layout of `openraft-rocksstore-0.7`.
+///
+/// The db is opened read-only.
+pub struct RocksSource07 {
+    db: DB,
+}
+
+impl RocksSource07 {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, AnyError> {
+        let cfs = ["meta", "sm_meta", "sm_data", "logs"];
+        let db = DB::open_cf_for_read_only(&Options::default(), path, cfs, false).map_err(|e| AnyError::new(&e))?;
+        Ok(Self { db })
+    }
+
+    fn cf(&self, name: &str) -> Result<&ColumnFamily, AnyError> {
+        self.db.cf_handle(name).ok_or_else(|| AnyError::error(format!("column family not found: {}", name)))
+    }
+
+    fn get(&self, cf: &str, key: &str) -> Result<Option<Vec<u8>>, AnyError> {
+        self.db.get_cf(self.cf(cf)?, key).map_err(|e| AnyError::new(&e))
+    }
+
+    fn iter(&self, cf: &str) -> Records<'_, Vec<u8>> {
+        let cf = match self.cf(cf) {
+            Ok(x) => x,
+            Err(e) => return Box::new(std::iter::once(Err(e))),
+        };
+
+        let it = self.db.iterator_cf(cf, IteratorMode::Start);
+        Box::new(it.map(|res| res.map(|(k, v)| (k.to_vec(), v.to_vec())).map_err(|e| AnyError::new(&e))))
+    }
+}
+
+impl Source07 for RocksSource07 {
+    fn hard_state(&self) -> Result<Option<Vec<u8>>, AnyError> {
+        self.get("meta", "hard_state")
+    }
+
+    fn last_purged_log_id(&self) -> Result<Option<Vec<u8>>, AnyError> {
+        self.get("meta", "last_purged_log_id")
+    }
+
+    fn logs(&self) -> Records<'_, u64> {
+        Box::new(self.iter("logs").map(|res| res.and_then(|(k, v)| Ok((bin_to_id(&k)?, v)))))
+    }
+
+    fn last_applied_log(&self) -> Result<Option<Vec<u8>>, AnyError> {
+        self.get("sm_meta", "last_applied_log")
+    }
+
+    fn last_membership(&self) -> Result<Option<Vec<u8>>, AnyError> {
+        self.get("sm_meta", "last_membership")
+    }
+
+    fn state_machine_data(&self) -> Records<'_, Vec<u8>> {
+        self.iter("sm_data")
+    }
+
+    fn has_snapshot(&self) -> Result<bool, AnyError> {
+        Ok(self.get("meta", "snapshot")?.is_some())
+    }
+}
+
+/// A v0.7 store in sled, with the layout of `openraft-sledstore`: trees `store`, `logs`,
+/// `state_machine` and `data`.
+pub struct SledSource07 {
+    db: sled::Db,
+}
+
+impl SledSource07 {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, AnyError> {
+        let db = sled::open(path).map_err(|e| AnyError::new(&e))?;
+        Ok(Self { db })
+    }
+
+    fn tree(&self, name: &str) -> Result<sled::Tree, AnyError> {
+        self.db.open_tree(name).map_err(|e| AnyError::new(&e))
+    }
+
+    fn get(&self, tree: &str, key: &str) -> Result<Option<Vec<u8>>, AnyError> {
+        let v = self.tree(tree)?.get(key).map_err(|e| AnyError::new(&e))?;
+        Ok(v.map(|x| x.to_vec()))
+    }
+
+    fn iter(&self, tree: &str) -> Records<'_, Vec<u8>> {
+        let tree = match self.tree(tree) {
+            Ok(x) => x,
+            Err(e) => return Box::new(std::iter::once(Err(e))),
+        };
+
+        Box::new(tree.iter().map(|res| res.map(|(k, v)| (k.to_vec(), v.to_vec())).map_err(|e| AnyError::new(&e))))
+    }
+}
+
+impl Source07 for SledSource07 {
+    fn hard_state(&self) -> Result<Option<Vec<u8>>, AnyError> {
+        match self.get("store", "hard_state")? {
+            Some(x) => Ok(Some(x)),
+            None => self.get("store", "vote"),
+        }
+    }
+
+    fn last_purged_log_id(&self) -> Result<Option<Vec<u8>>, AnyError> {
+        self.get("store", "last_purged_log_id")
+    }
+
+    fn logs(&self) -> Records<'_, u64> {
+        Box::new(self.iter("logs").map(|res| res.and_then(|(k, v)| Ok((bin_to_id(&k)?, v)))))
+    }
+
+    fn last_applied_log(&self) -> Result<Option<Vec<u8>>, AnyError> {
+        self.get("state_machine", "last_applied_log")
+    }
+
+    fn last_membership(&self) -> Result<Option<Vec<u8>>, AnyError> {
+        self.get("state_machine", "last_membership")
+    }
+
+    fn state_machine_data(&self) -> Records<'_, Vec<u8>> {
+        self.iter("data")
+    }
+
+    fn has_snapshot(&self) -> Result<bool, AnyError> {
+        Ok(self.get("store", "snapshot")?.is_some())
+    }
+}
+
+/// Log entries are keyed by the big endian encoded index.
+fn bin_to_id(buf: &[u8]) -> Result<u64, AnyError> {
+    let b: [u8; 8] = buf.try_into().map_err(|_| AnyError::error(format!("invalid log key: {:?}", buf)))?;
+    Ok(u64::from_be_bytes(b))
+}
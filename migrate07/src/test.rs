@@ -0,0 +1,153 @@
+use std::path::Path;
+
+use maplit::btreemap;
+use maplit::btreeset;
+use openraft::CommittedLeaderId;
+use openraft::EmptyNode;
+use openraft::LogId;
+use openraft::Membership;
+use openraft::RaftLogReader;
+use openraft::RaftStorage;
+use openraft::Vote;
+use openraft_rocksstore_compat07::Config;
+use openraft_rocksstore_compat07::RocksStore;
+use tempfile::TempDir;
+
+use crate::dry_run;
+use crate::migrate;
+use crate::MigrateError;
+use crate::MigrateOptions;
+use crate::RocksSource07;
+
+fn log_id(term: u64, index: u64) -> LogId<u64> {
+    LogId::new(CommittedLeaderId::new(term, 0), index)
+}
+
+fn ent07(index: u64, payload: or07::EntryPayload<rocksstore07::RocksRequest>) -> or07::Entry<rocksstore07::RocksRequest> {
+    or07::Entry {
+        log_id: or07::LogId { term: 1, index },
+        payload,
+    }
+}
+
+fn set07(key: &str, value: &str) -> or07::EntryPayload<rocksstore07::RocksRequest> {
+    or07::EntryPayload::Normal(rocksstore07::RocksRequest::Set {
+        key: key.to_string(),
+        value: value.to_string(),
+    })
+}
+
+/// Write a v0.7 store: logs `[2, 4]`, of which `[1, 3]` are applied and `[0, 1]` are purged.
+async fn build_v07(path: &Path) -> anyhow::Result<()> {
+    let s7 = rocksstore07::RocksStore::new(path).await;
+
+    or07::RaftStorage::save_hard_state(&s7, &or07::HardState {
+        current_term: 2,
+        voted_for: Some(1),
+    })
+    .await?;
+
+    let entries = vec![
+        ent07(0, or07::EntryPayload::Blank),
+        ent07(1, or07::EntryPayload::Blank),
+        ent07(2, or07::EntryPayload::Membership(or07::Membership::new_single(btreeset! {1,2}))),
+        ent07(3, set07("foo", "bar")),
+        ent07(4, set07("foo", "baz")),
+    ];
+    let refs = entries.iter().collect::<Vec<_>>();
+
+    or07::RaftStorage::append_to_log(&s7, &refs).await?;
+    or07::RaftStorage::apply_to_state_machine(&s7, &refs[..4]).await?;
+    or07::RaftStorage::purge_logs_upto(&s7, or07::LogId { term: 1, index: 1 }).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dry_run() -> anyhow::Result<()> {
+    let td07 = TempDir::new()?;
+    build_v07(td07.path()).await?;
+
+    let src = RocksSource07::open(td07.path())?;
+    let report = dry_run::<Config, _>(&src, &MigrateOptions::default())?;
+
+    assert!(report.dry_run);
+    assert_eq!(Some(Vote::new(2, 1)), report.vote);
+    assert_eq!(Some(log_id(1, 1)), report.last_purged_log_id);
+    assert_eq!(Some(log_id(1, 4)), report.last_log_id);
+    assert_eq!(3, report.logs);
+    assert_eq!(1, report.membership_logs);
+    assert_eq!(Some(log_id(1, 2)), report.last_membership_log_id);
+    assert_eq!(Some(log_id(1, 3)), report.last_applied);
+    assert_eq!(1, report.state_machine_keys);
+    assert!(report.mismatches.is_empty(), "{:?}", report.mismatches);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_migrate() -> anyhow::Result<()> {
+    let td07 = TempDir::new()?;
+    let td08 = TempDir::new()?;
+    build_v07(td07.path()).await?;
+
+    let src = RocksSource07::open(td07.path())?;
+    let options = MigrateOptions { batch_size: 2 };
+
+    let want = dry_run::<Config, _>(&src, &options)?;
+
+    let mut target = RocksStore::new(td08.path()).await;
+    let report = migrate::<Config, _, _>(&src, &mut target, &options).await?;
+
+    assert!(report.mismatches.is_empty(), "{:?}", report.mismatches);
+    assert_eq!(
+        crate::Report {
+            dry_run: true,
+            ..report.clone()
+        },
+        want,
+        "a dry run reports the same as the migration"
+    );
+
+    assert_eq!(Some(Vote::new(2, 1)), target.read_vote().await?);
+
+    let st = target.get_log_state().await?;
+    assert_eq!(Some(log_id(1, 1)), st.last_purged_log_id);
+    assert_eq!(Some(log_id(1, 4)), st.last_log_id);
+
+    let logs = target.try_get_log_entries(..).await?;
+    assert_eq!(
+        vec![log_id(1, 2), log_id(1, 3), log_id(1, 4)],
+        logs.iter().map(|x| x.log_id).collect::<Vec<_>>()
+    );
+
+    let (last_applied, last_membership) = target.last_applied_state().await?;
+    assert_eq!(Some(log_id(1, 3)), last_applied);
+    assert_eq!(Some(log_id(1, 2)), *last_membership.log_id());
+    assert_eq!(
+        &Membership::new(vec![btreeset! {1,2}], btreemap! {1=>EmptyNode{}, 2=>EmptyNode{}}),
+        last_membership.membership()
+    );
+
+    let v = target.state_machine.read().await.get("foo")?;
+    assert_eq!(Some("bar".to_string()), v);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_migrate_to_non_empty_target() -> anyhow::Result<()> {
+    let td07 = TempDir::new()?;
+    let td08 = TempDir::new()?;
+    build_v07(td07.path()).await?;
+
+    let src = RocksSource07::open(td07.path())?;
+
+    let mut target = RocksStore::new(td08.path()).await;
+    migrate::<Config, _, _>(&src, &mut target, &MigrateOptions::default()).await?;
+
+    let res = migrate::<Config, _, _>(&src, &mut target, &MigrateOptions::default()).await;
+    assert!(matches!(res, Err(MigrateError::TargetNotEmpty(_))));
+
+    Ok(())
+}
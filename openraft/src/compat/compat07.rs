@@ -175,10 +175,14 @@ pub struct Membership {
 
 impl Upgrade<crate::Membership<u64, crate::EmptyNode>> for Membership {
     fn upgrade(self) -> crate::Membership<u64, crate::EmptyNode> {
-        if let Some(ns) = self.nodes {
-            crate::Membership::new(self.configs, ns)
-        } else {
-            crate::Membership::new(self.configs, self.all_nodes.unwrap())
+        match (self.nodes, self.all_nodes) {
+            (Some(ns), _) => crate::Membership::new(self.configs, ns),
+            (None, Some(all_nodes)) => crate::Membership::new(self.configs, all_nodes),
+            (None, None) => {
+                // Without a node list, every node is a voter.
+                let voter_ids = self.configs.iter().flatten().copied().collect::<BTreeSet<_>>();
+                crate::Membership::new(self.configs, voter_ids)
+            }
         }
     }
 }
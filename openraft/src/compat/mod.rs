@@ -2,8 +2,12 @@
 //! read data written by an older application.
 
 #[cfg(feature = "compat-07")] pub mod compat07;
+#[cfg(feature = "compat-schema")] pub mod schema;
+#[cfg(feature = "compat-testing")] mod schema_suite;
 pub mod testing;
 
+#[cfg(all(test, feature = "compat-07"))] mod schema_test;
+
 mod upgrade;
 
 pub use upgrade::Compat;
@@ -0,0 +1,305 @@
+//! Versioned serde forms of persisted data and the upgrades between them.
+//!
+//! A store records the [`SchemaVersion`] of the data it writes. When a store finds data of an
+//! older version, it converts every record through the ordered [`UpgradeStep`]s registered in
+//! [`Upgrades`], from the stored version to the latest one.
+//!
+//! Upgrade steps work on the serde form of a record, as a [`serde_json::Value`], so that a step
+//! does not depend on the Rust types of an older openraft. The kinds of persisted data are listed
+//! in [`DataKind`].
+//!
+//! [`Upgrades::builtin`] contains the steps provided by openraft, e.g., from the openraft-0.7
+//! format to the openraft-0.8 format with feature `compat-07`.
+
+use std::fmt;
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+#[cfg(feature = "compat-07")] use crate::compat::compat07;
+#[cfg(feature = "compat-07")] use crate::compat::Compat;
+#[cfg(feature = "compat-07")] use crate::compat::Upgrade;
+use crate::AnyError;
+#[cfg(feature = "compat-07")] use crate::EmptyNode;
+#[cfg(feature = "compat-07")] use crate::Entry;
+#[cfg(feature = "compat-07")] use crate::LogId;
+#[cfg(feature = "compat-07")] use crate::StoredMembership;
+#[cfg(feature = "compat-07")] use crate::Vote;
+
+/// The version of the serde forms of persisted data.
+///
+/// | version | openraft release |
+/// | :--     | :--              |
+/// | 1       | 0.7              |
+/// | 2       | 0.8              |
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct SchemaVersion(pub u32);
+
+impl SchemaVersion {
+    /// The data format of openraft-0.7.
+    pub const V07: SchemaVersion = SchemaVersion(1);
+
+    /// The data format of openraft-0.8.
+    pub const V08: SchemaVersion = SchemaVersion(2);
+
+    /// The data format this openraft writes.
+    pub const CURRENT: SchemaVersion = Self::V08;
+}
+
+impl fmt::Display for SchemaVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}", self.0)
+    }
+}
+
+/// The kinds of persisted data whose serde form is defined by openraft.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DataKind {
+    Vote,
+    LogId,
+    Entry,
+    StoredMembership,
+    SnapshotMeta,
+}
+
+impl fmt::Display for DataKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum UpgradeError {
+    #[error("no upgrade for {kind} from {version}")]
+    Unsupported { kind: DataKind, version: SchemaVersion },
+
+    #[error("{kind} of {version} is newer than the latest supported {latest}")]
+    TooNew {
+        kind: DataKind,
+        version: SchemaVersion,
+        latest: SchemaVersion,
+    },
+
+    #[error("{kind} of {version} can not be upgraded: {reason}")]
+    NotUpgradable {
+        kind: DataKind,
+        version: SchemaVersion,
+        reason: String,
+    },
+
+    #[error("invalid {kind} of {version}: {source}")]
+    Decode {
+        kind: DataKind,
+        version: SchemaVersion,
+        source: AnyError,
+    },
+}
+
+/// Upgrades the serde form of persisted data from one version to the next.
+pub trait UpgradeStep: Send + Sync + 'static {
+    /// The version of the data this step accepts.
+    fn from_version(&self) -> SchemaVersion;
+
+    /// The version of the data this step produces.
+    fn to_version(&self) -> SchemaVersion;
+
+    /// Upgrade a record of `kind`.
+    ///
+    /// `Value::Null`, the serde form of an absent optional value, is passed through by
+    /// [`Upgrades`] and is never fed to a step.
+    fn upgrade(&self, kind: DataKind, value: Value) -> Result<Value, UpgradeError>;
+}
+
+/// An ordered registry of [`UpgradeStep`]s.
+///
+/// Every step must start from the version the previous step produces.
+pub struct Upgrades {
+    steps: Vec<Box<dyn UpgradeStep>>,
+}
+
+impl Upgrades {
+    /// Create an empty registry, which only loads data of [`SchemaVersion::CURRENT`].
+    pub fn new() -> Self {
+        Self { steps: vec![] }
+    }
+
+    /// Create a registry with the steps provided by openraft.
+    ///
+    /// The upgrade from the openraft-0.7 format, `V07ToV08`, is included with feature
+    /// `compat-07`.
+    pub fn builtin() -> Self {
+        let upgrades = Self::new();
+
+        #[cfg(feature = "compat-07")]
+        let upgrades = upgrades.add(V07ToV08);
+
+        upgrades
+    }
+
+    /// Append a step.
+    ///
+    /// # Panics
+    ///
+    /// If the step does not start from the version the last registered step produces.
+    pub fn add(mut self, step: impl UpgradeStep) -> Self {
+        if let Some(last) = self.steps.last() {
+            assert_eq!(
+                last.to_version(),
+                step.from_version(),
+                "upgrade step must start from the version the last step produces"
+            );
+        }
+        assert!(step.from_version() < step.to_version(), "upgrade step must increase the version");
+
+        self.steps.push(Box::new(step));
+        self
+    }
+
+    /// The version data is upgraded to.
+    pub fn latest(&self) -> SchemaVersion {
+        self.steps.last().map(|x| x.to_version()).unwrap_or(SchemaVersion::CURRENT)
+    }
+
+    /// All of the versions that can be loaded, in ascending order.
+    pub fn versions(&self) -> Vec<SchemaVersion> {
+        let mut vs = self.steps.iter().map(|x| x.from_version()).collect::<Vec<_>>();
+        vs.push(self.latest());
+        vs
+    }
+
+    /// Upgrade the serde form of a record of `kind` from `version` to [`Self::latest`].
+    pub fn upgrade(&self, kind: DataKind, version: SchemaVersion, value: Value) -> Result<Value, UpgradeError> {
+        let latest = self.latest();
+
+        if version > latest {
+            return Err(UpgradeError::TooNew { kind, version, latest });
+        }
+
+        if version == latest || value.is_null() {
+            return Ok(value);
+        }
+
+        let start = self.steps.iter().position(|x| x.from_version() == version);
+        let start = start.ok_or(UpgradeError::Unsupported { kind, version })?;
+
+        let mut value = value;
+        for step in &self.steps[start..] {
+            value = step.upgrade(kind, value)?;
+        }
+
+        Ok(value)
+    }
+
+    /// Decode a record of `kind` serialized as JSON by `version`, upgrade it, and deserialize it
+    /// into the latest type.
+    pub fn load<T: DeserializeOwned>(
+        &self,
+        kind: DataKind,
+        version: SchemaVersion,
+        bytes: &[u8],
+    ) -> Result<T, UpgradeError> {
+        let value = serde_json::from_slice::<Value>(bytes).map_err(|e| decode_err(kind, version, &e))?;
+        let value = self.upgrade(kind, version, value)?;
+
+        let latest = self.latest();
+        serde_json::from_value(value).map_err(|e| decode_err(kind, latest, &e))
+    }
+}
+
+impl Default for Upgrades {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl fmt::Debug for Upgrades {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Upgrades").field("versions", &self.versions()).finish()
+    }
+}
+
+fn decode_err(kind: DataKind, version: SchemaVersion, e: &(impl std::error::Error + 'static)) -> UpgradeError {
+    UpgradeError::Decode {
+        kind,
+        version,
+        source: AnyError::new(e),
+    }
+}
+
+/// Upgrade the openraft-0.7 format to the openraft-0.8 format.
+///
+/// In v0.7 the node id is `u64` and there is no `Node`, thus the upgraded data has
+/// `NodeId=u64` and `Node=EmptyNode`.
+///
+/// A record is decoded with the v0.7 compatible types in [`compat07`], which accept the v0.7 and
+/// the v0.8 form, because a v0.7 compatible store built with `compat07` may have written both.
+///
+/// A v0.7 `SnapshotMeta` can not be upgraded because it does not contain the last membership.
+#[cfg(feature = "compat-07")]
+pub struct V07ToV08;
+
+#[cfg(feature = "compat-07")]
+mod v07 {
+    use serde_json::Value;
+
+    use crate::EmptyNode;
+
+    crate::declare_raft_types!(
+        /// Entries of any application: the application data is kept as is.
+        pub(super) ValueConfig: D = Value, R = (), NodeId = u64, Node = EmptyNode, Entry = crate::Entry<ValueConfig>
+    );
+}
+
+#[cfg(feature = "compat-07")]
+impl V07ToV08 {
+    /// Decode `value` with the compatible type `T` and encode the upgraded `U`.
+    fn upgrade_with<T, U>(&self, kind: DataKind, value: Value) -> Result<Value, UpgradeError>
+    where
+        T: DeserializeOwned + Upgrade<U>,
+        U: serde::Serialize,
+    {
+        let v: T = serde_json::from_value(value).map_err(|e| decode_err(kind, self.from_version(), &e))?;
+        serde_json::to_value(v.upgrade()).map_err(|e| decode_err(kind, self.to_version(), &e))
+    }
+}
+
+#[cfg(feature = "compat-07")]
+impl UpgradeStep for V07ToV08 {
+    fn from_version(&self) -> SchemaVersion {
+        SchemaVersion::V07
+    }
+
+    fn to_version(&self) -> SchemaVersion {
+        SchemaVersion::V08
+    }
+
+    fn upgrade(&self, kind: DataKind, value: Value) -> Result<Value, UpgradeError> {
+        match kind {
+            DataKind::LogId => self.upgrade_with::<compat07::LogId, LogId<u64>>(kind, value),
+            DataKind::Vote => self.upgrade_with::<compat07::Vote, Vote<u64>>(kind, value),
+            DataKind::StoredMembership => {
+                self.upgrade_with::<compat07::StoredMembership, StoredMembership<u64, EmptyNode>>(kind, value)
+            }
+            DataKind::Entry => {
+                self.upgrade_with::<compat07::Entry<v07::ValueConfig>, Entry<v07::ValueConfig>>(kind, value)
+            }
+            DataKind::SnapshotMeta => {
+                let meta: compat07::SnapshotMeta =
+                    serde_json::from_value(value.clone()).map_err(|e| decode_err(kind, self.from_version(), &e))?;
+
+                match meta {
+                    Compat::New(_) => Ok(value),
+                    Compat::Old(_) => Err(UpgradeError::NotUpgradable {
+                        kind,
+                        version: self.from_version(),
+                        reason: format!(
+                            "snapshot {} upto {} has no last membership, it has to be rebuilt",
+                            value["snapshot_id"], value["last_log_id"]
+                        ),
+                    }),
+                }
+            }
+        }
+    }
+}
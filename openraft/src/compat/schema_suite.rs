@@ -0,0 +1,256 @@
+//! A test suite that checks a `RaftStorage` loads the data written in every supported
+//! [`SchemaVersion`].
+
+use std::path::Path;
+
+use maplit::btreemap;
+use maplit::btreeset;
+use serde_json::json;
+use serde_json::Value;
+
+use crate::compat::schema::DataKind;
+use crate::compat::schema::SchemaVersion;
+use crate::compat::schema::UpgradeError;
+use crate::compat::schema::Upgrades;
+use crate::log_id::RaftLogId;
+use crate::CommittedLeaderId;
+use crate::EmptyNode;
+use crate::LogId;
+use crate::Membership;
+use crate::RaftLogReader;
+use crate::RaftStorage;
+use crate::RaftTypeConfig;
+use crate::SnapshotMeta;
+use crate::StoredMembership;
+use crate::Vote;
+
+/// The persisted records of a store, in the serde form of one [`SchemaVersion`].
+///
+/// A fixture contains only the data whose form is defined by openraft: there is no application
+/// data in it and `logs` contains only blank and membership entries.
+#[derive(Debug, Clone)]
+pub struct Fixture {
+    pub version: SchemaVersion,
+
+    /// The vote, `HardState` in v0.7.
+    pub vote: Value,
+
+    pub last_purged_log_id: Value,
+
+    /// Log entries in index order.
+    pub logs: Vec<Value>,
+
+    pub last_applied: Value,
+
+    /// The last applied membership, `EffectiveMembership` in v0.7.
+    pub last_membership: Value,
+
+    /// The meta of the current snapshot.
+    pub snapshot_meta: Value,
+}
+
+impl Fixture {
+    /// The index of a log entry in [`Self::logs`].
+    ///
+    /// The index of a log id is stored at the same path in every version.
+    pub fn log_index(entry: &Value) -> u64 {
+        entry["log_id"]["index"].as_u64().expect("log entry has no index")
+    }
+}
+
+/// Returns a fixture for every [`SchemaVersion`] openraft has released, oldest first.
+///
+/// Every fixture describes the same state:
+/// vote `(2, 1)`, logs `[2, 4]` with `(1, 1)` purged, membership `{1,2}` at `(1, 2)`,
+/// `(1, 3)` applied and a snapshot upto `(1, 3)`.
+pub fn fixtures() -> Vec<Fixture> {
+    let mut fs = vec![];
+
+    // The upgrade from v0.7 is provided only with `compat-07`.
+    #[cfg(feature = "compat-07")]
+    fs.push(fixture_v07());
+
+    fs.push(fixture_v08());
+    fs
+}
+
+#[cfg(feature = "compat-07")]
+fn fixture_v07() -> Fixture {
+    let membership = json!({"configs": [[1, 2]], "all_nodes": [1, 2]});
+
+    Fixture {
+        version: SchemaVersion::V07,
+        vote: json!({"current_term": 2, "voted_for": 1}),
+        last_purged_log_id: json!({"term": 1, "index": 1}),
+        logs: vec![
+            json!({"log_id": {"term": 1, "index": 2}, "payload": {"Membership": membership}}),
+            json!({"log_id": {"term": 1, "index": 3}, "payload": "Blank"}),
+            json!({"log_id": {"term": 1, "index": 4}, "payload": "Blank"}),
+        ],
+        last_applied: json!({"term": 1, "index": 3}),
+        last_membership: json!({"log_id": {"term": 1, "index": 2}, "membership": membership}),
+        snapshot_meta: json!({"last_log_id": {"term": 1, "index": 3}, "snapshot_id": "1-3-1"}),
+    }
+}
+
+fn fixture_v08() -> Fixture {
+    let state = expected_state();
+    let membership = to_value(state.last_membership.membership());
+
+    Fixture {
+        version: SchemaVersion::V08,
+        vote: to_value(&state.vote),
+        last_purged_log_id: to_value(&state.last_purged_log_id),
+        logs: vec![
+            json!({"log_id": to_value(&log_id(1, 2)), "payload": {"Membership": membership}}),
+            json!({"log_id": to_value(&log_id(1, 3)), "payload": "Blank"}),
+            json!({"log_id": to_value(&log_id(1, 4)), "payload": "Blank"}),
+        ],
+        last_applied: to_value(&state.last_applied),
+        last_membership: to_value(&state.last_membership),
+        snapshot_meta: to_value(&state.snapshot_meta),
+    }
+}
+
+fn to_value<T: serde::Serialize>(v: &T) -> Value {
+    serde_json::to_value(v).expect("serialize fixture")
+}
+
+/// The state every fixture describes, in the latest types.
+struct ExpectedState {
+    vote: Vote<u64>,
+    last_purged_log_id: Option<LogId<u64>>,
+    last_log_id: Option<LogId<u64>>,
+    log_ids: Vec<LogId<u64>>,
+    last_applied: Option<LogId<u64>>,
+    last_membership: StoredMembership<u64, EmptyNode>,
+    snapshot_meta: SnapshotMeta<u64, EmptyNode>,
+}
+
+fn expected_state() -> ExpectedState {
+    let membership = Membership::new(vec![btreeset! {1,2}], btreemap! {1=>EmptyNode{}, 2=>EmptyNode{}});
+    let last_membership = StoredMembership::new(Some(log_id(1, 2)), membership);
+
+    ExpectedState {
+        vote: Vote::new(2, 1),
+        last_purged_log_id: Some(log_id(1, 1)),
+        last_log_id: Some(log_id(1, 4)),
+        log_ids: vec![log_id(1, 2), log_id(1, 3), log_id(1, 4)],
+        last_applied: Some(log_id(1, 3)),
+        last_membership: last_membership.clone(),
+        snapshot_meta: SnapshotMeta {
+            last_log_id: Some(log_id(1, 3)),
+            last_membership,
+            snapshot_id: "1-3-1".to_string(),
+        },
+    }
+}
+
+fn log_id(term: u64, index: u64) -> LogId<u64> {
+    LogId::new(CommittedLeaderId::new(term, 0), index)
+}
+
+/// Build a `RaftStorage` implementation for the schema test.
+#[async_trait::async_trait]
+pub trait SchemaStoreBuilder {
+    type C: RaftTypeConfig<NodeId = u64, Node = EmptyNode>;
+    type S: RaftStorage<Self::C>;
+
+    /// Write the records of a fixture to a new store at `p`, in the layout of this store, the way a
+    /// store of `fixture.version` would have written them.
+    ///
+    /// E.g., a store that did not record a schema version before a release should not write it
+    /// for a fixture of that release.
+    async fn write_fixture(&self, p: &Path, fixture: &Fixture) -> anyhow::Result<()>;
+
+    /// Open the store at `p`, which is expected to upgrade the data if it is of an older version.
+    async fn build(&self, p: &Path) -> Self::S;
+}
+
+/// A test suite that ensures a `RaftStorage` implementation loads data written in every
+/// supported [`SchemaVersion`].
+pub struct SchemaSuite<B>
+where B: SchemaStoreBuilder
+{
+    pub builder: B,
+}
+
+impl<B> SchemaSuite<B>
+where B: SchemaStoreBuilder
+{
+    pub async fn test_all(&self) -> anyhow::Result<()> {
+        self.test_fixtures_cover_versions().await?;
+
+        for fixture in fixtures() {
+            self.test_load(&fixture).await?;
+            self.test_reopen(&fixture).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Every version an upgrade starts from must have a fixture.
+    async fn test_fixtures_cover_versions(&self) -> anyhow::Result<()> {
+        let versions = fixtures().iter().map(|x| x.version).collect::<Vec<_>>();
+        assert_eq!(Upgrades::builtin().versions(), versions);
+
+        Ok(())
+    }
+
+    async fn test_load(&self, fixture: &Fixture) -> anyhow::Result<()> {
+        let td = tmp_dir();
+
+        self.builder.write_fixture(td.path(), fixture).await?;
+
+        let mut store = self.builder.build(td.path()).await;
+        self.check_state(&mut store, fixture).await
+    }
+
+    /// Upgraded data loads the same after the store is reopened.
+    async fn test_reopen(&self, fixture: &Fixture) -> anyhow::Result<()> {
+        let td = tmp_dir();
+
+        self.builder.write_fixture(td.path(), fixture).await?;
+
+        {
+            let _store = self.builder.build(td.path()).await;
+        }
+
+        let mut store = self.builder.build(td.path()).await;
+        self.check_state(&mut store, fixture).await
+    }
+
+    async fn check_state(&self, store: &mut B::S, fixture: &Fixture) -> anyhow::Result<()> {
+        let want = expected_state();
+        let v = fixture.version;
+
+        assert_eq!(Some(want.vote), store.read_vote().await?, "vote of {}", v);
+        let log_state = store.get_log_state().await?;
+        assert_eq!(want.last_purged_log_id, log_state.last_purged_log_id, "last purged of {}", v);
+        assert_eq!(want.last_log_id, log_state.last_log_id, "last log id of {}", v);
+
+        let logs = store.try_get_log_entries(..).await?;
+        let log_ids = logs.iter().map(|x| *x.get_log_id()).collect::<Vec<_>>();
+        assert_eq!(want.log_ids, log_ids, "log ids of {}", v);
+
+        let (last_applied, last_membership) = store.last_applied_state().await?;
+        assert_eq!(want.last_applied, last_applied, "last applied of {}", v);
+        assert_eq!(want.last_membership, last_membership, "last membership of {}", v);
+
+        // A snapshot meta that can not be upgraded has to be discarded.
+        let meta = Upgrades::builtin().upgrade(DataKind::SnapshotMeta, v, fixture.snapshot_meta.clone());
+        let want_snapshot = match meta {
+            Ok(_) => Some(want.snapshot_meta),
+            Err(UpgradeError::NotUpgradable { .. }) => None,
+            Err(e) => return Err(e.into()),
+        };
+        let snapshot = store.get_current_snapshot().await?;
+        assert_eq!(want_snapshot, snapshot.map(|x| x.meta), "snapshot of {}", v);
+
+        Ok(())
+    }
+}
+
+fn tmp_dir() -> tempfile::TempDir {
+    tempfile::TempDir::new().expect("couldn't create temp dir")
+}
@@ -0,0 +1,228 @@
+use maplit::btreemap;
+use maplit::btreeset;
+use serde_json::json;
+use serde_json::Value;
+
+use crate::compat::schema::DataKind;
+use crate::compat::schema::SchemaVersion;
+use crate::compat::schema::UpgradeError;
+use crate::compat::schema::UpgradeStep;
+use crate::compat::schema::Upgrades;
+use crate::CommittedLeaderId;
+use crate::EmptyNode;
+use crate::Entry;
+use crate::EntryPayload;
+use crate::LogId;
+use crate::Membership;
+use crate::SnapshotMeta;
+use crate::StoredMembership;
+use crate::Vote;
+
+crate::declare_raft_types!(
+    pub(crate) UTConfig: D = u64, R = u64, NodeId = u64, Node = EmptyNode, Entry = Entry<UTConfig>
+);
+
+fn log_id(term: u64, index: u64) -> LogId<u64> {
+    LogId::new(CommittedLeaderId::new(term, 0), index)
+}
+
+fn m12() -> Membership<u64, EmptyNode> {
+    Membership::new(vec![btreeset! {1,2}], btreemap! {1=>EmptyNode{}, 2=>EmptyNode{}})
+}
+
+fn v07_membership() -> Value {
+    json!({"configs": [[1, 2]], "all_nodes": [1, 2]})
+}
+
+fn upgrade_v07(kind: DataKind, v: Value) -> Result<Value, UpgradeError> {
+    Upgrades::builtin().upgrade(kind, SchemaVersion::V07, v)
+}
+
+fn to_value<T: serde::Serialize>(v: &T) -> Value {
+    serde_json::to_value(v).unwrap()
+}
+
+#[test]
+fn test_upgrades_versions() -> anyhow::Result<()> {
+    assert_eq!(SchemaVersion::CURRENT, Upgrades::new().latest());
+    assert_eq!(vec![SchemaVersion::CURRENT], Upgrades::new().versions());
+
+    assert_eq!(SchemaVersion::CURRENT, Upgrades::builtin().latest());
+    assert_eq!(
+        vec![SchemaVersion::V07, SchemaVersion::V08],
+        Upgrades::builtin().versions()
+    );
+
+    assert_eq!("v2", SchemaVersion::V08.to_string());
+
+    Ok(())
+}
+
+#[test]
+#[should_panic(expected = "upgrade step must start from the version the last step produces")]
+fn test_upgrades_add_non_contiguous_step() {
+    struct V3ToV4;
+
+    impl UpgradeStep for V3ToV4 {
+        fn from_version(&self) -> SchemaVersion {
+            SchemaVersion(3)
+        }
+
+        fn to_version(&self) -> SchemaVersion {
+            SchemaVersion(4)
+        }
+
+        fn upgrade(&self, _kind: DataKind, value: Value) -> Result<Value, UpgradeError> {
+            Ok(value)
+        }
+    }
+
+    let _ = Upgrades::builtin().add(V3ToV4);
+}
+
+#[test]
+fn test_upgrade_too_new_and_unsupported() -> anyhow::Result<()> {
+    let res = Upgrades::builtin().upgrade(DataKind::Vote, SchemaVersion(3), json!({}));
+    assert_eq!(
+        Err(UpgradeError::TooNew {
+            kind: DataKind::Vote,
+            version: SchemaVersion(3),
+            latest: SchemaVersion::V08,
+        }),
+        res
+    );
+
+    let res = Upgrades::new().upgrade(DataKind::Vote, SchemaVersion::V07, json!({}));
+    assert_eq!(
+        Err(UpgradeError::Unsupported {
+            kind: DataKind::Vote,
+            version: SchemaVersion::V07,
+        }),
+        res
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_upgrade_v07_log_id() -> anyhow::Result<()> {
+    let got = upgrade_v07(DataKind::LogId, json!({"term": 3, "index": 5}))?;
+    assert_eq!(to_value(&log_id(3, 5)), got);
+
+    // Null is the serde form of `None`
+    let got = upgrade_v07(DataKind::LogId, Value::Null)?;
+    assert_eq!(Value::Null, got);
+
+    // The latest form is kept
+    let got = upgrade_v07(DataKind::LogId, to_value(&log_id(3, 5)))?;
+    assert_eq!(to_value(&log_id(3, 5)), got);
+
+    Ok(())
+}
+
+#[test]
+fn test_upgrade_v07_vote() -> anyhow::Result<()> {
+    let got = upgrade_v07(DataKind::Vote, json!({"current_term": 2, "voted_for": 3}))?;
+    assert_eq!(to_value(&Vote::new(2, 3)), got);
+
+    let got = upgrade_v07(DataKind::Vote, json!({"current_term": 2, "voted_for": null}))?;
+    assert_eq!(to_value(&Vote::new(2, 0)), got);
+
+    let got = upgrade_v07(DataKind::Vote, to_value(&Vote::new_committed(2, 3)))?;
+    assert_eq!(to_value(&Vote::new_committed(2, 3)), got);
+
+    Ok(())
+}
+
+#[test]
+fn test_upgrade_v07_entry() -> anyhow::Result<()> {
+    let got = upgrade_v07(
+        DataKind::Entry,
+        json!({"log_id": {"term": 1, "index": 2}, "payload": "Blank"}),
+    )?;
+    let want = Entry::<UTConfig> {
+        log_id: log_id(1, 2),
+        payload: EntryPayload::Blank,
+    };
+    assert_eq!(to_value(&want), got);
+
+    let got = upgrade_v07(
+        DataKind::Entry,
+        json!({"log_id": {"term": 1, "index": 3}, "payload": {"Membership": v07_membership()}}),
+    )?;
+    let want = Entry::<UTConfig> {
+        log_id: log_id(1, 3),
+        payload: EntryPayload::Membership(m12()),
+    };
+    assert_eq!(to_value(&want), got);
+
+    // Application data is kept as is.
+    let got = upgrade_v07(
+        DataKind::Entry,
+        json!({"log_id": {"term": 1, "index": 4}, "payload": {"Normal": {"Set": {"key": "foo"}}}}),
+    )?;
+    assert_eq!(
+        json!({"log_id": to_value(&log_id(1, 4)), "payload": {"Normal": {"Set": {"key": "foo"}}}}),
+        got
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_upgrade_v07_stored_membership() -> anyhow::Result<()> {
+    let got = upgrade_v07(
+        DataKind::StoredMembership,
+        json!({"log_id": {"term": 1, "index": 3}, "membership": v07_membership()}),
+    )?;
+    assert_eq!(to_value(&StoredMembership::new(Some(log_id(1, 3)), m12())), got);
+
+    // A v0.7 membership that has neither `nodes` nor `all_nodes`
+    let got = upgrade_v07(
+        DataKind::StoredMembership,
+        json!({"log_id": {"term": 1, "index": 3}, "membership": {"configs": [[1, 2]]}}),
+    )?;
+    assert_eq!(to_value(&StoredMembership::new(Some(log_id(1, 3)), m12())), got);
+
+    let want = StoredMembership::new(Some(log_id(1, 3)), m12());
+    let got = upgrade_v07(DataKind::StoredMembership, to_value(&want))?;
+    assert_eq!(to_value(&want), got);
+
+    Ok(())
+}
+
+#[test]
+fn test_upgrade_v07_snapshot_meta() -> anyhow::Result<()> {
+    let res = upgrade_v07(
+        DataKind::SnapshotMeta,
+        json!({"last_log_id": {"term": 1, "index": 3}, "snapshot_id": "1-3-1"}),
+    );
+    assert!(matches!(res, Err(UpgradeError::NotUpgradable { .. })), "{:?}", res);
+
+    let meta = SnapshotMeta {
+        last_log_id: Some(log_id(1, 3)),
+        last_membership: StoredMembership::new(Some(log_id(1, 2)), m12()),
+        snapshot_id: "1-3-1".to_string(),
+    };
+    let got = upgrade_v07(DataKind::SnapshotMeta, to_value(&meta))?;
+    assert_eq!(to_value(&meta), got);
+
+    Ok(())
+}
+
+#[test]
+fn test_load() -> anyhow::Result<()> {
+    let upgrades = Upgrades::builtin();
+
+    let got: Vote<u64> = upgrades.load(DataKind::Vote, SchemaVersion::V07, br#"{"current_term":2,"voted_for":1}"#)?;
+    assert_eq!(Vote::new(2, 1), got);
+
+    let bs = serde_json::to_vec(&Vote::new(2, 1))?;
+    let got: Vote<u64> = upgrades.load(DataKind::Vote, SchemaVersion::V08, &bs)?;
+    assert_eq!(Vote::new(2, 1), got);
+
+    let res = upgrades.load::<Vote<u64>>(DataKind::Vote, SchemaVersion::V07, b"{");
+    assert!(matches!(res, Err(UpgradeError::Decode { .. })), "{:?}", res);
+
+    Ok(())
+}
@@ -1,6 +1,9 @@
 //! This mod provides supporting utilities for compatibility testing.
 //!
 //! An application that tries to be compatible with an older format data must ensure its `RaftStorage` implementation to pass test suite, just like [rocksstore-compat07/compatibility_test.rs](https://github.com/datafuselabs/openraft/blob/main/rocksstore-compat07/src/compatibility_test.rs) does
+//!
+//! With feature `compat-testing`, `SchemaSuite` checks that a `RaftStorage` loads the data of
+//! every supported `SchemaVersion`, with the `fixtures()` of each version.
 
 use std::path::Path;
 
@@ -20,3 +23,8 @@ pub trait StoreBuilder {
 #[cfg(feature = "compat-07")]
 pub use crate::compat::compat07::testing::StoreBuilder07;
 #[cfg(feature = "compat-07")] pub use crate::compat::compat07::testing::Suite07;
+
+#[cfg(feature = "compat-testing")] pub use crate::compat::schema_suite::fixtures;
+#[cfg(feature = "compat-testing")] pub use crate::compat::schema_suite::Fixture;
+#[cfg(feature = "compat-testing")] pub use crate::compat::schema_suite::SchemaStoreBuilder;
+#[cfg(feature = "compat-testing")] pub use crate::compat::schema_suite::SchemaSuite;
@@ -12,8 +12,7 @@ pub trait Upgrade<To> {
 /// and a newer type. It serves as an intermediate type container for newer programs to read old
 /// data.
 #[derive(Debug)]
-#[derive(serde::Deserialize, serde::Serialize)]
-#[serde(untagged)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize), serde(untagged))]
 pub enum Compat<From, To>
 where From: Upgrade<To>
 {
@@ -1,6 +1,7 @@
 //! Raft runtime configuration.
 
 use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
 use std::time::Duration;
 
 use clap::Parser;
@@ -120,6 +121,18 @@ pub struct Config {
     #[clap(long, default_value = "300")]
     pub max_payload_entries: u64,
 
+    /// The maximum total size in bytes of the entries per payload allowed to be transmitted during
+    /// replication.
+    ///
+    /// The size of an entry is [`RaftEntry::size_hint()`](crate::entry::RaftEntry::size_hint).
+    /// A payload always contains at least one entry, even if the entry is larger than this limit.
+    /// The size of an entry is known only if the entry is appended after the node starts: an
+    /// entry that is loaded from the store is limited only by `max_payload_entries`.
+    /// It is disabled by default, by setting it to `0`: a payload is limited only by
+    /// `max_payload_entries`.
+    #[clap(long, default_value = "0", parse(try_from_str=parse_bytes_with_unit))]
+    pub max_payload_bytes: u64,
+
     /// The distance behind in log replication a follower must fall before it is considered lagging
     ///
     /// A follower falls behind this index are replicated with snapshot.
@@ -142,6 +155,63 @@ pub struct Config {
     #[clap(long, default_value = "3MiB", parse(try_from_str=parse_bytes_with_unit))]
     pub snapshot_max_chunk_size: u64,
 
+    /// The maximum rate in bytes per second at which a leader sends logs and snapshots to all
+    /// followers and learners in total.
+    ///
+    /// It is disabled by default, by setting it to `0`.
+    /// Heartbeat is not limited. The replication rate limits can be changed at runtime, e.g., with
+    /// [`Raft::set_replication_max_bytes_per_sec()`](crate::Raft::set_replication_max_bytes_per_sec).
+    #[clap(long, default_value = "0", parse(try_from_str=parse_bytes_with_unit))]
+    pub replication_max_bytes_per_sec: u64,
+
+    /// The maximum rate in bytes per second at which a leader sends logs and snapshots to every
+    /// single follower or learner.
+    ///
+    /// It is disabled by default, by setting it to `0`.
+    #[clap(long, default_value = "0", parse(try_from_str=parse_bytes_with_unit))]
+    pub replication_target_max_bytes_per_sec: u64,
+
+    /// The maximum rate in bytes per second at which a leader sends logs and snapshots to every
+    /// single learner.
+    ///
+    /// It replaces `replication_target_max_bytes_per_sec` for learners, e.g., to let a new node
+    /// catch up slower than the voters. It is disabled by default, by setting it to `0`: a learner
+    /// is limited by `replication_target_max_bytes_per_sec`.
+    #[clap(long, default_value = "0", parse(try_from_str=parse_bytes_with_unit))]
+    pub replication_learner_max_bytes_per_sec: u64,
+
+    /// The maximum number of uncommitted entries on a leader.
+    ///
+    /// When it is reached, a client write waits or fails, according to `backpressure_timeout`.
+    /// It is disabled by default, by setting it to `0`.
+    #[clap(long, default_value = "0")]
+    pub max_uncommitted_entries: u64,
+
+    /// The maximum total size in bytes of the uncommitted entries proposed by a leader.
+    ///
+    /// The size of an entry is [`RaftEntry::size_hint()`](crate::entry::RaftEntry::size_hint).
+    /// When it is reached, a client write waits or fails, according to `backpressure_timeout`.
+    /// It is disabled by default, by setting it to `0`.
+    #[clap(long, default_value = "0", parse(try_from_str=parse_bytes_with_unit))]
+    pub max_uncommitted_bytes: u64,
+
+    /// The time in milliseconds a client write waits for the uncommitted entries on the leader to
+    /// drop below `max_uncommitted_entries` and `max_uncommitted_bytes`.
+    ///
+    /// A write that can not be accepted in time fails with
+    /// [`ClientWriteError::Backpressure`](crate::error::ClientWriteError::Backpressure).
+    /// By default it is `0`: a write fails at once when a limit is reached.
+    #[clap(long, default_value = "0")]
+    pub backpressure_timeout: u64,
+
+    /// The time in milliseconds a leader handling a join request waits for the joining node to
+    /// become a voter.
+    ///
+    /// A join that does not complete in time fails with
+    /// [`JoinError::Timeout`](crate::error::JoinError::Timeout), and can be retried.
+    #[clap(long, default_value = "10000")]
+    pub join_timeout: u64,
+
     /// The maximum number of logs to keep that are already included in **snapshot**.
     ///
     /// Logs that are not in snapshot will never be purged.
@@ -184,12 +254,30 @@ pub struct Config {
            action = clap::ArgAction::Set,
            default_missing_value = "true")]
     pub enable_elect: bool,
+
+    /// Whether a leader steps down to a follower if it does not hear from a quorum of voters within
+    /// the election timeout. It keeps its vote and term.
+    ///
+    /// A leader that is cut off from a quorum stops accepting client writes that can never commit,
+    /// instead of waiting for a greater vote to reach it. The leader counts every response to
+    /// replication, including heartbeat, as an acknowledgement: `enable_heartbeat` should be on
+    /// when this is enabled.
+    ///
+    /// A restarted leader does not resume its leadership either: it starts as a follower.
+    #[clap(long,
+           default_value_t = false,
+           action = clap::ArgAction::Set,
+           default_missing_value = "true")]
+    pub enable_check_quorum: bool,
 }
 
 /// Updatable config for a raft runtime.
 pub(crate) struct RuntimeConfig {
     pub(crate) enable_heartbeat: AtomicBool,
     pub(crate) enable_elect: AtomicBool,
+    pub(crate) replication_max_bytes_per_sec: AtomicU64,
+    pub(crate) replication_target_max_bytes_per_sec: AtomicU64,
+    pub(crate) replication_learner_max_bytes_per_sec: AtomicU64,
 }
 
 impl RuntimeConfig {
@@ -197,6 +285,9 @@ impl RuntimeConfig {
         Self {
             enable_heartbeat: AtomicBool::from(config.enable_heartbeat),
             enable_elect: AtomicBool::from(config.enable_elect),
+            replication_max_bytes_per_sec: AtomicU64::from(config.replication_max_bytes_per_sec),
+            replication_target_max_bytes_per_sec: AtomicU64::from(config.replication_target_max_bytes_per_sec),
+            replication_learner_max_bytes_per_sec: AtomicU64::from(config.replication_learner_max_bytes_per_sec),
         }
     }
 }
@@ -213,6 +304,17 @@ impl Config {
         thread_rng().gen_range(self.election_timeout_min..self.election_timeout_max)
     }
 
+    /// Get the time a client write waits when the uncommitted entries on the leader reach the
+    /// limits.
+    pub fn backpressure_timeout(&self) -> Duration {
+        Duration::from_millis(self.backpressure_timeout)
+    }
+
+    /// Get the time a leader waits for a joining node to become a voter.
+    pub fn join_timeout(&self) -> Duration {
+        Duration::from_millis(self.join_timeout)
+    }
+
     /// Get the timeout for sending and installing the last snapshot segment.
     pub fn install_snapshot_timeout(&self) -> Duration {
         Duration::from_millis(self.install_snapshot_timeout)
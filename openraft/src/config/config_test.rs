@@ -13,10 +13,20 @@ fn test_config_defaults() {
 
     assert_eq!(50, cfg.heartbeat_interval);
     assert_eq!(300, cfg.max_payload_entries);
+    assert_eq!(0, cfg.max_payload_bytes);
     assert_eq!(5000, cfg.replication_lag_threshold);
 
     assert_eq!(3 * 1024 * 1024, cfg.snapshot_max_chunk_size);
     assert_eq!(SnapshotPolicy::LogsSinceLast(5000), cfg.snapshot_policy);
+
+    assert_eq!(0, cfg.replication_max_bytes_per_sec);
+    assert_eq!(0, cfg.replication_target_max_bytes_per_sec);
+    assert_eq!(0, cfg.replication_learner_max_bytes_per_sec);
+
+    assert_eq!(0, cfg.max_uncommitted_entries);
+    assert_eq!(0, cfg.max_uncommitted_bytes);
+    assert_eq!(0, cfg.backpressure_timeout);
+    assert_eq!(10_000, cfg.join_timeout);
 }
 
 #[test]
@@ -57,9 +67,17 @@ fn test_build() -> anyhow::Result<()> {
         "--send-snapshot-timeout=199",
         "--install-snapshot-timeout=200",
         "--max-payload-entries=201",
+        "--max-payload-bytes=2KiB",
         "--snapshot-policy=since_last:202",
         "--replication-lag-threshold=203",
         "--snapshot-max-chunk-size=204",
+        "--replication-max-bytes-per-sec=10MiB",
+        "--replication-target-max-bytes-per-sec=2MiB",
+        "--replication-learner-max-bytes-per-sec=208",
+        "--max-uncommitted-entries=209",
+        "--max-uncommitted-bytes=1KiB",
+        "--backpressure-timeout=210",
+        "--join-timeout=211",
         "--max-in-snapshot-log-to-keep=205",
         "--purge-batch-size=207",
     ])?;
@@ -71,9 +89,17 @@ fn test_build() -> anyhow::Result<()> {
     assert_eq!(199, config.send_snapshot_timeout);
     assert_eq!(200, config.install_snapshot_timeout);
     assert_eq!(201, config.max_payload_entries);
+    assert_eq!(2048, config.max_payload_bytes);
     assert_eq!(SnapshotPolicy::LogsSinceLast(202), config.snapshot_policy);
     assert_eq!(203, config.replication_lag_threshold);
     assert_eq!(204, config.snapshot_max_chunk_size);
+    assert_eq!(10 * 1024 * 1024, config.replication_max_bytes_per_sec);
+    assert_eq!(2 * 1024 * 1024, config.replication_target_max_bytes_per_sec);
+    assert_eq!(208, config.replication_learner_max_bytes_per_sec);
+    assert_eq!(209, config.max_uncommitted_entries);
+    assert_eq!(1024, config.max_uncommitted_bytes);
+    assert_eq!(210, config.backpressure_timeout);
+    assert_eq!(211, config.join_timeout);
     assert_eq!(205, config.max_in_snapshot_log_to_keep);
     assert_eq!(207, config.purge_batch_size);
 
@@ -82,6 +108,8 @@ fn test_build() -> anyhow::Result<()> {
         let mut c = config;
         assert_eq!(Duration::from_millis(199), c.send_snapshot_timeout());
         assert_eq!(Duration::from_millis(200), c.install_snapshot_timeout());
+        assert_eq!(Duration::from_millis(210), c.backpressure_timeout());
+        assert_eq!(Duration::from_millis(211), c.join_timeout());
 
         c.send_snapshot_timeout = 0;
         assert_eq!(
@@ -143,3 +171,20 @@ fn test_config_enable_elect() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_config_enable_check_quorum() -> anyhow::Result<()> {
+    let config = Config::build(&["foo", "--enable-check-quorum=false"])?;
+    assert_eq!(false, config.enable_check_quorum);
+
+    let config = Config::build(&["foo", "--enable-check-quorum=true"])?;
+    assert_eq!(true, config.enable_check_quorum);
+
+    let config = Config::build(&["foo", "--enable-check-quorum"])?;
+    assert_eq!(true, config.enable_check_quorum);
+
+    let config = Config::build(&["foo"])?;
+    assert_eq!(false, config.enable_check_quorum);
+
+    Ok(())
+}
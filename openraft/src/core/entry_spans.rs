@@ -0,0 +1,71 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use tracing::Span;
+
+use crate::LogId;
+use crate::NodeId;
+
+/// Spans that follow each log entry proposed by this leader, from proposal to apply.
+///
+/// It is enabled by feature `otel`. A span is created as a child of the span that proposes the
+/// entry, usually the one of `Raft::client_write()`. It records an event when the entry is
+/// appended and committed, and is closed when the entry is applied. The duration of the span is
+/// the latency of the write.
+///
+/// The log entries replicated to followers are sent in the span of the last entry, thus the
+/// `AppendEntries` RPCs and the handling on followers are part of the same trace.
+#[derive(Debug, Default)]
+pub(crate) struct EntrySpans {
+    /// Log index to the span of the entry.
+    spans: BTreeMap<u64, Span>,
+}
+
+impl EntrySpans {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start the span of a proposed entry, as a child of the current span.
+    pub(crate) fn proposed<NID: NodeId>(&mut self, log_id: &LogId<NID>) {
+        let span = tracing::info_span!("raft_entry", log_id = display(log_id));
+        span.in_scope(|| tracing::info!("proposed"));
+
+        self.spans.insert(log_id.index, span);
+    }
+
+    /// The span to replicate logs upto `index` in, or the current span if `index` is not proposed
+    /// by this leader.
+    pub(crate) fn span_for(&self, index: Option<u64>) -> Span {
+        index.and_then(|i| self.spans.get(&i)).cloned().unwrap_or_else(Span::current)
+    }
+
+    pub(crate) fn appended(&self, indexes: Range<u64>) {
+        self.event(indexes, "appended");
+    }
+
+    pub(crate) fn committed(&self, indexes: Range<u64>) {
+        self.event(indexes, "committed");
+    }
+
+    /// Close the spans of applied entries.
+    pub(crate) fn applied(&mut self, indexes: Range<u64>) {
+        self.event(indexes.clone(), "applied");
+
+        let rest = self.spans.split_off(&indexes.end);
+        self.spans = rest;
+    }
+
+    /// Close all of the spans when leadership is lost: the entries may never be applied.
+    pub(crate) fn clear(&mut self) {
+        for (_, span) in std::mem::take(&mut self.spans) {
+            span.in_scope(|| tracing::info!("leader quit"));
+        }
+    }
+
+    fn event(&self, indexes: Range<u64>, what: &'static str) {
+        for (_, span) in self.spans.range(indexes) {
+            span.in_scope(|| tracing::info!("{}", what));
+        }
+    }
+}
@@ -3,6 +3,7 @@ use tokio::io::AsyncWriteExt;
 use crate::core::streaming_state::StreamingState;
 use crate::core::RaftCore;
 use crate::core::SnapshotState;
+use crate::error::Fatal;
 use crate::error::SnapshotMismatch;
 use crate::raft::InstallSnapshotRequest;
 use crate::raft::InstallSnapshotResponse;
@@ -23,12 +24,15 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
     /// spec, a log may only have one snapshot at any time. As snapshot contents are application
     /// specific, the Raft log will only store a pointer to the snapshot file along with the
     /// index & term.
+    ///
+    /// The response reports the end of the data received for the snapshot, so that the leader can
+    /// resume an interrupted transfer from there, instead of from the beginning.
     #[tracing::instrument(level = "debug", skip_all)]
     pub(super) async fn handle_install_snapshot_request(
         &mut self,
         req: InstallSnapshotRequest<C>,
         tx: InstallSnapshotTx<C::NodeId>,
-    ) -> Result<(), StorageError<C::NodeId>> {
+    ) -> Result<(), Fatal<C::NodeId>> {
         tracing::debug!(req = display(req.summary()));
 
         let res = self.engine.vote_handler().handle_message_vote(&req.vote);
@@ -41,6 +45,7 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
             );
             let _ = tx.send(Ok(InstallSnapshotResponse {
                 vote: *self.engine.state.vote_ref(),
+                offset: None,
             }));
             return Ok(());
         }
@@ -61,13 +66,26 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
             self.snapshot_state = SnapshotState::None;
         }
 
-        // Init a new streaming state if it is None.
-        if let SnapshotState::None = self.snapshot_state {
-            if let Err(e) = self.check_new_install_snapshot(&req) {
+        // Init a new streaming state if it is None or it is receiving another snapshot.
+        let receiving = if let SnapshotState::Streaming(streaming) = &self.snapshot_state {
+            Some(streaming.snapshot_id.clone())
+        } else {
+            None
+        };
+
+        if receiving.as_ref() != Some(&req.meta.snapshot_id) {
+            // The storage may reuse the resource of the snapshot being received, e.g., a file, for
+            // another snapshot. The unfinished one is discarded before asking the storage.
+            self.snapshot_state = SnapshotState::None;
+
+            // Resume from the data received before, if the storage keeps it, e.g., across a restart.
+            let (snapshot_data, received) = self.storage.resume_receiving_snapshot(&req.meta).await?;
+
+            if let Err(e) = self.check_new_install_snapshot(&req, received) {
                 let _ = tx.send(Err(e.into()));
                 return Ok(());
             }
-            self.begin_installing_snapshot(&req).await?;
+            self.begin_installing_snapshot(&req, snapshot_data, received);
         }
 
         // It's Streaming.
@@ -75,28 +93,21 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
         let done = req.done;
         let req_meta = req.meta.clone();
 
-        // Changed to another stream. re-init snapshot state.
-        let stream_changed = if let SnapshotState::Streaming(streaming) = &self.snapshot_state {
-            req_meta.snapshot_id != streaming.snapshot_id
-        } else {
-            unreachable!("It has to be Streaming")
-        };
-
-        if stream_changed {
-            if let Err(e) = self.check_new_install_snapshot(&req) {
-                let _ = tx.send(Err(e.into()));
-                return Ok(());
-            }
-            self.begin_installing_snapshot(&req).await?;
-        }
-
         // Receive the data.
-        if let SnapshotState::Streaming(streaming) = &mut self.snapshot_state {
+        let offset = if let SnapshotState::Streaming(streaming) = &mut self.snapshot_state {
             debug_assert_eq!(req_meta.snapshot_id, streaming.snapshot_id);
             streaming.receive(req).await?;
+
+            // The last chunk is made durable when the snapshot is installed.
+            if !done {
+                self.storage
+                    .save_received_snapshot(&req_meta, &mut streaming.snapshot_data, streaming.offset)
+                    .await?;
+            }
+            streaming.offset
         } else {
             unreachable!("It has to be Streaming")
-        }
+        };
 
         if done {
             self.finalize_snapshot_installation(req_meta).await?;
@@ -104,22 +115,32 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
 
         let _ = tx.send(Ok(InstallSnapshotResponse {
             vote: *self.engine.state.vote_ref(),
+            offset: Some(offset),
         }));
 
         Ok(())
     }
 
+    /// Check if a new session can begin with the segment in `req`, when `received` bytes of the
+    /// snapshot have been received before.
+    ///
+    /// A new session must not begin with a gap. Otherwise it returns a `SnapshotMismatch` error
+    /// with the offset the leader should resume from.
     #[tracing::instrument(level = "debug", skip_all)]
-    fn check_new_install_snapshot(&mut self, req: &InstallSnapshotRequest<C>) -> Result<(), SnapshotMismatch> {
-        tracing::debug!(req = display(req.summary()));
+    fn check_new_install_snapshot(
+        &mut self,
+        req: &InstallSnapshotRequest<C>,
+        received: u64,
+    ) -> Result<(), SnapshotMismatch> {
+        tracing::debug!(req = display(req.summary()), received);
 
         let id = req.meta.snapshot_id.clone();
 
-        if req.offset > 0 {
+        if req.offset > received {
             return Err(SnapshotMismatch {
                 expect: SnapshotSegmentId {
                     id: id.clone(),
-                    offset: 0,
+                    offset: received,
                 },
                 got: SnapshotSegmentId { id, offset: req.offset },
             });
@@ -129,18 +150,16 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
     }
 
     #[tracing::instrument(level = "debug", skip_all)]
-    async fn begin_installing_snapshot(
+    fn begin_installing_snapshot(
         &mut self,
         req: &InstallSnapshotRequest<C>,
-    ) -> Result<(), StorageError<C::NodeId>> {
-        tracing::debug!(req = display(req.summary()));
+        snapshot_data: Box<S::SnapshotData>,
+        received: u64,
+    ) {
+        tracing::debug!(req = display(req.summary()), received);
 
         let id = req.meta.snapshot_id.clone();
-
-        let snapshot_data = self.storage.begin_receiving_snapshot().await?;
-        self.snapshot_state = SnapshotState::Streaming(StreamingState::new(id, snapshot_data));
-
-        Ok(())
+        self.snapshot_state = SnapshotState::Streaming(StreamingState::new(id, snapshot_data, received));
     }
 
     /// Finalize the installation of a new snapshot.
@@ -150,7 +169,7 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
     async fn finalize_snapshot_installation(
         &mut self,
         meta: SnapshotMeta<C::NodeId, C::Node>,
-    ) -> Result<(), StorageError<C::NodeId>> {
+    ) -> Result<(), Fatal<C::NodeId>> {
         tracing::debug!(meta = display(meta.summary()));
 
         let state = std::mem::take(&mut self.snapshot_state);
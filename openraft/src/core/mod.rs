@@ -4,6 +4,7 @@
 //! Also it receives and execute `Command` emitted by `Engine` to apply raft state to underlying
 //! storage or forward messages to other raft nodes.
 
+#[cfg(feature = "otel")] mod entry_spans;
 mod install_snapshot;
 mod raft_core;
 mod replication_state;
@@ -12,6 +13,7 @@ mod snapshot_state;
 mod streaming_state;
 mod tick;
 
+#[cfg(feature = "otel")] pub(crate) use entry_spans::EntrySpans;
 pub use raft_core::RaftCore;
 pub(crate) use replication_state::replication_lag;
 pub use server_state::ServerState;
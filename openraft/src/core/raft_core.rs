@@ -29,29 +29,37 @@ use tracing::Span;
 use crate::config::Config;
 use crate::config::RuntimeConfig;
 use crate::config::SnapshotPolicy;
+#[cfg(feature = "otel")] use crate::core::EntrySpans;
 use crate::core::ServerState;
 use crate::core::SnapshotResult;
 use crate::core::SnapshotState;
 use crate::display_ext::DisplaySlice;
+#[cfg(feature = "invariant-check")] use crate::engine::invariants::InvariantChecker;
 use crate::engine::Command;
 use crate::engine::Engine;
 use crate::engine::SendResult;
 use crate::entry::FromAppData;
 use crate::entry::RaftEntry;
 use crate::entry::RaftPayload;
+use crate::error::Backpressure;
 use crate::error::CheckIsLeaderError;
 use crate::error::ClientWriteError;
 use crate::error::Fatal;
 use crate::error::ForwardToLeader;
 use crate::error::InitializeError;
+use crate::error::JoinError;
+use crate::error::LogIdMismatch;
+use crate::error::NodeConflict;
 use crate::error::QuorumNotEnough;
 use crate::error::RPCError;
 use crate::error::Timeout;
+use crate::error::WaitAppliedError;
 use crate::log_id::LogIdOptionExt;
 use crate::log_id::RaftLogId;
 use crate::metrics::RaftMetrics;
 use crate::metrics::ReplicationMetrics;
 use crate::metrics::UpdateMatchedLogId;
+use crate::metrics::UpdateThrottled;
 use crate::progress::entry::ProgressEntry;
 use crate::progress::Inflight;
 use crate::progress::Progress;
@@ -60,6 +68,7 @@ use crate::raft::AppendEntriesRequest;
 use crate::raft::AppendEntriesResponse;
 use crate::raft::AppendEntriesTx;
 use crate::raft::ClientWriteResponse;
+use crate::raft::ClientWriteRx;
 use crate::raft::ClientWriteTx;
 use crate::raft::ExternalCommand;
 use crate::raft::RaftMsg;
@@ -67,12 +76,14 @@ use crate::raft::RaftRespTx;
 use crate::raft::VoteRequest;
 use crate::raft::VoteResponse;
 use crate::raft::VoteTx;
+use crate::raft::WaitAppliedTx;
 use crate::raft_state::LogStateReader;
 use crate::replication::Replicate;
 use crate::replication::ReplicationCore;
 use crate::replication::ReplicationHandle;
 use crate::replication::ReplicationResult;
 use crate::replication::ReplicationSessionId;
+use crate::replication::Throttle;
 use crate::runtime::RaftRuntime;
 use crate::storage::RaftSnapshotBuilder;
 use crate::versioned::Updatable;
@@ -89,6 +100,7 @@ use crate::RaftTypeConfig;
 use crate::SnapshotId;
 use crate::StorageError;
 use crate::StorageHelper;
+use crate::TraceContext;
 use crate::Update;
 use crate::Vote;
 
@@ -111,6 +123,16 @@ where SD: AsyncRead + AsyncSeek + Send + Unpin + 'static
 
     /// The time to send next heartbeat.
     pub(crate) next_heartbeat: Instant,
+
+    /// The sizes of the uncommitted entries proposed by this leader, keyed by log index.
+    pub(crate) uncommitted_sizes: BTreeMap<u64, u64>,
+
+    /// The total size of the entries in `uncommitted_sizes`.
+    pub(crate) uncommitted_bytes: u64,
+
+    /// Client writes that wait for the uncommitted entries to drop below the limits, in the order
+    /// they are received.
+    pub(crate) pending_writes: VecDeque<PendingWrite<C>>,
 }
 
 impl<C: RaftTypeConfig, SD> LeaderData<C, SD>
@@ -122,8 +144,36 @@ where SD: AsyncRead + AsyncSeek + Send + Unpin + 'static
             replications: BTreeMap::new(),
             replication_metrics: Versioned::new(ReplicationMetrics::default()),
             next_heartbeat: Instant::now(),
+            uncommitted_sizes: BTreeMap::new(),
+            uncommitted_bytes: 0,
+            pending_writes: VecDeque::new(),
         }
     }
+
+    /// Count the entry of `size` bytes at `index` as uncommitted.
+    pub(crate) fn add_uncommitted(&mut self, index: u64, size: u64) {
+        self.uncommitted_sizes.insert(index, size);
+        self.uncommitted_bytes += size;
+    }
+
+    /// Stop counting the entries up to `index`, inclusive, which are committed.
+    pub(crate) fn commit_upto(&mut self, index: u64) {
+        let rest = self.uncommitted_sizes.split_off(&(index + 1));
+        let committed = std::mem::replace(&mut self.uncommitted_sizes, rest);
+        self.uncommitted_bytes -= committed.values().sum::<u64>();
+    }
+}
+
+/// A client write that waits for the uncommitted entries on the leader to drop below the limits.
+pub(crate) struct PendingWrite<C: RaftTypeConfig> {
+    pub(crate) entry: C::Entry,
+    pub(crate) tx: ClientWriteTx<C>,
+
+    /// The write fails with [`Backpressure`] if it is still waiting at this time.
+    pub(crate) deadline: Instant,
+
+    #[cfg(feature = "otel")]
+    pub(crate) span: Span,
 }
 
 /// The core type implementing the Raft protocol.
@@ -136,6 +186,9 @@ pub struct RaftCore<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<
 
     pub(crate) runtime_config: Arc<RuntimeConfig>,
 
+    /// Limits the total rate of all replication streams.
+    pub(crate) replication_throttle: Arc<Throttle>,
+
     /// The `RaftNetworkFactory` implementation.
     pub(crate) network: N,
 
@@ -158,11 +211,22 @@ pub struct RaftCore<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<
     /// Received snapshot that are ready to install.
     pub(crate) received_snapshot: BTreeMap<SnapshotId, Box<S::SnapshotData>>,
 
+    /// Callers waiting for a log to be applied, keyed by log index.
+    pub(crate) wait_applied: BTreeMap<u64, Vec<(LogId<C::NodeId>, WaitAppliedTx<C::NodeId>)>>,
+
     pub(crate) tx_api: mpsc::UnboundedSender<RaftMsg<C, N, S>>,
     pub(crate) rx_api: mpsc::UnboundedReceiver<RaftMsg<C, N, S>>,
 
     pub(crate) tx_metrics: watch::Sender<RaftMetrics<C::NodeId, C::Node>>,
 
+    /// Checks the engine state after every batch of commands.
+    #[cfg(feature = "invariant-check")]
+    pub(crate) invariants: InvariantChecker<C::NodeId>,
+
+    /// Spans that follow the entries proposed by this leader.
+    #[cfg(feature = "otel")]
+    pub(crate) entry_spans: EntrySpans,
+
     pub(crate) span: Span,
 }
 
@@ -198,6 +262,8 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
         let now = Instant::now();
         self.engine.timer.update_now(now);
 
+        self.apply_committed_at_startup().await?;
+
         self.engine.startup();
         self.run_engine_commands().await?;
 
@@ -207,6 +273,29 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
         self.runtime_loop(rx_shutdown).await
     }
 
+    /// Apply the logs that are committed but not yet applied before the last shutdown.
+    ///
+    /// The committed log id is restored from storage, if the storage saves it.
+    async fn apply_committed_at_startup(&mut self) -> Result<(), StorageError<C::NodeId>> {
+        let committed = match self.engine.state.committed() {
+            None => return Ok(()),
+            Some(x) => *x,
+        };
+
+        let (last_applied, _) = self.storage.last_applied_state().await?;
+
+        if Some(committed) > last_applied {
+            tracing::info!(
+                last_applied = display(last_applied.summary()),
+                committed = display(committed),
+                "apply committed logs at startup"
+            );
+            self.apply_to_state_machine(last_applied.next_index(), committed.index).await?;
+        }
+
+        Ok(())
+    }
+
     /// Handle `is_leader` requests.
     ///
     /// Spawn requests to all members of the cluster, include members being added in joint
@@ -223,7 +312,7 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
     pub(super) async fn handle_check_is_leader_request(
         &mut self,
         tx: RaftRespTx<(), CheckIsLeaderError<C::NodeId, C::Node>>,
-    ) -> Result<(), StorageError<C::NodeId>> {
+    ) -> Result<(), Fatal<C::NodeId>> {
         // Setup sentinel values to track when we've received majority confirmation of leadership.
 
         let em = self.engine.state.membership_state.effective();
@@ -256,6 +345,7 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
                 prev_log_id: progress.matching,
                 entries: vec![],
                 leader_commit: self.engine.state.committed().copied(),
+                trace_context: TraceContext::current(),
             };
 
             let my_id = self.id;
@@ -390,6 +480,153 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
         Ok(())
     }
 
+    /// Add a joining node as a learner, unless it is already in the membership.
+    ///
+    /// It replies `None` if the node is already in the membership with the same `Node`, otherwise
+    /// the receiver of the result of writing the membership log.
+    #[tracing::instrument(level = "debug", skip(self, tx))]
+    pub(super) async fn handle_join(
+        &mut self,
+        id: C::NodeId,
+        node: C::Node,
+        tx: RaftRespTx<Option<ClientWriteRx<C>>, JoinError<C::NodeId, C::Node>>,
+    ) -> Result<(), Fatal<C::NodeId>> {
+        if !self.engine.state.is_leader(&self.engine.config.id) {
+            self.reject_with_forward_to_leader(tx);
+            return Ok(());
+        }
+
+        if let Some(existing) = self.engine.state.membership_state.effective().get_node(&id) {
+            let res = if existing == &node {
+                Ok(None)
+            } else {
+                Err(JoinError::NodeConflict(NodeConflict {
+                    node_id: id,
+                    existing: existing.clone(),
+                    joining: node,
+                }))
+            };
+            let _ = tx.send(res);
+            return Ok(());
+        }
+
+        let (write_tx, write_rx) = oneshot::channel();
+        let _ = tx.send(Ok(Some(write_rx)));
+
+        self.change_membership(ChangeMembers::AddNodes(btreemap! {id=>node}), true, write_tx).await
+    }
+
+    /// Handle a client write, or hold it back if the uncommitted entries on the leader reach the
+    /// limits.
+    ///
+    /// A held write is written when the uncommitted entries drop below the limits, or fails with
+    /// [`Backpressure`] if it waits for longer than `backpressure_timeout`. If the timeout is `0`,
+    /// it fails at once.
+    #[tracing::instrument(level = "debug", skip_all, fields(id = display(self.id)))]
+    pub(crate) async fn handle_client_write(
+        &mut self,
+        entry: C::Entry,
+        tx: ClientWriteTx<C>,
+        #[cfg(feature = "otel")] span: Span,
+    ) -> Result<(), Fatal<C::NodeId>> {
+        if let Some(l) = &self.leader_data {
+            // Writes are accepted in order: a write does not overtake the held ones.
+            if self.is_backpressure_reached() || !l.pending_writes.is_empty() {
+                let timeout = self.config.backpressure_timeout();
+
+                if timeout.is_zero() {
+                    let usage = self.write_usage();
+                    tracing::debug!("reject client write: {}", usage);
+
+                    let _ = tx.send(Err(usage.into()));
+                    return Ok(());
+                }
+
+                if let Some(l) = &mut self.leader_data {
+                    l.pending_writes.push_back(PendingWrite {
+                        entry,
+                        tx,
+                        deadline: Instant::now() + timeout,
+                        #[cfg(feature = "otel")]
+                        span,
+                    });
+                }
+                return Ok(());
+            }
+        }
+
+        let fu = self.write_entry(entry, Some(tx));
+
+        // Handle the write in the trace of the client.
+        #[cfg(feature = "otel")]
+        let fu = fu.instrument(span);
+
+        fu.await?;
+        Ok(())
+    }
+
+    /// Write the held client writes in order while the uncommitted entries are below the limits,
+    /// and fail those that have waited for `backpressure_timeout`.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub(crate) async fn handle_pending_writes(&mut self) -> Result<(), Fatal<C::NodeId>> {
+        while !self.is_backpressure_reached() {
+            let w = match self.leader_data.as_mut().and_then(|l| l.pending_writes.pop_front()) {
+                Some(w) => w,
+                None => break,
+            };
+
+            let fu = self.write_entry(w.entry, Some(w.tx));
+
+            #[cfg(feature = "otel")]
+            let fu = fu.instrument(w.span);
+
+            fu.await?;
+        }
+
+        let now = Instant::now();
+        let usage = self.write_usage();
+
+        if let Some(l) = &mut self.leader_data {
+            // All writes wait for the same timeout: the earlier received ones time out earlier.
+            while l.pending_writes.front().map(|w| w.deadline <= now).unwrap_or(false) {
+                let w = l.pending_writes.pop_front().unwrap();
+                let _ = w.tx.send(Err(usage.clone().into()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the uncommitted entries on the leader and the limits of them.
+    ///
+    /// The usage is `0` if this node is not a leader.
+    pub(crate) fn write_usage(&self) -> Backpressure {
+        let (uncommitted_entries, uncommitted_bytes) = match &self.leader_data {
+            Some(l) => (self.uncommitted_entries(), l.uncommitted_bytes),
+            None => (0, 0),
+        };
+
+        Backpressure {
+            uncommitted_entries,
+            uncommitted_bytes,
+            max_entries: self.config.max_uncommitted_entries,
+            max_bytes: self.config.max_uncommitted_bytes,
+        }
+    }
+
+    /// Returns `true` if the uncommitted entries on the leader reach any of the limits.
+    fn is_backpressure_reached(&self) -> bool {
+        let u = self.write_usage();
+
+        (u.max_entries > 0 && u.uncommitted_entries >= u.max_entries)
+            || (u.max_bytes > 0 && u.uncommitted_bytes >= u.max_bytes)
+    }
+
+    /// The number of the entries in the local log that are not yet committed.
+    fn uncommitted_entries(&self) -> u64 {
+        self.engine.state.last_log_id().next_index() - self.engine.state.committed().next_index()
+    }
+
     /// Write a log entry to the cluster through raft protocol.
     ///
     /// I.e.: append the log entry to local store, forward it to a quorum(including the leader),
@@ -416,10 +653,20 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
         //       caller.
         lh.leader_append_entries(&mut entries);
 
-        // Install callback channels.
-        if let Some(tx) = tx {
-            if let Some(l) = &mut self.leader_data {
-                l.client_resp_channels.insert(entries[0].get_log_id().index, tx);
+        // The size is computed once, when the entry is appended.
+        let size = self.engine.state.log_size(entries[0].get_log_id().index).unwrap_or_default();
+
+        #[cfg(feature = "otel")]
+        self.entry_spans.proposed(entries[0].get_log_id());
+
+        if let Some(l) = &mut self.leader_data {
+            let index = entries[0].get_log_id().index;
+
+            l.add_uncommitted(index, size);
+
+            // Install callback channels.
+            if let Some(tx) = tx {
+                l.client_resp_channels.insert(index, tx);
             }
         }
 
@@ -431,7 +678,7 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
 
     /// Send a heartbeat message to every followers/learners.
     ///
-    /// Currently heartbeat is a blank log
+    /// A heartbeat is sent by the heartbeat task of every target, independent of the replication.
     #[tracing::instrument(level = "debug", skip_all, fields(id = display(self.id)))]
     pub async fn send_heartbeat(&mut self, emitter: impl Display) -> Result<bool, Fatal<C::NodeId>> {
         tracing::debug!(now = debug(self.engine.timer.now()), "send_heartbeat");
@@ -487,6 +734,8 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
             Update::AsIs => self.tx_metrics.borrow().replication.clone(),
         };
 
+        let usage = self.write_usage();
+
         let m = RaftMetrics {
             running_state: Ok(()),
             id: self.id,
@@ -504,6 +753,8 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
 
             // --- replication ---
             replication,
+            uncommitted_entries: usage.uncommitted_entries,
+            uncommitted_bytes: usage.uncommitted_bytes,
         };
 
         {
@@ -531,7 +782,7 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
         &mut self,
         member_nodes: BTreeMap<C::NodeId, C::Node>,
         tx: RaftRespTx<(), InitializeError<C::NodeId, C::Node>>,
-    ) -> Result<(), StorageError<C::NodeId>> {
+    ) -> Result<(), Fatal<C::NodeId>> {
         let membership = Membership::from(member_nodes);
 
         let mut entries = [C::Entry::new_membership(LogId::default(), membership)];
@@ -549,7 +800,7 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
     pub(crate) async fn handle_building_snapshot_result(
         &mut self,
         result: SnapshotResult<C::NodeId, C::Node>,
-    ) -> Result<(), StorageError<C::NodeId>> {
+    ) -> Result<(), Fatal<C::NodeId>> {
         tracing::info!("handle_building_snapshot_result: {:?}", result);
 
         if let SnapshotState::Streaming { .. } = &self.snapshot_state {
@@ -564,7 +815,7 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
                 self.run_engine_commands().await?;
             }
             SnapshotResult::StorageError(sto_err) => {
-                return Err(sto_err);
+                return Err(sto_err.into());
             }
             SnapshotResult::Aborted => {}
         }
@@ -664,7 +915,7 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
 
         let vote = self.engine.state.vote_ref();
 
-        if !vote.is_committed() {
+        if !vote.is_committed() || self.engine.state.is_stepped_down() {
             return None;
         }
 
@@ -739,10 +990,88 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
             }
         }
 
+        #[cfg(feature = "otel")]
+        self.entry_spans.applied(since..end);
+
+        self.notify_applied();
+
         self.trigger_snapshot_if_needed(false).await;
         Ok(())
     }
 
+    /// Reply at once if `log_id` is applied, otherwise wait until it is applied.
+    pub(crate) fn handle_wait_applied(&mut self, log_id: LogId<C::NodeId>, tx: WaitAppliedTx<C::NodeId>) {
+        if let Some(res) = self.check_applied(&log_id) {
+            let _ = tx.send(res);
+            return;
+        }
+
+        // Only the waiters of the same index are checked: the others are released when the
+        // applied log id reaches them.
+        let waiters = self.wait_applied.entry(log_id.index).or_default();
+
+        // Remove the waiters whose caller has timed out and dropped the receiver.
+        waiters.retain(|(_, tx)| !tx.is_closed());
+        waiters.push((log_id, tx));
+    }
+
+    /// Wake up the callers waiting for a log that is applied.
+    pub(crate) fn notify_applied(&mut self) {
+        let applied_index = match self.engine.state.committed() {
+            None => return,
+            Some(x) => x.index,
+        };
+
+        let waiting = self.wait_applied.split_off(&(applied_index + 1));
+        let ready = std::mem::replace(&mut self.wait_applied, waiting);
+
+        for (log_id, tx) in ready.into_values().flatten() {
+            let res = self.check_applied(&log_id).unwrap_or_else(|| unreachable!("{} is applied", log_id));
+            let _ = tx.send(res);
+        }
+    }
+
+    /// Check if `log_id` is applied to the state machine.
+    ///
+    /// It returns `None` if no log is applied at `log_id.index` yet, or an error if another log
+    /// is applied there.
+    fn check_applied(&self, log_id: &LogId<C::NodeId>) -> Option<Result<(), WaitAppliedError<C::NodeId>>> {
+        // Every committed log is applied before RaftCore handles the next message.
+        if self.engine.state.committed().index() < Some(log_id.index) {
+            return None;
+        }
+
+        let got = self.engine.state.get_log_id(log_id.index);
+
+        if let Some(got) = got {
+            return if got == *log_id {
+                Some(Ok(()))
+            } else {
+                Some(Err(LogIdMismatch {
+                    expect: *log_id,
+                    got: Some(got),
+                }
+                .into()))
+            };
+        }
+
+        // The log is applied then purged, or is covered by an installed snapshot. Only the last
+        // purged log id is still known.
+        let purged = self.engine.state.last_purged_log_id();
+
+        // The leader id of the committed logs only increases: a greater leader id than the last
+        // purged log must be another log.
+        if purged.map(|x| x.leader_id) < Some(log_id.leader_id) {
+            return Some(Err(LogIdMismatch {
+                expect: *log_id,
+                got: None,
+            }
+            .into()));
+        }
+
+        Some(Ok(()))
+    }
+
     /// Send result of applying a log entry to its client.
     #[tracing::instrument(level = "debug", skip_all)]
     pub(super) fn send_response(entry: &C::Entry, resp: C::R, tx: Option<ClientWriteTx<C>>) {
@@ -780,7 +1109,9 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
         let target_node = self.engine.state.membership_state.effective().get_node(&target).unwrap();
 
         let membership_log_id = self.engine.state.membership_state.effective().log_id();
+        let is_learner = !self.engine.state.membership_state.effective().is_voter(&target);
         let network = self.network.new_client(target, target_node).await;
+        let heartbeat_network = self.network.new_client(target, target_node).await;
 
         let session_id = ReplicationSessionId::new(*self.engine.state.vote_ref(), *membership_log_id);
 
@@ -788,9 +1119,13 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
             target,
             session_id,
             self.config.clone(),
+            self.runtime_config.clone(),
+            self.replication_throttle.clone(),
+            is_learner,
             self.engine.state.committed().copied(),
             progress_entry.matching,
             network,
+            heartbeat_network,
             self.storage.get_log_reader().await,
             self.tx_api.clone(),
             tracing::span!(parent: &self.span, Level::DEBUG, "replication", id=display(self.id), target=display(target)),
@@ -830,7 +1165,7 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
 
 impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C, N, S> {
     #[tracing::instrument(level = "debug", skip_all)]
-    pub(crate) async fn run_engine_commands(&mut self) -> Result<(), StorageError<C::NodeId>> {
+    pub(crate) async fn run_engine_commands(&mut self) -> Result<(), Fatal<C::NodeId>> {
         if tracing::enabled!(Level::DEBUG) {
             tracing::debug!("queued commands: start...");
             for c in self.engine.output.iter_commands() {
@@ -844,6 +1179,9 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
             self.run_command(cmd).await?;
         }
 
+        #[cfg(feature = "invariant-check")]
+        self.invariants.check(&self.engine)?;
+
         Ok(())
     }
 
@@ -853,23 +1191,38 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
         loop {
             self.flush_metrics();
 
-            let msg_res: Result<RaftMsg<C, N, S>, &str> = {
-                let recv = self.rx_api.recv();
+            // Wake up when the earliest held write times out, even if no message is received.
+            let write_deadline = self.leader_data.as_ref().and_then(|l| l.pending_writes.front()).map(|w| w.deadline);
+
+            let msg_res: Result<Option<RaftMsg<C, N, S>>, &str> = {
+                let recv = async {
+                    match write_deadline {
+                        Some(deadline) => tokio::time::timeout_at(deadline, self.rx_api.recv()).await.ok(),
+                        None => Some(self.rx_api.recv().await),
+                    }
+                };
                 pin_mut!(recv);
 
                 let either = select(recv, Pin::new(&mut rx_shutdown)).await;
 
                 match either {
                     Either::Left((recv_res, _shutdown)) => match recv_res {
-                        Some(msg) => Ok(msg),
-                        None => Err("all rx_api senders are dropped"),
+                        Some(Some(msg)) => Ok(Some(msg)),
+                        Some(None) => Err("all rx_api senders are dropped"),
+                        None => Ok(None),
                     },
                     Either::Right((_rx_shutdown_res, _recv)) => Err("recv from rx_shutdown"),
                 }
             };
 
             match msg_res {
-                Ok(msg) => self.handle_api_msg(msg).await?,
+                Ok(Some(msg)) => {
+                    self.handle_api_msg(msg).await?;
+                    self.handle_pending_writes().await?;
+                }
+                Ok(None) => {
+                    self.handle_pending_writes().await?;
+                }
                 Err(reason) => {
                     tracing::info!(reason);
                     return Ok(());
@@ -890,7 +1243,8 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
                 continue;
             }
 
-            let req = vote_req.clone();
+            let mut req = vote_req.clone();
+            req.trace_context = TraceContext::current();
 
             // Safe unwrap(): target must be in membership
             let target_node = self.engine.state.membership_state.effective().get_node(&target).unwrap().clone();
@@ -940,7 +1294,7 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
         &mut self,
         req: VoteRequest<C::NodeId>,
         tx: VoteTx<C::NodeId>,
-    ) -> Result<(), StorageError<C::NodeId>> {
+    ) -> Result<(), Fatal<C::NodeId>> {
         tracing::debug!(req = display(req.summary()), func = func_name!());
 
         let resp = self.engine.handle_vote_req(req);
@@ -959,7 +1313,7 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
         &mut self,
         resp: VoteResponse<C::NodeId>,
         target: C::NodeId,
-    ) -> Result<(), StorageError<C::NodeId>> {
+    ) -> Result<(), Fatal<C::NodeId>> {
         tracing::debug!(
             resp = debug(&resp),
             target = display(target),
@@ -979,7 +1333,7 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
         &mut self,
         req: AppendEntriesRequest<C>,
         tx: AppendEntriesTx<C::NodeId>,
-    ) -> Result<(), StorageError<C::NodeId>> {
+    ) -> Result<(), Fatal<C::NodeId>> {
         tracing::debug!(req = display(req.summary()), func = func_name!());
 
         let resp = self.engine.handle_append_entries_req(&req.vote, req.prev_log_id, &req.entries, req.leader_commit);
@@ -1043,8 +1397,22 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
                     self.reject_with_forward_to_leader(tx);
                 }
             }
-            RaftMsg::ClientWriteRequest { app_data, tx } => {
-                self.write_entry(C::Entry::from_app_data(app_data), Some(tx)).await?;
+            RaftMsg::ClientWriteRequest {
+                app_data,
+                tx,
+                #[cfg(feature = "otel")]
+                span,
+            } => {
+                self.handle_client_write(
+                    C::Entry::from_app_data(app_data),
+                    tx,
+                    #[cfg(feature = "otel")]
+                    span,
+                )
+                .await?;
+            }
+            RaftMsg::WaitApplied { log_id, tx } => {
+                self.handle_wait_applied(log_id, tx);
             }
             RaftMsg::Initialize { members, tx } => {
                 self.handle_initialize(members, tx).await?;
@@ -1055,13 +1423,22 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
             RaftMsg::ChangeMembership { changes, retain, tx } => {
                 self.change_membership(changes, retain, tx).await?;
             }
+            RaftMsg::Join { id, node, tx } => {
+                self.handle_join(id, node, tx).await?;
+            }
+            RaftMsg::NewNetworkClient { target, node, tx } => {
+                let client = self.network.new_client(target, &node).await;
+                let _ = tx.send(Ok(client));
+            }
             RaftMsg::ExternalRequest { req } => {
                 req(&self.engine.state, &mut self.storage, &mut self.network);
             }
             RaftMsg::ExternalCommand { cmd } => {
                 match cmd {
                     ExternalCommand::Elect => {
-                        if self.engine.state.membership_state.effective().is_voter(&self.id) {
+                        if self.engine.state.server_state == ServerState::Removed {
+                            tracing::debug!("ExternalCommand: this node is removed, do not elect");
+                        } else if self.engine.state.membership_state.effective().is_voter(&self.id) {
                             // TODO: reject if it is already a leader?
                             self.engine.elect();
                             self.run_engine_commands().await?;
@@ -1138,6 +1515,26 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
                     self.handle_replication_progress(target, id, result).await?;
                 }
             }
+            RaftMsg::UpdateReplicationThrottled {
+                target,
+                throttled,
+                session_id,
+            } => {
+                if self.does_replication_session_match(&session_id, "UpdateReplicationThrottled") {
+                    if let Some(l) = &mut self.leader_data {
+                        l.replication_metrics.update(UpdateThrottled { target, throttled });
+                        self.engine.output.metrics_flags.set_replication_changed();
+                    }
+                }
+            }
+            RaftMsg::HeartbeatAck { target, session_id } => {
+                if self.does_replication_session_match(&session_id, "HeartbeatAck")
+                    && self.engine.internal_server_state.is_leading()
+                {
+                    self.engine.timer.update_now(Instant::now());
+                    self.engine.handle_heartbeat_ack(target);
+                }
+            }
             RaftMsg::ReplicationFatal => {
                 return Err(Fatal::Stopped);
             }
@@ -1146,7 +1543,7 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
     }
 
     #[tracing::instrument(level = "debug", skip_all)]
-    async fn handle_tick_election(&mut self) -> Result<(), StorageError<C::NodeId>> {
+    async fn handle_tick_election(&mut self) -> Result<(), Fatal<C::NodeId>> {
         let now = *self.engine.timer.now();
 
         tracing::debug!("try to trigger election by tick, now: {:?}", now);
@@ -1158,6 +1555,11 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
             return Ok(());
         }
 
+        if self.engine.state.server_state == ServerState::Removed {
+            tracing::debug!("this node is removed from the cluster, do not elect");
+            return Ok(());
+        }
+
         if !self.engine.state.membership_state.effective().is_voter(&self.id) {
             tracing::debug!("this node is not a voter");
             return Ok(());
@@ -1220,7 +1622,7 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
         target: C::NodeId,
         id: u64,
         result: Result<ReplicationResult<C::NodeId>, String>,
-    ) -> Result<(), StorageError<C::NodeId>> {
+    ) -> Result<(), Fatal<C::NodeId>> {
         tracing::debug!(
             target = display(target),
             result = debug(&result),
@@ -1239,7 +1641,8 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftCore<C,
 
         // TODO: A leader may have stepped down.
         if self.engine.internal_server_state.is_leading() {
-            self.engine.replication_handler().update_progress(target, id, result);
+            self.engine.timer.update_now(Instant::now());
+            self.engine.handle_replication_progress(target, id, result);
             self.run_engine_commands().await?;
         }
 
@@ -1316,7 +1719,9 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftRuntime
                 if let Some(l) = &mut self.leader_data {
                     // Leadership lost, inform waiting clients
                     let chans = std::mem::take(&mut l.client_resp_channels);
-                    for (_, tx) in chans.into_iter() {
+                    let pending = std::mem::take(&mut l.pending_writes);
+
+                    for tx in chans.into_values().chain(pending.into_iter().map(|w| w.tx)) {
                         let _ = tx.send(Err(ClientWriteError::ForwardToLeader(ForwardToLeader {
                             leader_id: None,
                             leader_node: None,
@@ -1324,6 +1729,9 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftRuntime
                     }
                 }
                 self.leader_data = None;
+
+                #[cfg(feature = "otel")]
+                self.entry_spans.clear();
             }
             Command::AppendInputEntries { range } => {
                 // AppendInputEntries implies to consume the input.
@@ -1334,7 +1742,11 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftRuntime
                 tracing::debug!("AppendInputEntries: {}", DisplaySlice::<_>(&entries));
 
                 if !entries.is_empty() {
-                    self.storage.append_to_log(&entries).await?
+                    self.storage.append_to_log(&entries).await?;
+
+                    #[cfg(feature = "otel")]
+                    self.entry_spans
+                        .appended(entries[0].get_log_id().index..entries[entries.len() - 1].get_log_id().index + 1);
                 }
             }
             Command::AppendBlankLog { log_id } => {
@@ -1345,6 +1757,9 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftRuntime
             Command::SaveVote { vote } => {
                 self.storage.save_vote(&vote).await?;
             }
+            Command::SaveRemovedBy { removed_by } => {
+                self.storage.save_removed_by(removed_by).await?;
+            }
             Command::PurgeLog { upto } => self.storage.purge_logs_upto(upto).await?,
             Command::DeleteConflictLog { since } => {
                 self.storage.delete_conflict_logs_since(since).await?;
@@ -1367,24 +1782,56 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftRuntime
                 ref already_committed,
                 ref upto,
             } => {
+                self.storage.save_committed(Some(*upto)).await?;
+
+                if let Some(l) = &mut self.leader_data {
+                    l.commit_upto(upto.index);
+                }
+
+                #[cfg(feature = "otel")]
+                self.entry_spans.committed(already_committed.next_index()..upto.index + 1);
+
                 self.apply_to_state_machine(already_committed.next_index(), upto.index).await?;
             }
             Command::FollowerCommit {
                 ref already_committed,
                 ref upto,
             } => {
+                self.storage.save_committed(Some(*upto)).await?;
                 self.apply_to_state_machine(already_committed.next_index(), upto.index).await?;
             }
+            Command::SendHeartbeat { committed } => {
+                if let Some(l) = &self.leader_data {
+                    for node in l.replications.values() {
+                        let _ = node.tx_heartbeat.send(committed);
+                    }
+                } else {
+                    unreachable!("it has to be a leader!!!");
+                }
+            }
             Command::Replicate { req, target } => {
                 if let Some(l) = &self.leader_data {
                     let node = l.replications.get(&target).expect("replication to target node exists");
 
                     match req {
                         Inflight::None => {
-                            let _ = node.tx_repl.send(Replicate::Heartbeat);
+                            // Heartbeats are sent on their own lane, not as a replication request.
+                            tracing::debug!("no data to replicate to target: {}", target);
                         }
                         Inflight::Logs { id, log_id_range } => {
-                            let _ = node.tx_repl.send(Replicate::logs(id, log_id_range));
+                            #[cfg(feature = "otel")]
+                            let span = self.entry_spans.span_for(log_id_range.last_log_id.index());
+
+                            let start = log_id_range.prev_log_id.next_index();
+                            let end = log_id_range.last_log_id.next_index();
+                            let bytes = (start..end).filter_map(|i| self.engine.state.log_size(i)).sum();
+
+                            let r = Replicate::logs(id, log_id_range, bytes);
+
+                            #[cfg(feature = "otel")]
+                            let r = r.in_span(span);
+
+                            let _ = node.tx_repl.send(r);
                         }
                         Inflight::Snapshot { id, last_log_id } => {
                             let snapshot = self.storage.get_current_snapshot().await?;
@@ -1431,6 +1878,7 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> RaftRuntime
                 if let Some(data) = snapshot_data {
                     self.storage.install_snapshot(&snapshot_meta, data).await?;
                     tracing::debug!("Done install_snapshot, meta: {:?}", snapshot_meta);
+                    self.notify_applied();
                 } else {
                     unreachable!("buffered snapshot not found: snapshot meta: {:?}", snapshot_meta)
                 }
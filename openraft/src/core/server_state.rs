@@ -14,6 +14,16 @@ pub enum ServerState {
     Leader,
     /// The Raft node is shutting down.
     Shutdown,
+    /// The node learned that it is removed from the cluster by a committed membership.
+    ///
+    /// The node neither elects nor becomes a leader any more. It can be shut down and
+    /// decommissioned safely. It leaves this state when it is added back by a newer membership,
+    /// e.g., as a learner with `Raft::add_learner()`. A restarted node stays in this state if the
+    /// store persists it with `RaftStorage::save_removed_by()`.
+    ///
+    /// This variant is added after 0.8.4: an application that matches `ServerState` exhaustively
+    /// has to handle it.
+    Removed,
 }
 
 impl ServerState {
@@ -36,4 +46,9 @@ impl ServerState {
     pub fn is_leader(&self) -> bool {
         matches!(self, Self::Leader)
     }
+
+    /// Check if this node is removed from the cluster.
+    pub fn is_removed(&self) -> bool {
+        matches!(self, Self::Removed)
+    }
 }
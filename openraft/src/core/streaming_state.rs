@@ -15,7 +15,8 @@ use crate::StorageError;
 
 /// The Raft node is streaming in a snapshot from the leader.
 pub(crate) struct StreamingState<C: RaftTypeConfig, SD> {
-    /// The offset of the last byte written to the snapshot.
+    /// The end of the last byte written to the snapshot, i.e., the offset of the next byte to
+    /// receive.
     pub(crate) offset: u64,
     /// The ID of the snapshot being written.
     pub(crate) snapshot_id: SnapshotId,
@@ -28,9 +29,11 @@ pub(crate) struct StreamingState<C: RaftTypeConfig, SD> {
 impl<C: RaftTypeConfig, SD> StreamingState<C, SD>
 where SD: AsyncSeek + AsyncWrite + Unpin
 {
-    pub(crate) fn new(snapshot_id: SnapshotId, snapshot_data: Box<SD>) -> Self {
+    /// Create a streaming state with `offset` bytes of the snapshot already written to
+    /// `snapshot_data`.
+    pub(crate) fn new(snapshot_id: SnapshotId, snapshot_data: Box<SD>, offset: u64) -> Self {
         Self {
-            offset: 0,
+            offset,
             snapshot_id,
             snapshot_data,
             _p: Default::default(),
@@ -38,11 +41,17 @@ where SD: AsyncSeek + AsyncWrite + Unpin
     }
 
     /// Receive a chunk of snapshot data.
+    ///
+    /// The part of the chunk that has already been written, e.g., a chunk resent by the leader
+    /// after it restarted the transfer, is skipped.
     pub(crate) async fn receive(&mut self, req: InstallSnapshotRequest<C>) -> Result<bool, StorageError<C::NodeId>> {
-        // TODO: check id?
+        let end = req.offset + req.data.len() as u64;
+        if end <= self.offset {
+            return Ok(req.done);
+        }
 
-        // Always seek to the target offset if not an exact match.
-        if req.offset != self.offset {
+        // Seek to the target offset if it is after the written data.
+        if req.offset > self.offset {
             if let Err(err) = self.snapshot_data.as_mut().seek(SeekFrom::Start(req.offset)).await {
                 return Err(StorageError::from_io_error(
                     ErrorSubject::Snapshot(req.meta.signature()),
@@ -53,8 +62,9 @@ where SD: AsyncSeek + AsyncWrite + Unpin
             self.offset = req.offset;
         }
 
-        // Write the next segment & update offset.
-        let res = self.snapshot_data.as_mut().write_all(&req.data).await;
+        // Write the unseen part of the segment & update offset.
+        let start = (self.offset - req.offset) as usize;
+        let res = self.snapshot_data.as_mut().write_all(&req.data[start..]).await;
         if let Err(err) = res {
             return Err(StorageError::from_io_error(
                 ErrorSubject::Snapshot(req.meta.signature()),
@@ -62,7 +72,7 @@ where SD: AsyncSeek + AsyncWrite + Unpin
                 err,
             ));
         }
-        self.offset += req.data.len() as u64;
+        self.offset = end;
         Ok(req.done)
     }
 }
@@ -6,6 +6,7 @@ use async_trait::async_trait;
 
 use crate::log_id::LogIdOptionExt;
 use crate::log_id::RaftLogId;
+use crate::storage::StorageHelper;
 use crate::DefensiveError;
 use crate::ErrorSubject;
 use crate::LogId;
@@ -201,6 +202,42 @@ where
         Ok(())
     }
 
+    /// The membership log ids recorded by a store must be the same as the ones found by scanning
+    /// the log.
+    async fn defensive_membership_log_ids(
+        &mut self,
+        recorded: &[LogId<C::NodeId>],
+    ) -> Result<(), StorageError<C::NodeId>> {
+        if !self.is_defensive() {
+            return Ok(());
+        }
+
+        let st = self.inner().get_log_state().await?;
+        let start = st.last_purged_log_id.next_index();
+        let end = st.last_log_id.next_index();
+
+        let mut recorded =
+            recorded.iter().rev().filter(|x| x.index >= start && x.index < end).take(2).copied().collect::<Vec<_>>();
+        recorded.reverse();
+
+        let in_log = StorageHelper::new(self.inner())
+            .scan_membership_in_log(0)
+            .await?
+            .iter()
+            .filter_map(|x| *x.log_id())
+            .collect::<Vec<_>>();
+
+        if recorded != in_log {
+            return Err(DefensiveError::new(ErrorSubject::Logs, Violation::MembershipLogIdsMismatch {
+                recorded,
+                in_log,
+            })
+            .into());
+        }
+
+        Ok(())
+    }
+
     async fn defensive_purge_applied_le_last_applied(
         &mut self,
         upto: LogId<C::NodeId>,
@@ -66,6 +66,10 @@ where
         upto: LogId<NID>,
     },
 
+    /// Send a heartbeat with the committed log id to every target, on the heartbeat lane that is
+    /// independent of the log replication.
+    SendHeartbeat { committed: Option<LogId<NID>> },
+
     /// Replicate log entries or snapshot to a target.
     Replicate { target: NID, req: Inflight<NID> },
 
@@ -99,6 +103,9 @@ where
     /// Save vote to storage
     SaveVote { vote: Vote<NID> },
 
+    /// Save the log id of the membership that removes this node, or `None` if it is added back.
+    SaveRemovedBy { removed_by: Option<LogId<NID>> },
+
     /// Send vote to all other members
     SendVote { vote_req: VoteRequest<NID> },
 
@@ -168,11 +175,13 @@ where
             Command::ReplicateCommitted { .. } => {}
             Command::LeaderCommit { .. } => flags.set_data_changed(),
             Command::FollowerCommit { .. } => flags.set_data_changed(),
+            Command::SendHeartbeat { .. } => {}
             Command::Replicate { .. } => {}
             Command::UpdateMembership { .. } => flags.set_cluster_changed(),
             Command::RebuildReplicationStreams { .. } => flags.set_replication_changed(),
             Command::UpdateProgressMetrics { .. } => flags.set_replication_changed(),
             Command::SaveVote { .. } => flags.set_data_changed(),
+            Command::SaveRemovedBy { .. } => {}
             Command::SendVote { .. } => {}
             Command::PurgeLog { .. } => flags.set_data_changed(),
             Command::DeleteConflictLog { .. } => flags.set_data_changed(),
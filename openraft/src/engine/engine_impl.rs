@@ -10,7 +10,6 @@ use crate::engine::handler::following_handler::FollowingHandler;
 use crate::engine::handler::leader_handler::LeaderHandler;
 use crate::engine::handler::log_handler::LogHandler;
 use crate::engine::handler::replication_handler::ReplicationHandler;
-use crate::engine::handler::replication_handler::SendNone;
 use crate::engine::handler::server_state_handler::ServerStateHandler;
 use crate::engine::handler::snapshot_handler::SnapshotHandler;
 use crate::engine::handler::vote_handler::VoteHandler;
@@ -31,6 +30,7 @@ use crate::raft::VoteRequest;
 use crate::raft::VoteResponse;
 use crate::raft_state::LogStateReader;
 use crate::raft_state::RaftState;
+use crate::replication::ReplicationResult;
 use crate::summary::MessageSummary;
 use crate::validate::Valid;
 use crate::Config;
@@ -57,6 +57,12 @@ pub(crate) struct EngineConfig<NID: NodeId> {
     /// The maximum number of entries per payload allowed to be transmitted during replication
     pub(crate) max_payload_entries: u64,
 
+    /// The maximum total size in bytes of the entries per payload, `0` means no limit.
+    pub(crate) max_payload_bytes: u64,
+
+    /// Whether a leader steps down if no quorum acknowledges it within the election timeout.
+    pub(crate) check_quorum: bool,
+
     pub(crate) timer_config: time_state::Config,
 }
 
@@ -67,6 +73,8 @@ impl<NID: NodeId> Default for EngineConfig<NID> {
             max_in_snapshot_log_to_keep: 1000,
             purge_batch_size: 256,
             max_payload_entries: 300,
+            max_payload_bytes: 0,
+            check_quorum: false,
             timer_config: time_state::Config::default(),
         }
     }
@@ -80,6 +88,8 @@ impl<NID: NodeId> EngineConfig<NID> {
             max_in_snapshot_log_to_keep: config.max_in_snapshot_log_to_keep,
             purge_batch_size: config.purge_batch_size,
             max_payload_entries: config.max_payload_entries,
+            max_payload_bytes: config.max_payload_bytes,
+            check_quorum: config.enable_check_quorum,
             timer_config: time_state::Config {
                 election_timeout,
                 smaller_log_timeout: Duration::from_millis(config.election_timeout_max * 2),
@@ -216,13 +226,35 @@ where
             self.state.membership_state.effective().is_voter(&self.config.id)
         );
 
+        // A removed node does not elect or lead any more.
+        if self.state.removed_by.is_some() {
+            self.state.server_state = ServerState::Removed;
+
+            tracing::info!(
+                removed_by = display(self.state.removed_by.summary()),
+                "startup: this node is removed from the cluster"
+            );
+            return;
+        }
+
+        // With check-quorum, a leader has to confirm a quorum to stay a leader, which a restarted
+        // node has not. `stepped_down` is not persisted: a leader that stepped down before
+        // restarting must not resume the leadership with the same vote.
+        if self.config.check_quorum && self.state.is_leader(&self.config.id) {
+            tracing::info!(
+                "startup: check-quorum is enabled, do not restore leadership with {}",
+                self.state.vote_ref()
+            );
+            self.state.stepped_down = Some(*self.state.vote_ref());
+        }
+
         // Previously it is a leader. restore it as leader at once
         if self.state.is_leader(&self.config.id) {
             self.vote_handler().update_internal_server_state();
 
             let mut rh = self.replication_handler();
             rh.rebuild_replication_streams();
-            rh.initiate_replication(SendNone::False);
+            rh.initiate_replication();
 
             return;
         }
@@ -348,6 +380,9 @@ where
         // Make default vote-last-modified a low enough value, that expires leader lease.
         let vote_utime = self.state.vote_last_modified().unwrap_or_else(|| now - lease - Duration::from_millis(1));
 
+        // Tell the candidate if it is removed, no matter the vote is granted or not.
+        let removed_by = self.candidate_removed_by(&req.vote);
+
         tracing::info!(req = display(req.summary()), "Engine::handle_vote_req");
         tracing::info!(
             my_vote = display(self.state.vote_ref().summary()),
@@ -378,6 +413,7 @@ where
                     vote: *self.state.vote_ref(),
                     vote_granted: false,
                     last_log_id: self.state.last_log_id().copied(),
+                    removed_by,
                 };
             }
         }
@@ -400,6 +436,7 @@ where
                 vote: *self.state.vote_ref(),
                 vote_granted: false,
                 last_log_id: self.state.last_log_id().copied(),
+                removed_by,
             };
         }
 
@@ -421,9 +458,23 @@ where
             vote: *self.state.vote_ref(),
             vote_granted,
             last_log_id: self.state.last_log_id().copied(),
+            removed_by,
         }
     }
 
+    /// Returns the log id of the committed membership if the candidate of `vote` is not a member of
+    /// it.
+    fn candidate_removed_by(&self, vote: &Vote<NID>) -> Option<LogId<NID>> {
+        let candidate = vote.leader_id().voted_for()?;
+        let committed = self.state.membership_state.committed();
+
+        if committed.get_node(&candidate).is_some() {
+            return None;
+        }
+
+        *committed.log_id()
+    }
+
     #[tracing::instrument(level = "debug", skip(self, resp))]
     pub(crate) fn handle_vote_resp(&mut self, target: NID, resp: VoteResponse<NID>) {
         tracing::debug!(
@@ -437,6 +488,15 @@ where
             "handle_vote_resp"
         );
 
+        if let Some(removed_by) = resp.removed_by {
+            // The peer has committed a membership newer than any this node has seen, and this node
+            // is not in it.
+            if Some(removed_by) > *self.state.membership_state.effective().log_id() {
+                self.handle_removed(removed_by);
+                return;
+            }
+        }
+
         // If this node is no longer a leader(i.e., electing), just ignore the delayed vote_resp.
         let leader = match &mut self.internal_server_state {
             InternalServerState::Leading(l) => l,
@@ -474,6 +534,35 @@ where
         }
     }
 
+    /// Update the replication progress of `target` with the result of a replication request.
+    ///
+    /// A response, no matter it matches or conflicts, means `target` acknowledges this leader.
+    #[tracing::instrument(level = "debug", skip(self, result))]
+    pub(crate) fn handle_replication_progress(
+        &mut self,
+        target: NID,
+        id: u64,
+        result: Result<ReplicationResult<NID>, String>,
+    ) {
+        if result.is_ok() {
+            let now = *self.timer.now();
+            if let Some(l) = self.internal_server_state.leading_mut() {
+                l.ack_by(target, now);
+            }
+        }
+
+        self.replication_handler().update_progress(target, id, result);
+    }
+
+    /// `target` accepted a heartbeat, i.e., it acknowledges this leader.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(crate) fn handle_heartbeat_ack(&mut self, target: NID) {
+        let now = *self.timer.now();
+        if let Some(l) = self.internal_server_state.leading_mut() {
+            l.ack_by(target, now);
+        }
+    }
+
     /// Append entries to follower/learner.
     ///
     /// Also clean conflicting entries and update membership state.
@@ -510,7 +599,11 @@ where
         fh.append_entries(prev_log_id, entries, leader_committed)
     }
 
-    /// Leader steps down(convert to learner) once the membership not containing it is committed.
+    /// Leader steps down if it should no longer be a leader.
+    ///
+    /// - Once the membership not containing it is committed, it converts to a learner.
+    /// - If check-quorum is enabled and no quorum of voters responded to it within the election
+    ///   timeout, it gives up the leadership.
     ///
     /// This is only called by leader.
     #[tracing::instrument(level = "debug", skip_all)]
@@ -533,8 +626,72 @@ where
             if !em.is_voter(&self.config.id) && self.state.is_leading(&self.config.id) {
                 tracing::debug!("leader {} is stepping down", self.config.id);
                 self.vote_handler().become_following();
+                return;
             }
         }
+
+        if self.config.check_quorum && !self.is_quorum_acked() {
+            // The leader becomes a follower with the same vote: the term is not bumped, so that it
+            // does not disrupt the cluster when it reconnects. It elects when the election timeout
+            // passes, as other followers do.
+            tracing::info!(
+                "leader {} has not heard from a quorum in {:?}, stepping down to follower with {}",
+                self.config.id,
+                self.config.timer_config.election_timeout,
+                self.state.vote_ref()
+            );
+
+            self.state.stepped_down = Some(*self.state.vote_ref());
+            self.vote_handler().become_following();
+        }
+    }
+
+    /// Returns `false` if this node is a leader and no quorum of voters responded to it within the
+    /// election timeout.
+    fn is_quorum_acked(&self) -> bool {
+        let leader = match self.internal_server_state.leading() {
+            Some(l) => l,
+            None => return true,
+        };
+
+        if !self.state.is_leader(&self.config.id) {
+            return true;
+        }
+
+        let now = *self.timer.now();
+        let since = match now.checked_sub(self.config.timer_config.election_timeout) {
+            Some(x) => x,
+            None => return true,
+        };
+
+        // A leader is given a full election timeout to hear from a quorum after it is established.
+        if self.state.vote_last_modified() > Some(since) {
+            return true;
+        }
+
+        leader.is_quorum_acked(&self.config.id, since)
+    }
+
+    /// This node learns that it is removed from the cluster by the committed membership at
+    /// `removed_by`.
+    ///
+    /// A removed node quits electing and stays in [`ServerState::Removed`]: it does not become a
+    /// leader any more, even if some peer grants its vote later.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(crate) fn handle_removed(&mut self, removed_by: LogId<NID>) {
+        tracing::info!(
+            removed_by = display(&removed_by),
+            "this node is removed from the cluster"
+        );
+
+        self.state.removed_by = Some(removed_by);
+        self.output.push_command(Command::SaveRemovedBy {
+            removed_by: Some(removed_by),
+        });
+        self.internal_server_state = InternalServerState::Following;
+
+        self.server_state_handler().update_server_state_if_changed();
+        self.output.metrics_flags.set_cluster_changed();
     }
 
     #[tracing::instrument(level = "debug", skip_all)]
@@ -583,7 +740,7 @@ where
 
         rh.rebuild_replication_streams();
         rh.append_blank_log();
-        rh.initiate_replication(SendNone::False);
+        rh.initiate_replication();
     }
 
     /// Check if a raft node is in a state that allows to initialize.
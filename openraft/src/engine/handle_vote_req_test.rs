@@ -16,6 +16,7 @@ use crate::testing::log_id;
 use crate::utime::UTime;
 use crate::EffectiveMembership;
 use crate::Membership;
+use crate::MembershipState;
 use crate::MetricsChangeFlags;
 use crate::Vote;
 
@@ -45,16 +46,14 @@ fn test_handle_vote_req_rejected_by_leader_lease() -> anyhow::Result<()> {
     let mut eng = eng();
     eng.state.vote.update(*eng.timer.now(), Vote::new_committed(2, 1));
 
-    let resp = eng.handle_vote_req(VoteRequest {
-        vote: Vote::new(3, 2),
-        last_log_id: Some(log_id(2, 3)),
-    });
+    let resp = eng.handle_vote_req(VoteRequest::new(Vote::new(3, 2), Some(log_id(2, 3))));
 
     assert_eq!(
         VoteResponse {
             vote: Vote::new_committed(2, 1),
             vote_granted: false,
-            last_log_id: None
+            last_log_id: None,
+            removed_by: None,
         },
         resp
     );
@@ -81,16 +80,14 @@ fn test_handle_vote_req_rejected_by_leader_lease() -> anyhow::Result<()> {
 fn test_handle_vote_req_reject_smaller_vote() -> anyhow::Result<()> {
     let mut eng = eng();
 
-    let resp = eng.handle_vote_req(VoteRequest {
-        vote: Vote::new(1, 2),
-        last_log_id: None,
-    });
+    let resp = eng.handle_vote_req(VoteRequest::new(Vote::new(1, 2), None));
 
     assert_eq!(
         VoteResponse {
             vote: Vote::new(2, 1),
             vote_granted: false,
-            last_log_id: None
+            last_log_id: None,
+            removed_by: None,
         },
         resp
     );
@@ -118,16 +115,14 @@ fn test_handle_vote_req_reject_smaller_last_log_id() -> anyhow::Result<()> {
     let mut eng = eng();
     eng.state.log_ids = LogIdList::new(vec![log_id(2, 3)]);
 
-    let resp = eng.handle_vote_req(VoteRequest {
-        vote: Vote::new(3, 2),
-        last_log_id: Some(log_id(1, 3)),
-    });
+    let resp = eng.handle_vote_req(VoteRequest::new(Vote::new(3, 2), Some(log_id(1, 3))));
 
     assert_eq!(
         VoteResponse {
             vote: Vote::new(2, 1),
             vote_granted: false,
-            last_log_id: Some(log_id(2, 3))
+            last_log_id: Some(log_id(2, 3)),
+            removed_by: None,
         },
         resp
     );
@@ -160,16 +155,14 @@ fn test_handle_vote_req_granted_equal_vote_and_last_log_id() -> anyhow::Result<(
 
     eng.output.clear_commands();
 
-    let resp = eng.handle_vote_req(VoteRequest {
-        vote: Vote::new(2, 1),
-        last_log_id: Some(log_id(2, 3)),
-    });
+    let resp = eng.handle_vote_req(VoteRequest::new(Vote::new(2, 1), Some(log_id(2, 3))));
 
     assert_eq!(
         VoteResponse {
             vote: Vote::new(2, 1),
             vote_granted: true,
-            last_log_id: Some(log_id(2, 3))
+            last_log_id: Some(log_id(2, 3)),
+            removed_by: None,
         },
         resp
     );
@@ -202,17 +195,15 @@ fn test_handle_vote_req_granted_greater_vote() -> anyhow::Result<()> {
 
     eng.output.clear_commands();
 
-    let resp = eng.handle_vote_req(VoteRequest {
-        vote: Vote::new(3, 1),
-        last_log_id: Some(log_id(2, 3)),
-    });
+    let resp = eng.handle_vote_req(VoteRequest::new(Vote::new(3, 1), Some(log_id(2, 3))));
 
     assert_eq!(
         VoteResponse {
             // respond the updated vote.
             vote: Vote::new(3, 1),
             vote_granted: true,
-            last_log_id: Some(log_id(2, 3))
+            last_log_id: Some(log_id(2, 3)),
+            removed_by: None,
         },
         resp
     );
@@ -251,10 +242,7 @@ fn test_handle_vote_req_granted_follower_learner_does_not_emit_update_server_sta
         eng.state.server_state = st;
         eng.output.clear_commands();
 
-        eng.handle_vote_req(VoteRequest {
-            vote: Vote::new(3, 1),
-            last_log_id: Some(log_id(2, 3)),
-        });
+        eng.handle_vote_req(VoteRequest::new(Vote::new(3, 1), Some(log_id(2, 3))));
 
         assert_eq!(st, eng.state.server_state);
         assert_eq!(
@@ -275,10 +263,7 @@ fn test_handle_vote_req_granted_follower_learner_does_not_emit_update_server_sta
         eng.state.server_state = st;
         eng.output.clear_commands();
 
-        eng.handle_vote_req(VoteRequest {
-            vote: Vote::new(3, 1),
-            last_log_id: Some(log_id(2, 3)),
-        });
+        eng.handle_vote_req(VoteRequest::new(Vote::new(3, 1), Some(log_id(2, 3))));
 
         assert_eq!(st, eng.state.server_state);
         assert_eq!(
@@ -291,3 +276,42 @@ fn test_handle_vote_req_granted_follower_learner_does_not_emit_update_server_sta
     }
     Ok(())
 }
+
+#[test]
+fn test_handle_vote_req_candidate_removed() -> anyhow::Result<()> {
+    let mut eng = eng();
+    let m = Arc::new(EffectiveMembership::new(Some(log_id(1, 1)), m01()));
+    eng.state.membership_state = MembershipState::new(m.clone(), m);
+
+    tracing::info!("--- the candidate is not in the committed membership");
+    {
+        let resp = eng.handle_vote_req(VoteRequest::new(Vote::new(3, 2), Some(log_id(1, 0))));
+
+        assert_eq!(
+            VoteResponse {
+                vote: Vote::new(3, 2),
+                vote_granted: true,
+                last_log_id: None,
+                removed_by: Some(log_id(1, 1)),
+            },
+            resp
+        );
+    }
+
+    tracing::info!("--- the candidate is in the committed membership");
+    {
+        let resp = eng.handle_vote_req(VoteRequest::new(Vote::new(4, 0), Some(log_id(1, 0))));
+
+        assert_eq!(
+            VoteResponse {
+                vote: Vote::new(4, 0),
+                vote_granted: true,
+                last_log_id: None,
+                removed_by: None,
+            },
+            resp
+        );
+    }
+
+    Ok(())
+}
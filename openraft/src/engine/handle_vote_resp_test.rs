@@ -53,6 +53,7 @@ fn test_handle_vote_resp() -> anyhow::Result<()> {
             vote: Vote::new(2, 2),
             vote_granted: true,
             last_log_id: Some(log_id(2, 2)),
+            removed_by: None,
         });
 
         assert_eq!(Vote::new(2, 1), *eng.state.vote_ref());
@@ -87,6 +88,7 @@ fn test_handle_vote_resp() -> anyhow::Result<()> {
             vote: Vote::new(1, 1),
             vote_granted: false,
             last_log_id: Some(log_id(2, 2)),
+            removed_by: None,
         });
 
         assert_eq!(Vote::new(2, 1), *eng.state.vote_ref());
@@ -126,6 +128,7 @@ fn test_handle_vote_resp() -> anyhow::Result<()> {
             vote: Vote::new(3, 2),
             vote_granted: false,
             last_log_id: Some(log_id(2, 2)),
+            removed_by: None,
         });
 
         assert_eq!(Vote::new(3, 2), *eng.state.vote_ref());
@@ -163,6 +166,7 @@ fn test_handle_vote_resp() -> anyhow::Result<()> {
             vote: Vote::new(2, 1),
             vote_granted: false,
             last_log_id: Some(log_id(2, 2)),
+            removed_by: None,
         });
 
         assert_eq!(Vote::new(2, 1), *eng.state.vote_ref());
@@ -200,6 +204,7 @@ fn test_handle_vote_resp() -> anyhow::Result<()> {
             vote: Vote::new(2, 1),
             vote_granted: true,
             last_log_id: Some(log_id(2, 2)),
+            removed_by: None,
         });
 
         assert_eq!(Vote::new(2, 1), *eng.state.vote_ref());
@@ -237,6 +242,7 @@ fn test_handle_vote_resp() -> anyhow::Result<()> {
             vote: Vote::new(2, 1),
             vote_granted: true,
             last_log_id: Some(log_id(2, 2)),
+            removed_by: None,
         });
 
         assert_eq!(Vote::new_committed(2, 1), *eng.state.vote_ref());
@@ -281,3 +287,85 @@ fn test_handle_vote_resp() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_handle_vote_resp_removed() -> anyhow::Result<()> {
+    tracing::info!("--- removed by a membership this node has not seen. quit electing");
+    {
+        let mut eng = eng();
+        eng.config.id = 1;
+        eng.state.vote = UTime::new(Instant::now(), Vote::new(2, 1));
+        eng.state
+            .membership_state
+            .set_effective(Arc::new(EffectiveMembership::new(Some(log_id(1, 1)), m12())));
+        eng.vote_handler().become_leading();
+        eng.internal_server_state.leading_mut().map(|l| l.vote_granted_by.insert(1));
+        eng.state.server_state = ServerState::Candidate;
+
+        eng.handle_vote_resp(2, VoteResponse {
+            vote: Vote::new(2, 1),
+            vote_granted: false,
+            last_log_id: Some(log_id(1, 3)),
+            removed_by: Some(log_id(1, 3)),
+        });
+
+        assert_eq!(Vote::new(2, 1), *eng.state.vote_ref());
+        assert!(eng.internal_server_state.is_following());
+
+        assert_eq!(ServerState::Removed, eng.state.server_state);
+        assert_eq!(
+            MetricsChangeFlags {
+                replication: false,
+                local_data: false,
+                cluster: true,
+            },
+            eng.output.metrics_flags
+        );
+
+        assert_eq!(
+            vec![Command::SaveRemovedBy {
+                removed_by: Some(log_id(1, 3))
+            }],
+            eng.output.take_commands()
+        );
+
+        tracing::info!("--- a granted vote does not make a removed node a leader");
+
+        eng.handle_vote_resp(2, VoteResponse {
+            vote: Vote::new(2, 1),
+            vote_granted: true,
+            last_log_id: Some(log_id(1, 3)),
+            removed_by: None,
+        });
+
+        assert_eq!(Vote::new(2, 1), *eng.state.vote_ref());
+        assert_eq!(ServerState::Removed, eng.state.server_state);
+        assert!(eng.output.take_commands().is_empty());
+    }
+
+    tracing::info!("--- removed by a membership older than the effective one. ignore it");
+    {
+        let mut eng = eng();
+        eng.config.id = 1;
+        eng.state.vote = UTime::new(Instant::now(), Vote::new(2, 1));
+        eng.state
+            .membership_state
+            .set_effective(Arc::new(EffectiveMembership::new(Some(log_id(1, 2)), m12())));
+        eng.vote_handler().become_leading();
+        eng.internal_server_state.leading_mut().map(|l| l.vote_granted_by.insert(1));
+        eng.state.server_state = ServerState::Candidate;
+
+        eng.handle_vote_resp(2, VoteResponse {
+            vote: Vote::new(2, 1),
+            vote_granted: false,
+            last_log_id: Some(log_id(1, 2)),
+            removed_by: Some(log_id(1, 1)),
+        });
+
+        assert!(eng.internal_server_state.is_leading());
+        assert_eq!(ServerState::Candidate, eng.state.server_state);
+        assert!(eng.output.take_commands().is_empty());
+    }
+
+    Ok(())
+}
@@ -14,6 +14,7 @@ use crate::Entry;
 use crate::Membership;
 use crate::MembershipState;
 use crate::MetricsChangeFlags;
+use crate::ServerState;
 
 fn blank(term: u64, index: u64) -> Entry<UTCfg> {
     crate::Entry::new_blank(log_id(term, index))
@@ -31,6 +32,7 @@ fn eng() -> CEngine<UTCfg> {
     let mut eng = Engine::default();
     eng.state.enable_validate = false; // Disable validation for incomplete state
 
+    eng.config.id = 2;
     eng.state.committed = Some(log_id(1, 1));
     eng.state.membership_state = MembershipState::new(
         Arc::new(EffectiveMembership::new(Some(log_id(1, 1)), m01())),
@@ -170,3 +172,40 @@ fn test_follower_commit_entries_gt_last_entry() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_follower_commit_entries_removed() -> anyhow::Result<()> {
+    let mut eng = eng();
+    eng.config.id = 0;
+    eng.state.server_state = ServerState::Follower;
+
+    eng.following_handler().commit_entries(Some(log_id(2, 3)), None, &[blank(2, 3)]);
+
+    assert_eq!(Some(&log_id(2, 3)), eng.state.committed());
+    assert_eq!(Some(log_id(2, 3)), eng.state.removed_by);
+    assert_eq!(ServerState::Removed, eng.state.server_state);
+
+    assert_eq!(
+        MetricsChangeFlags {
+            replication: false,
+            local_data: true,
+            cluster: true,
+        },
+        eng.output.metrics_flags
+    );
+
+    assert_eq!(
+        vec![
+            Command::FollowerCommit {
+                already_committed: Some(log_id(1, 1)),
+                upto: log_id(2, 3)
+            },
+            Command::SaveRemovedBy {
+                removed_by: Some(log_id(2, 3))
+            },
+        ],
+        eng.output.take_commands()
+    );
+
+    Ok(())
+}
@@ -133,6 +133,7 @@ where
         debug_assert!(Some(entries[0].get_log_id()) > self.state.log_ids.last());
 
         self.state.extend_log_ids(entries);
+        self.state.record_log_sizes(entries);
         self.append_membership(entries.iter());
     }
 
@@ -155,18 +156,57 @@ where
 
         tracing::debug!(committed = display(committed.summary()), "update committed");
 
+        let prev_membership = self.state.membership_state.committed().clone();
+
         if let Some(prev_committed) = self.state.update_committed(&committed) {
             self.output.push_command(Command::FollowerCommit {
-                // TODO(xp): when restart, commit is reset to None. Use last_applied instead.
                 already_committed: prev_committed,
                 upto: committed.unwrap(),
             });
+
+            self.check_removed(&prev_membership);
         }
 
         // TODO(5): follower has not yet commit the membership_state.
         //          For now it is OK. But it should be done here.
     }
 
+    /// Enter [`ServerState::Removed`] if a membership that removes this node is committed.
+    ///
+    /// A node is removed only if it is a member of the previously committed membership, so that a
+    /// new node that is replicating the logs before it is added is not mistaken as removed.
+    ///
+    /// [`ServerState::Removed`]: crate::ServerState::Removed
+    fn check_removed(&mut self, prev_membership: &EffectiveMembership<NID, N>) {
+        let id = &self.config.id;
+        let committed = self.state.membership_state.committed();
+
+        if committed.log_id() == prev_membership.log_id() {
+            return;
+        }
+
+        if prev_membership.get_node(id).is_none() || committed.get_node(id).is_some() {
+            return;
+        }
+
+        let removed_by = match committed.log_id() {
+            Some(log_id) => *log_id,
+            None => return,
+        };
+
+        tracing::info!(
+            removed_by = display(&removed_by),
+            "this node is removed from the cluster"
+        );
+
+        self.state.removed_by = Some(removed_by);
+        self.output.push_command(Command::SaveRemovedBy {
+            removed_by: Some(removed_by),
+        });
+        self.server_state_handler().update_server_state_if_changed();
+        self.output.metrics_flags.set_cluster_changed();
+    }
+
     /// Delete log entries since log index `since`, inclusive, when the log at `since` is found
     /// conflict with the leader.
     ///
@@ -185,7 +225,7 @@ where
             Some(x) => x,
         };
 
-        self.state.log_ids.truncate(since);
+        self.state.truncate_log_ids(since);
         self.output.push_command(Command::DeleteConflictLog { since: since_log_id });
 
         let changed = self.state.membership_state.truncate(since);
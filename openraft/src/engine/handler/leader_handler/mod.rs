@@ -2,7 +2,6 @@ use std::marker::PhantomData;
 
 use crate::engine::engine_impl::EngineOutput;
 use crate::engine::handler::replication_handler::ReplicationHandler;
-use crate::engine::handler::replication_handler::SendNone;
 use crate::engine::Command;
 use crate::engine::EngineConfig;
 use crate::entry::RaftEntry;
@@ -59,6 +58,7 @@ where
 
         self.state.assign_log_ids(entries.iter_mut());
         self.state.extend_log_ids_from_same_leader(entries);
+        self.state.record_log_sizes(entries);
 
         self.output.push_command(Command::AppendInputEntries { range: 0..l });
 
@@ -112,13 +112,21 @@ where
         };
 
         rh.update_local_progress(last_log_id);
-        rh.initiate_replication(SendNone::False);
+        rh.initiate_replication();
     }
 
+    /// Send a heartbeat to every target, and retry replication to the targets that are not sending
+    /// data in flight, e.g., after a failed RPC.
+    ///
+    /// A heartbeat does not wait for the data in flight: it is sent on a separate lane.
     #[tracing::instrument(level = "debug", skip_all)]
     pub(crate) fn send_heartbeat(&mut self) -> () {
+        self.output.push_command(Command::SendHeartbeat {
+            committed: self.state.committed().copied(),
+        });
+
         let mut rh = self.replication_handler();
-        rh.initiate_replication(SendNone::True);
+        rh.initiate_replication();
     }
 
     pub(crate) fn replication_handler(&mut self) -> ReplicationHandler<NID, N> {
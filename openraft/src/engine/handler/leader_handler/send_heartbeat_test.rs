@@ -50,11 +50,14 @@ fn test_leader_send_heartbeat() -> anyhow::Result<()> {
     let mut eng = eng();
     eng.vote_handler().become_leading();
 
-    // A heartbeat is a normal AppendEntries RPC if there are pending data to send.
+    // A heartbeat is sent to every target, and the pending data is sent by replication.
     {
         eng.leader_handler()?.send_heartbeat();
         assert_eq!(
             vec![
+                Command::SendHeartbeat {
+                    committed: Some(log_id(0, 0)),
+                },
                 Command::Replicate {
                     target: 2,
                     req: Inflight::logs(None, Some(log_id(2, 3))).with_id(1),
@@ -68,14 +71,19 @@ fn test_leader_send_heartbeat() -> anyhow::Result<()> {
         );
     }
 
-    // No RPC will be sent if there are inflight RPC
+    // A heartbeat does not wait for the inflight RPC, which is not sent again.
     {
         eng.output.clear_commands();
         eng.leader_handler()?.send_heartbeat();
-        assert!(eng.output.take_commands().is_empty());
+        assert_eq!(
+            vec![Command::SendHeartbeat {
+                committed: Some(log_id(0, 0)),
+            }],
+            eng.output.take_commands()
+        );
     }
 
-    // No data to send, sending a heartbeat is to send empty RPC:
+    // No data to send, only the heartbeat is sent:
     {
         let l = eng.leader_handler()?;
         let _ = l.leader.progress.update_with(&2, |ent| ent.update_matching(Some(log_id(2, 3))));
@@ -84,16 +92,9 @@ fn test_leader_send_heartbeat() -> anyhow::Result<()> {
     eng.output.clear_commands();
     eng.leader_handler()?.send_heartbeat();
     assert_eq!(
-        vec![
-            Command::Replicate {
-                target: 2,
-                req: Inflight::logs(Some(log_id(2, 3)), Some(log_id(2, 3))).with_id(1),
-            },
-            Command::Replicate {
-                target: 3,
-                req: Inflight::logs(Some(log_id(2, 3)), Some(log_id(2, 3))).with_id(1),
-            },
-        ],
+        vec![Command::SendHeartbeat {
+            committed: Some(log_id(0, 0)),
+        }],
         eng.output.take_commands()
     );
 
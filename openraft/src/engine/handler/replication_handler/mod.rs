@@ -23,6 +23,7 @@ use crate::ServerState;
 
 #[cfg(test)] mod append_membership_test;
 #[cfg(test)] mod update_matching_test;
+#[cfg(test)] mod update_progress_test;
 
 /// Handle replication operations.
 ///
@@ -43,16 +44,6 @@ where
     pub(crate) output: &'x mut EngineOutput<NID, N>,
 }
 
-/// An option about whether to send an RPC to follower/learner even when there is no data to send.
-///
-/// Sending none data serves as a heartbeat.
-#[derive(Debug)]
-#[derive(PartialEq, Eq)]
-pub(crate) enum SendNone {
-    False,
-    True,
-}
-
 impl<'x, NID, N> ReplicationHandler<'x, NID, N>
 where
     NID: NodeId,
@@ -105,7 +96,7 @@ where
 
         self.rebuild_progresses();
         self.rebuild_replication_streams();
-        self.initiate_replication(SendNone::False);
+        self.initiate_replication();
     }
 
     /// Rebuild leader's replication progress to reflect replication changes.
@@ -136,8 +127,6 @@ where
         );
         tracing::debug!(progress = display(&self.leader.progress), "leader progress");
 
-        debug_assert!(log_id.is_some(), "a valid update can never set matching to None");
-
         // Whether it is a response for the current inflight request.
         let mut is_mine = true;
 
@@ -154,10 +143,13 @@ where
             })
             .expect("it should always update existing progress");
 
+        // A response to other request, such as a heartbeat, does not update the progress.
         if !is_mine {
             return;
         }
 
+        debug_assert!(log_id.is_some(), "a valid update can never set matching to None");
+
         tracing::debug!(granted = display(granted.summary()), "granted after updating progress");
 
         if node_id != self.config.id {
@@ -227,7 +219,16 @@ where
 
                 match p {
                     ReplicationResult::Matching(matching) => {
+                        let is_mine = self.leader.progress.get(&target).inflight.is_my_id(id);
+
                         self.update_matching(target, id, matching);
+
+                        // A request is finished by its response, even if it acknowledges only the
+                        // leading part of its logs: the rest of the logs is selected by the
+                        // following `next_send()`, with a new inflight id.
+                        if is_mine {
+                            self.leader.progress.get_mut(&target).unwrap().inflight = Inflight::None;
+                        }
                     }
                     ReplicationResult::Conflict(conflict) => {
                         self.update_conflicting(target, id, conflict);
@@ -256,7 +257,11 @@ where
         {
             let p = self.leader.progress.get_mut(&target).unwrap();
 
-            let r = p.next_send(self.state.deref(), self.config.max_payload_entries);
+            let r = p.next_send(
+                self.state.deref(),
+                self.config.max_payload_entries,
+                self.config.max_payload_bytes,
+            );
             tracing::debug!(next_send_res = debug(&r), "next_send");
 
             if let Ok(inflight) = r {
@@ -287,9 +292,9 @@ where
 
     /// Initiate replication for every target that is not sending data in flight.
     ///
-    /// `send_none` specifies whether to force to send a message even when there is no data to send.
+    /// Nothing is sent to a target if there is no data to send: heartbeats are sent separately.
     #[tracing::instrument(level = "debug", skip_all)]
-    pub(crate) fn initiate_replication(&mut self, send_none: SendNone) {
+    pub(crate) fn initiate_replication(&mut self) {
         tracing::debug!(progress = debug(&self.leader.progress), "send_to_all");
 
         for (id, prog_entry) in self.leader.progress.iter_mut() {
@@ -299,26 +304,18 @@ where
                 continue;
             }
 
-            let t = prog_entry.next_send(self.state, self.config.max_payload_entries);
+            let t = prog_entry.next_send(
+                self.state,
+                self.config.max_payload_entries,
+                self.config.max_payload_bytes,
+            );
 
             match t {
                 Ok(inflight) => {
                     Self::send_to_target(self.output, id, inflight);
                 }
                 Err(e) => {
-                    tracing::debug!(
-                        "no data to replicate for node-{}: current inflight: {:?}, send_none: {:?}",
-                        id,
-                        e,
-                        send_none
-                    );
-
-                    #[allow(clippy::collapsible_if)]
-                    if e == &Inflight::None {
-                        if send_none == SendNone::True {
-                            Self::send_to_target(self.output, id, e);
-                        }
-                    }
+                    tracing::debug!("no data to replicate for node-{}: current inflight: {:?}", id, e);
                 }
             }
         }
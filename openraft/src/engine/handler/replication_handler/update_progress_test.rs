@@ -0,0 +1,114 @@
+use std::sync::Arc;
+
+use maplit::btreeset;
+use pretty_assertions::assert_eq;
+use tokio::time::Instant;
+
+use crate::engine::testing::UTCfg;
+use crate::engine::CEngine;
+use crate::engine::Command;
+use crate::engine::Engine;
+use crate::engine::LogIdList;
+use crate::progress::Inflight;
+use crate::replication::ReplicationResult;
+use crate::testing::log_id;
+use crate::utime::UTime;
+use crate::EffectiveMembership;
+use crate::Membership;
+use crate::MembershipState;
+use crate::Vote;
+
+fn m123() -> Membership<u64, ()> {
+    Membership::<u64, ()>::new(vec![btreeset! {1,2,3}], None)
+}
+
+fn eng() -> CEngine<UTCfg> {
+    let mut eng = Engine::default();
+    eng.state.enable_validate = false; // Disable validation for incomplete state
+
+    eng.config.id = 2;
+    eng.state.vote = UTime::new(Instant::now(), Vote::new_committed(2, 2));
+    eng.state.log_ids = LogIdList::new(vec![log_id(1, 1), log_id(2, 3), log_id(2, 10)]);
+    eng.state.membership_state = MembershipState::new(
+        Arc::new(EffectiveMembership::new(Some(log_id(1, 1)), m123())),
+        Arc::new(EffectiveMembership::new(Some(log_id(1, 1)), m123())),
+    );
+
+    eng
+}
+
+#[test]
+fn test_update_progress_partial_matching() -> anyhow::Result<()> {
+    // A response that acknowledges only the leading part of the inflight logs finishes the request:
+    // the rest of the logs is sent by a new request.
+
+    let mut eng = eng();
+    eng.vote_handler().become_leading();
+
+    let mut rh = eng.replication_handler();
+    {
+        let prog_entry = rh.leader.progress.get_mut(&1).unwrap();
+        prog_entry.matching = Some(log_id(2, 3));
+        prog_entry.curr_inflight_id = 5;
+        prog_entry.inflight = Inflight::logs(Some(log_id(2, 3)), Some(log_id(2, 10))).with_id(5);
+    }
+    rh.output.take_commands();
+
+    rh.update_progress(1, 5, Ok(ReplicationResult::Matching(Some(log_id(2, 6)))));
+
+    let prog_entry = rh.leader.progress.get_mut(&1).unwrap();
+    assert_eq!(Some(log_id(2, 6)), prog_entry.matching);
+    assert_eq!(
+        Inflight::logs(Some(log_id(2, 6)), Some(log_id(2, 10))).with_id(6),
+        prog_entry.inflight
+    );
+
+    let commands = rh.output.take_commands();
+    assert!(
+        commands.contains(&Command::UpdateProgressMetrics {
+            target: 1,
+            matching: log_id(2, 6),
+        }),
+        "the matching log id is applied to the progress"
+    );
+    assert_eq!(
+        Some(&Command::Replicate {
+            target: 1,
+            req: Inflight::logs(Some(log_id(2, 6)), Some(log_id(2, 10))).with_id(6),
+        }),
+        commands.last(),
+        "the rest of the logs is sent"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_update_progress_response_to_other_request() -> anyhow::Result<()> {
+    // A response to a request that is not the inflight one, e.g., a heartbeat, does not change the
+    // inflight request.
+
+    let mut eng = eng();
+    eng.vote_handler().become_leading();
+
+    let mut rh = eng.replication_handler();
+    {
+        let prog_entry = rh.leader.progress.get_mut(&1).unwrap();
+        prog_entry.matching = Some(log_id(2, 3));
+        prog_entry.curr_inflight_id = 5;
+        prog_entry.inflight = Inflight::logs(Some(log_id(2, 3)), Some(log_id(2, 10))).with_id(5);
+    }
+    rh.output.take_commands();
+
+    rh.update_progress(1, 4, Ok(ReplicationResult::Matching(Some(log_id(2, 6)))));
+
+    let prog_entry = rh.leader.progress.get_mut(&1).unwrap();
+    assert_eq!(Some(log_id(2, 3)), prog_entry.matching);
+    assert_eq!(
+        Inflight::logs(Some(log_id(2, 3)), Some(log_id(2, 10))).with_id(5),
+        prog_entry.inflight
+    );
+    assert_eq!(0, rh.output.take_commands().len());
+
+    Ok(())
+}
@@ -1,6 +1,7 @@
 use crate::engine::engine_impl::EngineOutput;
 use crate::engine::Command;
 use crate::engine::EngineConfig;
+use crate::MessageSummary;
 use crate::Node;
 use crate::NodeId;
 use crate::RaftState;
@@ -27,6 +28,8 @@ where
     /// Re-calculate the server-state, if it changed, update the `server_state` field and dispatch
     /// commands to inform a runtime.
     pub(crate) fn update_server_state_if_changed(&mut self) {
+        self.clear_removed_if_added_back();
+
         let server_state = self.state.calc_server_state(&self.config.id);
 
         tracing::debug!(
@@ -53,4 +56,28 @@ where
 
         self.state.server_state = server_state;
     }
+
+    /// A removed node leaves [`ServerState::Removed`] if it is added back by a membership newer
+    /// than the one that removed it.
+    fn clear_removed_if_added_back(&mut self) {
+        let removed_by = match self.state.removed_by {
+            None => return,
+            Some(x) => x,
+        };
+
+        let effective = self.state.membership_state.effective();
+
+        if *effective.log_id() <= Some(removed_by) || effective.get_node(&self.config.id).is_none() {
+            return;
+        }
+
+        tracing::info!(
+            removed_by = display(&removed_by),
+            membership = display(effective.summary()),
+            "this node is added back to the cluster"
+        );
+
+        self.state.removed_by = None;
+        self.output.push_command(Command::SaveRemovedBy { removed_by: None });
+    }
 }
@@ -67,3 +67,50 @@ fn test_update_server_state_if_changed() -> anyhow::Result<()> {
     //          A leader keeps working after it is removed from the voters.
     Ok(())
 }
+
+#[test]
+fn test_update_server_state_removed_node_added_back() -> anyhow::Result<()> {
+    let mut eng = eng();
+    eng.state.vote = UTime::new(Instant::now(), Vote::new_committed(2, 1));
+
+    // Removed by a membership older than the effective one, which contains this node.
+    {
+        let mut ssh = eng.server_state_handler();
+        ssh.state.removed_by = Some(log_id(1, 1));
+        ssh.state.server_state = ServerState::Removed;
+
+        ssh.update_server_state_if_changed();
+
+        assert_eq!(None, ssh.state.removed_by);
+        assert_eq!(ServerState::Follower, ssh.state.server_state);
+        assert_eq!(
+            vec![Command::SaveRemovedBy { removed_by: None }],
+            ssh.output.take_commands()
+        );
+    }
+
+    // Removed by the effective membership: stay removed.
+    {
+        let mut ssh = eng.server_state_handler();
+        ssh.state.removed_by = Some(log_id(2, 3));
+        ssh.state.server_state = ServerState::Removed;
+
+        ssh.update_server_state_if_changed();
+
+        assert_eq!(Some(log_id(2, 3)), ssh.state.removed_by);
+        assert_eq!(ServerState::Removed, ssh.state.server_state);
+    }
+
+    // Removed by a membership newer than the effective one: stay removed.
+    {
+        let mut ssh = eng.server_state_handler();
+        ssh.state.removed_by = Some(log_id(3, 5));
+
+        ssh.update_server_state_if_changed();
+
+        assert_eq!(Some(log_id(3, 5)), ssh.state.removed_by);
+        assert_eq!(ServerState::Removed, ssh.state.server_state);
+    }
+
+    Ok(())
+}
@@ -148,8 +148,9 @@ where
 
         debug_assert!(
             self.state.vote_ref().leader_id().voted_for() != Some(self.config.id)
-                || !self.state.membership_state.effective().membership().is_voter(&self.config.id),
-            "It must hold: vote is not mine, or I am not a voter(leader just left the cluster)"
+                || !self.state.membership_state.effective().membership().is_voter(&self.config.id)
+                || self.state.is_stepped_down(),
+            "It must hold: vote is not mine, or I am not a voter(leader just left the cluster), or I stepped down"
         );
 
         if self.internal_server_state.is_following() {
@@ -164,13 +164,13 @@ fn test_initialize() -> anyhow::Result<()> {
                 // command.
                 Command::SaveVote { vote: Vote::new(1, 1) },
                 Command::SendVote {
-                    vote_req: VoteRequest {
-                        vote: Vote::new(1, 1),
-                        last_log_id: Some(LogId {
+                    vote_req: VoteRequest::new(
+                        Vote::new(1, 1),
+                        Some(LogId {
                             leader_id: CommittedLeaderId::new(0, 0),
                             index: 0,
                         },),
-                    },
+                    ),
                 },
             ],
             eng.output.take_commands()
@@ -0,0 +1,125 @@
+use std::cmp::Ordering;
+use std::error::Error;
+
+use anyerror::AnyError;
+
+use crate::engine::Engine;
+use crate::entry::RaftEntry;
+use crate::equal;
+use crate::error::InvariantViolation;
+use crate::less_equal;
+use crate::progress::Progress;
+use crate::raft_state::LogStateReader;
+use crate::validate::Validate;
+use crate::LogId;
+use crate::LogIdOptionExt;
+use crate::Node;
+use crate::NodeId;
+use crate::RaftState;
+use crate::ServerState;
+use crate::Vote;
+
+/// Checks the invariants of the `Engine` state after every batch of commands is run.
+///
+/// It is enabled by feature `invariant-check`. Unlike the validation in `Valid`, which is turned
+/// on only when `debug_assertions` is enabled, it runs in release builds too, and a violation
+/// shuts down raft with a [`Fatal::InvariantViolation`] that contains a dump of the state.
+///
+/// Besides the invariants of [`RaftState`], it checks:
+/// - The committed and effective membership log ids are in the local log, unless purged.
+/// - The matching log id of every target a leader replicates to is in the leader's local log, and
+///   an established leader matches all of its own log.
+/// - The vote never decreases.
+///
+/// [`Fatal::InvariantViolation`]: crate::error::Fatal::InvariantViolation
+#[derive(Debug, Clone, Default)]
+pub(crate) struct InvariantChecker<NID: NodeId> {
+    /// The vote seen by the last check.
+    last_vote: Option<Vote<NID>>,
+}
+
+impl<NID: NodeId> InvariantChecker<NID> {
+    pub(crate) fn new() -> Self {
+        Self { last_vote: None }
+    }
+
+    /// Check the state of `engine`, and return the violation with a dump of the state if any.
+    pub(crate) fn check<N, Ent>(&mut self, engine: &Engine<NID, N, Ent>) -> Result<(), InvariantViolation>
+    where
+        N: Node,
+        Ent: RaftEntry<NID, N>,
+    {
+        self.check_state(engine).map_err(|e| {
+            let state: &RaftState<NID, N> = &engine.state;
+            InvariantViolation {
+                reason: e.to_string(),
+                state: format!(
+                    "id: {}, {:?}, internal_server_state: {:?}",
+                    engine.config.id, state, engine.internal_server_state
+                ),
+            }
+        })
+    }
+
+    fn check_state<N, Ent>(&mut self, engine: &Engine<NID, N, Ent>) -> Result<(), Box<dyn Error>>
+    where
+        N: Node,
+        Ent: RaftEntry<NID, N>,
+    {
+        let st: &RaftState<NID, N> = &engine.state;
+
+        self.check_vote(st.vote_ref())?;
+
+        st.validate()?;
+
+        for m in [st.membership_state.committed(), st.membership_state.effective()] {
+            less_equal!(m.log_id().as_ref(), st.last_log_id());
+            check_in_log(st, m.log_id())?;
+        }
+
+        if let Some(leader) = engine.internal_server_state.leading() {
+            for (_target, p) in leader.progress.iter() {
+                less_equal!(p.matching.as_ref(), st.last_log_id());
+                check_in_log(st, &p.matching)?;
+            }
+
+            if st.server_state == ServerState::Leader {
+                // The leader may not be in membership anymore
+                if let Some(p) = leader.progress.try_get(&engine.config.id) {
+                    equal!(p.matching.as_ref(), st.last_log_id());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_vote(&mut self, vote: &Vote<NID>) -> Result<(), Box<dyn Error>> {
+        if let Some(last) = &self.last_vote {
+            match vote.partial_cmp(last) {
+                Some(Ordering::Less) | None => {
+                    return Err(AnyError::error(format!("vote must not decrease: {} -> {}", last, vote)).into());
+                }
+                Some(Ordering::Equal) | Some(Ordering::Greater) => {}
+            }
+        }
+
+        self.last_vote = Some(*vote);
+        Ok(())
+    }
+}
+
+/// A log id that is not purged must be found in the local log.
+fn check_in_log<NID, N>(st: &RaftState<NID, N>, log_id: &Option<LogId<NID>>) -> Result<(), Box<dyn Error>>
+where
+    NID: NodeId,
+    N: Node,
+{
+    if log_id.as_ref() <= st.last_purged_log_id() {
+        return Ok(());
+    }
+
+    let index = log_id.index().unwrap();
+    equal!(&st.get_log_id(index), log_id);
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use std::sync::Arc;
+
+use maplit::btreeset;
+use tokio::time::Instant;
+
+use crate::engine::invariants::InvariantChecker;
+use crate::engine::testing::UTCfg;
+use crate::engine::CEngine;
+use crate::engine::Engine;
+use crate::engine::LogIdList;
+use crate::progress::entry::ProgressEntry;
+use crate::progress::Progress;
+use crate::testing::log_id;
+use crate::utime::UTime;
+use crate::EffectiveMembership;
+use crate::Membership;
+use crate::MembershipState;
+use crate::ServerState;
+use crate::Vote;
+
+fn m12() -> Membership<u64, ()> {
+    Membership::<u64, ()>::new(vec![btreeset! {1,2}], None)
+}
+
+/// A leader with logs `[0, 3]`, of which `[0, 2]` are committed.
+fn eng() -> CEngine<UTCfg> {
+    let mut eng = Engine::default();
+    eng.state.enable_validate = false; // Let the checker find the invalid state
+
+    eng.config.id = 1;
+    eng.state.vote = UTime::new(Instant::now(), Vote::new_committed(2, 1));
+    eng.state.log_ids = LogIdList::new([log_id(0, 0), log_id(1, 1), log_id(2, 3)]);
+    eng.state.committed = Some(log_id(1, 2));
+    eng.state.membership_state = MembershipState::new(
+        Arc::new(EffectiveMembership::new(Some(log_id(1, 1)), m12())),
+        Arc::new(EffectiveMembership::new(Some(log_id(2, 3)), m12())),
+    );
+
+    eng.vote_handler().become_leading();
+    eng.state.server_state = ServerState::Leader;
+
+    let leader = eng.internal_server_state.leading_mut().unwrap();
+    let _ = leader.progress.update(&1, ProgressEntry::new(Some(log_id(2, 3))));
+    let _ = leader.progress.update(&2, ProgressEntry::new(Some(log_id(1, 2))));
+
+    eng
+}
+
+#[test]
+fn test_invariants_ok() -> anyhow::Result<()> {
+    let eng = eng();
+
+    let mut checker = InvariantChecker::new();
+    checker.check(&eng)?;
+    checker.check(&eng)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_invariants_committed_greater_than_last_log_id() -> anyhow::Result<()> {
+    let mut eng = eng();
+    eng.state.committed = Some(log_id(2, 4));
+
+    let res = InvariantChecker::new().check(&eng);
+    let err = res.unwrap_err();
+    assert!(err.reason.contains("self.committed()"), "{}", err);
+    assert!(err.state.contains("committed: Some("), "state is dumped: {}", err);
+
+    Ok(())
+}
+
+#[test]
+fn test_invariants_membership_not_in_log() -> anyhow::Result<()> {
+    let mut eng = eng();
+    eng.state.membership_state = MembershipState::new(
+        Arc::new(EffectiveMembership::new(Some(log_id(1, 1)), m12())),
+        Arc::new(EffectiveMembership::new(Some(log_id(1, 3)), m12())),
+    );
+
+    let res = InvariantChecker::new().check(&eng);
+    assert!(res.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_invariants_leader_progress() -> anyhow::Result<()> {
+    tracing::info!("--- matching of a target is not in the leader log");
+    {
+        let mut eng = eng();
+        let leader = eng.internal_server_state.leading_mut().unwrap();
+        let _ = leader.progress.update(&2, ProgressEntry::new(Some(log_id(1, 3))));
+
+        let res = InvariantChecker::new().check(&eng);
+        assert!(res.is_err());
+    }
+
+    tracing::info!("--- leader does not match its own log");
+    {
+        let mut eng = eng();
+        eng.state.log_ids = LogIdList::new([log_id(0, 0), log_id(1, 1), log_id(2, 3), log_id(2, 4)]);
+
+        let res = InvariantChecker::new().check(&eng);
+        let err = res.unwrap_err();
+        assert!(err.reason.contains("p.matching"), "{}", err);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_invariants_vote_decreases() -> anyhow::Result<()> {
+    let mut eng = eng();
+
+    let mut checker = InvariantChecker::new();
+    checker.check(&eng)?;
+
+    eng.state.vote = UTime::new(Instant::now(), Vote::new(1, 2));
+
+    let res = checker.check(&eng);
+    let err = res.unwrap_err();
+    assert!(err.reason.contains("vote must not decrease"), "{}", err);
+
+    Ok(())
+}
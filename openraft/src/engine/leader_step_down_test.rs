@@ -0,0 +1,134 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use maplit::btreeset;
+use pretty_assertions::assert_eq;
+use tokio::time::Instant;
+
+use crate::core::ServerState;
+use crate::engine::testing::UTCfg;
+use crate::engine::CEngine;
+use crate::engine::Command;
+use crate::engine::Engine;
+use crate::testing::log_id;
+use crate::utime::UTime;
+use crate::EffectiveMembership;
+use crate::Membership;
+use crate::MembershipState;
+use crate::Vote;
+
+fn m123() -> Membership<u64, ()> {
+    Membership::<u64, ()>::new(vec![btreeset! {1,2,3}], None)
+}
+
+/// Build a leader of voters 1,2,3 whose vote is committed at `t0`.
+fn eng(t0: Instant) -> CEngine<UTCfg> {
+    let mut eng = Engine::default();
+    eng.state.enable_validate = false; // Disable validation for incomplete state
+
+    eng.config.id = 1;
+    eng.config.check_quorum = true;
+    eng.config.timer_config.election_timeout = Duration::from_millis(150);
+
+    eng.state.log_ids.append(log_id(1, 1));
+    eng.state.committed = Some(log_id(1, 1));
+    eng.state.membership_state = MembershipState::new(
+        Arc::new(EffectiveMembership::new(Some(log_id(1, 1)), m123())),
+        Arc::new(EffectiveMembership::new(Some(log_id(1, 1)), m123())),
+    );
+
+    eng.state.vote = UTime::new(t0, Vote::new_committed(2, 1));
+    eng.vote_handler().become_leading();
+    eng.state.server_state = eng.calc_server_state();
+    eng.output.clear_commands();
+
+    eng
+}
+
+#[test]
+fn test_leader_step_down_check_quorum() -> anyhow::Result<()> {
+    let t0 = Instant::now();
+    let ms = Duration::from_millis;
+
+    tracing::info!("--- a new leader is given an election timeout to hear from a quorum");
+    {
+        let mut eng = eng(t0);
+        eng.timer.update_now(t0 + ms(100));
+
+        eng.leader_step_down();
+
+        assert_eq!(ServerState::Leader, eng.state.server_state);
+        assert!(eng.output.take_commands().is_empty());
+    }
+
+    tracing::info!("--- a quorum responded within the election timeout");
+    {
+        let mut eng = eng(t0);
+        eng.internal_server_state.leading_mut().unwrap().ack_by(2, t0 + ms(200));
+        eng.internal_server_state.leading_mut().unwrap().ack_by(3, t0 + ms(50));
+        eng.timer.update_now(t0 + ms(300));
+
+        eng.leader_step_down();
+
+        assert_eq!(Vote::new_committed(2, 1), *eng.state.vote_ref());
+        assert_eq!(ServerState::Leader, eng.state.server_state);
+        assert!(eng.output.take_commands().is_empty());
+    }
+
+    tracing::info!("--- a heartbeat accepted by a target is a response");
+    {
+        let mut eng = eng(t0);
+        eng.timer.update_now(t0 + ms(200));
+        eng.handle_heartbeat_ack(2);
+        eng.timer.update_now(t0 + ms(300));
+
+        eng.leader_step_down();
+
+        assert_eq!(ServerState::Leader, eng.state.server_state);
+        assert!(eng.output.take_commands().is_empty());
+    }
+
+    tracing::info!("--- no quorum responded within the election timeout: step down");
+    {
+        let mut eng = eng(t0);
+        eng.internal_server_state.leading_mut().unwrap().ack_by(2, t0 + ms(100));
+        eng.internal_server_state.leading_mut().unwrap().ack_by(3, t0 + ms(50));
+        eng.timer.update_now(t0 + ms(300));
+
+        eng.leader_step_down();
+
+        assert_eq!(Vote::new_committed(2, 1), *eng.state.vote_ref());
+        assert_eq!(ServerState::Follower, eng.state.server_state);
+        assert!(eng.internal_server_state.is_following());
+        assert_eq!(None, eng.state.forward_to_leader().leader_id);
+        assert_eq!(vec![Command::QuitLeader], eng.output.take_commands());
+
+        tracing::info!("--- a stepped down leader does not step down again");
+
+        eng.leader_step_down();
+
+        assert_eq!(Vote::new_committed(2, 1), *eng.state.vote_ref());
+        assert!(eng.output.take_commands().is_empty());
+
+        tracing::info!("--- a stepped down leader elects with a greater term");
+
+        eng.elect();
+
+        assert_eq!(Vote::new(3, 1), *eng.state.vote_ref());
+        assert_eq!(ServerState::Candidate, eng.state.server_state);
+    }
+
+    tracing::info!("--- check-quorum is disabled");
+    {
+        let mut eng = eng(t0);
+        eng.config.check_quorum = false;
+        eng.timer.update_now(t0 + ms(300));
+
+        eng.leader_step_down();
+
+        assert_eq!(ServerState::Leader, eng.state.server_state);
+        assert!(eng.output.take_commands().is_empty());
+    }
+
+    Ok(())
+}
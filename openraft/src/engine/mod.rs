@@ -30,6 +30,7 @@
 mod command;
 mod engine_impl;
 pub(crate) mod handler;
+#[cfg(any(test, feature = "invariant-check"))] pub(crate) mod invariants;
 mod log_id_list;
 
 #[cfg(test)] mod elect_test;
@@ -37,6 +38,8 @@ mod log_id_list;
 #[cfg(test)] mod handle_vote_req_test;
 #[cfg(test)] mod handle_vote_resp_test;
 #[cfg(test)] mod initialize_test;
+#[cfg(test)] mod invariants_test;
+#[cfg(test)] mod leader_step_down_test;
 #[cfg(test)] mod log_id_list_test;
 #[cfg(test)] mod startup_test;
 #[cfg(test)] mod testing;
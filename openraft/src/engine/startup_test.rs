@@ -75,6 +75,28 @@ fn test_startup_as_leader() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_startup_as_leader_with_check_quorum() -> anyhow::Result<()> {
+    let mut eng = eng();
+    eng.config.check_quorum = true;
+    // self.id==2 is a voter:
+    eng.state
+        .membership_state
+        .set_effective(Arc::new(EffectiveMembership::new(Some(log_id(2, 3)), m23())));
+    // A committed vote does not restore the leadership: it may have been given up before restart.
+    eng.state.vote = UTime::new(Instant::now(), Vote::new_committed(1, 2));
+
+    eng.startup();
+
+    assert_eq!(ServerState::Follower, eng.state.server_state);
+    assert_eq!(Some(Vote::new_committed(1, 2)), eng.state.stepped_down);
+    assert!(!eng.state.is_leader(&2));
+
+    assert_eq!(0, eng.output.take_commands().len());
+
+    Ok(())
+}
+
 #[test]
 fn test_startup_candidate_becomes_follower() -> anyhow::Result<()> {
     let mut eng = eng();
@@ -8,8 +8,11 @@ use crate::MessageSummary;
 use crate::RaftTypeConfig;
 
 pub mod payload;
+mod size;
 mod traits;
 
+#[cfg(all(test, feature = "serde"))] mod size_test;
+
 pub use payload::EntryPayload;
 pub use traits::FromAppData;
 pub use traits::RaftEntry;
@@ -120,6 +123,10 @@ where C: RaftTypeConfig
             payload: EntryPayload::Membership(m),
         }
     }
+
+    fn size_hint(&self) -> u64 {
+        self.payload.size_hint()
+    }
 }
 
 impl<C> FromAppData<C::D> for Entry<C>
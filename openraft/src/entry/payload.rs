@@ -1,6 +1,7 @@
 use std::fmt;
 use std::fmt::Formatter;
 
+use crate::entry::size::encoded_size;
 use crate::entry::traits::RaftPayload;
 use crate::Membership;
 use crate::MessageSummary;
@@ -45,6 +46,19 @@ impl<C: RaftTypeConfig> MessageSummary<EntryPayload<C>> for EntryPayload<C> {
     }
 }
 
+impl<C: RaftTypeConfig> EntryPayload<C> {
+    /// Return the approximate number of bytes it takes to send this payload to another node.
+    ///
+    /// It is the encoded length of the app data or the membership, and `0` for a blank payload.
+    pub fn size_hint(&self) -> u64 {
+        match self {
+            EntryPayload::Blank => 0,
+            EntryPayload::Normal(d) => encoded_size(d),
+            EntryPayload::Membership(m) => encoded_size(m),
+        }
+    }
+}
+
 impl<C: RaftTypeConfig> RaftPayload<C::NodeId, C::Node> for EntryPayload<C> {
     fn is_blank(&self) -> bool {
         matches!(self, EntryPayload::Blank)
@@ -0,0 +1,325 @@
+use crate::OptionalSerde;
+
+/// Returns the length of `t` in a compact binary encoding, without serializing it.
+///
+/// Every primitive counts its width in bytes, a string, bytes, sequence or map counts an 8-byte
+/// length prefix, and an enum variant counts a 4-byte tag. No data is copied or formatted.
+///
+/// It is the default size of a log entry or its data, when an application does not provide one.
+#[cfg(feature = "serde")]
+pub(crate) fn encoded_size<T: OptionalSerde + ?Sized>(t: &T) -> u64 {
+    let mut counter = counter::SizeCounter(0);
+
+    // Counting never fails. It fails only if `T` can not be serialized, which is reported when the
+    // entry is sent or stored: count the bytes visited so far.
+    let _ = serde::Serialize::serialize(t, &mut counter);
+    counter.0
+}
+
+/// Without feature `serde` openraft can not measure an entry: the size is `0`, i.e., unknown.
+///
+/// An application that uses the byte limits without `serde` provides the size by implementing
+/// [`RaftEntry::size_hint()`](`crate::entry::RaftEntry::size_hint`).
+#[cfg(not(feature = "serde"))]
+pub(crate) fn encoded_size<T: OptionalSerde + ?Sized>(_t: &T) -> u64 {
+    0
+}
+
+#[cfg(feature = "serde")]
+mod counter {
+    use std::fmt::Display;
+
+    use serde::ser;
+    use serde::Serialize;
+
+    /// A [`serde::Serializer`] that only counts the bytes of the encoded data.
+    pub(super) struct SizeCounter(pub(super) u64);
+
+    const LEN: u64 = 8;
+    const TAG: u64 = 4;
+
+    #[derive(Debug)]
+    pub(super) struct SizeError(String);
+
+    impl Display for SizeError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{}", self.0)
+        }
+    }
+
+    impl std::error::Error for SizeError {}
+
+    impl ser::Error for SizeError {
+        fn custom<T: Display>(msg: T) -> Self {
+            SizeError(msg.to_string())
+        }
+    }
+
+    impl SizeCounter {
+        fn add(&mut self, n: u64) -> Result<(), SizeError> {
+            self.0 += n;
+            Ok(())
+        }
+    }
+
+    impl<'a> ser::Serializer for &'a mut SizeCounter {
+        type Ok = ();
+        type Error = SizeError;
+
+        type SerializeSeq = Self;
+        type SerializeTuple = Self;
+        type SerializeTupleStruct = Self;
+        type SerializeTupleVariant = Self;
+        type SerializeMap = Self;
+        type SerializeStruct = Self;
+        type SerializeStructVariant = Self;
+
+        fn serialize_bool(self, _v: bool) -> Result<(), SizeError> {
+            self.add(1)
+        }
+
+        fn serialize_i8(self, _v: i8) -> Result<(), SizeError> {
+            self.add(1)
+        }
+
+        fn serialize_i16(self, _v: i16) -> Result<(), SizeError> {
+            self.add(2)
+        }
+
+        fn serialize_i32(self, _v: i32) -> Result<(), SizeError> {
+            self.add(4)
+        }
+
+        fn serialize_i64(self, _v: i64) -> Result<(), SizeError> {
+            self.add(8)
+        }
+
+        fn serialize_i128(self, _v: i128) -> Result<(), SizeError> {
+            self.add(16)
+        }
+
+        fn serialize_u8(self, _v: u8) -> Result<(), SizeError> {
+            self.add(1)
+        }
+
+        fn serialize_u16(self, _v: u16) -> Result<(), SizeError> {
+            self.add(2)
+        }
+
+        fn serialize_u32(self, _v: u32) -> Result<(), SizeError> {
+            self.add(4)
+        }
+
+        fn serialize_u64(self, _v: u64) -> Result<(), SizeError> {
+            self.add(8)
+        }
+
+        fn serialize_u128(self, _v: u128) -> Result<(), SizeError> {
+            self.add(16)
+        }
+
+        fn serialize_f32(self, _v: f32) -> Result<(), SizeError> {
+            self.add(4)
+        }
+
+        fn serialize_f64(self, _v: f64) -> Result<(), SizeError> {
+            self.add(8)
+        }
+
+        fn serialize_char(self, v: char) -> Result<(), SizeError> {
+            self.add(v.len_utf8() as u64)
+        }
+
+        fn serialize_str(self, v: &str) -> Result<(), SizeError> {
+            self.add(LEN + v.len() as u64)
+        }
+
+        fn serialize_bytes(self, v: &[u8]) -> Result<(), SizeError> {
+            self.add(LEN + v.len() as u64)
+        }
+
+        fn serialize_none(self) -> Result<(), SizeError> {
+            self.add(1)
+        }
+
+        fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), SizeError> {
+            self.add(1)?;
+            value.serialize(self)
+        }
+
+        fn serialize_unit(self) -> Result<(), SizeError> {
+            Ok(())
+        }
+
+        fn serialize_unit_struct(self, _name: &'static str) -> Result<(), SizeError> {
+            Ok(())
+        }
+
+        fn serialize_unit_variant(
+            self,
+            _name: &'static str,
+            _variant_index: u32,
+            _variant: &'static str,
+        ) -> Result<(), SizeError> {
+            self.add(TAG)
+        }
+
+        fn serialize_newtype_struct<T: ?Sized + Serialize>(
+            self,
+            _name: &'static str,
+            value: &T,
+        ) -> Result<(), SizeError> {
+            value.serialize(self)
+        }
+
+        fn serialize_newtype_variant<T: ?Sized + Serialize>(
+            self,
+            _name: &'static str,
+            _variant_index: u32,
+            _variant: &'static str,
+            value: &T,
+        ) -> Result<(), SizeError> {
+            self.add(TAG)?;
+            value.serialize(self)
+        }
+
+        fn serialize_seq(self, _len: Option<usize>) -> Result<Self, SizeError> {
+            self.add(LEN)?;
+            Ok(self)
+        }
+
+        fn serialize_tuple(self, _len: usize) -> Result<Self, SizeError> {
+            Ok(self)
+        }
+
+        fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self, SizeError> {
+            Ok(self)
+        }
+
+        fn serialize_tuple_variant(
+            self,
+            _name: &'static str,
+            _variant_index: u32,
+            _variant: &'static str,
+            _len: usize,
+        ) -> Result<Self, SizeError> {
+            self.add(TAG)?;
+            Ok(self)
+        }
+
+        fn serialize_map(self, _len: Option<usize>) -> Result<Self, SizeError> {
+            self.add(LEN)?;
+            Ok(self)
+        }
+
+        fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, SizeError> {
+            Ok(self)
+        }
+
+        fn serialize_struct_variant(
+            self,
+            _name: &'static str,
+            _variant_index: u32,
+            _variant: &'static str,
+            _len: usize,
+        ) -> Result<Self, SizeError> {
+            self.add(TAG)?;
+            Ok(self)
+        }
+    }
+
+    impl<'a> ser::SerializeSeq for &'a mut SizeCounter {
+        type Ok = ();
+        type Error = SizeError;
+
+        fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), SizeError> {
+            value.serialize(&mut **self)
+        }
+
+        fn end(self) -> Result<(), SizeError> {
+            Ok(())
+        }
+    }
+
+    impl<'a> ser::SerializeTuple for &'a mut SizeCounter {
+        type Ok = ();
+        type Error = SizeError;
+
+        fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), SizeError> {
+            value.serialize(&mut **self)
+        }
+
+        fn end(self) -> Result<(), SizeError> {
+            Ok(())
+        }
+    }
+
+    impl<'a> ser::SerializeTupleStruct for &'a mut SizeCounter {
+        type Ok = ();
+        type Error = SizeError;
+
+        fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), SizeError> {
+            value.serialize(&mut **self)
+        }
+
+        fn end(self) -> Result<(), SizeError> {
+            Ok(())
+        }
+    }
+
+    impl<'a> ser::SerializeTupleVariant for &'a mut SizeCounter {
+        type Ok = ();
+        type Error = SizeError;
+
+        fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), SizeError> {
+            value.serialize(&mut **self)
+        }
+
+        fn end(self) -> Result<(), SizeError> {
+            Ok(())
+        }
+    }
+
+    impl<'a> ser::SerializeMap for &'a mut SizeCounter {
+        type Ok = ();
+        type Error = SizeError;
+
+        fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), SizeError> {
+            key.serialize(&mut **self)
+        }
+
+        fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), SizeError> {
+            value.serialize(&mut **self)
+        }
+
+        fn end(self) -> Result<(), SizeError> {
+            Ok(())
+        }
+    }
+
+    impl<'a> ser::SerializeStruct for &'a mut SizeCounter {
+        type Ok = ();
+        type Error = SizeError;
+
+        fn serialize_field<T: ?Sized + Serialize>(&mut self, _key: &'static str, value: &T) -> Result<(), SizeError> {
+            value.serialize(&mut **self)
+        }
+
+        fn end(self) -> Result<(), SizeError> {
+            Ok(())
+        }
+    }
+
+    impl<'a> ser::SerializeStructVariant for &'a mut SizeCounter {
+        type Ok = ();
+        type Error = SizeError;
+
+        fn serialize_field<T: ?Sized + Serialize>(&mut self, _key: &'static str, value: &T) -> Result<(), SizeError> {
+            value.serialize(&mut **self)
+        }
+
+        fn end(self) -> Result<(), SizeError> {
+            Ok(())
+        }
+    }
+}
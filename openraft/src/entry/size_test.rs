@@ -0,0 +1,49 @@
+use std::collections::BTreeMap;
+
+use crate::entry::size::encoded_size;
+
+#[test]
+fn test_encoded_size() -> anyhow::Result<()> {
+    assert_eq!(8, encoded_size(&"".to_string()));
+    assert_eq!(11, encoded_size(&"foo".to_string()));
+
+    assert_eq!(8 + 1000, encoded_size(&vec![b'x'; 1000]));
+    assert_eq!(8 + 3 * 4, encoded_size(&vec![1u32, 2, 3]));
+
+    assert_eq!(1, encoded_size(&None::<u64>));
+    assert_eq!(1 + 8, encoded_size(&Some(5u64)));
+
+    let m = BTreeMap::from([(1u64, "a".to_string()), (2, "bc".to_string())]);
+    assert_eq!(8 + (8 + 9) + (8 + 10), encoded_size(&m));
+
+    Ok(())
+}
+
+#[test]
+fn test_encoded_size_of_struct_and_enum() -> anyhow::Result<()> {
+    #[derive(serde::Serialize)]
+    struct Foo {
+        a: u64,
+        b: String,
+    }
+
+    #[derive(serde::Serialize)]
+    enum Bar {
+        Unit,
+        Newtype(u32),
+        Struct { c: bool },
+    }
+
+    assert_eq!(
+        8 + (8 + 3),
+        encoded_size(&Foo {
+            a: 1,
+            b: "xyz".to_string()
+        })
+    );
+    assert_eq!(4, encoded_size(&Bar::Unit));
+    assert_eq!(4 + 4, encoded_size(&Bar::Newtype(1)));
+    assert_eq!(4 + 1, encoded_size(&Bar::Struct { c: true }));
+
+    Ok(())
+}
@@ -1,6 +1,7 @@
 use std::fmt::Debug;
 use std::fmt::Display;
 
+use crate::entry::size::encoded_size;
 use crate::log_id::RaftLogId;
 use crate::LogId;
 use crate::Membership;
@@ -37,6 +38,20 @@ where
     ///
     /// The returned instance must return `Some()` for `Self::get_membership()`.
     fn new_membership(log_id: LogId<NID>, m: Membership<NID, N>) -> Self;
+
+    /// Return the approximate number of bytes it takes to send this entry to another node.
+    ///
+    /// It limits the size of a replication payload, the replication rate and the uncommitted
+    /// bytes of a leader. It is called once when an entry is appended: by the leader when the entry
+    /// is proposed, and by a follower when the entry is received. An application that knows the
+    /// size of an entry cheaply, e.g., the length of the bytes it carries, should override it.
+    ///
+    /// The default is the length of the entry in a compact binary encoding with feature `serde`,
+    /// or `0`, i.e., unknown, without it. [`Entry`](`crate::Entry`) returns the size of its
+    /// payload.
+    fn size_hint(&self) -> u64 {
+        encoded_size(self)
+    }
 }
 
 /// Build a raft log entry from app data.
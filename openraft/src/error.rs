@@ -114,6 +114,24 @@ where NID: NodeId
 
     #[error("raft stopped")]
     Stopped,
+
+    /// The raft state breaks an invariant.
+    ///
+    /// It is returned only when feature `invariant-check` is enabled.
+    #[error(transparent)]
+    InvariantViolation(#[from] InvariantViolation),
+}
+
+/// The raft state breaks an invariant.
+///
+/// It is reported, with `Fatal::InvariantViolation`, only when feature `invariant-check` is
+/// enabled. It contains a dump of the raft state when the violation is found.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[error("invariant violated: {reason}; state: {state}")]
+pub struct InvariantViolation {
+    pub reason: String,
+    pub state: String,
 }
 
 // TODO: remove
@@ -168,6 +186,10 @@ where
     /// When writing a change-membership entry.
     #[error(transparent)]
     ChangeMembershipError(#[from] ChangeMembershipError<NID>),
+
+    /// The uncommitted entries on the leader reach the limits.
+    #[error(transparent)]
+    Backpressure(#[from] Backpressure),
 }
 
 impl<NID, N> TryAsRef<ForwardToLeader<NID, N>> for ClientWriteError<NID, N>
@@ -212,6 +234,77 @@ where
     NotInMembers(#[from] NotInMembers<NID, N>),
 }
 
+/// The set of errors which may take place when a node joins a cluster.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize), serde(bound = ""))]
+pub enum JoinError<NID, N>
+where
+    NID: NodeId,
+    N: Node,
+{
+    #[error(transparent)]
+    ForwardToLeader(#[from] ForwardToLeader<NID, N>),
+
+    /// The id of the joining node is already used by another node.
+    #[error(transparent)]
+    NodeConflict(#[from] NodeConflict<NID, N>),
+
+    /// When adding the node to the membership.
+    #[error(transparent)]
+    ChangeMembershipError(#[from] ChangeMembershipError<NID>),
+
+    /// None of the seed nodes, or the leader they know, accepts the join request.
+    #[error(transparent)]
+    NoLeaderReachable(#[from] NoLeaderReachable<NID>),
+
+    /// The uncommitted entries on the leader reach the limits when adding the node.
+    #[error(transparent)]
+    Backpressure(#[from] Backpressure),
+
+    /// The joining node does not become a voter in `Config::join_timeout`.
+    #[error(transparent)]
+    Timeout(#[from] JoinTimeout<NID>),
+}
+
+impl<NID, N> From<ClientWriteError<NID, N>> for JoinError<NID, N>
+where
+    NID: NodeId,
+    N: Node,
+{
+    fn from(e: ClientWriteError<NID, N>) -> Self {
+        match e {
+            ClientWriteError::ForwardToLeader(e) => Self::ForwardToLeader(e),
+            ClientWriteError::ChangeMembershipError(e) => Self::ChangeMembershipError(e),
+            ClientWriteError::Backpressure(e) => Self::Backpressure(e),
+        }
+    }
+}
+
+impl<NID, N> TryAsRef<ForwardToLeader<NID, N>> for JoinError<NID, N>
+where
+    NID: NodeId,
+    N: Node,
+{
+    fn try_as_ref(&self) -> Option<&ForwardToLeader<NID, N>> {
+        match self {
+            Self::ForwardToLeader(f) => Some(f),
+            _ => None,
+        }
+    }
+}
+
+/// The set of errors which may take place when waiting for a log to be applied.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize), serde(bound = ""))]
+pub enum WaitAppliedError<NID: NodeId> {
+    /// Another log is applied at the index of the log to wait for.
+    #[error(transparent)]
+    LogIdMismatch(#[from] LogIdMismatch<NID>),
+
+    #[error(transparent)]
+    Timeout(#[from] WaitAppliedTimeout<NID>),
+}
+
 /// Error variants related to the Replication.
 #[derive(Debug, thiserror::Error)]
 #[allow(clippy::large_enum_variant)]
@@ -340,6 +433,25 @@ pub struct Timeout<NID: NodeId> {
     pub timeout: Duration,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize), serde(bound = ""))]
+#[error("timeout after {timeout:?} when waiting for {log_id} to be applied")]
+pub struct WaitAppliedTimeout<NID: NodeId> {
+    pub log_id: LogId<NID>,
+    pub timeout: Duration,
+}
+
+/// The log at an index is not the expected one.
+///
+/// `got` is `None` if the log is purged and is known to be another one.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize), serde(bound = ""))]
+#[error("log id mismatch, expect: {expect}, got: {got:?}")]
+pub struct LogIdMismatch<NID: NodeId> {
+    pub expect: LogId<NID>,
+    pub got: Option<LogId<NID>>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize), serde(bound = ""))]
 #[error("store has no log at: {index:?}, last purged: {last_purged_log_id:?}")]
@@ -380,6 +492,49 @@ where
     }
 }
 
+/// A client write is not accepted because the uncommitted entries on the leader reach
+/// `Config::max_uncommitted_entries` or `Config::max_uncommitted_bytes`.
+///
+/// A limit of `0` means no limit.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[error("too many uncommitted entries on the leader: {uncommitted_entries} entries(max: {max_entries}), {uncommitted_bytes} bytes(max: {max_bytes})")]
+pub struct Backpressure {
+    pub uncommitted_entries: u64,
+    pub uncommitted_bytes: u64,
+    pub max_entries: u64,
+    pub max_bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize), serde(bound = ""))]
+#[error("node {node_id} is already in the membership with {existing:?}, can not join with {joining:?}")]
+pub struct NodeConflict<NID, N>
+where
+    NID: NodeId,
+    N: Node,
+{
+    pub node_id: NID,
+    pub existing: N,
+    pub joining: N,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize), serde(bound = ""))]
+#[error("no leader accepts the join request, tried: {tried:?}, last error: {last_error:?}")]
+pub struct NoLeaderReachable<NID: NodeId> {
+    pub tried: BTreeSet<NID>,
+    pub last_error: Option<AnyError>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize), serde(bound = ""))]
+#[error("timeout after {timeout:?} when waiting for joining node {node_id} to become a voter")]
+pub struct JoinTimeout<NID: NodeId> {
+    pub node_id: NID,
+    pub timeout: Duration,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize), serde(bound = ""))]
 #[error("snapshot segment id mismatch, expect: {expect}, got: {got}")]
@@ -441,6 +596,16 @@ pub struct EmptyMembership {}
 #[error("infallible")]
 pub enum Infallible {}
 
+impl<NID, N> TryAsRef<ForwardToLeader<NID, N>> for Infallible
+where
+    NID: NodeId,
+    N: Node,
+{
+    fn try_as_ref(&self) -> Option<&ForwardToLeader<NID, N>> {
+        match *self {}
+    }
+}
+
 /// A place holder to mark RaftError won't have a ForwardToLeader variant.
 #[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
@@ -1,5 +1,8 @@
+use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 
+use tokio::time::Instant;
+
 use crate::log_id::LogIndexOptionExt;
 use crate::progress::entry::ProgressEntry;
 use crate::progress::Progress;
@@ -34,6 +37,9 @@ pub(crate) struct Leader<NID: NodeId, QS: QuorumSet<NID>> {
 
     /// Tracks the replication progress and committed index
     pub(crate) progress: VecProgress<NID, ProgressEntry<NID>, Option<LogId<NID>>, QS>,
+
+    /// The last time a following node responded to a replication request of this leader.
+    pub(crate) acked_at: BTreeMap<NID, Instant>,
 }
 
 impl<NID, QS> Leader<NID, QS>
@@ -55,6 +61,7 @@ where
                 learner_ids,
                 ProgressEntry::empty(last_log_index.next_index()),
             ),
+            acked_at: BTreeMap::new(),
         }
     }
 
@@ -68,4 +75,18 @@ where
         let qs = self.progress.quorum_set();
         qs.is_quorum(self.vote_granted_by.iter())
     }
+
+    /// Update that a following node has responded to this leader at `now`.
+    pub(crate) fn ack_by(&mut self, target: NID, now: Instant) {
+        self.acked_at.insert(target, now);
+    }
+
+    /// Return if a quorum, including the leader `me` itself, has responded to this leader since
+    /// `since`.
+    pub(crate) fn is_quorum_acked(&self, me: &NID, since: Instant) -> bool {
+        let acked = self.acked_at.iter().filter(|(_, t)| **t >= since).map(|(id, _)| id);
+
+        let qs = self.progress.quorum_set();
+        qs.is_quorum(std::iter::once(me).chain(acked))
+    }
 }
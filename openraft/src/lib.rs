@@ -44,10 +44,12 @@ mod progress;
 mod quorum;
 mod raft_types;
 mod replication;
+mod session;
 mod storage_error;
 mod store_ext;
 mod store_wrapper;
 mod summary;
+mod trace_context;
 mod vote;
 
 #[cfg(feature = "compat")] pub mod compat;
@@ -77,6 +79,8 @@ mod runtime;
 mod try_as_ref;
 
 #[cfg(test)] mod feature_serde_test;
+#[cfg(test)] mod session_test;
+#[cfg(test)] mod trace_context_test;
 
 pub use anyerror;
 pub use anyerror::AnyError;
@@ -115,6 +119,9 @@ pub(crate) use crate::raft_types::MetricsChangeFlags;
 pub use crate::raft_types::SnapshotId;
 pub use crate::raft_types::SnapshotSegmentId;
 pub use crate::raft_types::Update;
+pub use crate::session::Session;
+pub use crate::session::SessionResponse;
+pub use crate::session::Sessions;
 pub use crate::storage::LogState;
 pub use crate::storage::RaftLogReader;
 pub use crate::storage::RaftSnapshotBuilder;
@@ -133,6 +140,7 @@ pub use crate::storage_error::Violation;
 pub use crate::store_ext::StoreExt;
 pub use crate::store_wrapper::Wrapper;
 pub use crate::summary::MessageSummary;
+pub use crate::trace_context::TraceContext;
 pub use crate::try_as_ref::TryAsRef;
 pub use crate::vote::CommittedLeaderId;
 pub use crate::vote::LeaderId;
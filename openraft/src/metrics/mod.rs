@@ -18,5 +18,6 @@ pub use raft_metrics::RaftMetrics;
 pub use replication_metrics::ReplicationMetrics;
 pub use replication_metrics::ReplicationTargetMetrics;
 pub(crate) use replication_metrics::UpdateMatchedLogId;
+pub(crate) use replication_metrics::UpdateThrottled;
 pub use wait::Wait;
 pub use wait::WaitError;
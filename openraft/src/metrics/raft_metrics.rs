@@ -56,6 +56,19 @@ where
     // ---
     /// The metrics about the leader. It is Some() only when this node is leader.
     pub replication: Option<Versioned<ReplicationMetrics<NID>>>,
+
+    // ---
+    // --- backpressure ---
+    // ---
+    /// The number of uncommitted entries on the leader, limited by
+    /// `Config::max_uncommitted_entries`. It is `0` if this node is not a leader.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub uncommitted_entries: u64,
+
+    /// The total size in bytes of the uncommitted entries proposed by the leader, limited by
+    /// `Config::max_uncommitted_bytes`. It is `0` if this node is not a leader.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub uncommitted_bytes: u64,
 }
 
 impl<NID, N> MessageSummary<RaftMetrics<NID, N>> for RaftMetrics<NID, N>
@@ -64,7 +77,7 @@ where
     N: Node,
 {
     fn summary(&self) -> String {
-        format!("Metrics{{id:{},{:?}, term:{}, last_log:{:?}, last_applied:{:?}, leader:{:?}, membership:{}, snapshot:{:?}, replication:{}",
+        format!("Metrics{{id:{},{:?}, term:{}, last_log:{:?}, last_applied:{:?}, leader:{:?}, membership:{}, snapshot:{:?}, replication:{}, uncommitted:{}({} bytes)",
                 self.id,
                 self.state,
                 self.current_term,
@@ -74,6 +87,8 @@ where
                 self.membership_config.summary(),
                 self.snapshot,
                 self.replication.as_ref().map(|x| x.summary()).unwrap_or_default(),
+                self.uncommitted_entries,
+                self.uncommitted_bytes,
         )
     }
 }
@@ -95,6 +110,8 @@ where
             membership_config: Arc::new(StoredMembership::default()),
             snapshot: None,
             replication: None,
+            uncommitted_entries: 0,
+            uncommitted_bytes: 0,
         }
     }
 }
@@ -2,6 +2,7 @@ use std::collections::BTreeMap;
 use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::versioned::Update;
 use crate::versioned::UpdateError;
@@ -54,13 +55,39 @@ impl<NID: NodeId> Update<ReplicationMetrics<NID>> for UpdateMatchedLogId<NID> {
 
     /// To insert a new record always work.
     fn apply_mut(&self, to: &mut ReplicationMetrics<NID>) {
+        let throttled_ms =
+            to.replication.get(&self.target).map(|x| x.throttled_ms.load(Ordering::Relaxed)).unwrap_or(0);
+
         to.replication.insert(self.target, ReplicationTargetMetrics {
             matched_leader_id: self.matching.leader_id,
             matched_index: AtomicU64::new(self.matching.index),
+            throttled_ms: AtomicU64::new(throttled_ms),
         });
     }
 }
 
+/// Add the time a replication waited for the rate limits to `LeaderMetrics.replication`.
+pub(crate) struct UpdateThrottled<NID: NodeId> {
+    pub(crate) target: NID,
+    pub(crate) throttled: Duration,
+}
+
+impl<NID: NodeId> Update<ReplicationMetrics<NID>> for UpdateThrottled<NID> {
+    fn apply_in_place(&self, to: &Arc<ReplicationMetrics<NID>>) -> Result<(), UpdateError> {
+        let target_metrics = to.replication.get(&self.target).ok_or(UpdateError::CanNotUpdateInPlace)?;
+
+        target_metrics.throttled_ms.fetch_add(self.throttled.as_millis() as u64, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// There is no record for a target before it matches any log: the time is not counted.
+    fn apply_mut(&self, to: &mut ReplicationMetrics<NID>) {
+        if let Some(target_metrics) = to.replication.get(&self.target) {
+            target_metrics.throttled_ms.fetch_add(self.throttled.as_millis() as u64, Ordering::Relaxed);
+        }
+    }
+}
+
 /// Remove one replication metrics in `LeaderMetrics.replication`.
 pub(crate) struct RemoveTarget<NID: NodeId> {
     pub target: NID,
@@ -82,6 +109,10 @@ impl<NID: NodeId> Update<ReplicationMetrics<NID>> for RemoveTarget<NID> {
 pub struct ReplicationTargetMetrics<NID: NodeId> {
     pub(crate) matched_leader_id: CommittedLeaderId<NID>,
     pub(crate) matched_index: AtomicU64,
+
+    /// The total time in milliseconds the replication waited for the rate limits.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) throttled_ms: AtomicU64,
 }
 
 impl<NID: NodeId> Clone for ReplicationTargetMetrics<NID> {
@@ -89,6 +120,7 @@ impl<NID: NodeId> Clone for ReplicationTargetMetrics<NID> {
         Self {
             matched_leader_id: self.matched_leader_id,
             matched_index: AtomicU64::new(self.matched_index.load(Ordering::Relaxed)),
+            throttled_ms: AtomicU64::new(self.throttled_ms.load(Ordering::Relaxed)),
         }
     }
 }
@@ -97,6 +129,7 @@ impl<NID: NodeId> PartialEq for ReplicationTargetMetrics<NID> {
     fn eq(&self, other: &Self) -> bool {
         self.matched_leader_id == other.matched_leader_id
             && self.matched_index.load(Ordering::Relaxed) == other.matched_index.load(Ordering::Relaxed)
+            && self.throttled_ms.load(Ordering::Relaxed) == other.throttled_ms.load(Ordering::Relaxed)
     }
 }
 
@@ -107,6 +140,7 @@ impl<NID: NodeId> ReplicationTargetMetrics<NID> {
         Self {
             matched_leader_id: log_id.leader_id,
             matched_index: AtomicU64::new(log_id.index),
+            throttled_ms: AtomicU64::new(0),
         }
     }
 
@@ -117,6 +151,12 @@ impl<NID: NodeId> ReplicationTargetMetrics<NID> {
             index,
         }
     }
+
+    /// Returns the total time the replication to this target waited for the replication rate
+    /// limits.
+    pub fn throttled(&self) -> Duration {
+        Duration::from_millis(self.throttled_ms.load(Ordering::Relaxed))
+    }
 }
 
 impl<NID: NodeId> MessageSummary<ReplicationTargetMetrics<NID>> for ReplicationTargetMetrics<NID> {
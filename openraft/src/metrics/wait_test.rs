@@ -191,6 +191,8 @@ where
 
         snapshot: None,
         replication: None,
+        uncommitted_entries: 0,
+        uncommitted_bytes: 0,
     };
     let (tx, rx) = watch::channel(init.clone());
     let w = Wait {
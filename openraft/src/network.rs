@@ -2,15 +2,22 @@
 
 use std::fmt::Formatter;
 
+use anyerror::AnyError;
 use async_trait::async_trait;
 
 use crate::error::InstallSnapshotError;
+use crate::error::JoinError;
+use crate::error::NetworkError;
 use crate::error::RPCError;
 use crate::error::RaftError;
 use crate::raft::AppendEntriesRequest;
 use crate::raft::AppendEntriesResponse;
+use crate::raft::HeartbeatRequest;
+use crate::raft::HeartbeatResponse;
 use crate::raft::InstallSnapshotRequest;
 use crate::raft::InstallSnapshotResponse;
+use crate::raft::JoinRequest;
+use crate::raft::JoinResponse;
 use crate::raft::VoteRequest;
 use crate::raft::VoteResponse;
 use crate::RaftTypeConfig;
@@ -21,6 +28,8 @@ pub enum RPCTypes {
     Vote,
     AppendEntries,
     InstallSnapshot,
+    Join,
+    Heartbeat,
 }
 
 impl std::fmt::Display for RPCTypes {
@@ -49,6 +58,24 @@ where C: RaftTypeConfig
         rpc: AppendEntriesRequest<C>,
     ) -> Result<AppendEntriesResponse<C::NodeId>, RPCError<C::NodeId, C::Node, RaftError<C::NodeId>>>;
 
+    /// Send a heartbeat RPC to the target Raft node (§5.2), which is handled by
+    /// [`Raft::heartbeat()`].
+    ///
+    /// Heartbeats are sent on a connection other than the one for log replication, so that they
+    /// are sent on schedule even when the target is busy receiving logs or a snapshot.
+    ///
+    /// By default it sends the heartbeat as an AppendEntries RPC without entries. Override it to
+    /// send heartbeats with a lighter or higher priority RPC.
+    ///
+    /// [`Raft::heartbeat()`]: `crate::Raft::heartbeat`
+    async fn send_heartbeat(
+        &mut self,
+        rpc: HeartbeatRequest<C::NodeId>,
+    ) -> Result<HeartbeatResponse<C::NodeId>, RPCError<C::NodeId, C::Node, RaftError<C::NodeId>>> {
+        let resp = self.send_append_entries(rpc.into()).await?;
+        Ok(resp.into())
+    }
+
     /// Send an InstallSnapshot RPC to the target Raft node (§7).
     async fn send_install_snapshot(
         &mut self,
@@ -63,6 +90,25 @@ where C: RaftTypeConfig
         &mut self,
         rpc: VoteRequest<C::NodeId>,
     ) -> Result<VoteResponse<C::NodeId>, RPCError<C::NodeId, C::Node, RaftError<C::NodeId>>>;
+
+    /// Send a Join RPC to the target Raft node, which is handled by [`Raft::handle_join()`].
+    ///
+    /// It is used by [`Raft::join()`] to join a cluster through a seed node. An application that
+    /// does not use `Raft::join()` does not need to implement it.
+    ///
+    /// [`Raft::handle_join()`]: `crate::Raft::handle_join`
+    /// [`Raft::join()`]: `crate::Raft::join`
+    async fn send_join(
+        &mut self,
+        rpc: JoinRequest<C::NodeId, C::Node>,
+    ) -> Result<
+        JoinResponse<C::NodeId, C::Node>,
+        RPCError<C::NodeId, C::Node, RaftError<C::NodeId, JoinError<C::NodeId, C::Node>>>,
+    > {
+        let _ = rpc;
+        let e = AnyError::error("send_join is not implemented");
+        Err(RPCError::Network(NetworkError::from(e)))
+    }
 }
 
 /// A trait defining the interface for a Raft network factory to create connections between cluster
@@ -122,6 +122,7 @@ impl<NID: NodeId> ProgressEntry<NID> {
         &mut self,
         log_state: &impl LogStateReader<NID>,
         max_entries: u64,
+        max_bytes: u64,
     ) -> Result<&Inflight<NID>, &Inflight<NID>> {
         if !self.inflight.is_none() {
             return Err(&self.inflight);
@@ -156,7 +157,10 @@ impl<NID: NodeId> ProgressEntry<NID> {
             start = purge_upto_next;
         }
 
-        let end = std::cmp::min(start + max_entries, last_next);
+        let mut end = std::cmp::min(start + max_entries, last_next);
+        if max_bytes > 0 {
+            end = Self::calc_end_within_bytes(log_state, start, end, max_bytes);
+        }
 
         if start == end {
             self.inflight = Inflight::None;
@@ -181,6 +185,21 @@ impl<NID: NodeId> ProgressEntry<NID> {
         (mid, self.searching_end)
     }
 
+    /// Return the end(exclusive) of the leading logs in `[start, end)` whose total size is within
+    /// `max_bytes`, but at least one log is included.
+    ///
+    /// A log whose size is unknown counts as `0`.
+    fn calc_end_within_bytes(log_state: &impl LogStateReader<NID>, start: u64, end: u64, max_bytes: u64) -> u64 {
+        let mut total = 0;
+        for index in start..end {
+            total += log_state.log_size(index).unwrap_or_default();
+            if total > max_bytes {
+                return std::cmp::max(index, start + 1);
+            }
+        }
+        end
+    }
+
     fn calc_mid(matching_next: u64, end: u64) -> u64 {
         debug_assert!(matching_next <= end);
         let d = end - matching_next;
@@ -239,6 +258,7 @@ impl<NID: NodeId> Validate for ProgressEntry<NID> {
 #[cfg(test)]
 mod tests {
     use std::borrow::Borrow;
+    use std::collections::BTreeMap;
 
     use crate::progress::entry::ProgressEntry;
     use crate::progress::inflight::Inflight;
@@ -323,6 +343,7 @@ mod tests {
         snap_last: Option<LogId<u64>>,
         purge_upto: Option<LogId<u64>>,
         purged: Option<LogId<u64>>,
+        sizes: BTreeMap<u64, u64>,
     }
 
     impl LogState {
@@ -334,8 +355,14 @@ mod tests {
                 // We just fake a purged
                 purge_upto: Some(log_id(purge_upto)),
                 purged: Some(log_id(purge_upto - 1)),
+                sizes: BTreeMap::new(),
             }
         }
+
+        fn with_sizes(mut self, sizes: BTreeMap<u64, u64>) -> Self {
+            self.sizes = sizes;
+            self
+        }
     }
 
     impl LogStateReader<u64> for LogState {
@@ -367,6 +394,10 @@ mod tests {
         fn last_purged_log_id(&self) -> Option<&LogId<u64>> {
             self.purged.as_ref()
         }
+
+        fn log_size(&self, index: u64) -> Option<u64> {
+            self.sizes.get(&index).copied()
+        }
     }
 
     #[test]
@@ -375,7 +406,7 @@ mod tests {
         {
             let mut pe = ProgressEntry::empty(20);
             pe.inflight = inflight_logs(10, 11);
-            let res = pe.next_send(&LogState::new(6, 10, 20), 100);
+            let res = pe.next_send(&LogState::new(6, 10, 20), 100, 0);
             assert_eq!(Err(&inflight_logs(10, 11)), res);
         }
 
@@ -390,7 +421,7 @@ mod tests {
             let mut pe = ProgressEntry::empty(4);
             pe.matching = Some(log_id(4));
 
-            let res = pe.next_send(&LogState::new(6, 10, 20), 100);
+            let res = pe.next_send(&LogState::new(6, 10, 20), 100, 0);
             assert_eq!(Ok(&Inflight::snapshot(Some(log_id(10))).with_id(1)), res);
         }
         {
@@ -404,7 +435,7 @@ mod tests {
             let mut pe = ProgressEntry::empty(6);
             pe.matching = Some(log_id(4));
 
-            let res = pe.next_send(&LogState::new(6, 10, 20), 100);
+            let res = pe.next_send(&LogState::new(6, 10, 20), 100, 0);
             assert_eq!(Ok(&Inflight::snapshot(Some(log_id(10))).with_id(1)), res);
         }
 
@@ -419,7 +450,7 @@ mod tests {
             let mut pe = ProgressEntry::empty(7);
             pe.matching = Some(log_id(4));
 
-            let res = pe.next_send(&LogState::new(6, 10, 20), 100);
+            let res = pe.next_send(&LogState::new(6, 10, 20), 100, 0);
             assert_eq!(Ok(&inflight_logs(6, 20).with_id(1)), res);
         }
 
@@ -434,7 +465,7 @@ mod tests {
             let mut pe = ProgressEntry::empty(20);
             pe.matching = Some(log_id(4));
 
-            let res = pe.next_send(&LogState::new(6, 10, 20), 100);
+            let res = pe.next_send(&LogState::new(6, 10, 20), 100, 0);
             assert_eq!(Ok(&inflight_logs(6, 20).with_id(1)), res);
         }
 
@@ -451,7 +482,7 @@ mod tests {
             let mut pe = ProgressEntry::empty(7);
             pe.matching = Some(log_id(6));
 
-            let res = pe.next_send(&LogState::new(6, 10, 20), 100);
+            let res = pe.next_send(&LogState::new(6, 10, 20), 100, 0);
             assert_eq!(Ok(&inflight_logs(6, 20).with_id(1)), res);
         }
 
@@ -466,7 +497,7 @@ mod tests {
             let mut pe = ProgressEntry::empty(8);
             pe.matching = Some(log_id(6));
 
-            let res = pe.next_send(&LogState::new(6, 10, 20), 100);
+            let res = pe.next_send(&LogState::new(6, 10, 20), 100, 0);
             assert_eq!(Ok(&inflight_logs(6, 20).with_id(1)), res);
         }
 
@@ -481,7 +512,7 @@ mod tests {
             let mut pe = ProgressEntry::empty(20);
             pe.matching = Some(log_id(6));
 
-            let res = pe.next_send(&LogState::new(6, 10, 20), 100);
+            let res = pe.next_send(&LogState::new(6, 10, 20), 100, 0);
             assert_eq!(Ok(&inflight_logs(6, 20).with_id(1)), res);
         }
 
@@ -496,7 +527,7 @@ mod tests {
             let mut pe = ProgressEntry::empty(20);
             pe.matching = Some(log_id(7));
 
-            let res = pe.next_send(&LogState::new(6, 10, 20), 100);
+            let res = pe.next_send(&LogState::new(6, 10, 20), 100, 0);
             assert_eq!(Ok(&inflight_logs(7, 20).with_id(1)), res);
         }
 
@@ -511,7 +542,7 @@ mod tests {
             let mut pe = ProgressEntry::empty(8);
             pe.matching = Some(log_id(7));
 
-            let res = pe.next_send(&LogState::new(6, 10, 20), 100);
+            let res = pe.next_send(&LogState::new(6, 10, 20), 100, 0);
             assert_eq!(Ok(&inflight_logs(7, 20).with_id(1)), res);
         }
 
@@ -526,7 +557,7 @@ mod tests {
             let mut pe = ProgressEntry::empty(21);
             pe.matching = Some(log_id(20));
 
-            let res = pe.next_send(&LogState::new(6, 10, 20), 100);
+            let res = pe.next_send(&LogState::new(6, 10, 20), 100, 0);
             assert_eq!(Err(&Inflight::None), res, "nothing to send");
         }
 
@@ -542,9 +573,58 @@ mod tests {
             let mut pe = ProgressEntry::empty(20);
             pe.matching = Some(log_id(7));
 
-            let res = pe.next_send(&LogState::new(6, 10, 20), 5);
+            let res = pe.next_send(&LogState::new(6, 10, 20), 5, 0);
             assert_eq!(Ok(&inflight_logs(7, 12).with_id(1)), res);
         }
         Ok(())
     }
+
+    #[test]
+    fn test_next_send_within_bytes() -> anyhow::Result<()> {
+        // Logs 8..=12 are 10 bytes each, log 13 is 100 bytes, the size of other logs is unknown.
+        let sizes = || {
+            let mut sizes: BTreeMap<u64, u64> = (8..=12).map(|i| (i, 10)).collect();
+            sizes.insert(13, 100);
+            sizes
+        };
+        let log_state = || LogState::new(6, 10, 20).with_sizes(sizes());
+
+        {
+            // Send the leading logs within the limit.
+            let mut pe = ProgressEntry::empty(20);
+            pe.matching = Some(log_id(7));
+
+            let res = pe.next_send(&log_state(), 100, 25);
+            assert_eq!(Ok(&inflight_logs(7, 9).with_id(1)), res);
+        }
+
+        {
+            // A log larger than the limit is sent alone.
+            let mut pe = ProgressEntry::empty(20);
+            pe.matching = Some(log_id(12));
+
+            let res = pe.next_send(&log_state(), 100, 25);
+            assert_eq!(Ok(&inflight_logs(12, 13).with_id(1)), res);
+        }
+
+        {
+            // Logs of unknown size are limited only by the number of entries.
+            let mut pe = ProgressEntry::empty(20);
+            pe.matching = Some(log_id(13));
+
+            let res = pe.next_send(&log_state(), 5, 25);
+            assert_eq!(Ok(&inflight_logs(13, 18).with_id(1)), res);
+        }
+
+        {
+            // No limit
+            let mut pe = ProgressEntry::empty(20);
+            pe.matching = Some(log_id(7));
+
+            let res = pe.next_send(&log_state(), 100, 0);
+            assert_eq!(Ok(&inflight_logs(7, 20).with_id(1)), res);
+        }
+
+        Ok(())
+    }
 }
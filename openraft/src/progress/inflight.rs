@@ -145,14 +145,13 @@ impl<NID: NodeId> Inflight<NID> {
             Inflight::None => {
                 unreachable!("no inflight data")
             }
-            Inflight::Logs {
-                id: _,
-                log_id_range: logs,
-            } => {
+            Inflight::Logs { id, log_id_range: logs } => {
                 *self = {
                     debug_assert!(upto >= logs.prev_log_id);
                     debug_assert!(upto <= logs.last_log_id);
-                    Inflight::logs(upto, logs.last_log_id)
+
+                    // The rest of the logs are still being sent by the same request.
+                    Inflight::logs(upto, logs.last_log_id).with_id(*id)
                 }
             }
             Inflight::Snapshot { id: _, last_log_id } => {
@@ -275,6 +274,13 @@ mod tests {
             f.ack(Some(log_id(10)));
             assert_eq!(Inflight::None, f);
 
+            // A partial ack keeps the id of the request.
+            let mut f = Inflight::logs(Some(log_id(5)), Some(log_id(10))).with_id(3);
+
+            f.ack(Some(log_id(7)));
+            assert_eq!(Inflight::logs(Some(log_id(7)), Some(log_id(10))).with_id(3), f);
+            assert!(f.is_my_id(3));
+
             {
                 let res = std::panic::catch_unwind(|| {
                     let mut f = Inflight::logs(Some(log_id(5)), Some(log_id(10)));
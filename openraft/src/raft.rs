@@ -1,6 +1,7 @@
 //! Public Raft interface and data types.
 
 use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::collections::VecDeque;
 use std::fmt::Debug;
 use std::fmt::Display;
@@ -8,42 +9,56 @@ use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::Duration;
 
+use anyerror::AnyError;
+use maplit::btreeset;
 use tokio::sync::mpsc;
 use tokio::sync::oneshot;
 use tokio::sync::watch;
 use tokio::sync::Mutex;
-use tokio::task::JoinError;
 use tokio::task::JoinHandle;
 use tracing::trace_span;
 use tracing::Instrument;
 use tracing::Level;
+use tracing::Span;
 
 use crate::config::Config;
 use crate::config::RuntimeConfig;
 use crate::core::replication_lag;
+#[cfg(feature = "otel")] use crate::core::EntrySpans;
 use crate::core::RaftCore;
 use crate::core::SnapshotResult;
 use crate::core::SnapshotState;
 use crate::core::Tick;
 use crate::core::TickHandle;
 use crate::display_ext::DisplaySlice;
+#[cfg(feature = "invariant-check")] use crate::engine::invariants::InvariantChecker;
 use crate::engine::Engine;
 use crate::engine::EngineConfig;
 use crate::entry::FromAppData;
 use crate::entry::RaftEntry;
+use crate::error::ChangeMembershipError;
 use crate::error::CheckIsLeaderError;
 use crate::error::ClientWriteError;
 use crate::error::Fatal;
+use crate::error::ForwardToLeader;
 use crate::error::Infallible;
 use crate::error::InitializeError;
 use crate::error::InstallSnapshotError;
+use crate::error::JoinError;
+use crate::error::JoinTimeout;
+use crate::error::NoLeaderReachable;
+use crate::error::RPCError;
 use crate::error::RaftError;
+use crate::error::WaitAppliedError;
+use crate::error::WaitAppliedTimeout;
 use crate::membership::IntoNodes;
 use crate::metrics::RaftMetrics;
 use crate::metrics::Wait;
+use crate::metrics::WaitError;
 use crate::node::Node;
 use crate::replication::ReplicationResult;
 use crate::replication::ReplicationSessionId;
+use crate::replication::Throttle;
 use crate::AppData;
 use crate::AppDataResponse;
 use crate::ChangeMembers;
@@ -51,11 +66,14 @@ use crate::LogId;
 use crate::Membership;
 use crate::MessageSummary;
 use crate::NodeId;
+use crate::RaftNetwork;
 use crate::RaftNetworkFactory;
 use crate::RaftState;
 use crate::RaftStorage;
 use crate::SnapshotMeta;
 use crate::StorageHelper;
+use crate::StoredMembership;
+use crate::TraceContext;
 use crate::Vote;
 
 /// Configuration of types used by the [`Raft`] core engine.
@@ -239,6 +257,7 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> Raft<C, N,
             id,
             config: config.clone(),
             runtime_config: runtime_config.clone(),
+            replication_throttle: Arc::new(Throttle::new()),
             network,
             storage,
 
@@ -248,12 +267,19 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> Raft<C, N,
 
             snapshot_state: SnapshotState::None,
             received_snapshot: BTreeMap::new(),
+            wait_applied: BTreeMap::new(),
 
             tx_api: tx_api.clone(),
             rx_api,
 
             tx_metrics,
 
+            #[cfg(feature = "invariant-check")]
+            invariants: InvariantChecker::new(),
+
+            #[cfg(feature = "otel")]
+            entry_spans: EntrySpans::new(),
+
             span: core_span,
         };
 
@@ -292,6 +318,34 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> Raft<C, N,
         self.inner.runtime_config.enable_elect.store(enabled, Ordering::Relaxed);
     }
 
+    /// Change the maximum rate in bytes per second of replication to all targets in total.
+    ///
+    /// `0` means no limit. See [`Config::replication_max_bytes_per_sec`].
+    pub fn set_replication_max_bytes_per_sec(&self, bytes_per_sec: u64) {
+        self.inner.runtime_config.replication_max_bytes_per_sec.store(bytes_per_sec, Ordering::Relaxed);
+    }
+
+    /// Change the maximum rate in bytes per second of replication to every single target.
+    ///
+    /// `0` means no limit. See [`Config::replication_target_max_bytes_per_sec`].
+    pub fn set_replication_target_max_bytes_per_sec(&self, bytes_per_sec: u64) {
+        self.inner
+            .runtime_config
+            .replication_target_max_bytes_per_sec
+            .store(bytes_per_sec, Ordering::Relaxed);
+    }
+
+    /// Change the maximum rate in bytes per second of replication to every single learner.
+    ///
+    /// `0` means a learner is limited as other targets. See
+    /// [`Config::replication_learner_max_bytes_per_sec`].
+    pub fn set_replication_learner_max_bytes_per_sec(&self, bytes_per_sec: u64) {
+        self.inner
+            .runtime_config
+            .replication_learner_max_bytes_per_sec
+            .store(bytes_per_sec, Ordering::Relaxed);
+    }
+
     /// Trigger election at once and return at once.
     ///
     /// Returns error when RaftCore has Fatal error, e.g. shut down or having storage error.
@@ -333,6 +387,8 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> Raft<C, N,
     ///
     /// These RPCs are sent by the cluster leader to replicate log entries (§5.3), and are also
     /// used as heartbeats (§5.2).
+    ///
+    /// With feature `otel`, the trace of the leader is continued with `rpc.trace_context`.
     #[tracing::instrument(level = "debug", skip(self, rpc))]
     pub async fn append_entries(
         &self,
@@ -340,8 +396,28 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> Raft<C, N,
     ) -> Result<AppendEntriesResponse<C::NodeId>, RaftError<C::NodeId>> {
         tracing::debug!(rpc = display(rpc.summary()), "Raft::append_entries");
 
+        let span = TraceContext::rpc_span("append_entries", rpc.trace_context.as_ref());
+
         let (tx, rx) = oneshot::channel();
-        self.call_core(RaftMsg::AppendEntries { rpc, tx }, rx).await
+        self.call_core(RaftMsg::AppendEntries { rpc, tx }, rx).instrument(span).await
+    }
+
+    /// Submit a heartbeat RPC to this Raft node.
+    ///
+    /// These RPCs are sent by the cluster leader with [`RaftNetwork::send_heartbeat()`], on a lane
+    /// independent of the log replication. A heartbeat is handled as an AppendEntries RPC without
+    /// entries.
+    ///
+    /// [`RaftNetwork::send_heartbeat()`]: `crate::RaftNetwork::send_heartbeat`
+    #[tracing::instrument(level = "debug", skip(self, rpc))]
+    pub async fn heartbeat(
+        &self,
+        rpc: HeartbeatRequest<C::NodeId>,
+    ) -> Result<HeartbeatResponse<C::NodeId>, RaftError<C::NodeId>> {
+        tracing::debug!(rpc = display(rpc.summary()), "Raft::heartbeat");
+
+        let resp = self.append_entries(rpc.into()).await?;
+        Ok(resp.into())
     }
 
     /// Submit a VoteRequest (RequestVote in the spec) RPC to this Raft node.
@@ -352,8 +428,10 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> Raft<C, N,
     pub async fn vote(&self, rpc: VoteRequest<C::NodeId>) -> Result<VoteResponse<C::NodeId>, RaftError<C::NodeId>> {
         tracing::debug!(rpc = display(rpc.summary()), "Raft::vote()");
 
+        let span = TraceContext::rpc_span("vote", rpc.trace_context.as_ref());
+
         let (tx, rx) = oneshot::channel();
-        self.call_core(RaftMsg::RequestVote { rpc, tx }, rx).await
+        self.call_core(RaftMsg::RequestVote { rpc, tx }, rx).instrument(span).await
     }
 
     /// Submit an InstallSnapshot RPC to this Raft node.
@@ -367,8 +445,10 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> Raft<C, N,
     ) -> Result<InstallSnapshotResponse<C::NodeId>, RaftError<C::NodeId, InstallSnapshotError>> {
         tracing::debug!(rpc = display(rpc.summary()), "Raft::install_snapshot()");
 
+        let span = TraceContext::rpc_span("install_snapshot", rpc.trace_context.as_ref());
+
         let (tx, rx) = oneshot::channel();
-        self.call_core(RaftMsg::InstallSnapshot { rpc, tx }, rx).await
+        self.call_core(RaftMsg::InstallSnapshot { rpc, tx }, rx).instrument(span).await
     }
 
     /// Get the ID of the current leader from this Raft node.
@@ -404,19 +484,59 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> Raft<C, N,
     /// should assign unique serial numbers to every command. Then, the state machine should
     /// track the latest serial number processed for each client, along with the associated
     /// response. If it receives a command whose serial number has already been executed, it
-    /// responds immediately without re-executing the request (§8). The
-    /// `RaftStorage::apply_entry_to_state_machine` method is the perfect place to implement
-    /// this.
+    /// responds immediately without re-executing the request (§8).
     ///
-    /// These are application specific requirements, and must be implemented by the application
-    /// which is being built on top of Raft.
+    /// This is not done by `client_write()`: it must be implemented by the application in
+    /// `RaftStorage::apply_to_state_machine()`. The optional helper [`Sessions`](`crate::Sessions`)
+    /// implements it, with [`Sessions::apply()`](`crate::Sessions::apply`).
     #[tracing::instrument(level = "debug", skip(self, app_data))]
     pub async fn client_write(
         &self,
         app_data: C::D,
     ) -> Result<ClientWriteResponse<C>, RaftError<C::NodeId, ClientWriteError<C::NodeId, C::Node>>> {
         let (tx, rx) = oneshot::channel();
-        self.call_core(RaftMsg::ClientWriteRequest { app_data, tx }, rx).await
+        self.call_core(
+            RaftMsg::ClientWriteRequest {
+                app_data,
+                tx,
+                #[cfg(feature = "otel")]
+                span: Span::current(),
+            },
+            rx,
+        )
+        .await
+    }
+
+    /// Wait until the log `log_id` is applied to the state machine of this node.
+    ///
+    /// It is woken up by the state machine applying path, without polling the metrics. A client
+    /// that has written a log through the leader, with the `log_id` in [`ClientWriteResponse`],
+    /// calls this method on a follower or a learner before reading from its state machine, to
+    /// read its own writes.
+    ///
+    /// A log that is purged, or covered by an installed snapshot, is applied. Only the last purged
+    /// log id is kept: a purged `log_id` is reported as applied unless its leader id is greater
+    /// than that of the last purged log.
+    ///
+    /// It returns [`WaitAppliedError::LogIdMismatch`] if a different log is applied at
+    /// `log_id.index`, e.g., `log_id` was not committed and is truncated, and returns
+    /// [`WaitAppliedError::Timeout`] if `log_id` is not applied in `timeout`.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn wait_applied(
+        &self,
+        log_id: LogId<C::NodeId>,
+        timeout: Duration,
+    ) -> Result<(), RaftError<C::NodeId, WaitAppliedError<C::NodeId>>> {
+        let (tx, rx) = oneshot::channel();
+        let fu = self.call_core(RaftMsg::WaitApplied { log_id, tx }, rx);
+
+        match tokio::time::timeout(timeout, fu).await {
+            Ok(res) => res,
+            Err(_elapsed) => Err(RaftError::APIError(WaitAppliedError::Timeout(WaitAppliedTimeout {
+                log_id,
+                timeout,
+            }))),
+        }
     }
 
     /// Initialize a pristine Raft node with the given config.
@@ -494,14 +614,26 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> Raft<C, N,
         }
 
         // Otherwise, blocks until the replication to the new learner becomes up to date.
-
         // The log id of the membership that contains the added learner.
-        let membership_log_id = resp.log_id;
+        let _ = self.wait_learner_up_to_date(id, Some(resp.log_id), None).await;
+
+        Ok(resp)
+    }
 
+    /// Blocks until the replication to a learner becomes up to date, or it is removed, or this
+    /// node is no longer the leader, or `timeout` passes.
+    ///
+    /// `membership_log_id` is the log id of the membership that contains the learner.
+    async fn wait_learner_up_to_date(
+        &self,
+        id: C::NodeId,
+        membership_log_id: Option<LogId<C::NodeId>>,
+        timeout: Option<Duration>,
+    ) -> Result<(), WaitError> {
         let wait_res = self
-            .wait(None)
+            .wait(timeout)
             .metrics(
-                |metrics| match self.check_replication_upto_date(metrics, id, Some(membership_log_id)) {
+                |metrics| match self.check_replication_upto_date(metrics, id, membership_log_id) {
                     Ok(_matching) => true,
                     // keep waiting
                     Err(_) => false,
@@ -511,8 +643,171 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> Raft<C, N,
             .await;
 
         tracing::info!(wait_res = debug(&wait_res), "waiting for replication to new learner");
+        wait_res.map(|_| ())
+    }
 
-        Ok(resp)
+    /// Join a cluster through the `seeds`, as a learner, or as a voter if `voter` is `true`.
+    ///
+    /// `node` is the node info of this node, with which the other nodes connect to it.
+    ///
+    /// It sends a [`JoinRequest`] to the seeds in turn, with [`RaftNetwork::send_join()`], and
+    /// follows the [`ForwardToLeader`] they reply, until the leader handles it with
+    /// [`Raft::handle_join()`]. The leader adds this node as a learner, waits for it to catch up,
+    /// then promotes it to a voter if `voter` is `true`.
+    ///
+    /// Joining again with the same `node` is safe: a node that is already in the cluster is not
+    /// added again. It returns [`JoinError::NodeConflict`] if the id of this node is used by
+    /// another node, and [`JoinError::NoLeaderReachable`] if no seed leads to a leader, e.g.,
+    /// during an election, in which case it can be retried later.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn join(
+        &self,
+        node: C::Node,
+        seeds: BTreeMap<C::NodeId, C::Node>,
+        voter: bool,
+    ) -> Result<JoinResponse<C::NodeId, C::Node>, RaftError<C::NodeId, JoinError<C::NodeId, C::Node>>> {
+        let req = JoinRequest {
+            node_id: self.inner.id,
+            node,
+            voter,
+        };
+
+        // Every seed is tried once, and so is the leader every seed forwards to.
+        let max_attempts = seeds.len() * 2;
+        let mut targets = seeds.into_iter().collect::<VecDeque<_>>();
+        let mut tried = BTreeSet::new();
+        let mut last_error = None;
+
+        for _ in 0..max_attempts {
+            let (target, target_node) = match targets.pop_front() {
+                None => break,
+                Some(x) => x,
+            };
+            tried.insert(target);
+
+            let mut client = self.new_network_client(target, &target_node).await?;
+            let err = match client.send_join(req.clone()).await {
+                Ok(resp) => return Ok(resp),
+                Err(e) => e,
+            };
+
+            tracing::info!(error = display(&err), "join through {} failed", target);
+
+            if let RPCError::RemoteError(remote) = &err {
+                match &remote.source {
+                    RaftError::APIError(JoinError::ForwardToLeader(ForwardToLeader {
+                        leader_id: Some(leader_id),
+                        leader_node: Some(leader_node),
+                    })) => {
+                        targets.push_front((*leader_id, leader_node.clone()));
+                    }
+                    RaftError::APIError(JoinError::ForwardToLeader(_)) => {}
+                    RaftError::APIError(api_err) => return Err(RaftError::APIError(api_err.clone())),
+                    RaftError::Fatal(_) => {}
+                }
+            }
+
+            last_error = Some(AnyError::new(&err));
+        }
+
+        Err(RaftError::APIError(JoinError::NoLeaderReachable(NoLeaderReachable {
+            tried,
+            last_error,
+        })))
+    }
+
+    /// Handle a [`JoinRequest`] sent by a node that joins the cluster with [`Raft::join()`].
+    ///
+    /// The leader adds the joining node as a learner, unless it is already in the membership with
+    /// the same `Node`, and if `rpc.voter` is `true`, promotes it to a voter once it catches up.
+    /// A node that is already a voter, or is being promoted by another join request, is joined.
+    ///
+    /// It returns [`JoinError::Timeout`] if the node does not catch up in
+    /// [`Config::join_timeout`], and a node other than the leader replies with
+    /// [`JoinError::ForwardToLeader`].
+    #[tracing::instrument(level = "debug", skip(self, rpc))]
+    pub async fn handle_join(
+        &self,
+        rpc: JoinRequest<C::NodeId, C::Node>,
+    ) -> Result<JoinResponse<C::NodeId, C::Node>, RaftError<C::NodeId, JoinError<C::NodeId, C::Node>>> {
+        tracing::debug!(rpc = display(rpc.summary()), "Raft::handle_join()");
+
+        let JoinRequest { node_id, node, voter } = rpc;
+
+        let (tx, rx) = oneshot::channel();
+        let written = self.call_core(RaftMsg::Join { id: node_id, node, tx }, rx).await?;
+
+        let mut membership = match written {
+            None => self.metrics().borrow().membership_config.as_ref().clone(),
+            Some(rx) => match rx.await {
+                Ok(res) => {
+                    let resp = res.map_err(|e| RaftError::APIError(JoinError::from(e)))?;
+                    // Safe unwrap(): it is the response of a membership log.
+                    StoredMembership::new(Some(resp.log_id), resp.membership.unwrap())
+                }
+                Err(_) => {
+                    let fatal =
+                        self.get_core_stopped_error("receiving join result from RaftCore", None::<String>).await;
+                    return Err(RaftError::Fatal(fatal));
+                }
+            },
+        };
+
+        if voter && !membership.membership().is_voter(&node_id) {
+            let timeout = self.inner.config.join_timeout();
+
+            let waited = self.wait_learner_up_to_date(node_id, *membership.log_id(), Some(timeout)).await;
+            match waited {
+                Ok(()) => {}
+                Err(WaitError::Timeout(_, _)) => {
+                    return Err(RaftError::APIError(JoinError::Timeout(JoinTimeout {
+                        node_id,
+                        timeout,
+                    })));
+                }
+                Err(WaitError::ShuttingDown) => {
+                    let fatal = self.get_core_stopped_error("waiting for joining node", None::<String>).await;
+                    return Err(RaftError::Fatal(fatal));
+                }
+            }
+
+            let res = self.change_membership(ChangeMembers::AddVoterIds(btreeset! {node_id}), true).await;
+            membership = match res {
+                // Safe unwrap(): it is the response of a membership log.
+                Ok(resp) => StoredMembership::new(Some(resp.log_id), resp.membership.unwrap()),
+                Err(RaftError::APIError(ClientWriteError::ChangeMembershipError(
+                    ChangeMembershipError::InProgress(e),
+                ))) => {
+                    // Another join request of the same node may be promoting it.
+                    let m = self.metrics().borrow().membership_config.as_ref().clone();
+                    if !m.membership().is_voter(&node_id) {
+                        return Err(RaftError::APIError(JoinError::ChangeMembershipError(e.into())));
+                    }
+                    m
+                }
+                Err(RaftError::APIError(e)) => return Err(RaftError::APIError(JoinError::from(e))),
+                Err(RaftError::Fatal(f)) => return Err(RaftError::Fatal(f)),
+            };
+        }
+
+        Ok(JoinResponse { membership })
+    }
+
+    /// Create a network client to the target node with the `RaftNetworkFactory` owned by
+    /// `RaftCore`.
+    async fn new_network_client(&self, target: C::NodeId, node: &C::Node) -> Result<N::Network, Fatal<C::NodeId>> {
+        let (tx, rx) = oneshot::channel();
+        let msg = RaftMsg::NewNetworkClient {
+            target,
+            node: node.clone(),
+            tx,
+        };
+
+        match self.call_core(msg, rx).await {
+            Ok(client) => Ok(client),
+            Err(RaftError::APIError(e)) => match e {},
+            Err(RaftError::Fatal(f)) => Err(f),
+        }
     }
 
     /// Returns Ok() with the latest known matched log id if it should quit waiting: leader change,
@@ -783,7 +1078,7 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> Raft<C, N,
     /// Shutdown this Raft node.
     ///
     /// It sends a shutdown signal and waits until `RaftCore` returns.
-    pub async fn shutdown(&self) -> Result<(), JoinError> {
+    pub async fn shutdown(&self) -> Result<(), tokio::task::JoinError> {
         if let Some(tx) = self.inner.tx_shutdown.lock().await.take() {
             // A failure to send means the RaftCore is already shutdown. Continue to check the task
             // return value.
@@ -814,6 +1109,14 @@ pub(crate) type AppendEntriesTx<NID> = RaftRespTx<AppendEntriesResponse<NID>, In
 pub(crate) type ClientWriteTx<C> =
     RaftRespTx<ClientWriteResponse<C>, ClientWriteError<<C as RaftTypeConfig>::NodeId, <C as RaftTypeConfig>::Node>>;
 
+/// RX for Client Write Response
+pub(crate) type ClientWriteRx<C> = oneshot::Receiver<
+    Result<ClientWriteResponse<C>, ClientWriteError<<C as RaftTypeConfig>::NodeId, <C as RaftTypeConfig>::Node>>,
+>;
+
+/// TX for the result of waiting for a log to be applied
+pub(crate) type WaitAppliedTx<NID> = RaftRespTx<(), WaitAppliedError<NID>>;
+
 /// A message coming from the Raft API.
 pub(crate) enum RaftMsg<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> {
     AppendEntries {
@@ -848,12 +1151,22 @@ pub(crate) enum RaftMsg<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStor
     ClientWriteRequest {
         app_data: C::D,
         tx: ClientWriteTx<C>,
+
+        /// The span of the client, in which the write is handled.
+        #[cfg(feature = "otel")]
+        span: Span,
     },
 
     CheckIsLeaderRequest {
         tx: RaftRespTx<(), CheckIsLeaderError<C::NodeId, C::Node>>,
     },
 
+    /// Wait for a log to be applied to the state machine.
+    WaitApplied {
+        log_id: LogId<C::NodeId>,
+        tx: WaitAppliedTx<C::NodeId>,
+    },
+
     Initialize {
         members: BTreeMap<C::NodeId, C::Node>,
         tx: RaftRespTx<(), InitializeError<C::NodeId, C::Node>>,
@@ -869,6 +1182,23 @@ pub(crate) enum RaftMsg<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStor
         tx: ClientWriteTx<C>,
     },
 
+    /// Add a joining node as a learner.
+    ///
+    /// It replies with `None` if the node is already in the membership, otherwise with the
+    /// receiver of the result of writing the membership log.
+    Join {
+        id: C::NodeId,
+        node: C::Node,
+        tx: RaftRespTx<Option<ClientWriteRx<C>>, JoinError<C::NodeId, C::Node>>,
+    },
+
+    /// Create a network client with the `RaftNetworkFactory`, e.g., to send a join request.
+    NewNetworkClient {
+        target: C::NodeId,
+        node: C::Node,
+        tx: RaftRespTx<N::Network, Infallible>,
+    },
+
     ChangeMembership {
         changes: ChangeMembers<C::NodeId, C::Node>,
 
@@ -912,8 +1242,30 @@ pub(crate) enum RaftMsg<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStor
         session_id: ReplicationSessionId<C::NodeId>,
     },
 
+    /// A replication task `ReplicationCore` has waited for the replication rate limits.
+    UpdateReplicationThrottled {
+        /// The ID of the target node to which the replication is throttled.
+        target: C::NodeId,
+
+        /// The time the replication waited.
+        throttled: Duration,
+
+        /// In which session this message is sent.
+        session_id: ReplicationSessionId<C::NodeId>,
+    },
+
+    /// A target has accepted a heartbeat.
+    /// Sent by a heartbeat task `HeartbeatWorker`.
+    HeartbeatAck {
+        /// The ID of the target node that accepted the heartbeat.
+        target: C::NodeId,
+
+        /// In which session this message is sent.
+        session_id: ReplicationSessionId<C::NodeId>,
+    },
+
     /// ReplicationCore has seen a higher `vote`.
-    /// Sent by a replication task `ReplicationCore`.
+    /// Sent by a replication task `ReplicationCore` or a heartbeat task `HeartbeatWorker`.
     HigherVote {
         /// The ID of the target node from which the new term was observed.
         target: C::NodeId,
@@ -958,12 +1310,21 @@ where
             }
             RaftMsg::ClientWriteRequest { .. } => "ClientWriteRequest".to_string(),
             RaftMsg::CheckIsLeaderRequest { .. } => "CheckIsLeaderRequest".to_string(),
+            RaftMsg::WaitApplied { log_id, .. } => {
+                format!("WaitApplied: {}", log_id)
+            }
             RaftMsg::Initialize { members, .. } => {
                 format!("Initialize: {:?}", members)
             }
             RaftMsg::AddLearner { id, node, .. } => {
                 format!("AddLearner: id: {}, node: {:?}", id, node)
             }
+            RaftMsg::Join { id, node, .. } => {
+                format!("Join: id: {}, node: {:?}", id, node)
+            }
+            RaftMsg::NewNetworkClient { target, node, .. } => {
+                format!("NewNetworkClient: target: {}, node: {:?}", target, node)
+            }
             RaftMsg::ChangeMembership {
                 changes: members,
                 retain,
@@ -989,6 +1350,22 @@ where
                     target, id, result, session_id,
                 )
             }
+            RaftMsg::UpdateReplicationThrottled {
+                ref target,
+                ref throttled,
+                ref session_id,
+            } => {
+                format!(
+                    "UpdateReplicationThrottled: target: {}, throttled: {:?}, session_id: {}",
+                    target, throttled, session_id,
+                )
+            }
+            RaftMsg::HeartbeatAck {
+                ref target,
+                ref session_id,
+            } => {
+                format!("HeartbeatAck: target: {}, session_id: {}", target, session_id)
+            }
             RaftMsg::HigherVote {
                 ref target,
                 higher: ref new_vote,
@@ -1031,6 +1408,10 @@ pub struct AppendEntriesRequest<C: RaftTypeConfig> {
 
     /// The leader's committed log id.
     pub leader_commit: Option<LogId<C::NodeId>>,
+
+    /// The tracing context of the sender, to continue the trace on the receiver.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub trace_context: Option<TraceContext>,
 }
 
 impl<C: RaftTypeConfig> Debug for AppendEntriesRequest<C>
@@ -1042,10 +1423,32 @@ where C::D: Debug
             .field("prev_log_id", &self.prev_log_id)
             .field("entries", &self.entries)
             .field("leader_commit", &self.leader_commit)
+            .field("trace_context", &self.trace_context)
             .finish()
     }
 }
 
+impl<C: RaftTypeConfig> AppendEntriesRequest<C> {
+    /// Build a request without a trace context.
+    ///
+    /// Prefer it to a struct literal, which breaks when a field is added. Assign `trace_context` to
+    /// continue the trace of the sender on the receiver.
+    pub fn new(
+        vote: Vote<C::NodeId>,
+        prev_log_id: Option<LogId<C::NodeId>>,
+        entries: Vec<C::Entry>,
+        leader_commit: Option<LogId<C::NodeId>>,
+    ) -> Self {
+        Self {
+            vote,
+            prev_log_id,
+            entries,
+            leader_commit,
+            trace_context: None,
+        }
+    }
+}
+
 impl<C: RaftTypeConfig> MessageSummary<AppendEntriesRequest<C>> for AppendEntriesRequest<C> {
     fn summary(&self) -> String {
         format!(
@@ -1091,12 +1494,80 @@ impl<NID: NodeId> MessageSummary<AppendEntriesResponse<NID>> for AppendEntriesRe
     }
 }
 
+/// An RPC sent by a cluster leader as a heartbeat (§5.2), independent of the log replication.
+///
+/// It carries only the leader's vote and committed log id. It extends the leader's lease on the
+/// target and lets the target commit up to the leader's committed log id, if the target has it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize), serde(bound = ""))]
+pub struct HeartbeatRequest<NID: NodeId> {
+    pub vote: Vote<NID>,
+
+    /// The leader's committed log id.
+    pub committed: Option<LogId<NID>>,
+}
+
+impl<NID: NodeId> MessageSummary<HeartbeatRequest<NID>> for HeartbeatRequest<NID> {
+    fn summary(&self) -> String {
+        format!("vote={}, committed={}", self.vote, self.committed.summary())
+    }
+}
+
+/// A heartbeat is an AppendEntries RPC without entries, following the committed log id.
+///
+/// If the target does not have the committed log id, it replies with a `Conflict`, but it still
+/// accepts the vote of the leader.
+impl<C: RaftTypeConfig> From<HeartbeatRequest<C::NodeId>> for AppendEntriesRequest<C> {
+    fn from(hb: HeartbeatRequest<C::NodeId>) -> Self {
+        Self {
+            vote: hb.vote,
+            prev_log_id: hb.committed,
+            entries: vec![],
+            leader_commit: hb.committed,
+            trace_context: TraceContext::current(),
+        }
+    }
+}
+
+/// The response to a `HeartbeatRequest`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize), serde(bound = ""))]
+pub enum HeartbeatResponse<NID: NodeId> {
+    /// The target accepts the vote of the leader.
+    Success,
+
+    /// Seen a vote `v` that does not hold `mine_vote >= v`.
+    HigherVote(Vote<NID>),
+}
+
+impl<NID: NodeId> From<AppendEntriesResponse<NID>> for HeartbeatResponse<NID> {
+    fn from(resp: AppendEntriesResponse<NID>) -> Self {
+        match resp {
+            AppendEntriesResponse::Success | AppendEntriesResponse::Conflict => HeartbeatResponse::Success,
+            AppendEntriesResponse::HigherVote(vote) => HeartbeatResponse::HigherVote(vote),
+        }
+    }
+}
+
+impl<NID: NodeId> MessageSummary<HeartbeatResponse<NID>> for HeartbeatResponse<NID> {
+    fn summary(&self) -> String {
+        match self {
+            HeartbeatResponse::Success => "Success".to_string(),
+            HeartbeatResponse::HigherVote(vote) => format!("Higher vote, {}", vote),
+        }
+    }
+}
+
 /// An RPC sent by candidates to gather votes (§5.2).
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize), serde(bound = ""))]
 pub struct VoteRequest<NID: NodeId> {
     pub vote: Vote<NID>,
     pub last_log_id: Option<LogId<NID>>,
+
+    /// The tracing context of the sender, to continue the trace on the receiver.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub trace_context: Option<TraceContext>,
 }
 
 impl<NID: NodeId> MessageSummary<VoteRequest<NID>> for VoteRequest<NID> {
@@ -1106,8 +1577,16 @@ impl<NID: NodeId> MessageSummary<VoteRequest<NID>> for VoteRequest<NID> {
 }
 
 impl<NID: NodeId> VoteRequest<NID> {
+    /// Build a request without a trace context.
+    ///
+    /// Prefer it to a struct literal, which breaks when a field is added. Assign `trace_context` to
+    /// continue the trace of the sender on the receiver.
     pub fn new(vote: Vote<NID>, last_log_id: Option<LogId<NID>>) -> Self {
-        Self { vote, last_log_id }
+        Self {
+            vote,
+            last_log_id,
+            trace_context: None,
+        }
     }
 }
 
@@ -1124,15 +1603,24 @@ pub struct VoteResponse<NID: NodeId> {
 
     /// The last log id stored on the remote voter.
     pub last_log_id: Option<LogId<NID>>,
+
+    /// The log id of the committed membership on the remote voter, if the candidate is not a member
+    /// of it.
+    ///
+    /// A candidate that has not yet seen this membership learns that it is removed from the
+    /// cluster, and stops electing.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub removed_by: Option<LogId<NID>>,
 }
 
 impl<NID: NodeId> MessageSummary<VoteResponse<NID>> for VoteResponse<NID> {
     fn summary(&self) -> String {
         format!(
-            "{{granted:{}, {}, last_log:{:?}}}",
+            "{{granted:{}, {}, last_log:{:?}, removed_by:{}}}",
             self.vote_granted,
             self.vote,
-            self.last_log_id.map(|x| x.to_string())
+            self.last_log_id.map(|x| x.to_string()),
+            self.removed_by.summary()
         )
     }
 }
@@ -1153,6 +1641,33 @@ pub struct InstallSnapshotRequest<C: RaftTypeConfig> {
 
     /// Will be `true` if this is the last chunk in the snapshot.
     pub done: bool,
+
+    /// The tracing context of the sender, to continue the trace on the receiver.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub trace_context: Option<TraceContext>,
+}
+
+impl<C: RaftTypeConfig> InstallSnapshotRequest<C> {
+    /// Build a request without a trace context.
+    ///
+    /// Prefer it to a struct literal, which breaks when a field is added. Assign `trace_context` to
+    /// continue the trace of the sender on the receiver.
+    pub fn new(
+        vote: Vote<C::NodeId>,
+        meta: SnapshotMeta<C::NodeId, C::Node>,
+        offset: u64,
+        data: Vec<u8>,
+        done: bool,
+    ) -> Self {
+        Self {
+            vote,
+            meta,
+            offset,
+            data,
+            done,
+            trace_context: None,
+        }
+    }
 }
 
 impl<C: RaftTypeConfig> MessageSummary<InstallSnapshotRequest<C>> for InstallSnapshotRequest<C> {
@@ -1174,6 +1689,46 @@ impl<C: RaftTypeConfig> MessageSummary<InstallSnapshotRequest<C>> for InstallSna
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize), serde(bound = ""))]
 pub struct InstallSnapshotResponse<NID: NodeId> {
     pub vote: Vote<NID>,
+
+    /// The end of the data of the snapshot in the request that the receiver has written, i.e.,
+    /// the offset from which the leader should send the next chunk.
+    ///
+    /// It lets the leader resume an interrupted snapshot transfer from where the receiver is.
+    /// `None` if the receiver does not report it: the leader just sends the next chunk.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub offset: Option<u64>,
+}
+
+/// An RPC sent by a node to join a cluster, to the leader or to a node that forwards it to the
+/// leader.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize), serde(bound = ""))]
+pub struct JoinRequest<NID: NodeId, N: Node> {
+    /// The id of the joining node.
+    pub node_id: NID,
+
+    /// The node info of the joining node, e.g., its network address.
+    pub node: N,
+
+    /// Promote the node to a voter once it catches up with the leader.
+    pub voter: bool,
+}
+
+impl<NID: NodeId, N: Node> MessageSummary<JoinRequest<NID, N>> for JoinRequest<NID, N> {
+    fn summary(&self) -> String {
+        format!(
+            "node_id: {}, node: {:?}, voter: {}",
+            self.node_id, self.node, self.voter
+        )
+    }
+}
+
+/// The response to a `JoinRequest`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize), serde(bound = ""))]
+pub struct JoinResponse<NID: NodeId, N: Node> {
+    /// The membership config on the leader after the node joins.
+    pub membership: StoredMembership<NID, N>,
 }
 
 /// The response to a client-request.
@@ -65,4 +65,9 @@ pub(crate) trait LogStateReader<NID: NodeId> {
     ///
     /// `last_purged_log_id == last_log_id` means there is no log entry in the storage.
     fn last_purged_log_id(&self) -> Option<&LogId<NID>>;
+
+    /// Return the size of the log at `index`, if it is known.
+    ///
+    /// The size is known if the log is appended after this node starts, and is not yet purged.
+    fn log_size(&self, index: u64) -> Option<u64>;
 }
@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::ops::Deref;
 
@@ -14,6 +15,7 @@ use crate::utime::UTime;
 use crate::validate::Validate;
 use crate::LogId;
 use crate::LogIdOptionExt;
+use crate::MessageSummary;
 use crate::NodeId;
 use crate::ServerState;
 use crate::SnapshotMeta;
@@ -65,6 +67,14 @@ where
     /// The metadata of the last snapshot.
     pub snapshot_meta: SnapshotMeta<NID, N>,
 
+    /// The log id of the committed membership that removes this node from the cluster, if this
+    /// node has learned about it.
+    ///
+    /// A removed node stays in [`ServerState::Removed`] until a newer membership that contains it
+    /// is seen. It is persisted if the store supports
+    /// [`RaftStorage::save_removed_by()`](crate::RaftStorage::save_removed_by).
+    pub(crate) removed_by: Option<LogId<NID>>,
+
     // --
     // -- volatile fields: they are not persisted.
     // --
@@ -76,6 +86,21 @@ where
     /// If a log is in use by a replication task, the purge is postponed and is stored in this
     /// field.
     pub(crate) purge_upto: Option<LogId<NID>>,
+
+    /// The vote with which this node gave up the leadership, because it did not hear from a
+    /// quorum.
+    ///
+    /// A leader steps down to a follower without changing its vote: it is not a leader as long as
+    /// its vote is still this one.
+    ///
+    /// It is not persisted: with check-quorum enabled, a leader also steps down on startup.
+    pub(crate) stepped_down: Option<Vote<NID>>,
+
+    /// The size of every log appended since this node started, keyed by log index.
+    ///
+    /// The size of an entry is computed once when it is appended, and is used to limit the
+    /// replication payloads by bytes.
+    pub(crate) log_sizes: BTreeMap<u64, u64>,
 }
 
 impl<NID, N> LogStateReader<NID> for RaftState<NID, N>
@@ -109,6 +134,10 @@ where
         }
         self.log_ids.first()
     }
+
+    fn log_size(&self, index: u64) -> Option<u64> {
+        self.log_sizes.get(&index).copied()
+    }
 }
 
 impl<NID, N> VoteStateReader<NID> for RaftState<NID, N>
@@ -177,6 +206,19 @@ where
         self.log_ids.extend(new_log_id)
     }
 
+    /// Record the size of every appended entry.
+    pub(crate) fn record_log_sizes<Ent: RaftEntry<NID, N>>(&mut self, entries: &[Ent]) {
+        for ent in entries {
+            self.log_sizes.insert(ent.get_log_id().index, ent.size_hint());
+        }
+    }
+
+    /// Remove logs since index `since`, inclusive, from the log ids and the log sizes.
+    pub(crate) fn truncate_log_ids(&mut self, since: u64) {
+        self.log_ids.truncate(since);
+        let _truncated = self.log_sizes.split_off(&since);
+    }
+
     /// Update field `committed` if the input is greater.
     /// If updated, it returns the previous value in a `Some()`.
     #[tracing::instrument(level = "debug", skip_all)]
@@ -220,6 +262,7 @@ where
     pub(crate) fn purge_log(&mut self, upto: &LogId<NID>) {
         self.purged_next = upto.index + 1;
         self.log_ids.purge(upto);
+        self.log_sizes = self.log_sizes.split_off(&(upto.index + 1));
     }
 
     /// Determine the current server state by state.
@@ -229,8 +272,13 @@ where
             is_member = display(self.is_voter(id)),
             is_leader = display(self.is_leader(id)),
             is_leading = display(self.is_leading(id)),
+            removed_by = display(self.removed_by.summary()),
             "states"
         );
+        if self.removed_by.is_some() {
+            return ServerState::Removed;
+        }
+
         if self.is_voter(id) {
             if self.is_leader(id) {
                 ServerState::Leader
@@ -251,11 +299,16 @@ where
     /// The node is candidate(leadership is not granted by a quorum) or leader(leadership is granted
     /// by a quorum)
     pub(crate) fn is_leading(&self, id: &NID) -> bool {
-        self.vote.leader_id().voted_for().as_ref() == Some(id)
+        self.vote.leader_id().voted_for().as_ref() == Some(id) && !self.is_stepped_down()
     }
 
     pub(crate) fn is_leader(&self, id: &NID) -> bool {
-        self.vote.leader_id().voted_for().as_ref() == Some(id) && self.vote.is_committed()
+        self.vote.leader_id().voted_for().as_ref() == Some(id) && self.vote.is_committed() && !self.is_stepped_down()
+    }
+
+    /// Returns `true` if this node has given up the leadership granted by its current vote.
+    pub(crate) fn is_stepped_down(&self) -> bool {
+        self.stepped_down.as_ref() == Some(self.vote_ref())
     }
 
     pub(crate) fn assign_log_ids<'a, Ent: RaftEntry<NID, N> + 'a>(
@@ -277,7 +330,7 @@ where
     pub(crate) fn forward_to_leader(&self) -> ForwardToLeader<NID, N> {
         let vote = self.vote_ref();
 
-        if vote.is_committed() {
+        if vote.is_committed() && !self.is_stepped_down() {
             // Safe unwrap(): vote that is committed has to already have voted for some node.
             let id = vote.leader_id().voted_for().unwrap();
 
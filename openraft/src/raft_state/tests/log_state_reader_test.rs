@@ -146,3 +146,28 @@ fn test_raft_state_last_purged_log_id() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_raft_state_log_size() -> anyhow::Result<()> {
+    let mut rs = RaftState::<u64, ()> {
+        log_ids: LogIdList::new(vec![log_id(1, 1), log_id(3, 4), log_id(3, 8)]),
+        log_sizes: (2..=8).map(|i| (i, i * 10)).collect(),
+        ..Default::default()
+    };
+
+    assert_eq!(None, rs.log_size(1));
+    assert_eq!(Some(20), rs.log_size(2));
+    assert_eq!(Some(80), rs.log_size(8));
+
+    // The sizes of the truncated logs are removed.
+    rs.truncate_log_ids(7);
+    assert_eq!(Some(60), rs.log_size(6));
+    assert_eq!(None, rs.log_size(7));
+
+    // The sizes of the purged logs are removed.
+    rs.purge_log(&log_id(3, 4));
+    assert_eq!(None, rs.log_size(4));
+    assert_eq!(Some(50), rs.log_size(5));
+
+    Ok(())
+}
@@ -0,0 +1,134 @@
+use std::sync::Arc;
+
+use tokio::sync::mpsc;
+use tokio::sync::watch;
+use tokio::time::timeout;
+use tokio::time::Duration;
+use tracing_futures::Instrument;
+
+use crate::config::Config;
+use crate::raft::HeartbeatRequest;
+use crate::raft::HeartbeatResponse;
+use crate::raft::RaftMsg;
+use crate::replication::ReplicationSessionId;
+use crate::LogId;
+use crate::MessageSummary;
+use crate::RaftNetwork;
+use crate::RaftNetworkFactory;
+use crate::RaftStorage;
+use crate::RaftTypeConfig;
+
+/// A task sending heartbeats to a target, independent of the log and snapshot replication.
+///
+/// It has its own connection to the target, so that a heartbeat is sent on schedule even when the
+/// replication stream is busy sending a large batch of logs or a snapshot.
+///
+/// RaftCore triggers a heartbeat by sending the committed log id through a `watch` channel. If the
+/// target is slow, the heartbeats triggered in the meantime are merged into one with the latest
+/// committed log id. The task quits when the sender is dropped.
+pub(crate) struct HeartbeatWorker<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> {
+    /// The ID of the target Raft node to which heartbeats are sent.
+    target: C::NodeId,
+
+    /// Identifies which session this heartbeat task belongs to.
+    session_id: ReplicationSessionId<C::NodeId>,
+
+    /// A channel for sending events to the RaftCore.
+    #[allow(clippy::type_complexity)]
+    tx_raft_core: mpsc::UnboundedSender<RaftMsg<C, N, S>>,
+
+    /// A channel for receiving the committed log id to send with the next heartbeat.
+    rx_heartbeat: watch::Receiver<Option<LogId<C::NodeId>>>,
+
+    /// The `RaftNetwork` interface, a connection other than the one for replication.
+    network: N::Network,
+
+    /// The Raft's runtime config.
+    config: Arc<Config>,
+}
+
+impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> HeartbeatWorker<C, N, S> {
+    /// Spawn a new heartbeat task for the target node.
+    ///
+    /// It returns the sender to trigger a heartbeat with the committed log id.
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn spawn(
+        target: C::NodeId,
+        session_id: ReplicationSessionId<C::NodeId>,
+        config: Arc<Config>,
+        committed: Option<LogId<C::NodeId>>,
+        network: N::Network,
+        tx_raft_core: mpsc::UnboundedSender<RaftMsg<C, N, S>>,
+        span: tracing::Span,
+    ) -> watch::Sender<Option<LogId<C::NodeId>>> {
+        let (tx_heartbeat, rx_heartbeat) = watch::channel(committed);
+
+        let this = Self {
+            target,
+            session_id,
+            tx_raft_core,
+            rx_heartbeat,
+            network,
+            config,
+        };
+
+        let _ = tokio::spawn(this.main().instrument(span));
+
+        tx_heartbeat
+    }
+
+    #[tracing::instrument(level="debug", skip(self), fields(session=%self.session_id, target=display(self.target), cluster=%self.config.cluster_name))]
+    async fn main(mut self) {
+        loop {
+            if self.rx_heartbeat.changed().await.is_err() {
+                tracing::debug!("heartbeat sender is dropped, quit");
+                return;
+            }
+
+            let committed = *self.rx_heartbeat.borrow();
+
+            let req = HeartbeatRequest {
+                vote: self.session_id.vote,
+                committed,
+            };
+
+            tracing::debug!(req = display(req.summary()), "send heartbeat");
+
+            let ttl = Duration::from_millis(self.config.heartbeat_interval);
+            let res = timeout(ttl, self.network.send_heartbeat(req)).await;
+
+            let resp = match res {
+                Ok(Ok(resp)) => resp,
+                Ok(Err(err)) => {
+                    tracing::warn!(error = display(&err), "heartbeat to target={} failed", self.target);
+                    continue;
+                }
+                Err(_elapsed) => {
+                    tracing::warn!("heartbeat to target={} timeout after {:?}", self.target, ttl);
+                    continue;
+                }
+            };
+
+            tracing::debug!(resp = display(resp.summary()), "heartbeat resp");
+
+            match resp {
+                HeartbeatResponse::Success => {
+                    let _ = self.tx_raft_core.send(RaftMsg::HeartbeatAck {
+                        target: self.target,
+                        session_id: self.session_id,
+                    });
+                }
+                HeartbeatResponse::HigherVote(vote) => {
+                    tracing::debug!(%vote, "heartbeat seen a higher vote");
+
+                    let _ = self.tx_raft_core.send(RaftMsg::HigherVote {
+                        target: self.target,
+                        higher: vote,
+                        vote: self.session_id.vote,
+                    });
+                    return;
+                }
+            }
+        }
+    }
+}
@@ -1,19 +1,27 @@
 //! Replication stream.
 
+mod heartbeat;
 mod replication_session_id;
+mod throttle;
+
+#[cfg(test)] mod throttle_test;
 
 use std::fmt::Debug;
 use std::fmt::Formatter;
 use std::io::SeekFrom;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 use futures::future::FutureExt;
+pub(crate) use heartbeat::HeartbeatWorker;
 pub(crate) use replication_session_id::ReplicationSessionId;
+pub(crate) use throttle::Throttle;
 use tokio::io::AsyncRead;
 use tokio::io::AsyncReadExt;
 use tokio::io::AsyncSeek;
 use tokio::io::AsyncSeekExt;
 use tokio::sync::mpsc;
+use tokio::sync::watch;
 use tokio::task::JoinHandle;
 use tokio::time::sleep;
 use tokio::time::timeout;
@@ -21,8 +29,11 @@ use tokio::time::Duration;
 use tracing_futures::Instrument;
 
 use crate::config::Config;
+use crate::config::RuntimeConfig;
 use crate::error::HigherVote;
+use crate::error::InstallSnapshotError;
 use crate::error::RPCError;
+use crate::error::RaftError;
 use crate::error::ReplicationError;
 use crate::error::Timeout;
 use crate::log_id::LogIdOptionExt;
@@ -44,7 +55,9 @@ use crate::RaftNetwork;
 use crate::RaftNetworkFactory;
 use crate::RaftStorage;
 use crate::RaftTypeConfig;
+use crate::SnapshotId;
 use crate::ToStorageResult;
+use crate::TraceContext;
 
 /// The handle to a spawned replication stream.
 pub(crate) struct ReplicationHandle<NID, N, S>
@@ -58,6 +71,9 @@ where
 
     /// The channel used for communicating with the replication task.
     pub(crate) tx_repl: mpsc::UnboundedSender<Replicate<NID, N, S>>,
+
+    /// The channel to trigger a heartbeat with the committed log id, sent by the heartbeat task.
+    pub(crate) tx_heartbeat: watch::Sender<Option<LogId<NID>>>,
 }
 
 /// A task responsible for sending replication events to a target follower in the Raft cluster.
@@ -88,6 +104,23 @@ pub(crate) struct ReplicationCore<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S
     /// The Raft's runtime config.
     config: Arc<Config>,
 
+    /// The config that can be changed at runtime, e.g., the replication rate limits.
+    runtime_config: Arc<RuntimeConfig>,
+
+    /// Limits the total rate of all replication streams of this leader.
+    global_throttle: Arc<Throttle>,
+
+    /// Limits the rate of this replication stream.
+    target_throttle: Throttle,
+
+    /// Whether the target is a learner in the effective membership of this replication session,
+    /// which may be given a lower rate than a voter.
+    ///
+    /// It does not change in a session: when the effective membership changes, e.g., a learner is
+    /// promoted to a voter, the session id changes and the leader respawns every replication task
+    /// with the role in the new membership.
+    is_learner: bool,
+
     /// The log id of the highest log entry which is known to be committed in the cluster.
     committed: Option<LogId<C::NodeId>>,
 
@@ -107,9 +140,13 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> Replication
         target: C::NodeId,
         session_id: ReplicationSessionId<C::NodeId>,
         config: Arc<Config>,
+        runtime_config: Arc<RuntimeConfig>,
+        global_throttle: Arc<Throttle>,
+        is_learner: bool,
         committed: Option<LogId<C::NodeId>>,
         matching: Option<LogId<C::NodeId>>,
         network: N::Network,
+        heartbeat_network: N::Network,
         log_reader: S::LogReader,
         tx_raft_core: mpsc::UnboundedSender<RaftMsg<C, N, S>>,
         span: tracing::Span,
@@ -125,12 +162,26 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> Replication
         // other component to ReplicationStream
         let (tx_repl, rx_repl) = mpsc::unbounded_channel();
 
+        let tx_heartbeat = HeartbeatWorker::<C, N, S>::spawn(
+            target,
+            session_id,
+            config.clone(),
+            committed,
+            heartbeat_network,
+            tx_raft_core.clone(),
+            span.clone(),
+        );
+
         let this = Self {
             target,
             session_id,
             network,
             log_reader,
             config,
+            runtime_config,
+            global_throttle,
+            target_throttle: Throttle::new(),
+            is_learner,
             committed,
             matching,
             tx_raft_core,
@@ -140,7 +191,11 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> Replication
 
         let join_handle = tokio::spawn(this.main().instrument(span));
 
-        ReplicationHandle { join_handle, tx_repl }
+        ReplicationHandle {
+            join_handle,
+            tx_repl,
+            tx_heartbeat,
+        }
     }
 
     #[tracing::instrument(level="debug", skip(self), fields(session=%self.session_id, target=display(self.target), cluster=%self.config.cluster_name))]
@@ -152,10 +207,22 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> Replication
 
             let res = match action {
                 None => Ok(()),
-                Some(Data { id, payload: r_action }) => {
+                Some(Data {
+                    id,
+                    payload: r_action,
+                    #[cfg(feature = "otel")]
+                    span,
+                }) => {
                     repl_id = id;
                     match r_action {
-                        Payload::Logs(log_id_range) => self.send_log_entries(id, log_id_range).await,
+                        Payload::Logs(log_id_range, bytes) => {
+                            let fu = self.send_log_entries(id, log_id_range, bytes);
+
+                            #[cfg(feature = "otel")]
+                            let fu = fu.instrument(span);
+
+                            fu.await
+                        }
                         Payload::Snapshot(snapshot) => self.stream_snapshot(id, snapshot).await,
                     }
                 }
@@ -216,6 +283,9 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> Replication
 
     /// Send an AppendEntries RPC to the target.
     ///
+    /// `bytes` is the known total size of the logs in `req`, which is throttled by the replication
+    /// rate limits.
+    ///
     /// This request will timeout if no response is received within the
     /// configured heartbeat interval.
     #[tracing::instrument(level = "debug", skip_all)]
@@ -223,6 +293,7 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> Replication
         &mut self,
         id: u64,
         req: LogIdRange<C::NodeId>,
+        bytes: u64,
     ) -> Result<(), ReplicationError<C::NodeId, C::Node>> {
         tracing::debug!(id = display(id), send_req = display(&req), "send_log_entries",);
 
@@ -244,12 +315,53 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> Replication
             logs
         };
 
+        match self.send_append_entries(req.prev_log_id, logs, bytes).await? {
+            AppendEntriesResponse::Success => {
+                self.update_matching(id, req.last_log_id);
+                Ok(())
+            }
+            AppendEntriesResponse::HigherVote(vote) => {
+                debug_assert!(
+                    vote > self.session_id.vote,
+                    "higher vote({}) should be greater than leader's vote({})",
+                    vote,
+                    self.session_id.vote,
+                );
+                tracing::debug!(%vote, "append entries failed. converting to follower");
+
+                Err(ReplicationError::HigherVote(HigherVote {
+                    higher: vote,
+                    mine: self.session_id.vote,
+                }))
+            }
+            AppendEntriesResponse::Conflict => {
+                let conflict = req.prev_log_id;
+                debug_assert!(conflict.is_some(), "prev_log_id=None never conflict");
+
+                let conflict = conflict.unwrap();
+                self.update_conflicting(id, conflict);
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Send one AppendEntries RPC with `entries` following `prev_log_id` to the target.
+    async fn send_append_entries(
+        &mut self,
+        prev_log_id: Option<LogId<C::NodeId>>,
+        entries: Vec<C::Entry>,
+        bytes: u64,
+    ) -> Result<AppendEntriesResponse<C::NodeId>, ReplicationError<C::NodeId, C::Node>> {
+        self.throttle(bytes).await;
+
         // Build the heartbeat frame to be sent to the follower.
         let payload = AppendEntriesRequest {
             vote: self.session_id.vote,
-            prev_log_id: req.prev_log_id,
+            prev_log_id,
             leader_commit: self.committed,
-            entries: logs,
+            entries,
+            trace_context: TraceContext::current(),
         };
 
         // Send the payload.
@@ -277,35 +389,7 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> Replication
 
         tracing::debug!("append_entries resp: {:?}", append_resp);
 
-        match append_resp {
-            AppendEntriesResponse::Success => {
-                self.update_matching(id, req.last_log_id);
-                Ok(())
-            }
-            AppendEntriesResponse::HigherVote(vote) => {
-                debug_assert!(
-                    vote > self.session_id.vote,
-                    "higher vote({}) should be greater than leader's vote({})",
-                    vote,
-                    self.session_id.vote,
-                );
-                tracing::debug!(%vote, "append entries failed. converting to follower");
-
-                Err(ReplicationError::HigherVote(HigherVote {
-                    higher: vote,
-                    mine: self.session_id.vote,
-                }))
-            }
-            AppendEntriesResponse::Conflict => {
-                let conflict = req.prev_log_id;
-                debug_assert!(conflict.is_some(), "prev_log_id=None never conflict");
-
-                let conflict = conflict.unwrap();
-                self.update_conflicting(id, conflict);
-
-                Ok(())
-            }
-        }
+        Ok(append_resp)
     }
 
     fn update_conflicting(&mut self, id: u64, conflict: LogId<C::NodeId>) {
@@ -338,16 +422,56 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> Replication
 
         debug_assert!(self.matching <= new_matching);
 
-        if self.matching < new_matching {
-            self.matching = new_matching;
+        // With check-quorum, report even if matching does not change, e.g., a response to a
+        // heartbeat: it tells RaftCore that the target still acknowledges this leader.
+        if self.matching == new_matching && !self.config.enable_check_quorum {
+            return;
+        }
 
-            let _ = self.tx_raft_core.send(RaftMsg::UpdateReplicationProgress {
-                session_id: self.session_id,
-                id,
-                target: self.target,
-                result: Ok(ReplicationResult::Matching(new_matching)),
-            });
+        self.matching = new_matching;
+
+        let _ = self.tx_raft_core.send(RaftMsg::UpdateReplicationProgress {
+            session_id: self.session_id,
+            id,
+            target: self.target,
+            result: Ok(ReplicationResult::Matching(new_matching)),
+        });
+    }
+
+    /// Wait until `bytes` can be sent without exceeding the replication rate limits.
+    ///
+    /// The time waited is reported to RaftCore, to be added to the replication metrics.
+    async fn throttle(&mut self, bytes: u64) {
+        if bytes == 0 {
+            return;
+        }
+
+        let rc = &self.runtime_config;
+
+        let global_rate = rc.replication_max_bytes_per_sec.load(Ordering::Relaxed);
+        let learner_rate = rc.replication_learner_max_bytes_per_sec.load(Ordering::Relaxed);
+        let target_rate = if self.is_learner && learner_rate > 0 {
+            learner_rate
+        } else {
+            rc.replication_target_max_bytes_per_sec.load(Ordering::Relaxed)
+        };
+
+        // Wait for this stream first, so that it does not hold a slot of the global limit while
+        // waiting.
+        let mut throttled = self.target_throttle.acquire(bytes, target_rate).await;
+        throttled += self.global_throttle.acquire(bytes, global_rate).await;
+
+        if throttled.is_zero() {
+            return;
         }
+
+        tracing::debug!(bytes, throttled = debug(throttled), "replication is throttled");
+
+        let _ = self.tx_raft_core.send(RaftMsg::UpdateReplicationThrottled {
+            target: self.target,
+            throttled,
+            session_id: self.session_id,
+        });
     }
 
     /// Receive and process events from RaftCore, until `next_action` is filled.
@@ -371,10 +495,15 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> Replication
             self.next_action = Some(Data {
                 // id==0 will be ignored by RaftCore.
                 id: 0,
-                payload: Payload::Logs(LogIdRange {
-                    prev_log_id: *m,
-                    last_log_id: *m,
-                }),
+                payload: Payload::Logs(
+                    LogIdRange {
+                        prev_log_id: *m,
+                        last_log_id: *m,
+                    },
+                    0,
+                ),
+                #[cfg(feature = "otel")]
+                span: tracing::Span::current(),
             });
         }
 
@@ -420,13 +549,6 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> Replication
 
                 self.committed = c;
             }
-            Replicate::Heartbeat => {
-                // Nothing to do. Heartbeat message is just for waking up replication to send
-                // something: When all messages are drained,
-                // - if self.next_action is None, it resend an empty AppendEntries request as
-                //   heartbeat.
-                //-  If self.next_action is not None, next_action will serve as a heartbeat.
-            }
             Replicate::Data(d) => {
                 debug_assert!(self.next_action.is_none(),);
                 self.next_action = Some(d);
@@ -448,6 +570,10 @@ where
 {
     id: u64,
     payload: Payload<NID, N, SD>,
+
+    /// The span to send the data in, so that the RPCs are part of the trace of the log entries.
+    #[cfg(feature = "otel")]
+    span: tracing::Span,
 }
 
 impl<NID, N, S> MessageSummary<Data<NID, N, S>> for Data<NID, N, S>
@@ -458,7 +584,7 @@ where
 {
     fn summary(&self) -> String {
         match &self.payload {
-            Payload::Logs(log_id_range) => {
+            Payload::Logs(log_id_range, _) => {
                 format!("Logs{{id={}, {}}}", self.id, log_id_range)
             }
             Payload::Snapshot(snapshot) => {
@@ -474,10 +600,12 @@ where
     N: Node,
     SD: AsyncRead + AsyncSeek + Send + Unpin + 'static,
 {
-    fn new_logs(id: u64, log_id_range: LogIdRange<NID>) -> Self {
+    fn new_logs(id: u64, log_id_range: LogIdRange<NID>, bytes: u64) -> Self {
         Self {
             id,
-            payload: Payload::Logs(log_id_range),
+            payload: Payload::Logs(log_id_range, bytes),
+            #[cfg(feature = "otel")]
+            span: tracing::Span::current(),
         }
     }
 
@@ -485,6 +613,8 @@ where
         Self {
             id,
             payload: Payload::Snapshot(snapshot),
+            #[cfg(feature = "otel")]
+            span: tracing::Span::current(),
         }
     }
 }
@@ -498,7 +628,8 @@ where
     N: Node,
     SD: AsyncRead + AsyncSeek + Send + Unpin + 'static,
 {
-    Logs(LogIdRange<NID>),
+    /// A range of logs and the known total size of them in bytes.
+    Logs(LogIdRange<NID>, u64),
     Snapshot(Snapshot<NID, N, SD>),
 }
 
@@ -510,8 +641,8 @@ where
 {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Logs(log_id_range) => {
-                write!(f, "Logs({})", log_id_range)
+            Self::Logs(log_id_range, bytes) => {
+                write!(f, "Logs({}, {} bytes)", log_id_range, bytes)
             }
             Self::Snapshot(snapshot) => {
                 write!(f, "Snapshot({:?})", snapshot.meta)
@@ -537,9 +668,6 @@ where
     /// Inform replication stream to forward the committed log id to followers/learners.
     Committed(Option<LogId<NID>>),
 
-    /// Send an empty AppendEntries RPC as heartbeat.
-    Heartbeat,
-
     /// Send a chunk of data, e.g., logs or snapshot.
     Data(Data<NID, N, SD>),
 }
@@ -550,13 +678,22 @@ where
     N: Node,
     SD: AsyncRead + AsyncSeek + Send + Unpin + 'static,
 {
-    pub(crate) fn logs(id: u64, log_id_range: LogIdRange<NID>) -> Self {
-        Self::Data(Data::new_logs(id, log_id_range))
+    pub(crate) fn logs(id: u64, log_id_range: LogIdRange<NID>, bytes: u64) -> Self {
+        Self::Data(Data::new_logs(id, log_id_range, bytes))
     }
 
     pub(crate) fn snapshot(id: u64, snapshot: Snapshot<NID, N, SD>) -> Self {
         Self::Data(Data::new_snapshot(id, snapshot))
     }
+
+    /// Send the data in `span`.
+    #[cfg(feature = "otel")]
+    pub(crate) fn in_span(mut self, span: tracing::Span) -> Self {
+        if let Self::Data(d) = &mut self {
+            d.span = span;
+        }
+        self
+    }
 }
 
 impl<NID, N, S> MessageSummary<Replicate<NID, N, S>> for Replicate<NID, N, S>
@@ -570,7 +707,6 @@ where
             Replicate::Committed(c) => {
                 format!("Replicate::Committed: {:?}", c)
             }
-            Replicate::Heartbeat => "Replicate::Heartbeat".to_string(),
             Replicate::Data(d) => {
                 format!("Replicate::Data({})", d.summary())
             }
@@ -605,6 +741,7 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> Replication
                 offset,
                 data: Vec::from(&buf[..n_read]),
                 done,
+                trace_context: TraceContext::current(),
             };
             buf.clear();
 
@@ -623,6 +760,8 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> Replication
                 self.config.send_snapshot_timeout()
             };
 
+            self.throttle(req.data.len() as u64).await;
+
             let res = timeout(snap_timeout, self.network.send_install_snapshot(req)).await;
 
             let res = match res {
@@ -631,6 +770,19 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> Replication
                     Err(err) => {
                         tracing::warn!(error=%err, "error sending InstallSnapshot RPC to target");
 
+                        // The target has received a different number of bytes, e.g., it restarted
+                        // and resumed from the data it persisted. Continue from there, after the
+                        // same sleep as other errors, so that a target that keeps rejecting does
+                        // not cause a busy loop.
+                        if let Some(expect_offset) = Self::expected_snapshot_offset(&err, &snapshot.meta.snapshot_id) {
+                            tracing::info!(
+                                offset,
+                                expect_offset,
+                                "target expects another snapshot offset, resume from it"
+                            );
+                            offset = expect_offset.min(end);
+                        }
+
                         // Sleep a short time otherwise in test environment it is a dead-loop that
                         // never yields. Because network implementation does
                         // not yield.
@@ -670,10 +822,31 @@ impl<C: RaftTypeConfig, N: RaftNetworkFactory<C>, S: RaftStorage<C>> Replication
             }
 
             // Everything is good, so update offset for sending the next chunk.
-            offset += n_read as u64;
+            // Resume from the offset the target reports, if it does.
+            offset = res.offset.unwrap_or(offset + n_read as u64).min(end);
 
             // Check raft channel to ensure we are staying up-to-date, then loop.
             self.try_drain_events().await?;
         }
     }
+
+    /// Returns the offset the target expects for snapshot `snapshot_id`, if `err` is a
+    /// `SnapshotMismatch` error about it.
+    fn expected_snapshot_offset(
+        err: &RPCError<C::NodeId, C::Node, RaftError<C::NodeId, InstallSnapshotError>>,
+        snapshot_id: &SnapshotId,
+    ) -> Option<u64> {
+        let remote = if let RPCError::RemoteError(remote) = err {
+            remote
+        } else {
+            return None;
+        };
+
+        match remote.source.api_error()? {
+            InstallSnapshotError::SnapshotMismatch(mismatch) if &mismatch.expect.id == snapshot_id => {
+                Some(mismatch.expect.offset)
+            }
+            _ => None,
+        }
+    }
 }
@@ -0,0 +1,64 @@
+use std::sync::Mutex;
+
+use tokio::time::sleep_until;
+use tokio::time::Duration;
+use tokio::time::Instant;
+
+/// Limits the rate in bytes per second at which data is sent.
+///
+/// Every send reserves a time slot as long as it takes to send its bytes at the rate, following the
+/// slot reserved by the previous send. A send waits until its slot starts.
+/// The rate is passed in with every send, so that it can be changed at any time.
+///
+/// A `Throttle` can be shared by several senders, to limit the total rate of them.
+#[derive(Debug, Default)]
+pub(crate) struct Throttle {
+    /// The time when the last reserved slot ends.
+    next_at: Mutex<Option<Instant>>,
+}
+
+impl Throttle {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reserve a slot to send `bytes` at `bytes_per_sec` and wait until it starts.
+    ///
+    /// `bytes_per_sec` of `0` means no limit. It returns the time it waited.
+    pub(crate) async fn acquire(&self, bytes: u64, bytes_per_sec: u64) -> Duration {
+        let start = match self.reserve(Instant::now(), bytes, bytes_per_sec) {
+            Some(start) => start,
+            None => return Duration::ZERO,
+        };
+
+        let now = Instant::now();
+        if start <= now {
+            return Duration::ZERO;
+        }
+
+        sleep_until(start).await;
+        start - now
+    }
+
+    /// Reserve a slot to send `bytes` at `bytes_per_sec`, and return the time the slot starts.
+    ///
+    /// It returns `None` if there is no limit.
+    pub(crate) fn reserve(&self, now: Instant, bytes: u64, bytes_per_sec: u64) -> Option<Instant> {
+        if bytes_per_sec == 0 {
+            return None;
+        }
+
+        let cost = Duration::from_secs_f64(bytes as f64 / bytes_per_sec as f64);
+
+        let mut next_at = self.next_at.lock().unwrap();
+
+        // An idle sender does not save up the unused time for a burst.
+        let start = match *next_at {
+            Some(t) if t > now => t,
+            _ => now,
+        };
+        *next_at = Some(start + cost);
+
+        Some(start)
+    }
+}
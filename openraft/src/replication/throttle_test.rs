@@ -0,0 +1,58 @@
+use tokio::time::Duration;
+use tokio::time::Instant;
+
+use crate::replication::throttle::Throttle;
+
+#[test]
+fn test_throttle_reserve() -> anyhow::Result<()> {
+    let t = Throttle::new();
+    let now = Instant::now();
+    let ms = Duration::from_millis;
+
+    tracing::info!("--- no limit");
+    {
+        assert_eq!(None, t.reserve(now, 1_000, 0));
+    }
+
+    tracing::info!("--- a send waits for the slots reserved by previous sends");
+    {
+        assert_eq!(Some(now), t.reserve(now, 1_000, 10_000));
+        assert_eq!(Some(now + ms(100)), t.reserve(now, 2_000, 10_000));
+        assert_eq!(Some(now + ms(300)), t.reserve(now + ms(50), 1_000, 10_000));
+    }
+
+    tracing::info!("--- a changed rate applies to the next slot");
+    {
+        assert_eq!(Some(now + ms(400)), t.reserve(now + ms(50), 1_000, 1_000));
+        assert_eq!(Some(now + ms(1_400)), t.reserve(now + ms(50), 1_000, 1_000));
+    }
+
+    tracing::info!("--- idle time is not saved up");
+    {
+        let later = now + ms(5_000);
+        assert_eq!(Some(later), t.reserve(later, 1_000, 10_000));
+        assert_eq!(Some(later + ms(100)), t.reserve(later, 1_000, 10_000));
+    }
+
+    Ok(())
+}
+
+#[async_entry::test(worker_threads = 3)]
+async fn test_throttle_acquire() -> anyhow::Result<()> {
+    let t = Throttle::new();
+
+    let waited = t.acquire(1_000, 0).await;
+    assert_eq!(Duration::ZERO, waited);
+
+    let waited = t.acquire(1_000, 10_000).await;
+    assert_eq!(Duration::ZERO, waited);
+
+    let now = Instant::now();
+    let waited = t.acquire(1_000, 10_000).await;
+    let elapsed = now.elapsed();
+
+    assert!(waited > Duration::from_millis(50), "waited: {:?}", waited);
+    assert!(elapsed >= waited, "elapsed: {:?}, waited: {:?}", elapsed, waited);
+
+    Ok(())
+}
@@ -0,0 +1,146 @@
+//! A helper for applications to apply every client write at most once.
+//!
+//! Openraft itself does not deduplicate writes: it replicates and applies every entry it is given.
+//! The helper is used by a state machine, in `RaftStorage::apply_to_state_machine()`.
+
+use std::collections::BTreeMap;
+
+/// The result of applying a request of a client session with [`Sessions::apply()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionResponse<R> {
+    /// The request is applied to the state machine, with this response.
+    Applied(R),
+
+    /// The request is the last applied one of the session, e.g., resent by a client that did not
+    /// receive the response. It is not applied again, and this is the cached response.
+    Duplicate(R),
+}
+
+/// The last applied request of a client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct Session<R> {
+    /// The serial of the last applied request.
+    pub serial: u64,
+
+    /// The response of the last applied request.
+    pub response: R,
+
+    /// The index of the last log entry of this client, applied or not.
+    pub last_index: u64,
+}
+
+/// The sessions of the clients that write to a state machine, to deduplicate retried writes
+/// (§6.3 of the Raft dissertation).
+///
+/// This is an application side helper, not part of the write path of openraft:
+/// [`Raft::client_write()`](`crate::Raft::client_write`) does not know about client ids or
+/// serials, and an entry is applied as is unless the state machine uses `Sessions`.
+///
+/// A client assigns a serial number to every write and resends a write with the same serial until
+/// it receives the response. A state machine keeps a `Sessions` as part of its applied state, and
+/// applies every normal log entry through [`Sessions::apply()`]: a write with the serial of the
+/// last applied one of its session returns the cached response without calling the state machine
+/// again.
+///
+/// Only the last applied write of a session is remembered: a write with any other serial is a new
+/// one and is applied. Serials do not have to increase, e.g., a client may start over from `0`
+/// after it restarts, but a client must not resend a write once it has sent a newer one.
+///
+/// `Sessions` must be updated, persisted and included in snapshots along with the rest of the
+/// state machine, so that every replica deduplicates the same writes. It is serializable with
+/// feature `serde`.
+///
+/// A session expires when no entry of its client is applied in the last `max_age` log entries.
+/// A write resent after its session expires is applied again: `max_age` should cover the longest
+/// time a client retries.
+///
+/// `K` is the type of client id and `R` is the response of the state machine, usually `C::R`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize, serde::Serialize),
+    serde(bound(
+        serialize = "K: serde::Serialize, R: serde::Serialize",
+        deserialize = "K: Ord + serde::Deserialize<'de>, R: serde::Deserialize<'de>"
+    ))
+)]
+pub struct Sessions<K, R> {
+    max_age: u64,
+
+    /// The log index at which a session may expire: no session expires before it.
+    next_expire: u64,
+
+    sessions: BTreeMap<K, Session<R>>,
+}
+
+impl<K, R> Sessions<K, R>
+where
+    K: Ord + Clone,
+    R: Clone,
+{
+    /// Create an empty set of sessions, which expire after `max_age` log entries.
+    pub fn new(max_age: u64) -> Self {
+        Self {
+            max_age,
+            next_expire: max_age,
+            sessions: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the session of `client` if it is not expired.
+    pub fn get(&self, client: &K) -> Option<&Session<R>> {
+        self.sessions.get(client)
+    }
+
+    /// Returns the number of sessions.
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Returns `true` if there is no session.
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+
+    /// Apply the request with `serial` of `client` in the log entry at `index`.
+    ///
+    /// `f` applies the request to the state machine. It is called unless the request is the last
+    /// applied one of the session, i.e., `serial` is the serial of the last applied request.
+    ///
+    /// Sessions that are not used in the last `max_age` entries before `index` are removed.
+    pub fn apply<F>(&mut self, index: u64, client: &K, serial: u64, f: F) -> SessionResponse<R>
+    where F: FnOnce() -> R {
+        self.expire(index);
+
+        match self.sessions.get_mut(client) {
+            Some(s) if serial == s.serial => {
+                s.last_index = index;
+                SessionResponse::Duplicate(s.response.clone())
+            }
+            _ => {
+                let response = f();
+                self.sessions.insert(client.clone(), Session {
+                    serial,
+                    response: response.clone(),
+                    last_index: index,
+                });
+                SessionResponse::Applied(response)
+            }
+        }
+    }
+
+    /// Remove the sessions that are not used in the last `max_age` entries before `index`.
+    pub fn expire(&mut self, index: u64) {
+        if index < self.next_expire {
+            return;
+        }
+
+        let max_age = self.max_age;
+        self.sessions.retain(|_, s| s.last_index.saturating_add(max_age) > index);
+
+        // The last index of a session only increases: the oldest one expires first.
+        let oldest = self.sessions.values().map(|s| s.last_index).min().unwrap_or(index);
+        self.next_expire = oldest.saturating_add(max_age);
+    }
+}
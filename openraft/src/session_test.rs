@@ -0,0 +1,83 @@
+use crate::SessionResponse;
+use crate::Sessions;
+
+#[test]
+fn test_sessions_apply() -> anyhow::Result<()> {
+    let mut sessions = Sessions::<String, u64>::new(100);
+    let c = "c".to_string();
+    let mut applied = 0;
+
+    let mut apply = |sessions: &mut Sessions<String, u64>, index, serial| {
+        sessions.apply(index, &c, serial, || {
+            applied += 1;
+            serial * 10
+        })
+    };
+
+    assert_eq!(SessionResponse::Applied(10), apply(&mut sessions, 1, 1));
+    assert_eq!(SessionResponse::Applied(20), apply(&mut sessions, 2, 2));
+
+    // Resent by the client: the cached response is returned.
+    assert_eq!(SessionResponse::Duplicate(20), apply(&mut sessions, 3, 2));
+
+    // Serials do not have to be consecutive.
+    assert_eq!(SessionResponse::Applied(50), apply(&mut sessions, 4, 5));
+
+    // Only the last applied request is remembered: another serial is a new request.
+    assert_eq!(SessionResponse::Applied(10), apply(&mut sessions, 5, 1));
+    assert_eq!(SessionResponse::Duplicate(10), apply(&mut sessions, 6, 1));
+
+    assert_eq!(4, applied);
+
+    let s = sessions.get(&c).unwrap();
+    assert_eq!(1, s.serial);
+    assert_eq!(10, s.response);
+    assert_eq!(6, s.last_index);
+
+    Ok(())
+}
+
+#[test]
+fn test_sessions_expire() -> anyhow::Result<()> {
+    let mut sessions = Sessions::<u64, u64>::new(10);
+
+    sessions.apply(1, &1, 1, || 1);
+    sessions.apply(5, &2, 1, || 2);
+    assert_eq!(2, sessions.len());
+
+    // A duplicate keeps the session alive.
+    assert_eq!(SessionResponse::Duplicate(1), sessions.apply(8, &1, 1, || 0));
+
+    sessions.expire(14);
+    assert_eq!(2, sessions.len());
+
+    sessions.expire(15);
+    assert_eq!(1, sessions.len());
+    assert!(sessions.get(&2).is_none());
+
+    // The session of client 2 expired: the request is applied again.
+    assert_eq!(SessionResponse::Applied(3), sessions.apply(16, &2, 1, || 3));
+
+    sessions.expire(18);
+    assert_eq!(1, sessions.len());
+    assert!(sessions.get(&1).is_none());
+
+    sessions.expire(100);
+    assert!(sessions.is_empty());
+
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_sessions_serde() -> anyhow::Result<()> {
+    let mut sessions = Sessions::<String, Option<String>>::new(10);
+    sessions.apply(1, &"a".to_string(), 3, || Some("x".to_string()));
+    sessions.apply(2, &"b".to_string(), 4, || None);
+
+    let s = serde_json::to_string(&sessions)?;
+    let got: Sessions<String, Option<String>> = serde_json::from_str(&s)?;
+    assert_eq!(sessions, got);
+
+    Ok(())
+}
@@ -10,7 +10,9 @@ use crate::engine::LogIdList;
 use crate::entry::RaftPayload;
 use crate::log_id::RaftLogId;
 use crate::utime::UTime;
+use crate::DefensiveError;
 use crate::EffectiveMembership;
+use crate::ErrorSubject;
 use crate::LogId;
 use crate::LogIdOptionExt;
 use crate::MembershipState;
@@ -19,6 +21,7 @@ use crate::RaftStorage;
 use crate::RaftTypeConfig;
 use crate::StorageError;
 use crate::StoredMembership;
+use crate::Violation;
 
 /// StorageHelper provides additional methods to access a [`RaftStorage`] implementation.
 pub struct StorageHelper<'a, C, Sto>
@@ -58,7 +61,7 @@ where
         let mut last_purged_log_id = st.last_purged_log_id;
         let mut last_log_id = st.last_log_id;
         let (last_applied, _) = self.sto.last_applied_state().await?;
-        let mem_state = self.get_membership().await?;
+        let mut mem_state = self.get_membership().await?;
 
         // Clean up dirty state: snapshot is installed but logs are not cleaned.
         if last_log_id < last_applied {
@@ -67,14 +70,22 @@ where
             last_purged_log_id = last_applied;
         }
 
+        // The saved committed log id may be greater than last_applied, if the logs are not all
+        // applied before shutdown. They will be applied at startup.
+        let committed = self.sto.read_committed().await?;
+        let committed = std::cmp::max(last_applied, std::cmp::min(committed, last_log_id));
+        mem_state.commit(&committed);
+
         let log_ids = LogIdList::load_log_ids(last_purged_log_id, last_log_id, self).await?;
 
         let snapshot_meta = self.sto.get_current_snapshot().await?.map(|x| x.meta).unwrap_or_default();
 
+        let removed_by = self.sto.read_removed_by().await?;
+
         let now = Instant::now();
 
         Ok(RaftState {
-            committed: last_applied,
+            committed,
             // The initial value for `vote` is the minimal possible value.
             // See: [Conditions for initialization](https://datafuselabs.github.io/openraft/cluster-formation.html#conditions-for-initialization)
             vote: UTime::new(now, vote.unwrap_or_default()),
@@ -82,10 +93,13 @@ where
             log_ids,
             membership_state: mem_state,
             snapshot_meta,
+            removed_by,
 
             // -- volatile fields: they are not persisted.
             server_state: Default::default(),
             purge_upto: last_purged_log_id,
+            stepped_down: None,
+            log_sizes: Default::default(),
         })
     }
 
@@ -151,12 +165,71 @@ where
     /// Get the last 2 membership configs found in the log.
     ///
     /// This method returns at most membership logs with greatest log index which is
-    /// `>=since_index`. If no such membership log is found, it returns `None`, e.g., when logs
-    /// are cleaned after being applied.
+    /// `>=since_index`. If no such membership log is found, it returns an empty `Vec`, e.g., when
+    /// logs are cleaned after being applied.
+    ///
+    /// If the store records membership log ids, the membership logs are read directly by their ids.
+    /// Otherwise the log is scanned backward.
     #[tracing::instrument(level = "trace", skip(self))]
     pub async fn last_membership_in_log(
         &mut self,
         since_index: u64,
+    ) -> Result<Vec<StoredMembership<C::NodeId, C::Node>>, StorageError<C::NodeId>> {
+        match self.sto.read_membership_log_ids().await? {
+            Some(log_ids) => self.load_membership_in_log(since_index, &log_ids).await,
+            None => self.scan_membership_in_log(since_index).await,
+        }
+    }
+
+    /// Read the last 2 membership logs that are not purged and are `>=since_index`, by their log
+    /// ids.
+    async fn load_membership_in_log(
+        &mut self,
+        since_index: u64,
+        log_ids: &[LogId<C::NodeId>],
+    ) -> Result<Vec<StoredMembership<C::NodeId, C::Node>>, StorageError<C::NodeId>> {
+        let st = self.sto.get_log_state().await?;
+
+        let end = st.last_log_id.next_index();
+        let start = std::cmp::max(st.last_purged_log_id.next_index(), since_index);
+
+        let mut res = vec![];
+
+        for log_id in log_ids.iter().rev().filter(|x| x.index >= start && x.index < end).take(2) {
+            let ent = self.try_get_log_entry(log_id.index).await?;
+
+            let mem = match &ent {
+                Some(ent) if ent.get_log_id() == log_id => ent.get_membership(),
+                _ => None,
+            };
+
+            let mem = match mem {
+                Some(x) => x.clone(),
+                None => {
+                    return Err(
+                        DefensiveError::new(ErrorSubject::Log(*log_id), Violation::MembershipLogNotFound {
+                            log_id: *log_id,
+                        })
+                        .into(),
+                    );
+                }
+            };
+
+            res.insert(0, StoredMembership::new(Some(*log_id), mem));
+        }
+
+        Ok(res)
+    }
+
+    /// Get the last 2 membership configs found in the log, by scanning the log backward.
+    ///
+    /// This method returns at most membership logs with greatest log index which is
+    /// `>=since_index`. If no such membership log is found, it returns an empty `Vec`, e.g., when
+    /// logs are cleaned after being applied.
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub async fn scan_membership_in_log(
+        &mut self,
+        since_index: u64,
     ) -> Result<Vec<StoredMembership<C::NodeId, C::Node>>, StorageError<C::NodeId>> {
         let st = self.sto.get_log_state().await?;
 
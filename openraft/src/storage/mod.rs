@@ -200,8 +200,71 @@ where C: RaftTypeConfig
 
     async fn read_vote(&mut self) -> Result<Option<Vote<C::NodeId>>, StorageError<C::NodeId>>;
 
+    // --- Committed
+
+    /// Saves the last committed log id.
+    ///
+    /// It is optional to persist the committed log id: by default it does nothing, and after a
+    /// restart only the logs in the state machine are known to be committed, until a leader tells
+    /// otherwise.
+    ///
+    /// If it is persisted, a restarted node applies the logs that are committed but not yet
+    /// applied at once, without waiting for a leader.
+    /// The committed log id does not have to be persisted before returning, because a committed log
+    /// id that is lost or stale only delays applying, but it must not be persisted before the logs
+    /// it refers to are.
+    async fn save_committed(&mut self, _committed: Option<LogId<C::NodeId>>) -> Result<(), StorageError<C::NodeId>> {
+        Ok(())
+    }
+
+    /// Returns the last committed log id saved by [`Self::save_committed`].
+    ///
+    /// By default it returns `None`.
+    async fn read_committed(&mut self) -> Result<Option<LogId<C::NodeId>>, StorageError<C::NodeId>> {
+        Ok(None)
+    }
+
+    // --- Removed
+
+    /// Saves the log id of the committed membership that removes this node from the cluster, or
+    /// `None` when the node is added back by a newer membership.
+    ///
+    /// It is optional to persist it: by default it does nothing, and a restarted node that learned
+    /// it is removed only from the vote responses of its peers, i.e., the removing membership is
+    /// not in its log, campaigns again until a peer tells it again.
+    ///
+    /// If it is persisted, a restarted node stays in [`ServerState::Removed`] and does not elect.
+    ///
+    /// [`ServerState::Removed`]: crate::ServerState::Removed
+    async fn save_removed_by(&mut self, _removed_by: Option<LogId<C::NodeId>>) -> Result<(), StorageError<C::NodeId>> {
+        Ok(())
+    }
+
+    /// Returns the log id saved by [`Self::save_removed_by`].
+    ///
+    /// By default it returns `None`.
+    async fn read_removed_by(&mut self) -> Result<Option<LogId<C::NodeId>>, StorageError<C::NodeId>> {
+        Ok(None)
+    }
+
     // --- Log
 
+    /// Returns the log ids of the membership log entries in the log, in ascending order.
+    ///
+    /// It is optional: it lets raft find the last membership configs at startup without scanning
+    /// the log backward. A store supporting it records the log id of every membership entry
+    /// written by `append_to_log()` and removes the ones deleted by `delete_conflict_logs_since()`
+    /// or `purge_logs_upto()`.
+    ///
+    /// Only the last two are used. Since there is at most one uncommitted membership entry, which
+    /// may be deleted by `delete_conflict_logs_since()`, a store only has to keep the last three,
+    /// and should remove the older ones so that the recorded list does not grow without bound.
+    ///
+    /// By default it returns `None`, and the log is scanned.
+    async fn read_membership_log_ids(&mut self) -> Result<Option<Vec<LogId<C::NodeId>>>, StorageError<C::NodeId>> {
+        Ok(None)
+    }
+
     /// Get the log reader.
     ///
     /// The method is intentionally async to give the implementation a chance to use asynchronous
@@ -311,6 +374,44 @@ where C: RaftTypeConfig
     /// for details on log compaction / snapshotting.
     async fn begin_receiving_snapshot(&mut self) -> Result<Box<Self::SnapshotData>, StorageError<C::NodeId>>;
 
+    /// Returns a writable handle to continue receiving the snapshot `meta`, and the number of bytes
+    /// of it that have been received.
+    ///
+    /// It is optional: it lets a restarted node resume receiving a large snapshot instead of
+    /// receiving it from the beginning. A store supporting it persists the data written to the
+    /// handles it returns along with the snapshot id, and the length of the data made durable by
+    /// `save_received_snapshot()`. If it has data of `meta.snapshot_id`, it returns a handle with
+    /// the write position at the end of the durable data, and the length of it; otherwise it
+    /// discards the data of any other snapshot and returns a blank handle, like
+    /// `begin_receiving_snapshot()`, and `0`.
+    ///
+    /// By default it returns a handle built by `begin_receiving_snapshot()` and `0`.
+    async fn resume_receiving_snapshot(
+        &mut self,
+        meta: &SnapshotMeta<C::NodeId, C::Node>,
+    ) -> Result<(Box<Self::SnapshotData>, u64), StorageError<C::NodeId>> {
+        let _ = meta;
+        let snapshot = self.begin_receiving_snapshot().await?;
+        Ok((snapshot, 0))
+    }
+
+    /// Make the first `received` bytes written to `snapshot`, a handle returned by
+    /// `resume_receiving_snapshot(meta)`, durable, so that receiving can be resumed from there
+    /// after a restart.
+    ///
+    /// It is called after every chunk of the snapshot is written, except the last one.
+    ///
+    /// It is optional and does nothing by default.
+    async fn save_received_snapshot(
+        &mut self,
+        meta: &SnapshotMeta<C::NodeId, C::Node>,
+        snapshot: &mut Self::SnapshotData,
+        received: u64,
+    ) -> Result<(), StorageError<C::NodeId>> {
+        let _ = (meta, snapshot, received);
+        Ok(())
+    }
+
     /// Install a snapshot which has finished streaming from the leader.
     ///
     /// All other snapshots should be deleted at this point.
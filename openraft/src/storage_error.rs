@@ -162,6 +162,15 @@ pub enum Violation<NID: NodeId> {
         last_applied: Option<LogId<NID>>,
         purge_upto: LogId<NID>,
     },
+
+    #[error("recorded membership log is not found in logs: {log_id}")]
+    MembershipLogNotFound { log_id: LogId<NID> },
+
+    #[error("recorded membership log ids {recorded:?} do not match membership logs {in_log:?}")]
+    MembershipLogIdsMismatch {
+        recorded: Vec<LogId<NID>>,
+        in_log: Vec<LogId<NID>>,
+    },
 }
 
 /// A storage error could be either a defensive check error or an error occurred when doing the
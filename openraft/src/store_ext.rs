@@ -6,8 +6,10 @@ use std::sync::atomic::AtomicBool;
 use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::sync::Mutex;
 use std::time::Duration;
 
+use anyerror::AnyError;
 use tokio::time::sleep;
 
 use crate::async_trait::async_trait;
@@ -18,12 +20,15 @@ use crate::storage::RaftLogReader;
 use crate::storage::RaftSnapshotBuilder;
 use crate::storage::Snapshot;
 use crate::DefensiveCheck;
+use crate::ErrorSubject;
+use crate::ErrorVerb;
 use crate::LogId;
 use crate::RaftStorage;
 use crate::RaftStorageDebug;
 use crate::RaftTypeConfig;
 use crate::SnapshotMeta;
 use crate::StorageError;
+use crate::StorageIOError;
 use crate::StoredMembership;
 use crate::Vote;
 use crate::Wrapper;
@@ -35,10 +40,54 @@ pub(crate) struct Config {
     /// For debug only.
     delay_log_read: Arc<AtomicU64>,
 
+    /// Storage faults to inject.
+    ///
+    /// For testing only.
+    faults: Arc<Mutex<Faults>>,
+
     #[allow(dead_code)]
     id: u64,
 }
 
+/// Storage faults injected by [`StoreExt`].
+#[derive(Debug, Default)]
+pub(crate) struct Faults {
+    /// Time to delay before every write.
+    write_latency: Option<Duration>,
+
+    /// Fail the n-th `append_to_log()` call from now on, 1-based.
+    fail_append: Option<u64>,
+
+    /// Number of entries to persist by the failing `append_to_log()` before returning the error.
+    torn_append: usize,
+
+    /// Fail the n-th `apply_to_state_machine()` call from now on, 1-based.
+    fail_apply: Option<u64>,
+
+    /// Fail the n-th `install_snapshot()` call from now on, 1-based.
+    fail_install_snapshot: Option<u64>,
+
+    /// The store is crashed: every following operation fails and in-flight writes are discarded.
+    crashed: bool,
+}
+
+impl Faults {
+    /// Count down a scheduled failure, returns `true` if the current call should fail.
+    fn count_down(nth: &mut Option<u64>) -> bool {
+        match nth {
+            Some(1) => {
+                *nth = None;
+                true
+            }
+            Some(n) => {
+                *n -= 1;
+                false
+            }
+            None => false,
+        }
+    }
+}
+
 impl Config {
     pub(crate) fn new() -> Self {
         static CONFIG_ID: AtomicU64 = AtomicU64::new(1);
@@ -47,6 +96,7 @@ impl Config {
 
         Self {
             delay_log_read: Arc::new(AtomicU64::new(0)),
+            faults: Arc::new(Mutex::new(Faults::default())),
             id,
         }
     }
@@ -59,6 +109,38 @@ impl Config {
         }
         Some(Duration::from_millis(d))
     }
+
+    fn faults(&self) -> std::sync::MutexGuard<'_, Faults> {
+        self.faults.lock().unwrap()
+    }
+
+    /// Returns an error of `verb` on `subject` if the store is crashed.
+    pub(crate) fn check_crashed<NID: crate::NodeId>(
+        &self,
+        subject: ErrorSubject<NID>,
+        verb: ErrorVerb,
+    ) -> Result<(), StorageError<NID>> {
+        if self.faults().crashed {
+            return Err(StorageIOError::new(subject, verb, AnyError::error("injected fault: store crashed")).into());
+        }
+        Ok(())
+    }
+
+    /// Delay a write by the configured latency, and fail it if the store crashed in the meantime.
+    pub(crate) async fn before_write<NID: crate::NodeId>(
+        &self,
+        subject: ErrorSubject<NID>,
+        verb: ErrorVerb,
+    ) -> Result<(), StorageError<NID>> {
+        self.check_crashed(subject.clone(), verb.clone())?;
+
+        let latency = self.faults().write_latency;
+        if let Some(d) = latency {
+            sleep(d).await;
+        }
+
+        self.check_crashed(subject, verb)
+    }
 }
 
 /// Extended store backed by another impl.
@@ -111,6 +193,66 @@ impl<C: RaftTypeConfig, T: RaftStorage<C>> StoreExt<C, T> {
         let delay = self.config.delay_log_read.load(Ordering::Relaxed);
         tracing::info!("Set log reading delay to {delay}");
     }
+
+    /// Delay every write operation by `ms` milliseconds. 0 means no delay.
+    ///
+    /// A write that is delayed is not yet flushed: it is discarded if the store crashes before the
+    /// delay expires.
+    pub fn set_write_latency(&self, ms: u64) {
+        let d = if ms == 0 { None } else { Some(Duration::from_millis(ms)) };
+        self.config.faults().write_latency = d;
+        tracing::info!("Set write latency to {ms} ms");
+    }
+
+    /// Return a `StorageError` on the `nth` call to `append_to_log()` from now on, 1-based.
+    pub fn fail_append_to_log_at(&self, nth: u64) {
+        self.fail_append_to_log_torn_at(nth, 0)
+    }
+
+    /// Return a `StorageError` on the `nth` call to `append_to_log()` from now on, 1-based, after
+    /// persisting only the first `persisted` entries, i.e., a torn write.
+    pub fn fail_append_to_log_torn_at(&self, nth: u64, persisted: usize) {
+        assert!(nth > 0, "nth is 1-based");
+        let mut faults = self.config.faults();
+        faults.fail_append = Some(nth);
+        faults.torn_append = persisted;
+    }
+
+    /// Return a `StorageError` on the `nth` call to `apply_to_state_machine()` from now on,
+    /// 1-based.
+    pub fn fail_apply_to_state_machine_at(&self, nth: u64) {
+        assert!(nth > 0, "nth is 1-based");
+        self.config.faults().fail_apply = Some(nth);
+    }
+
+    /// Return a `StorageError` on the `nth` call to `install_snapshot()` from now on, 1-based.
+    pub fn fail_install_snapshot_at(&self, nth: u64) {
+        assert!(nth > 0, "nth is 1-based");
+        self.config.faults().fail_install_snapshot = Some(nth);
+    }
+
+    /// Simulate a crash of the store.
+    ///
+    /// Every following operation on this store and its clones returns a `StorageError`, and the
+    /// writes that are not yet flushed, i.e., still delayed by the write latency, are discarded.
+    /// Use [`StoreExt::restart`] to get a store with the state that survived the crash.
+    pub fn crash(&self) {
+        tracing::info!("Crash store");
+        self.config.faults().crashed = true;
+    }
+
+    pub fn is_crashed(&self) -> bool {
+        self.config.faults().crashed
+    }
+
+    /// Simulate restarting a crashed store: returns a new `StoreExt` without any injected fault,
+    /// backed by the same underlying store.
+    pub fn restart(&self) -> Self
+    where T: Clone {
+        let sto = StoreExt::new(self.inner.clone());
+        sto.set_defensive(self.is_defensive());
+        sto
+    }
 }
 
 impl<C: RaftTypeConfig, T: RaftStorage<C>> Wrapper<C, T> for StoreExt<C, T>
@@ -170,30 +312,70 @@ where
     #[tracing::instrument(level = "trace", skip(self))]
     async fn save_vote(&mut self, vote: &Vote<C::NodeId>) -> Result<(), StorageError<C::NodeId>> {
         self.defensive_incremental_vote(vote).await?;
+        self.config.before_write(ErrorSubject::Vote, ErrorVerb::Write).await?;
         self.inner().save_vote(vote).await
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
     async fn read_vote(&mut self) -> Result<Option<Vote<C::NodeId>>, StorageError<C::NodeId>> {
+        self.config.check_crashed(ErrorSubject::Vote, ErrorVerb::Read)?;
         self.inner().read_vote().await
     }
 
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn read_membership_log_ids(&mut self) -> Result<Option<Vec<LogId<C::NodeId>>>, StorageError<C::NodeId>> {
+        self.config.check_crashed(ErrorSubject::Logs, ErrorVerb::Read)?;
+
+        let log_ids = self.inner().read_membership_log_ids().await?;
+        if let Some(ids) = &log_ids {
+            self.defensive_membership_log_ids(ids).await?;
+        }
+        Ok(log_ids)
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn save_committed(&mut self, committed: Option<LogId<C::NodeId>>) -> Result<(), StorageError<C::NodeId>> {
+        self.config.before_write(ErrorSubject::Store, ErrorVerb::Write).await?;
+        self.inner().save_committed(committed).await
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn read_committed(&mut self) -> Result<Option<LogId<C::NodeId>>, StorageError<C::NodeId>> {
+        self.config.check_crashed(ErrorSubject::Store, ErrorVerb::Read)?;
+        self.inner().read_committed().await
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn save_removed_by(&mut self, removed_by: Option<LogId<C::NodeId>>) -> Result<(), StorageError<C::NodeId>> {
+        self.config.before_write(ErrorSubject::Store, ErrorVerb::Write).await?;
+        self.inner().save_removed_by(removed_by).await
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn read_removed_by(&mut self) -> Result<Option<LogId<C::NodeId>>, StorageError<C::NodeId>> {
+        self.config.check_crashed(ErrorSubject::Store, ErrorVerb::Read)?;
+        self.inner().read_removed_by().await
+    }
+
     #[tracing::instrument(level = "trace", skip(self))]
     async fn last_applied_state(
         &mut self,
     ) -> Result<(Option<LogId<C::NodeId>>, StoredMembership<C::NodeId, C::Node>), StorageError<C::NodeId>> {
+        self.config.check_crashed(ErrorSubject::StateMachine, ErrorVerb::Read)?;
         self.inner().last_applied_state().await
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
     async fn delete_conflict_logs_since(&mut self, log_id: LogId<C::NodeId>) -> Result<(), StorageError<C::NodeId>> {
         self.defensive_delete_conflict_gt_last_applied(log_id).await?;
+        self.config.before_write(ErrorSubject::Log(log_id), ErrorVerb::Delete).await?;
         self.inner().delete_conflict_logs_since(log_id).await
     }
 
     #[tracing::instrument(level = "trace", skip_all)]
     async fn purge_logs_upto(&mut self, log_id: LogId<C::NodeId>) -> Result<(), StorageError<C::NodeId>> {
         self.defensive_purge_applied_le_last_applied(log_id).await?;
+        self.config.before_write(ErrorSubject::Log(log_id), ErrorVerb::Delete).await?;
         self.inner().purge_logs_upto(log_id).await
     }
 
@@ -204,6 +386,27 @@ where
         self.defensive_append_log_index_is_last_plus_one(entries).await?;
         self.defensive_append_log_id_gt_last(entries).await?;
 
+        self.config.before_write(ErrorSubject::Logs, ErrorVerb::Write).await?;
+
+        let torn = {
+            let mut faults = self.config.faults();
+            if Faults::count_down(&mut faults.fail_append) {
+                Some(faults.torn_append)
+            } else {
+                None
+            }
+        };
+
+        if let Some(persisted) = torn {
+            let persisted = std::cmp::min(persisted, entries.len());
+            tracing::info!("injected fault: append_to_log fails after persisting {} entries", persisted);
+
+            if persisted > 0 {
+                self.inner().append_to_log(&entries[..persisted]).await?;
+            }
+            return Err(StorageIOError::write_logs(AnyError::error("injected fault: append_to_log")).into());
+        }
+
         self.inner().append_to_log(entries).await
     }
 
@@ -213,20 +416,62 @@ where
         self.defensive_apply_index_is_last_applied_plus_one(entries).await?;
         self.defensive_apply_log_id_gt_last(entries).await?;
 
+        self.config.before_write(ErrorSubject::StateMachine, ErrorVerb::Write).await?;
+
+        let fail = Faults::count_down(&mut self.config.faults().fail_apply);
+        if fail {
+            tracing::info!("injected fault: apply_to_state_machine fails");
+            return Err(
+                StorageIOError::write_state_machine(AnyError::error("injected fault: apply_to_state_machine")).into(),
+            );
+        }
+
         self.inner().apply_to_state_machine(entries).await
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
     async fn begin_receiving_snapshot(&mut self) -> Result<Box<Self::SnapshotData>, StorageError<C::NodeId>> {
+        self.config.check_crashed(ErrorSubject::Store, ErrorVerb::Write)?;
         self.inner().begin_receiving_snapshot().await
     }
 
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn resume_receiving_snapshot(
+        &mut self,
+        meta: &SnapshotMeta<C::NodeId, C::Node>,
+    ) -> Result<(Box<Self::SnapshotData>, u64), StorageError<C::NodeId>> {
+        self.config.check_crashed(ErrorSubject::Snapshot(meta.signature()), ErrorVerb::Read)?;
+        self.inner().resume_receiving_snapshot(meta).await
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, snapshot))]
+    async fn save_received_snapshot(
+        &mut self,
+        meta: &SnapshotMeta<C::NodeId, C::Node>,
+        snapshot: &mut Self::SnapshotData,
+        received: u64,
+    ) -> Result<(), StorageError<C::NodeId>> {
+        self.config.before_write(ErrorSubject::Snapshot(meta.signature()), ErrorVerb::Write).await?;
+        self.inner().save_received_snapshot(meta, snapshot, received).await
+    }
+
     #[tracing::instrument(level = "trace", skip(self, snapshot))]
     async fn install_snapshot(
         &mut self,
         meta: &SnapshotMeta<C::NodeId, C::Node>,
         snapshot: Box<Self::SnapshotData>,
     ) -> Result<(), StorageError<C::NodeId>> {
+        self.config.before_write(ErrorSubject::Snapshot(meta.signature()), ErrorVerb::Write).await?;
+
+        let fail = Faults::count_down(&mut self.config.faults().fail_install_snapshot);
+        if fail {
+            tracing::info!("injected fault: install_snapshot fails");
+            return Err(
+                StorageIOError::write_snapshot(meta.signature(), AnyError::error("injected fault: install_snapshot"))
+                    .into(),
+            );
+        }
+
         self.inner().install_snapshot(meta, snapshot).await
     }
 
@@ -234,6 +479,7 @@ where
     async fn get_current_snapshot(
         &mut self,
     ) -> Result<Option<Snapshot<C::NodeId, C::Node, Self::SnapshotData>>, StorageError<C::NodeId>> {
+        self.config.check_crashed(ErrorSubject::Store, ErrorVerb::Read)?;
         self.inner().get_current_snapshot().await
     }
 
@@ -263,11 +509,13 @@ impl<C: RaftTypeConfig, T: RaftStorage<C>> RaftLogReader<C> for StoreExt<C, T> {
             sleep(d).await;
         }
 
+        self.config.check_crashed(ErrorSubject::Logs, ErrorVerb::Read)?;
         self.defensive_nonempty_range(range.clone())?;
         self.inner().try_get_log_entries(range).await
     }
 
     async fn get_log_state(&mut self) -> Result<LogState<C>, StorageError<C::NodeId>> {
+        self.config.check_crashed(ErrorSubject::Logs, ErrorVerb::Read)?;
         self.defensive_no_dirty_log().await?;
         self.inner().get_log_state().await
     }
@@ -310,6 +558,7 @@ impl<C: RaftTypeConfig, T: RaftStorage<C>> RaftLogReader<C> for LogReaderExt<C,
             sleep(d).await;
         }
 
+        self.config.check_crashed(ErrorSubject::Logs, ErrorVerb::Read)?;
         self.defensive_nonempty_range(range.clone())?;
         self.inner.try_get_log_entries(range).await
     }
@@ -318,6 +567,7 @@ impl<C: RaftTypeConfig, T: RaftStorage<C>> RaftLogReader<C> for LogReaderExt<C,
         // TODO self.defensive_no_dirty_log().await?;
         // Log state via LogReader is requested exactly at one place in the replication loop.
         // Find a way how to either remove it there or assert here properly.
+        self.config.check_crashed(ErrorSubject::Logs, ErrorVerb::Read)?;
         self.inner.get_log_state().await
     }
 }
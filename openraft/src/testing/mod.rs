@@ -1,7 +1,14 @@
+mod network;
 mod store_builder;
 mod suite;
 
+#[cfg(test)] mod network_test;
+
+pub use network::FaultInjector;
+pub use network::FaultNetwork;
+pub use network::FaultNetworkFactory;
 pub use store_builder::DefensiveStoreBuilder;
+pub use store_builder::ReopenableStoreBuilder;
 pub use store_builder::StoreBuilder;
 pub use suite::Suite;
 
@@ -22,3 +29,11 @@ pub fn log_id(term: u64, index: u64) -> LogId<u64> {
         index,
     }
 }
+
+/// Builds a sample key and value for the normal log entry at `index`, for a key-value store to
+/// implement [`StoreBuilder::sample_app_data`].
+///
+/// Keys are reused, so that a later entry overwrites the value set by an earlier one.
+pub fn sample_key_value(index: u64) -> (String, String) {
+    (format!("key-{}", index % 3), format!("value-{}", index))
+}
@@ -0,0 +1,450 @@
+//! A [`RaftNetworkFactory`] wrapper that injects network faults, for chaos testing a Raft
+//! application against an unreliable network.
+
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyerror::AnyError;
+use async_trait::async_trait;
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand::SeedableRng;
+
+use crate::error::InstallSnapshotError;
+use crate::error::JoinError;
+use crate::error::NetworkError;
+use crate::error::RPCError;
+use crate::error::RaftError;
+use crate::network::RPCTypes;
+use crate::raft::AppendEntriesRequest;
+use crate::raft::AppendEntriesResponse;
+use crate::raft::HeartbeatRequest;
+use crate::raft::HeartbeatResponse;
+use crate::raft::InstallSnapshotRequest;
+use crate::raft::InstallSnapshotResponse;
+use crate::raft::JoinRequest;
+use crate::raft::JoinResponse;
+use crate::raft::VoteRequest;
+use crate::raft::VoteResponse;
+use crate::NodeId;
+use crate::RaftNetwork;
+use crate::RaftNetworkFactory;
+use crate::RaftTypeConfig;
+
+/// Runtime control of the faults injected by every [`FaultNetworkFactory`] sharing it.
+///
+/// A `FaultInjector` is cheap to clone: all clones share the same fault settings, so that a test
+/// can hand one clone to every node and keep another to change the network behavior at runtime.
+///
+/// Supported faults:
+/// - Isolating a node, so that it can neither send nor receive.
+/// - Blocking a single directed link. Blocking `a -> b` but not `b -> a` builds an asymmetric
+///   partition: requests from `a` to `b` are lost, and responses to requests from `b` to `a` are
+///   lost after `a` handled the request.
+/// - Dropping a request or a response with a probability.
+/// - Delaying a request by a base latency plus a random jitter.
+/// - Duplicating a request: the target receives it twice.
+/// - Reordering: holding back a request for an extra delay, so that requests sent later on other
+///   connections overtake it.
+///
+/// All random decisions are made with a single seedable RNG. Note that with more than one node
+/// the order in which decisions are made still depends on task scheduling.
+#[derive(Clone)]
+pub struct FaultInjector<NID: NodeId> {
+    state: Arc<Mutex<FaultState<NID>>>,
+}
+
+struct FaultState<NID: NodeId> {
+    /// Nodes that can neither send nor receive.
+    isolated: BTreeSet<NID>,
+
+    /// Directed links `(from, to)` on which nothing is delivered.
+    blocked: BTreeSet<(NID, NID)>,
+
+    /// The probability to drop a request or a response.
+    drop_rate: f64,
+
+    /// The base latency for every request.
+    latency: Duration,
+
+    /// The upper bound of the random latency added to the base latency.
+    jitter: Duration,
+
+    /// The probability to deliver a request twice.
+    duplicate_rate: f64,
+
+    /// The probability to hold back a request for an extra `reorder_delay`.
+    reorder_rate: f64,
+
+    reorder_delay: Duration,
+
+    rng: StdRng,
+}
+
+impl<NID: NodeId> FaultState<NID> {
+    fn new(rng: StdRng) -> Self {
+        Self {
+            isolated: BTreeSet::new(),
+            blocked: BTreeSet::new(),
+            drop_rate: 0.0,
+            latency: Duration::from_millis(0),
+            jitter: Duration::from_millis(0),
+            duplicate_rate: 0.0,
+            reorder_rate: 0.0,
+            reorder_delay: Duration::from_millis(0),
+            rng,
+        }
+    }
+
+    fn check_link(&self, action: &RPCTypes, from: NID, to: NID) -> Result<(), NetworkError> {
+        if self.isolated.contains(&from) || self.isolated.contains(&to) {
+            return Err(NetworkError::new(&AnyError::error(format!(
+                "isolated:{} -> {}, {}",
+                from, to, action
+            ))));
+        }
+
+        if self.blocked.contains(&(from, to)) {
+            return Err(NetworkError::new(&AnyError::error(format!(
+                "link blocked:{} -> {}, {}",
+                from, to, action
+            ))));
+        }
+
+        Ok(())
+    }
+
+    fn roll(&mut self, p: f64) -> bool {
+        p > 0.0 && self.rng.gen_bool(p)
+    }
+
+    fn roll_drop(&mut self, action: &RPCTypes, from: NID, to: NID) -> Result<(), NetworkError> {
+        if self.roll(self.drop_rate) {
+            return Err(NetworkError::new(&AnyError::error(format!(
+                "dropped:{} -> {}, {}",
+                from, to, action
+            ))));
+        }
+        Ok(())
+    }
+}
+
+/// What to do with a single request, decided before sending it.
+struct Plan {
+    delay: Duration,
+    duplicate: bool,
+}
+
+impl<NID: NodeId> Default for FaultInjector<NID> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<NID: NodeId> FaultInjector<NID> {
+    /// Create a `FaultInjector` that injects no fault, with a randomly seeded RNG.
+    pub fn new() -> Self {
+        Self::build(StdRng::from_entropy())
+    }
+
+    /// Create a `FaultInjector` that injects no fault, with an RNG seeded with `seed`.
+    pub fn with_seed(seed: u64) -> Self {
+        Self::build(StdRng::seed_from_u64(seed))
+    }
+
+    fn build(rng: StdRng) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(FaultState::new(rng))),
+        }
+    }
+
+    /// Re-seed the RNG used for all random decisions.
+    pub fn reseed(&self, seed: u64) {
+        self.state.lock().unwrap().rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Isolate a node: it can neither send nor receive.
+    pub fn isolate_node(&self, id: NID) {
+        tracing::debug!("fault: isolate node {}", id);
+        self.state.lock().unwrap().isolated.insert(id);
+    }
+
+    /// Restore the network of an isolated node.
+    pub fn restore_node(&self, id: NID) {
+        tracing::debug!("fault: restore node {}", id);
+        self.state.lock().unwrap().isolated.remove(&id);
+    }
+
+    pub fn is_isolated(&self, id: NID) -> bool {
+        self.state.lock().unwrap().isolated.contains(&id)
+    }
+
+    /// Returns all isolated nodes.
+    pub fn isolated_nodes(&self) -> BTreeSet<NID> {
+        self.state.lock().unwrap().isolated.clone()
+    }
+
+    /// Block the directed link `from -> to`. The reverse direction is not affected.
+    pub fn block_link(&self, from: NID, to: NID) {
+        tracing::debug!("fault: block link {} -> {}", from, to);
+        self.state.lock().unwrap().blocked.insert((from, to));
+    }
+
+    /// Unblock the directed link `from -> to`.
+    pub fn unblock_link(&self, from: NID, to: NID) {
+        tracing::debug!("fault: unblock link {} -> {}", from, to);
+        self.state.lock().unwrap().blocked.remove(&(from, to));
+    }
+
+    /// Block all links between two groups of nodes, in both directions.
+    pub fn partition(&self, a: impl IntoIterator<Item = NID>, b: impl IntoIterator<Item = NID>) {
+        let a = a.into_iter().collect::<Vec<_>>();
+        let b = b.into_iter().collect::<Vec<_>>();
+
+        let mut st = self.state.lock().unwrap();
+        for x in a.iter() {
+            for y in b.iter() {
+                st.blocked.insert((*x, *y));
+                st.blocked.insert((*y, *x));
+            }
+        }
+    }
+
+    /// Remove all isolations and blocked links.
+    pub fn heal(&self) {
+        tracing::debug!("fault: heal all partitions");
+        let mut st = self.state.lock().unwrap();
+        st.isolated.clear();
+        st.blocked.clear();
+    }
+
+    /// Returns `true` if neither isolation nor a blocked link prevents `from` from sending to `to`.
+    pub fn is_reachable(&self, from: NID, to: NID) -> bool {
+        self.state.lock().unwrap().check_link(&RPCTypes::Vote, from, to).is_ok()
+    }
+
+    /// Set the probability to drop a request, and independently, its response.
+    pub fn set_drop_rate(&self, p: f64) {
+        assert!((0.0..=1.0).contains(&p), "drop rate must be in [0, 1], got: {}", p);
+        self.state.lock().unwrap().drop_rate = p;
+    }
+
+    /// Delay every request by `latency` plus a random duration in `[0, jitter)`.
+    pub fn set_latency(&self, latency: Duration, jitter: Duration) {
+        let mut st = self.state.lock().unwrap();
+        st.latency = latency;
+        st.jitter = jitter;
+    }
+
+    /// Set the probability to deliver a request twice.
+    pub fn set_duplicate_rate(&self, p: f64) {
+        assert!((0.0..=1.0).contains(&p), "duplicate rate must be in [0, 1], got: {}", p);
+        self.state.lock().unwrap().duplicate_rate = p;
+    }
+
+    /// Hold back a request for an extra `delay` with probability `p`.
+    pub fn set_reorder(&self, p: f64, delay: Duration) {
+        assert!((0.0..=1.0).contains(&p), "reorder rate must be in [0, 1], got: {}", p);
+        let mut st = self.state.lock().unwrap();
+        st.reorder_rate = p;
+        st.reorder_delay = delay;
+    }
+
+    /// Remove all injected faults. The RNG is left as is.
+    pub fn reset(&self) {
+        let mut st = self.state.lock().unwrap();
+        let rng = st.rng.clone();
+        *st = FaultState::new(rng);
+    }
+
+    /// Decide what happens to a request before sending it.
+    fn plan_request(&self, action: RPCTypes, from: NID, to: NID) -> Result<Plan, NetworkError> {
+        let mut guard = self.state.lock().unwrap();
+        let st = &mut *guard;
+
+        st.check_link(&action, from, to)?;
+        st.roll_drop(&action, from, to)?;
+
+        let mut delay = st.latency;
+        if st.jitter > Duration::from_millis(0) {
+            let j = st.rng.gen_range(0..st.jitter.as_nanos() as u64);
+            delay += Duration::from_nanos(j);
+        }
+        if st.roll(st.reorder_rate) {
+            delay += st.reorder_delay;
+        }
+
+        let duplicate = st.roll(st.duplicate_rate);
+
+        Ok(Plan { delay, duplicate })
+    }
+
+    /// Decide whether the response to a request from `from` to `to` is delivered.
+    fn check_response(&self, action: RPCTypes, from: NID, to: NID) -> Result<(), NetworkError> {
+        let mut st = self.state.lock().unwrap();
+
+        st.check_link(&action, to, from)?;
+        st.roll_drop(&action, to, from)?;
+
+        Ok(())
+    }
+}
+
+/// A [`RaftNetworkFactory`] that wraps another one and injects faults into every connection it
+/// builds, as configured by a [`FaultInjector`].
+///
+/// A `FaultNetworkFactory` is built for every Raft node, since it has to know the node the
+/// requests are sent from.
+pub struct FaultNetworkFactory<C, F>
+where
+    C: RaftTypeConfig,
+    F: RaftNetworkFactory<C>,
+{
+    source: C::NodeId,
+    inner: F,
+    faults: FaultInjector<C::NodeId>,
+}
+
+impl<C, F> FaultNetworkFactory<C, F>
+where
+    C: RaftTypeConfig,
+    F: RaftNetworkFactory<C>,
+{
+    /// Wrap the network factory `inner` of node `source`.
+    pub fn new(source: C::NodeId, inner: F, faults: FaultInjector<C::NodeId>) -> Self {
+        Self { source, inner, faults }
+    }
+
+    pub fn faults(&self) -> &FaultInjector<C::NodeId> {
+        &self.faults
+    }
+
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+}
+
+#[async_trait]
+impl<C, F> RaftNetworkFactory<C> for FaultNetworkFactory<C, F>
+where
+    C: RaftTypeConfig,
+    F: RaftNetworkFactory<C>,
+{
+    type Network = FaultNetwork<C, F::Network>;
+
+    async fn new_client(&mut self, target: C::NodeId, node: &C::Node) -> Self::Network {
+        FaultNetwork {
+            source: self.source,
+            target,
+            inner: self.inner.new_client(target, node).await,
+            faults: self.faults.clone(),
+        }
+    }
+}
+
+/// A single connection built by [`FaultNetworkFactory`].
+pub struct FaultNetwork<C, N>
+where
+    C: RaftTypeConfig,
+    N: RaftNetwork<C>,
+{
+    source: C::NodeId,
+    target: C::NodeId,
+    inner: N,
+    faults: FaultInjector<C::NodeId>,
+}
+
+#[async_trait]
+impl<C, N> RaftNetwork<C> for FaultNetwork<C, N>
+where
+    C: RaftTypeConfig,
+    N: RaftNetwork<C>,
+{
+    async fn send_append_entries(
+        &mut self,
+        rpc: AppendEntriesRequest<C>,
+    ) -> Result<AppendEntriesResponse<C::NodeId>, RPCError<C::NodeId, C::Node, RaftError<C::NodeId>>> {
+        let plan = self.faults.plan_request(RPCTypes::AppendEntries, self.source, self.target)?;
+        tokio::time::sleep(plan.delay).await;
+
+        if plan.duplicate {
+            let _ = self.inner.send_append_entries(rpc.clone()).await;
+        }
+        let resp = self.inner.send_append_entries(rpc).await?;
+
+        self.faults.check_response(RPCTypes::AppendEntries, self.source, self.target)?;
+        Ok(resp)
+    }
+
+    async fn send_heartbeat(
+        &mut self,
+        rpc: HeartbeatRequest<C::NodeId>,
+    ) -> Result<HeartbeatResponse<C::NodeId>, RPCError<C::NodeId, C::Node, RaftError<C::NodeId>>> {
+        let plan = self.faults.plan_request(RPCTypes::Heartbeat, self.source, self.target)?;
+        tokio::time::sleep(plan.delay).await;
+
+        if plan.duplicate {
+            let _ = self.inner.send_heartbeat(rpc.clone()).await;
+        }
+        let resp = self.inner.send_heartbeat(rpc).await?;
+
+        self.faults.check_response(RPCTypes::Heartbeat, self.source, self.target)?;
+        Ok(resp)
+    }
+
+    async fn send_install_snapshot(
+        &mut self,
+        rpc: InstallSnapshotRequest<C>,
+    ) -> Result<
+        InstallSnapshotResponse<C::NodeId>,
+        RPCError<C::NodeId, C::Node, RaftError<C::NodeId, InstallSnapshotError>>,
+    > {
+        let plan = self.faults.plan_request(RPCTypes::InstallSnapshot, self.source, self.target)?;
+        tokio::time::sleep(plan.delay).await;
+
+        if plan.duplicate {
+            let _ = self.inner.send_install_snapshot(rpc.clone()).await;
+        }
+        let resp = self.inner.send_install_snapshot(rpc).await?;
+
+        self.faults.check_response(RPCTypes::InstallSnapshot, self.source, self.target)?;
+        Ok(resp)
+    }
+
+    async fn send_vote(
+        &mut self,
+        rpc: VoteRequest<C::NodeId>,
+    ) -> Result<VoteResponse<C::NodeId>, RPCError<C::NodeId, C::Node, RaftError<C::NodeId>>> {
+        let plan = self.faults.plan_request(RPCTypes::Vote, self.source, self.target)?;
+        tokio::time::sleep(plan.delay).await;
+
+        if plan.duplicate {
+            let _ = self.inner.send_vote(rpc.clone()).await;
+        }
+        let resp = self.inner.send_vote(rpc).await?;
+
+        self.faults.check_response(RPCTypes::Vote, self.source, self.target)?;
+        Ok(resp)
+    }
+
+    async fn send_join(
+        &mut self,
+        rpc: JoinRequest<C::NodeId, C::Node>,
+    ) -> Result<
+        JoinResponse<C::NodeId, C::Node>,
+        RPCError<C::NodeId, C::Node, RaftError<C::NodeId, JoinError<C::NodeId, C::Node>>>,
+    > {
+        let plan = self.faults.plan_request(RPCTypes::Join, self.source, self.target)?;
+        tokio::time::sleep(plan.delay).await;
+
+        if plan.duplicate {
+            let _ = self.inner.send_join(rpc.clone()).await;
+        }
+        let resp = self.inner.send_join(rpc).await?;
+
+        self.faults.check_response(RPCTypes::Join, self.source, self.target)?;
+        Ok(resp)
+    }
+}
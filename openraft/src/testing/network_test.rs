@@ -0,0 +1,167 @@
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use anyerror::AnyError;
+use async_trait::async_trait;
+
+use crate::error::InstallSnapshotError;
+use crate::error::NetworkError;
+use crate::error::RPCError;
+use crate::error::RaftError;
+use crate::raft::AppendEntriesRequest;
+use crate::raft::AppendEntriesResponse;
+use crate::raft::InstallSnapshotRequest;
+use crate::raft::InstallSnapshotResponse;
+use crate::raft::VoteRequest;
+use crate::raft::VoteResponse;
+use crate::testing::DummyConfig;
+use crate::testing::FaultInjector;
+use crate::testing::FaultNetworkFactory;
+use crate::BasicNode;
+use crate::RaftNetwork;
+use crate::RaftNetworkFactory;
+use crate::Vote;
+
+/// A network that counts the vote requests it received.
+#[derive(Clone, Default)]
+struct CountingNetwork {
+    received: Arc<AtomicU64>,
+}
+
+#[async_trait]
+impl RaftNetworkFactory<DummyConfig> for CountingNetwork {
+    type Network = CountingNetwork;
+
+    async fn new_client(&mut self, _target: u64, _node: &BasicNode) -> Self::Network {
+        self.clone()
+    }
+}
+
+#[async_trait]
+impl RaftNetwork<DummyConfig> for CountingNetwork {
+    async fn send_append_entries(
+        &mut self,
+        _rpc: AppendEntriesRequest<DummyConfig>,
+    ) -> Result<AppendEntriesResponse<u64>, RPCError<u64, BasicNode, RaftError<u64>>> {
+        Err(unsupported("append-entries"))
+    }
+
+    async fn send_install_snapshot(
+        &mut self,
+        _rpc: InstallSnapshotRequest<DummyConfig>,
+    ) -> Result<InstallSnapshotResponse<u64>, RPCError<u64, BasicNode, RaftError<u64, InstallSnapshotError>>> {
+        Err(unsupported("install-snapshot"))
+    }
+
+    async fn send_vote(
+        &mut self,
+        rpc: VoteRequest<u64>,
+    ) -> Result<VoteResponse<u64>, RPCError<u64, BasicNode, RaftError<u64>>> {
+        self.received.fetch_add(1, Ordering::Relaxed);
+        Ok(VoteResponse {
+            vote: rpc.vote,
+            vote_granted: true,
+            last_log_id: None,
+            removed_by: None,
+        })
+    }
+}
+
+/// The error a [`CountingNetwork`] returns for an RPC it does not serve.
+fn unsupported<E: std::error::Error>(rpc: &str) -> RPCError<u64, BasicNode, E> {
+    RPCError::Network(NetworkError::new(&AnyError::error(format!(
+        "CountingNetwork does not serve {} RPC",
+        rpc
+    ))))
+}
+
+fn vote_req() -> VoteRequest<u64> {
+    VoteRequest::new(Vote::new(1, 1), None)
+}
+
+#[async_entry::test(worker_threads = 3)]
+async fn test_fault_network_partition() -> anyhow::Result<()> {
+    let inner = CountingNetwork::default();
+    let faults = FaultInjector::<u64>::with_seed(1);
+
+    let mut f1 = FaultNetworkFactory::new(1, inner.clone(), faults.clone());
+    let mut f2 = FaultNetworkFactory::new(2, inner.clone(), faults.clone());
+
+    let mut n12 = f1.new_client(2, &BasicNode::default()).await;
+    let mut n21 = f2.new_client(1, &BasicNode::default()).await;
+
+    tracing::info!("--- no fault");
+    {
+        assert!(n12.send_vote(vote_req()).await.is_ok());
+        assert_eq!(1, inner.received.load(Ordering::Relaxed));
+    }
+
+    tracing::info!("--- isolated node can not send or receive");
+    {
+        faults.isolate_node(2);
+        assert!(n12.send_vote(vote_req()).await.is_err());
+        assert!(n21.send_vote(vote_req()).await.is_err());
+        assert_eq!(1, inner.received.load(Ordering::Relaxed));
+
+        faults.restore_node(2);
+        assert!(n12.send_vote(vote_req()).await.is_ok());
+        assert_eq!(2, inner.received.load(Ordering::Relaxed));
+    }
+
+    tracing::info!("--- asymmetric partition: 1 -> 2 blocked");
+    {
+        faults.block_link(1, 2);
+
+        assert!(n12.send_vote(vote_req()).await.is_err());
+        assert_eq!(2, inner.received.load(Ordering::Relaxed));
+
+        // The request from 2 is delivered but the response is lost.
+        assert!(n21.send_vote(vote_req()).await.is_err());
+        assert_eq!(3, inner.received.load(Ordering::Relaxed));
+
+        faults.heal();
+        assert!(n12.send_vote(vote_req()).await.is_ok());
+        assert!(n21.send_vote(vote_req()).await.is_ok());
+        assert_eq!(5, inner.received.load(Ordering::Relaxed));
+    }
+
+    tracing::info!("--- duplicate every request");
+    {
+        faults.set_duplicate_rate(1.0);
+        assert!(n12.send_vote(vote_req()).await.is_ok());
+        assert_eq!(7, inner.received.load(Ordering::Relaxed));
+    }
+
+    tracing::info!("--- drop every request");
+    {
+        faults.reset();
+        faults.set_drop_rate(1.0);
+        assert!(n12.send_vote(vote_req()).await.is_err());
+        assert_eq!(7, inner.received.load(Ordering::Relaxed));
+    }
+
+    Ok(())
+}
+
+#[async_entry::test(worker_threads = 3)]
+async fn test_fault_network_seeded() -> anyhow::Result<()> {
+    let outcomes = |seed: u64| async move {
+        let inner = CountingNetwork::default();
+        let faults = FaultInjector::<u64>::with_seed(seed);
+        faults.set_drop_rate(0.5);
+
+        let mut f1 = FaultNetworkFactory::new(1, inner, faults);
+        let mut n12 = f1.new_client(2, &BasicNode::default()).await;
+
+        let mut res = vec![];
+        for _ in 0..32 {
+            res.push(n12.send_vote(vote_req()).await.is_ok());
+        }
+        res
+    };
+
+    assert_eq!(outcomes(7).await, outcomes(7).await);
+
+    Ok(())
+}
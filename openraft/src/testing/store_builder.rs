@@ -24,6 +24,33 @@ where
 {
     /// Build a [`RaftStorage`] implementation
     async fn build(&self) -> Result<(G, S), StorageError<C::NodeId>>;
+
+    /// Build a sample application data for the normal log entry at `index`.
+    ///
+    /// [`Suite`](`crate::testing::Suite`) does not know the application data type. It uses this
+    /// to feed normal log entries to the state machine and snapshots. By default it returns `None`
+    /// and the suite uses blank log entries instead, which leaves the application part of the state
+    /// machine untested.
+    fn sample_app_data(&self, _index: u64) -> Option<C::D> {
+        None
+    }
+}
+
+/// A [`StoreBuilder`] that is able to close a store and open it again from the same persistent
+/// data, to test that a [`RaftStorage`] implementation survives a restart.
+///
+/// The guard `G` returned by [`StoreBuilder::build`] is kept alive across restarts, thus it is the
+/// natural place to find the persistent data, e.g., a temp-dir.
+#[async_trait]
+pub trait ReopenableStoreBuilder<C, S, G = ()>: StoreBuilder<C, S, G>
+where
+    C: RaftTypeConfig,
+    S: RaftStorage<C>,
+{
+    /// Close `store` and open a new instance from the data it persisted.
+    ///
+    /// Anything not persisted by `store` must be lost, as if the process crashed.
+    async fn reopen(&self, guard: &G, store: S) -> Result<S, StorageError<C::NodeId>>;
 }
 
 /// A builder for testing [`StoreExt`].
@@ -55,4 +82,8 @@ where
 
         Ok((g, sto_ext))
     }
+
+    fn sample_app_data(&self, index: u64) -> Option<C::D> {
+        self.base_builder.sample_app_data(index)
+    }
 }
@@ -1,12 +1,18 @@
 use std::collections::BTreeSet;
 use std::fmt::Debug;
 use std::future::Future;
+use std::io::SeekFrom;
 use std::marker::PhantomData;
 use std::option::Option::None;
 
 use maplit::btreeset;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncSeekExt;
+use tokio::io::AsyncWriteExt;
 
+use crate::entry::FromAppData;
 use crate::entry::RaftEntry;
+use crate::entry::RaftPayload;
 use crate::log_id::RaftLogId;
 use crate::membership::EffectiveMembership;
 use crate::raft_state::LogStateReader;
@@ -14,6 +20,7 @@ use crate::raft_state::RaftState;
 use crate::storage::LogState;
 use crate::storage::StorageHelper;
 use crate::testing::DefensiveStoreBuilder;
+use crate::testing::ReopenableStoreBuilder;
 use crate::testing::StoreBuilder;
 use crate::vote::CommittedLeaderId;
 use crate::AppData;
@@ -26,7 +33,9 @@ use crate::NodeId;
 use crate::RaftSnapshotBuilder;
 use crate::RaftStorage;
 use crate::RaftTypeConfig;
+use crate::SnapshotMeta;
 use crate::StorageError;
+use crate::StorageIOError;
 use crate::StoredMembership;
 use crate::Violation;
 use crate::Vote;
@@ -89,6 +98,7 @@ where
         run_fut(run_test(builder, Self::last_membership_in_log_initial))?;
         run_fut(run_test(builder, Self::last_membership_in_log))?;
         run_fut(run_test(builder, Self::last_membership_in_log_multi_step))?;
+        run_fut(run_test(builder, Self::membership_log_ids))?;
         run_fut(run_test(builder, Self::get_membership_initial))?;
         run_fut(run_test(builder, Self::get_membership_from_log_and_empty_sm))?;
         run_fut(run_test(builder, Self::get_membership_from_log_and_sm))?;
@@ -114,11 +124,11 @@ where
         run_fut(run_test(builder, Self::append_to_log))?;
         run_fut(run_test(builder, Self::snapshot_meta))?;
 
-        // run_fut(Suite::apply_single(builder))?;
-        // run_fut(Suite::apply_multi(builder))?;
-
-        // TODO(xp): test: finalized_snapshot, do_log_compaction, begin_receiving_snapshot,
-        // get_current_snapshot
+        run_fut(Self::apply_single(builder))?;
+        run_fut(Self::apply_multi(builder))?;
+        run_fut(Self::snapshot_round_trip(builder))?;
+        run_fut(Self::install_snapshot_over_non_empty_state(builder))?;
+        run_fut(Self::get_initial_state_with_snapshot(builder))?;
 
         Ok(())
     }
@@ -233,6 +243,90 @@ where
         Ok(())
     }
 
+    pub async fn membership_log_ids(mut store: S) -> Result<(), StorageError<C::NodeId>> {
+        if store.read_membership_log_ids().await?.is_none() {
+            tracing::info!("--- membership log ids are not recorded by this store, skip");
+            return Ok(());
+        }
+
+        /// The recorded ids must locate the same membership logs as a scan does.
+        async fn check<C: RaftTypeConfig, S: RaftStorage<C>>(store: &mut S) -> Result<(), StorageError<C::NodeId>> {
+            let loaded = StorageHelper::new(store).last_membership_in_log(0).await?;
+            let scanned = StorageHelper::new(store).scan_membership_in_log(0).await?;
+            assert_eq!(scanned, loaded);
+            Ok(())
+        }
+
+        tracing::info!("--- membership logs are recorded when appended");
+        {
+            store
+                .append_to_log(&[
+                    blank_ent::<C>(0, 0),
+                    membership_ent::<C>(1, 1, btreeset! {1,2,3}),
+                    blank_ent::<C>(1, 2),
+                    membership_ent::<C>(1, 3, btreeset! {3,4,5}),
+                    blank_ent::<C>(1, 4),
+                    membership_ent::<C>(1, 5, btreeset! {5,6,7}),
+                ])
+                .await?;
+
+            check(&mut store).await?;
+            let ids = store.read_membership_log_ids().await?.unwrap();
+            assert_eq!(
+                vec![log_id(1, 3), log_id(1, 5)],
+                ids.into_iter().filter(|x| x.index >= 3).collect::<Vec<_>>()
+            );
+        }
+
+        tracing::info!("--- membership logs are removed when deleted");
+        {
+            store.delete_conflict_logs_since(log_id(1, 5)).await?;
+            check(&mut store).await?;
+
+            let mems = StorageHelper::new(&mut store).last_membership_in_log(0).await?;
+            assert_eq!(
+                vec![Some(log_id(1, 1)), Some(log_id(1, 3))],
+                mems.iter().map(|x| *x.log_id()).collect::<Vec<_>>()
+            );
+        }
+
+        tracing::info!("--- membership logs are replaced by non-membership logs");
+        {
+            // Conflicting logs are always deleted before being replaced.
+            store.delete_conflict_logs_since(log_id(1, 3)).await?;
+            store.append_to_log(&[blank_ent::<C>(2, 3), blank_ent::<C>(2, 4)]).await?;
+            check(&mut store).await?;
+
+            let mems = StorageHelper::new(&mut store).last_membership_in_log(0).await?;
+            assert_eq!(
+                vec![Some(log_id(1, 1))],
+                mems.iter().map(|x| *x.log_id()).collect::<Vec<_>>()
+            );
+        }
+
+        tracing::info!("--- purged membership logs are ignored");
+        {
+            store.purge_logs_upto(log_id(1, 2)).await?;
+            check(&mut store).await?;
+
+            let mems = StorageHelper::new(&mut store).last_membership_in_log(0).await?;
+            assert!(mems.is_empty());
+        }
+
+        tracing::info!("--- old membership log ids are trimmed");
+        {
+            for i in 5..15 {
+                store.append_to_log(&[membership_ent::<C>(2, i, btreeset! {1,2})]).await?;
+            }
+            check(&mut store).await?;
+
+            let ids = store.read_membership_log_ids().await?.unwrap();
+            assert!(ids.len() <= 3, "at most 3 ids are kept, got: {:?}", ids);
+        }
+
+        Ok(())
+    }
+
     pub async fn get_membership_initial(mut store: S) -> Result<(), StorageError<C::NodeId>> {
         let mem_state = StorageHelper::new(&mut store).get_membership().await?;
 
@@ -908,121 +1002,206 @@ where
         Ok(())
     }
 
-    // pub async fn apply_single(mut store: S) -> Result<(), StorageError<C::NodeId>> {
-
-    //
-    //     let entry = Entry {
-    //         log_id: log_id(3,1),
-    //
-    //         payload: EntryPayload::Normal(ClientRequest {
-    //             client: "0".into(),
-    //             serial: 0,
-    //             status: "lit".into(),
-    //         }),
-    //     };
-    //
-    //     store.apply_to_state_machine(&[&entry]).await?;
-    //     let (last_applied, _) = store.last_applied_state().await?;
-    //
-    //     assert_eq!(
-    //         last_applied,
-    //         Some(log_id(3,1)),
-    //         "expected last_applied_log to be 1, got {:?}",
-    //         last_applied
-    //     );
-    //
-    //     let sm = store.get_state_machine().await;
-    //     let client_serial =
-    //         sm.client_serial_responses.get("0").expect("expected entry to exist in
-    // client_serial_responses");     assert_eq!(client_serial, &(0, None), "unexpected client
-    // serial response");
-    //
-    //     let client_status = sm.client_status.get("0").expect("expected entry to exist in
-    // client_status");     assert_eq!(
-    //         client_status, "lit",
-    //         "expected client_status to be 'lit', got '{}'",
-    //         client_status
-    //     );
-    //     Ok(())
-    // }
-    //
-    // pub async fn apply_multi(mut store: S) -> Result<(), StorageError<C::NodeId>> {
-
-    //
-    //     let req0 = ClientRequest {
-    //         client: "1".into(),
-    //         serial: 0,
-    //         status: "old".into(),
-    //     };
-    //     let req1 = ClientRequest {
-    //         client: "1".into(),
-    //         serial: 1,
-    //         status: "new".into(),
-    //     };
-    //     let req2 = ClientRequest {
-    //         client: "2".into(),
-    //         serial: 0,
-    //         status: "other".into(),
-    //     };
-    //
-    //     let entries = vec![
-    //         (&log_id(3,1), &req0),
-    //         (&log_id(3,2), &req1),
-    //         (&log_id(3,3), &req2),
-    //     ]
-    //     .into_iter()
-    //     .map(|(id, req)| Entry {
-    //         log_id: *id,
-    //         payload: EntryPayload::Normal(req.clone()),
-    //     })
-    //     .collect::<Vec<_>>();
-    //
-    //     store.apply_to_state_machine(&entries.iter().collect::<Vec<_>>()).await?;
-    //
-    //     let (last_applied, _) = store.last_applied_state().await?;
-    //
-    //     assert_eq!(
-    //         last_applied,
-    //         Some(log_id(3,3)),
-    //         "expected last_applied_log to be 3, got {:?}",
-    //         last_applied
-    //     );
-    //
-    //     let sm = store.get_state_machine().await;
-    //
-    //     let client_serial1 = sm
-    //         .client_serial_responses
-    //         .get("1")
-    //         .expect("expected entry to exist in client_serial_responses for client 1");
-    //     assert_eq!(client_serial1.0, 1, "unexpected client serial response");
-    //     assert_eq!(
-    //         client_serial1.1,
-    //         Some(String::from("old")),
-    //         "unexpected client serial response"
-    //     );
-    //
-    //     let client_serial2 = sm
-    //         .client_serial_responses
-    //         .get("2")
-    //         .expect("expected entry to exist in client_serial_responses for client 2");
-    //     assert_eq!(client_serial2.0, 0, "unexpected client serial response");
-    //     assert_eq!(client_serial2.1, None, "unexpected client serial response");
-    //
-    //     let client_status1 = sm.client_status.get("1").expect("expected entry to exist in
-    // client_status for client 1");     let client_status2 =
-    // sm.client_status.get("2").expect("expected entry to exist in client_status for
-    // client 2");     assert_eq!(
-    //         client_status1, "new",
-    //         "expected client_status to be 'new', got '{}'",
-    //         client_status1
-    //     );
-    //     assert_eq!(
-    //         client_status2, "other",
-    //         "expected client_status to be 'other', got '{}'",
-    //         client_status2
-    //     );
-    //     Ok(())
-    // }
+    pub async fn apply_single(builder: &B) -> Result<(), StorageError<C::NodeId>> {
+        let (_g, mut store) = builder.build().await?;
+
+        let entries = [
+            blank_ent::<C>(1, 1),
+            membership_ent::<C>(1, 2, btreeset! {1,2}),
+            normal_ent::<C, S, B, G>(builder, 1, 3),
+            normal_ent::<C, S, B, G>(builder, 1, 4),
+        ];
+
+        for ent in entries.iter() {
+            let resp = store.apply_to_state_machine(std::slice::from_ref(ent)).await?;
+            assert_eq!(1, resp.len(), "one response per entry");
+
+            let (applied, mem) = store.last_applied_state().await?;
+            assert_eq!(Some(*ent.get_log_id()), applied);
+
+            if ent.get_log_id().index >= 2 {
+                assert_eq!(
+                    StoredMembership::new(Some(log_id(1, 2)), Membership::new(vec![btreeset! {1,2}], None)),
+                    mem
+                );
+            } else {
+                assert_eq!(StoredMembership::default(), mem);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn apply_multi(builder: &B) -> Result<(), StorageError<C::NodeId>> {
+        let (_g, mut store) = builder.build().await?;
+
+        let entries = [
+            membership_ent::<C>(1, 1, btreeset! {1,2}),
+            normal_ent::<C, S, B, G>(builder, 1, 2),
+            blank_ent::<C>(2, 3),
+            membership_ent::<C>(2, 4, btreeset! {3,4}),
+            normal_ent::<C, S, B, G>(builder, 2, 5),
+        ];
+
+        let resp = store.apply_to_state_machine(&entries).await?;
+        assert_eq!(entries.len(), resp.len(), "one response per entry");
+
+        let (applied, mem) = store.last_applied_state().await?;
+        assert_eq!(Some(log_id(2, 5)), applied);
+        assert_eq!(
+            StoredMembership::new(Some(log_id(2, 4)), Membership::new(vec![btreeset! {3,4}], None)),
+            mem
+        );
+
+        Ok(())
+    }
+
+    /// A snapshot built by one store and installed into a fresh store, restores the same state.
+    pub async fn snapshot_round_trip(builder: &B) -> Result<(), StorageError<C::NodeId>> {
+        let (_g1, mut store1) = builder.build().await?;
+
+        store1
+            .apply_to_state_machine(&[
+                membership_ent::<C>(1, 1, btreeset! {1,2}),
+                normal_ent::<C, S, B, G>(builder, 1, 2),
+                normal_ent::<C, S, B, G>(builder, 1, 3),
+            ])
+            .await?;
+
+        let (meta, data) = {
+            let mut b = store1.get_snapshot_builder().await;
+            let snap = b.build_snapshot().await?;
+            let data = read_snapshot_data::<C, S>(snap.snapshot, &snap.meta).await?;
+            (snap.meta, data)
+        };
+
+        tracing::info!("--- the built snapshot is the current snapshot");
+        {
+            let snap = store1.get_current_snapshot().await?.expect("snapshot is built");
+            assert_eq!(meta, snap.meta);
+            assert_eq!(data, read_snapshot_data::<C, S>(snap.snapshot, &snap.meta).await?);
+        }
+
+        tracing::info!("--- install into a fresh store");
+        let (_g2, mut store2) = builder.build().await?;
+        {
+            assert!(store2.get_current_snapshot().await?.is_none());
+
+            install_snapshot_data::<C, S>(&mut store2, &meta, &data).await?;
+
+            assert_eq!(store1.last_applied_state().await?, store2.last_applied_state().await?);
+
+            let snap = store2.get_current_snapshot().await?.expect("snapshot is installed");
+            assert_eq!(meta, snap.meta);
+            assert_eq!(data, read_snapshot_data::<C, S>(snap.snapshot, &snap.meta).await?);
+        }
+
+        tracing::info!("--- entries after the snapshot can be applied");
+        {
+            store2.apply_to_state_machine(&[normal_ent::<C, S, B, G>(builder, 1, 4)]).await?;
+
+            let (applied, _) = store2.last_applied_state().await?;
+            assert_eq!(Some(log_id(1, 4)), applied);
+        }
+
+        Ok(())
+    }
+
+    /// Installing a snapshot replaces the state machine and the current snapshot of a store that
+    /// already has state.
+    pub async fn install_snapshot_over_non_empty_state(builder: &B) -> Result<(), StorageError<C::NodeId>> {
+        let (_g1, mut store1) = builder.build().await?;
+        store1
+            .apply_to_state_machine(&[
+                membership_ent::<C>(1, 1, btreeset! {1,2}),
+                normal_ent::<C, S, B, G>(builder, 1, 2),
+                membership_ent::<C>(2, 3, btreeset! {3,4}),
+                normal_ent::<C, S, B, G>(builder, 2, 4),
+                normal_ent::<C, S, B, G>(builder, 2, 5),
+            ])
+            .await?;
+
+        let (meta, data) = {
+            let mut b = store1.get_snapshot_builder().await;
+            let snap = b.build_snapshot().await?;
+            let data = read_snapshot_data::<C, S>(snap.snapshot, &snap.meta).await?;
+            (snap.meta, data)
+        };
+
+        let (_g2, mut store2) = builder.build().await?;
+        store2
+            .apply_to_state_machine(&[
+                membership_ent::<C>(1, 1, btreeset! {5,6}),
+                normal_ent::<C, S, B, G>(builder, 1, 2),
+            ])
+            .await?;
+        {
+            let mut b = store2.get_snapshot_builder().await;
+            b.build_snapshot().await?;
+        }
+
+        install_snapshot_data::<C, S>(&mut store2, &meta, &data).await?;
+
+        let (applied, mem) = store2.last_applied_state().await?;
+        assert_eq!(Some(log_id(2, 5)), applied);
+        assert_eq!(
+            StoredMembership::new(Some(log_id(2, 3)), Membership::new(vec![btreeset! {3,4}], None)),
+            mem
+        );
+
+        let snap = store2.get_current_snapshot().await?.expect("snapshot is installed");
+        assert_eq!(meta, snap.meta, "the installed snapshot replaces the previous one");
+        assert_eq!(data, read_snapshot_data::<C, S>(snap.snapshot, &snap.meta).await?);
+
+        Ok(())
+    }
+
+    /// A snapshot installed beyond the last log determines the initial state: the logs before it
+    /// are purged and the membership is the one in the snapshot.
+    pub async fn get_initial_state_with_snapshot(builder: &B) -> Result<(), StorageError<C::NodeId>> {
+        let (_g1, mut store1) = builder.build().await?;
+        store1
+            .apply_to_state_machine(&[
+                membership_ent::<C>(1, 1, btreeset! {3,4}),
+                normal_ent::<C, S, B, G>(builder, 1, 2),
+                normal_ent::<C, S, B, G>(builder, 2, 6),
+            ])
+            .await?;
+
+        let (meta, data) = {
+            let mut b = store1.get_snapshot_builder().await;
+            let snap = b.build_snapshot().await?;
+            let data = read_snapshot_data::<C, S>(snap.snapshot, &snap.meta).await?;
+            (snap.meta, data)
+        };
+
+        let (_g2, mut store2) = builder.build().await?;
+        Self::default_vote(&mut store2).await?;
+        store2
+            .append_to_log(&[
+                blank_ent::<C>(0, 0),
+                membership_ent::<C>(1, 1, btreeset! {1,2}),
+                blank_ent::<C>(1, 2),
+                blank_ent::<C>(1, 3),
+            ])
+            .await?;
+
+        install_snapshot_data::<C, S>(&mut store2, &meta, &data).await?;
+
+        let initial = StorageHelper::new(&mut store2).get_initial_state().await?;
+
+        assert_eq!(Some(&log_id(2, 6)), initial.last_log_id());
+        assert_eq!(Some(&log_id(2, 6)), initial.last_purged_log_id());
+        assert_eq!(Some(&log_id(2, 6)), initial.committed());
+        assert_eq!(Some(&log_id(2, 6)), initial.snapshot_last_log_id());
+        assert_eq!(meta, initial.snapshot_meta);
+        assert_eq!(
+            &Membership::new(vec![btreeset! {3,4}], None),
+            initial.membership_state.effective().membership(),
+        );
+
+        Ok(())
+    }
 
     pub async fn feed_10_logs_vote_self(sto: &mut S) -> Result<(), StorageError<C::NodeId>> {
         sto.append_to_log(&[blank_ent::<C>(0, 0)]).await?;
@@ -1043,6 +1222,344 @@ where
     }
 }
 
+// Restart test:
+// If a RaftStore impl can be reopened from its persistent data, check that everything raft relies on
+// survives a restart.
+impl<C, S, B, G> Suite<C, S, B, G>
+where
+    C: RaftTypeConfig,
+    C::D: AppData + Debug,
+    C::R: AppDataResponse + Debug,
+    C::NodeId: From<u64>,
+    S: RaftStorage<C>,
+    B: ReopenableStoreBuilder<C, S, G>,
+    G: Send + Sync,
+{
+    pub fn test_restart(builder: &B) -> Result<(), StorageError<C::NodeId>> {
+        run_fut(Self::restart_vote(builder))?;
+        run_fut(Self::restart_logs(builder))?;
+        run_fut(Self::restart_after_purge(builder))?;
+        run_fut(Self::restart_after_delete_conflict_logs(builder))?;
+        run_fut(Self::restart_state_machine(builder))?;
+        run_fut(Self::restart_committed(builder))?;
+        run_fut(Self::restart_removed_by(builder))?;
+        run_fut(Self::restart_built_snapshot(builder))?;
+        run_fut(Self::restart_installed_snapshot(builder))?;
+        run_fut(Self::restart_partially_received_snapshot(builder))?;
+
+        Ok(())
+    }
+
+    pub async fn restart_vote(builder: &B) -> Result<(), StorageError<C::NodeId>> {
+        let (g, mut store) = builder.build().await?;
+
+        store.save_vote(&Vote::new(1, NODE_ID.into())).await?;
+        store.save_vote(&Vote::new_committed(2, NODE_ID.into())).await?;
+
+        let mut store = builder.reopen(&g, store).await?;
+        assert_eq!(Some(Vote::new_committed(2, NODE_ID.into())), store.read_vote().await?);
+
+        Ok(())
+    }
+
+    pub async fn restart_logs(builder: &B) -> Result<(), StorageError<C::NodeId>> {
+        let (g, mut store) = builder.build().await?;
+
+        Self::feed_10_logs_vote_self(&mut store).await?;
+        store.append_to_log(&[membership_ent::<C>(2, 11, btreeset! {1,2})]).await?;
+
+        let mut store = builder.reopen(&g, store).await?;
+
+        let st = store.get_log_state().await?;
+        assert_eq!(None, st.last_purged_log_id);
+        assert_eq!(Some(log_id(2, 11)), st.last_log_id);
+
+        let ents = store.try_get_log_entries(..).await?;
+        assert_eq!(12, ents.len());
+        assert_eq!(
+            Some(&Membership::new(vec![btreeset! {1,2}], None)),
+            ents[11].get_membership()
+        );
+
+        assert_eq!(Some(Vote::new(1, NODE_ID.into())), store.read_vote().await?);
+
+        Ok(())
+    }
+
+    pub async fn restart_after_purge(builder: &B) -> Result<(), StorageError<C::NodeId>> {
+        tracing::info!("--- purge some logs");
+        {
+            let (g, mut store) = builder.build().await?;
+
+            Self::feed_10_logs_vote_self(&mut store).await?;
+            store.purge_logs_upto(log_id(1, 5)).await?;
+
+            let mut store = builder.reopen(&g, store).await?;
+
+            let st = store.get_log_state().await?;
+            assert_eq!(Some(log_id(1, 5)), st.last_purged_log_id);
+            assert_eq!(Some(log_id(1, 10)), st.last_log_id);
+
+            let ents = store.try_get_log_entries(..).await?;
+            assert_eq!(Some(log_id(1, 6)), ents.first().map(|x| *x.get_log_id()));
+            assert_eq!(5, ents.len());
+        }
+
+        tracing::info!("--- purge all logs");
+        {
+            let (g, mut store) = builder.build().await?;
+
+            Self::feed_10_logs_vote_self(&mut store).await?;
+            store.purge_logs_upto(log_id(1, 10)).await?;
+
+            let mut store = builder.reopen(&g, store).await?;
+
+            let st = store.get_log_state().await?;
+            assert_eq!(Some(log_id(1, 10)), st.last_purged_log_id);
+            assert_eq!(Some(log_id(1, 10)), st.last_log_id);
+            assert!(store.try_get_log_entries(..).await?.is_empty());
+        }
+
+        Ok(())
+    }
+
+    pub async fn restart_after_delete_conflict_logs(builder: &B) -> Result<(), StorageError<C::NodeId>> {
+        let (g, mut store) = builder.build().await?;
+
+        Self::feed_10_logs_vote_self(&mut store).await?;
+        store.delete_conflict_logs_since(log_id(1, 6)).await?;
+
+        let mut store = builder.reopen(&g, store).await?;
+
+        let st = store.get_log_state().await?;
+        assert_eq!(Some(log_id(1, 5)), st.last_log_id);
+        assert_eq!(6, store.try_get_log_entries(..).await?.len());
+
+        tracing::info!("--- append logs of a greater term after the deleted ones");
+        {
+            store.append_to_log(&[blank_ent::<C>(2, 6), blank_ent::<C>(2, 7)]).await?;
+
+            let mut store = builder.reopen(&g, store).await?;
+
+            let st = store.get_log_state().await?;
+            assert_eq!(Some(log_id(2, 7)), st.last_log_id);
+
+            let ents = store.try_get_log_entries(5..).await?;
+            assert_eq!(
+                vec![log_id(1, 5), log_id(2, 6), log_id(2, 7)],
+                ents.iter().map(|x| *x.get_log_id()).collect::<Vec<_>>()
+            );
+        }
+
+        Ok(())
+    }
+
+    pub async fn restart_state_machine(builder: &B) -> Result<(), StorageError<C::NodeId>> {
+        let (g, mut store) = builder.build().await?;
+
+        store
+            .apply_to_state_machine(&[
+                membership_ent::<C>(1, 1, btreeset! {1,2}),
+                normal_ent::<C, S, B, G>(builder, 1, 2),
+                blank_ent::<C>(1, 3),
+            ])
+            .await?;
+
+        let mut store = builder.reopen(&g, store).await?;
+
+        let (applied, mem) = store.last_applied_state().await?;
+        assert_eq!(Some(log_id(1, 3)), applied);
+        assert_eq!(
+            StoredMembership::new(Some(log_id(1, 1)), Membership::new(vec![btreeset! {1,2}], None)),
+            mem
+        );
+
+        Ok(())
+    }
+
+    /// The committed log id survives restart, and the logs committed but not applied are known
+    /// to be committed at startup.
+    ///
+    /// It is skipped if the store does not persist the committed log id.
+    pub async fn restart_committed(builder: &B) -> Result<(), StorageError<C::NodeId>> {
+        let (g, mut store) = builder.build().await?;
+
+        Self::feed_10_logs_vote_self(&mut store).await?;
+        store.apply_to_state_machine(&[blank_ent::<C>(0, 0), blank_ent::<C>(1, 1)]).await?;
+        store.save_committed(Some(log_id(1, 5))).await?;
+
+        if store.read_committed().await?.is_none() {
+            tracing::info!("store does not persist committed log id, skip");
+            return Ok(());
+        }
+
+        let mut store = builder.reopen(&g, store).await?;
+
+        assert_eq!(Some(log_id(1, 5)), store.read_committed().await?);
+
+        let initial = StorageHelper::new(&mut store).get_initial_state().await?;
+        assert_eq!(Some(&log_id(1, 5)), initial.committed());
+
+        // Logs are applied by raft, not by the store.
+        let (applied, _) = store.last_applied_state().await?;
+        assert_eq!(Some(log_id(1, 1)), applied);
+
+        Ok(())
+    }
+
+    /// A node removed from the cluster stays removed after restart, until it is added back.
+    ///
+    /// It is skipped if the store does not persist `removed_by`.
+    pub async fn restart_removed_by(builder: &B) -> Result<(), StorageError<C::NodeId>> {
+        let (g, mut store) = builder.build().await?;
+
+        Self::feed_10_logs_vote_self(&mut store).await?;
+        store.save_removed_by(Some(log_id(1, 5))).await?;
+
+        if store.read_removed_by().await?.is_none() {
+            tracing::info!("store does not persist removed_by, skip");
+            return Ok(());
+        }
+
+        let mut store = builder.reopen(&g, store).await?;
+
+        assert_eq!(Some(log_id(1, 5)), store.read_removed_by().await?);
+
+        let initial = StorageHelper::new(&mut store).get_initial_state().await?;
+        assert_eq!(Some(log_id(1, 5)), initial.removed_by);
+
+        store.save_removed_by(None).await?;
+        let mut store = builder.reopen(&g, store).await?;
+        assert_eq!(None, store.read_removed_by().await?);
+
+        Ok(())
+    }
+
+    pub async fn restart_built_snapshot(builder: &B) -> Result<(), StorageError<C::NodeId>> {
+        let (g, mut store) = builder.build().await?;
+
+        store
+            .apply_to_state_machine(&[
+                membership_ent::<C>(1, 1, btreeset! {1,2}),
+                normal_ent::<C, S, B, G>(builder, 1, 2),
+            ])
+            .await?;
+
+        let (meta, data) = {
+            let mut b = store.get_snapshot_builder().await;
+            let snap = b.build_snapshot().await?;
+            let data = read_snapshot_data::<C, S>(snap.snapshot, &snap.meta).await?;
+            (snap.meta, data)
+        };
+
+        let mut store = builder.reopen(&g, store).await?;
+
+        let snap = store.get_current_snapshot().await?.expect("snapshot survives restart");
+        assert_eq!(meta, snap.meta);
+        assert_eq!(data, read_snapshot_data::<C, S>(snap.snapshot, &snap.meta).await?);
+
+        Ok(())
+    }
+
+    pub async fn restart_installed_snapshot(builder: &B) -> Result<(), StorageError<C::NodeId>> {
+        let (meta, data) = Self::build_sample_snapshot(builder).await?;
+
+        let (g, mut store) = builder.build().await?;
+        Self::feed_10_logs_vote_self(&mut store).await?;
+
+        install_snapshot_data::<C, S>(&mut store, &meta, &data).await?;
+
+        let mut store = builder.reopen(&g, store).await?;
+
+        let (applied, mem) = store.last_applied_state().await?;
+        assert_eq!(meta.last_log_id, applied);
+        assert_eq!(meta.last_membership, mem);
+
+        let snap = store.get_current_snapshot().await?.expect("installed snapshot survives restart");
+        assert_eq!(meta, snap.meta);
+        assert_eq!(data, read_snapshot_data::<C, S>(snap.snapshot, &snap.meta).await?);
+
+        let initial = StorageHelper::new(&mut store).get_initial_state().await?;
+        assert_eq!(meta.last_log_id.as_ref(), initial.last_log_id());
+        assert_eq!(meta.last_log_id.as_ref(), initial.last_purged_log_id());
+
+        Ok(())
+    }
+
+    /// A restart while receiving a snapshot leaves the state as it is before receiving.
+    pub async fn restart_partially_received_snapshot(builder: &B) -> Result<(), StorageError<C::NodeId>> {
+        let (meta, data) = Self::build_sample_snapshot(builder).await?;
+
+        let (g, mut store) = builder.build().await?;
+
+        store
+            .apply_to_state_machine(&[
+                membership_ent::<C>(1, 1, btreeset! {5,6}),
+                normal_ent::<C, S, B, G>(builder, 1, 2),
+            ])
+            .await?;
+
+        let prev_meta = {
+            let mut b = store.get_snapshot_builder().await;
+            b.build_snapshot().await?.meta
+        };
+
+        tracing::info!("--- receive half of a snapshot, then restart before installing it");
+        {
+            let mut snapshot = store.begin_receiving_snapshot().await?;
+            snapshot
+                .write_all(&data[..data.len() / 2])
+                .await
+                .map_err(|e| StorageIOError::write_snapshot(meta.signature(), &e))?;
+        }
+
+        let mut store = builder.reopen(&g, store).await?;
+
+        let (applied, mem) = store.last_applied_state().await?;
+        assert_eq!(Some(log_id(1, 2)), applied);
+        assert_eq!(prev_meta.last_membership, mem);
+
+        let snap = store.get_current_snapshot().await?.expect("previous snapshot survives restart");
+        assert_eq!(prev_meta, snap.meta);
+
+        tracing::info!("--- the snapshot can be received again after restart");
+        {
+            install_snapshot_data::<C, S>(&mut store, &meta, &data).await?;
+
+            let mut store = builder.reopen(&g, store).await?;
+
+            let (applied, _) = store.last_applied_state().await?;
+            assert_eq!(meta.last_log_id, applied);
+
+            let snap = store.get_current_snapshot().await?.expect("snapshot is installed");
+            assert_eq!(meta, snap.meta);
+        }
+
+        Ok(())
+    }
+
+    /// Build a snapshot with a membership and some app data, on a store that is dropped afterwards.
+    async fn build_sample_snapshot(
+        builder: &B,
+    ) -> Result<(SnapshotMeta<C::NodeId, C::Node>, Vec<u8>), StorageError<C::NodeId>> {
+        let (_g, mut store) = builder.build().await?;
+
+        store
+            .apply_to_state_machine(&[
+                membership_ent::<C>(1, 1, btreeset! {3,4}),
+                normal_ent::<C, S, B, G>(builder, 1, 2),
+                normal_ent::<C, S, B, G>(builder, 2, 15),
+            ])
+            .await?;
+
+        let mut b = store.get_snapshot_builder().await;
+        let snap = b.build_snapshot().await?;
+        let data = read_snapshot_data::<C, S>(snap.snapshot, &snap.meta).await?;
+
+        Ok((snap.meta, data))
+    }
+}
+
 // Defensive test:
 // If a RaftStore impl support defensive check, enable it and check if it returns errors when
 // abnormal input is seen. A RaftStore with defensive check is able to expose bugs in raft core.
@@ -1538,6 +2055,67 @@ where C::NodeId: From<u64> {
     C::Entry::new_membership(log_id(term, index), Membership::new(vec![bs], ()))
 }
 
+/// Create a normal log entry with the sample app data provided by the builder, or a blank log
+/// entry if the builder does not provide one.
+fn normal_ent<C, S, B, G>(builder: &B, term: u64, index: u64) -> C::Entry
+where
+    C: RaftTypeConfig,
+    C::NodeId: From<u64>,
+    S: RaftStorage<C>,
+    B: StoreBuilder<C, S, G>,
+{
+    match builder.sample_app_data(index) {
+        None => blank_ent::<C>(term, index),
+        Some(d) => {
+            let mut ent = C::Entry::from_app_data(d);
+            ent.set_log_id(&log_id(term, index));
+            ent
+        }
+    }
+}
+
+/// Read the entire data of a snapshot.
+async fn read_snapshot_data<C, S>(
+    mut snapshot: Box<S::SnapshotData>,
+    meta: &SnapshotMeta<C::NodeId, C::Node>,
+) -> Result<Vec<u8>, StorageError<C::NodeId>>
+where
+    C: RaftTypeConfig,
+    S: RaftStorage<C>,
+{
+    let mut data = vec![];
+
+    snapshot
+        .seek(SeekFrom::Start(0))
+        .await
+        .map_err(|e| StorageIOError::read_snapshot(meta.signature(), &e))?;
+    snapshot
+        .read_to_end(&mut data)
+        .await
+        .map_err(|e| StorageIOError::read_snapshot(meta.signature(), &e))?;
+
+    Ok(data)
+}
+
+/// Install a snapshot the same way `RaftCore` does: write the data into the buffer returned by
+/// `begin_receiving_snapshot()`, shut it down, then install it.
+async fn install_snapshot_data<C, S>(
+    store: &mut S,
+    meta: &SnapshotMeta<C::NodeId, C::Node>,
+    data: &[u8],
+) -> Result<(), StorageError<C::NodeId>>
+where
+    C: RaftTypeConfig,
+    S: RaftStorage<C>,
+{
+    let mut snapshot = store.begin_receiving_snapshot().await?;
+
+    snapshot.write_all(data).await.map_err(|e| StorageIOError::write_snapshot(meta.signature(), &e))?;
+    snapshot.shutdown().await.map_err(|e| StorageIOError::write_snapshot(meta.signature(), &e))?;
+
+    store.install_snapshot(meta, snapshot).await
+}
+
 /// Block until a future is finished.
 /// The future will be running in a clean tokio runtime, to prevent an unfinished task affecting the
 /// test.
@@ -0,0 +1,190 @@
+//! Propagate the tracing context across raft RPCs.
+
+use std::fmt;
+use std::str::FromStr;
+
+use anyerror::AnyError;
+use tracing::Span;
+
+/// The context of a distributed trace, carried by a raft RPC, in the form of a [W3C
+/// `traceparent`](https://www.w3.org/TR/trace-context/#traceparent-header).
+///
+/// A leader injects the context of the current span into the RPCs it sends, and the receiving
+/// `Raft::append_entries()`, `Raft::vote()` and `Raft::install_snapshot()` continue the trace with
+/// it.
+///
+/// The context is captured only when feature `otel` is enabled, otherwise the RPCs carry `None`.
+/// With `serde`, it is serialized as a `traceparent` string, e.g.:
+/// `00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize, serde::Serialize),
+    serde(into = "String", try_from = "String")
+)]
+pub struct TraceContext {
+    /// The id of the whole trace, it must not be 0.
+    pub trace_id: u128,
+
+    /// The id of the span on the sender side, the parent of the span on the receiver side. It must
+    /// not be 0.
+    pub span_id: u64,
+
+    /// Trace flags, e.g., `0x01` for a sampled trace.
+    pub trace_flags: u8,
+}
+
+impl TraceContext {
+    /// The only `traceparent` version this implementation produces.
+    const VERSION: u8 = 0;
+
+    /// Returns the context of the current span, if it belongs to a valid trace.
+    ///
+    /// It always returns `None` if feature `otel` is disabled.
+    pub fn current() -> Option<Self> {
+        #[cfg(feature = "otel")]
+        {
+            use opentelemetry::trace::TraceContextExt;
+            use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+            let cx = Span::current().context();
+            let span = cx.span();
+            let sc = span.span_context();
+
+            if !sc.is_valid() {
+                return None;
+            }
+
+            Some(Self {
+                trace_id: u128::from_be_bytes(sc.trace_id().to_bytes()),
+                span_id: u64::from_be_bytes(sc.span_id().to_bytes()),
+                trace_flags: sc.trace_flags().to_u8(),
+            })
+        }
+
+        #[cfg(not(feature = "otel"))]
+        {
+            None
+        }
+    }
+
+    /// Create the span of a received RPC, at info level, as a child of the sender side span in
+    /// `ctx`.
+    ///
+    /// The span is independent of the level of the spans the application enables for openraft
+    /// internals, so that the remote trace is continued whenever info spans are exported.
+    pub(crate) fn rpc_span(rpc: &'static str, ctx: Option<&Self>) -> Span {
+        let span = tracing::info_span!("raft_rpc", rpc);
+        Self::continue_in(ctx, &span);
+        span
+    }
+
+    /// Continue the remote trace in `span`, by making the sender side span the parent of it.
+    ///
+    /// It does nothing if feature `otel` is disabled.
+    fn continue_in(ctx: Option<&Self>, span: &Span) {
+        #[cfg(feature = "otel")]
+        {
+            use opentelemetry::trace::SpanContext;
+            use opentelemetry::trace::SpanId;
+            use opentelemetry::trace::TraceContextExt;
+            use opentelemetry::trace::TraceFlags;
+            use opentelemetry::trace::TraceId;
+            use opentelemetry::trace::TraceState;
+            use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+            if let Some(c) = ctx {
+                let sc = SpanContext::new(
+                    TraceId::from_bytes(c.trace_id.to_be_bytes()),
+                    SpanId::from_bytes(c.span_id.to_be_bytes()),
+                    TraceFlags::new(c.trace_flags),
+                    true,
+                    TraceState::default(),
+                );
+                span.set_parent(opentelemetry::Context::new().with_remote_span_context(sc));
+            }
+        }
+
+        #[cfg(not(feature = "otel"))]
+        {
+            let _ = (ctx, span);
+        }
+    }
+}
+
+impl fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:02x}-{:032x}-{:016x}-{:02x}",
+            Self::VERSION,
+            self.trace_id,
+            self.span_id,
+            self.trace_flags
+        )
+    }
+}
+
+impl FromStr for TraceContext {
+    type Err = AnyError;
+
+    /// Parse a `traceparent`.
+    ///
+    /// A version other than `00` is accepted as long as it starts with the 4 fields defined by
+    /// version `00`, as the spec requires.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| AnyError::error(format!("invalid traceparent: {}: {}", reason, s));
+
+        let parts = s.split('-').collect::<Vec<_>>();
+        if parts.len() < 4 {
+            return Err(invalid("expect 4 fields"));
+        }
+
+        // The lengths of version, trace-id, parent-id and trace-flags.
+        for (i, len) in [2, 32, 16, 2].into_iter().enumerate() {
+            let p = parts[i];
+            if p.len() != len || !p.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b)) {
+                return Err(invalid(&format!("field {} is not {} lower case hex digits", i, len)));
+            }
+        }
+
+        let version = u8::from_str_radix(parts[0], 16).unwrap();
+        if version == 0xff {
+            return Err(invalid("version ff is forbidden"));
+        }
+        if version == Self::VERSION && parts.len() != 4 {
+            return Err(invalid("version 00 has exactly 4 fields"));
+        }
+
+        let trace_id = u128::from_str_radix(parts[1], 16).unwrap();
+        let span_id = u64::from_str_radix(parts[2], 16).unwrap();
+        let trace_flags = u8::from_str_radix(parts[3], 16).unwrap();
+
+        if trace_id == 0 {
+            return Err(invalid("trace-id is all zero"));
+        }
+        if span_id == 0 {
+            return Err(invalid("parent-id is all zero"));
+        }
+
+        Ok(Self {
+            trace_id,
+            span_id,
+            trace_flags,
+        })
+    }
+}
+
+impl From<TraceContext> for String {
+    fn from(c: TraceContext) -> Self {
+        c.to_string()
+    }
+}
+
+impl TryFrom<String> for TraceContext {
+    type Error = AnyError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
@@ -0,0 +1,77 @@
+use crate::TraceContext;
+
+fn ctx() -> TraceContext {
+    TraceContext {
+        trace_id: 0x0af7651916cd43dd8448eb211c80319c,
+        span_id: 0xb7ad6b7169203331,
+        trace_flags: 1,
+    }
+}
+
+#[test]
+fn test_trace_context_display_and_parse() -> anyhow::Result<()> {
+    let s = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+
+    assert_eq!(s, ctx().to_string());
+    assert_eq!(ctx(), s.parse::<TraceContext>()?);
+
+    let small = TraceContext {
+        trace_id: 1,
+        span_id: 2,
+        trace_flags: 0,
+    };
+    assert_eq!(
+        "00-00000000000000000000000000000001-0000000000000002-00",
+        small.to_string()
+    );
+    assert_eq!(small, small.to_string().parse::<TraceContext>()?);
+
+    // A future version may append fields
+    let got = "01-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01-foo".parse::<TraceContext>()?;
+    assert_eq!(ctx(), got);
+
+    Ok(())
+}
+
+#[test]
+fn test_trace_context_parse_invalid() -> anyhow::Result<()> {
+    let invalid = [
+        "",
+        "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331",
+        "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01-foo",
+        "ff-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+        "00-0AF7651916CD43DD8448EB211C80319C-b7ad6b7169203331-01",
+        "00-0af7651916cd43dd8448eb211c8031-b7ad6b7169203331-01",
+        "00-00000000000000000000000000000000-b7ad6b7169203331-01",
+        "00-0af7651916cd43dd8448eb211c80319c-0000000000000000-01",
+        "00-0af7651916cd43dd8448eb211c80319c-b7ad6b716920333x-01",
+    ];
+
+    for s in invalid {
+        let res = s.parse::<TraceContext>();
+        assert!(res.is_err(), "{} should be invalid", s);
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_trace_context_serde() -> anyhow::Result<()> {
+    let s = serde_json::to_string(&ctx())?;
+    assert_eq!(r#""00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01""#, s);
+
+    let got: TraceContext = serde_json::from_str(&s)?;
+    assert_eq!(ctx(), got);
+
+    let res = serde_json::from_str::<TraceContext>(r#""foo""#);
+    assert!(res.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_trace_context_current_without_span() -> anyhow::Result<()> {
+    assert_eq!(None, TraceContext::current());
+    Ok(())
+}
@@ -32,7 +32,7 @@ impl compat::testing::StoreBuilder for BuilderLatest {
     type S = Arc<crate::RocksStore>;
 
     async fn build(&self, p: &Path) -> Arc<crate::RocksStore> {
-        crate::RocksStore::new(p).await
+        crate::RocksStore::new(p).await.unwrap()
     }
 
     fn sample_app_data(&self) -> <<Self as compat::testing::StoreBuilder>::C as openraft::RaftTypeConfig>::D {
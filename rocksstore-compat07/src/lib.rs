@@ -13,11 +13,19 @@
 //! rocksdb, and then upgrade it to the latest format. You can find usages of `compat07::*` that are
 //! used in this implementation to provide compatibility with older data.
 //!
+//! The store records the [`SchemaVersion`] of its data in the `meta` column family. Data without
+//! a recorded version is written by openraft-0.7. When opened, the store rewrites older data in the
+//! latest format with [`Upgrades`], so that a store of the next format only has to register one
+//! more upgrade step.
+//!
 //! [`RaftStorage`]: openraft::RaftStorage
 //! [`openraft::compat`]: openraft::compat
 //! [`compat07::LogId`]: openraft::compat::compat07::LogId
+//! [`SchemaVersion`]: openraft::compat::schema::SchemaVersion
+//! [`Upgrades`]: openraft::compat::schema::Upgrades
 
 #[cfg(test)] mod compatibility_test;
+#[cfg(test)] mod schema_test;
 #[cfg(test)] mod test;
 
 use std::collections::BTreeMap;
@@ -34,6 +42,10 @@ use byteorder::ReadBytesExt;
 use byteorder::WriteBytesExt;
 use openraft::async_trait::async_trait;
 use openraft::compat::compat07;
+use openraft::compat::schema::DataKind;
+use openraft::compat::schema::SchemaVersion;
+use openraft::compat::schema::UpgradeError;
+use openraft::compat::schema::Upgrades;
 use openraft::compat::Upgrade;
 use openraft::AnyError;
 use openraft::EmptyNode;
@@ -56,9 +68,11 @@ use rocksdb::ColumnFamily;
 use rocksdb::ColumnFamilyDescriptor;
 use rocksdb::Direction;
 use rocksdb::Options;
+use rocksdb::WriteBatch;
 use rocksdb::DB;
 use serde::Deserialize;
 use serde::Serialize;
+use serde_json::Value;
 
 pub type RocksNodeId = u64;
 
@@ -271,6 +285,8 @@ mod meta {
     pub(crate) struct HardState {}
     pub(crate) struct Vote {}
     pub(crate) struct Snapshot {}
+    pub(crate) struct SchemaVersion {}
+    pub(crate) struct UpgradingLogs {}
 
     impl StoreMeta for LastPurged {
         const KEY: &'static str = "last_purged_log_id";
@@ -313,6 +329,24 @@ mod meta {
             ErrorSubject::None
         }
     }
+    impl StoreMeta for SchemaVersion {
+        // Absent in data written by openraft-0.7.
+        const KEY: &'static str = "schema_version";
+        type Value = openraft::compat::schema::SchemaVersion;
+
+        fn subject(_v: Option<&Self::Value>) -> ErrorSubject<RocksNodeId> {
+            ErrorSubject::Store
+        }
+    }
+    /// The index of the next log to rewrite, present only while the schema is being upgraded.
+    impl StoreMeta for UpgradingLogs {
+        const KEY: &'static str = "schema_upgrading_logs";
+        type Value = u64;
+
+        fn subject(_v: Option<&Self::Value>) -> ErrorSubject<RocksNodeId> {
+            ErrorSubject::Logs
+        }
+    }
 }
 
 impl RocksStore {
@@ -672,23 +706,170 @@ impl RaftStorage<Config> for Arc<RocksStore> {
     }
 }
 
+/// The max number of logs rewritten in one write batch when upgrading the schema.
+const UPGRADE_LOGS_BATCH: u64 = 1024;
+
 impl RocksStore {
-    pub async fn new<P: AsRef<Path>>(db_path: P) -> Arc<RocksStore> {
-        let mut db_opts = Options::default();
-        db_opts.create_missing_column_families(true);
-        db_opts.create_if_missing(true);
+    /// Open the store at `db_path`, and upgrade the data if it is written by an older version.
+    pub async fn new<P: AsRef<Path>>(db_path: P) -> Result<Arc<RocksStore>, StorageError<RocksNodeId>> {
+        let db = Arc::new(open_db(db_path));
+        let state_machine = RwLock::new(RocksStateMachine::new(db.clone()));
+        let store = RocksStore { db, state_machine };
 
-        let meta = ColumnFamilyDescriptor::new("meta", Options::default());
-        let sm_meta = ColumnFamilyDescriptor::new("sm_meta", Options::default());
-        let sm_data = ColumnFamilyDescriptor::new("sm_data", Options::default());
-        let logs = ColumnFamilyDescriptor::new("logs", Options::default());
+        store.upgrade_schema(&Upgrades::builtin())?;
 
-        let db = DB::open_cf_descriptors(&db_opts, db_path, vec![meta, sm_meta, sm_data, logs]).unwrap();
+        Ok(Arc::new(store))
+    }
 
-        let db = Arc::new(db);
-        let state_machine = RwLock::new(RocksStateMachine::new(db.clone()));
-        Arc::new(RocksStore { db, state_machine })
+    /// Rewrite the data in the latest format, if it is written by an older version.
+    ///
+    /// The logs are rewritten first, in bounded batches. The other records are rewritten in one
+    /// batch, together with the new schema version. A snapshot that can not be upgraded is
+    /// removed, it will be rebuilt.
+    fn upgrade_schema(&self, upgrades: &Upgrades) -> StorageResult<()> {
+        use meta::StoreMeta;
+
+        let version = self.get_meta::<meta::SchemaVersion>()?.unwrap_or(SchemaVersion::V07);
+        let latest = upgrades.latest();
+
+        if version == latest {
+            return Ok(());
+        }
+
+        tracing::info!("upgrade data from schema {} to {}", version, latest);
+
+        self.upgrade_logs(upgrades, version)?;
+
+        let mut batch = WriteBatch::default();
+
+        let cf_meta = self.cf_meta();
+        let cf_sm_meta = self.db.cf_handle("sm_meta").unwrap();
+
+        self.upgrade_record(&mut batch, upgrades, version, cf_meta, meta::Vote::KEY, DataKind::Vote)?;
+        self.upgrade_record(&mut batch, upgrades, version, cf_meta, meta::LastPurged::KEY, DataKind::LogId)?;
+        self.upgrade_record(&mut batch, upgrades, version, cf_sm_meta, "last_applied_log", DataKind::LogId)?;
+        self.upgrade_record(
+            &mut batch,
+            upgrades,
+            version,
+            cf_sm_meta,
+            "last_membership",
+            DataKind::StoredMembership,
+        )?;
+
+        if let Some(bs) = self.get_meta_vec::<meta::Snapshot>()? {
+            let mut snapshot: Value = serde_json::from_slice(&bs).map_err(|e| StorageIOError::read(&e))?;
+            let snapshot_meta = snapshot["meta"].take();
+
+            match upgrades.upgrade(DataKind::SnapshotMeta, version, snapshot_meta) {
+                Ok(m) => {
+                    snapshot["meta"] = m;
+                    batch.put_cf(
+                        cf_meta,
+                        meta::Snapshot::KEY,
+                        serde_json::to_vec(&snapshot).map_err(|e| StorageIOError::write(&e))?,
+                    );
+                }
+                Err(e @ UpgradeError::NotUpgradable { .. }) => {
+                    tracing::warn!("remove snapshot: {}", e);
+                    batch.delete_cf(cf_meta, meta::Snapshot::KEY);
+                }
+                Err(e) => return Err(StorageIOError::read(&e).into()),
+            }
+        }
+
+        batch.delete_cf(cf_meta, meta::UpgradingLogs::KEY);
+        batch.put_cf(
+            cf_meta,
+            meta::SchemaVersion::KEY,
+            serde_json::to_vec(&latest).map_err(|e| StorageIOError::write(&e))?,
+        );
+
+        self.db.write(batch).map_err(|e| StorageIOError::write(&e))?;
+
+        Ok(())
+    }
+
+    /// Rewrite the logs in the latest format, at most [`UPGRADE_LOGS_BATCH`] logs in a batch.
+    ///
+    /// Every batch records the index of the next log to rewrite. An upgrade interrupted by a crash
+    /// resumes from there, and a log is never upgraded twice.
+    fn upgrade_logs(&self, upgrades: &Upgrades, version: SchemaVersion) -> StorageResult<()> {
+        let mut next = self.get_meta::<meta::UpgradingLogs>()?.unwrap_or_default();
+        let start = id_to_bin(next);
+
+        let mut batch = WriteBatch::default();
+        let mut n = 0;
+
+        let it = self.db.iterator_cf(self.cf_logs(), rocksdb::IteratorMode::From(&start, Direction::Forward));
+        for item in it {
+            let (key, value) = item.map_err(read_logs_err)?;
+
+            let entry: Value = upgrades.load(DataKind::Entry, version, &value).map_err(read_logs_err)?;
+            batch.put_cf(self.cf_logs(), &key, serde_json::to_vec(&entry).map_err(read_logs_err)?);
+            next = bin_to_id(&key) + 1;
+            n += 1;
+
+            if n == UPGRADE_LOGS_BATCH {
+                self.write_upgraded_logs(std::mem::take(&mut batch), next)?;
+                n = 0;
+            }
+        }
+
+        if n > 0 {
+            self.write_upgraded_logs(batch, next)?;
+        }
+
+        Ok(())
     }
+
+    /// Write a batch of upgraded logs, along with the index of the next log to upgrade.
+    fn write_upgraded_logs(&self, mut batch: WriteBatch, next: u64) -> StorageResult<()> {
+        use meta::StoreMeta;
+
+        let next = serde_json::to_vec(&next).map_err(|e| StorageIOError::write_logs(&e))?;
+        batch.put_cf(self.cf_meta(), meta::UpgradingLogs::KEY, next);
+
+        self.db.write(batch).map_err(|e| StorageIOError::write_logs(&e))?;
+        Ok(())
+    }
+
+    /// Upgrade a single record and put the result in `batch`.
+    fn upgrade_record(
+        &self,
+        batch: &mut WriteBatch,
+        upgrades: &Upgrades,
+        version: SchemaVersion,
+        cf: &ColumnFamily,
+        key: &str,
+        kind: DataKind,
+    ) -> StorageResult<()> {
+        let bs = self.db.get_cf(cf, key).map_err(|e| StorageIOError::read(&e))?;
+        let bs = if let Some(x) = bs {
+            x
+        } else {
+            return Ok(());
+        };
+
+        let value: Value = upgrades.load(kind, version, &bs).map_err(|e| StorageIOError::read(&e))?;
+        batch.put_cf(cf, key, serde_json::to_vec(&value).map_err(|e| StorageIOError::write(&e))?);
+
+        Ok(())
+    }
+}
+
+/// Open the db with all of the column families, without upgrading the data in it.
+fn open_db<P: AsRef<Path>>(db_path: P) -> DB {
+    let mut db_opts = Options::default();
+    db_opts.create_missing_column_families(true);
+    db_opts.create_if_missing(true);
+
+    let meta = ColumnFamilyDescriptor::new("meta", Options::default());
+    let sm_meta = ColumnFamilyDescriptor::new("sm_meta", Options::default());
+    let sm_data = ColumnFamilyDescriptor::new("sm_data", Options::default());
+    let logs = ColumnFamilyDescriptor::new("logs", Options::default());
+
+    DB::open_cf_descriptors(&db_opts, db_path, vec![meta, sm_meta, sm_data, logs]).unwrap()
 }
 
 fn read_logs_err(e: impl Error + 'static) -> StorageError<RocksNodeId> {
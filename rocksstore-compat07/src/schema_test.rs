@@ -0,0 +1,121 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use openraft::compat;
+use openraft::compat::schema::SchemaVersion;
+use openraft::compat::testing::Fixture;
+use openraft::compat::testing::SchemaStoreBuilder;
+use serde_json::json;
+use serde_json::Value;
+
+use crate::id_to_bin;
+use crate::open_db;
+
+/// Writes fixtures in the layout of rocksstore, which records the schema version since v0.8.
+struct SchemaBuilder;
+
+#[async_trait::async_trait]
+impl SchemaStoreBuilder for SchemaBuilder {
+    type C = crate::Config;
+    type S = Arc<crate::RocksStore>;
+
+    async fn write_fixture(&self, p: &Path, fixture: &Fixture) -> anyhow::Result<()> {
+        let db = open_db(p);
+
+        let meta = db.cf_handle("meta").unwrap();
+        let sm_meta = db.cf_handle("sm_meta").unwrap();
+        let logs = db.cf_handle("logs").unwrap();
+
+        let put = |v: &Value| serde_json::to_vec(v).unwrap();
+
+        db.put_cf(meta, "hard_state", put(&fixture.vote))?;
+        db.put_cf(meta, "last_purged_log_id", put(&fixture.last_purged_log_id))?;
+        db.put_cf(
+            meta,
+            "snapshot",
+            put(&json!({"meta": fixture.snapshot_meta, "data": []})),
+        )?;
+
+        db.put_cf(sm_meta, "last_applied_log", put(&fixture.last_applied))?;
+        db.put_cf(sm_meta, "last_membership", put(&fixture.last_membership))?;
+
+        for ent in &fixture.logs {
+            db.put_cf(logs, id_to_bin(Fixture::log_index(ent)), put(ent))?;
+        }
+
+        if fixture.version > SchemaVersion::V07 {
+            db.put_cf(meta, "schema_version", serde_json::to_vec(&fixture.version)?)?;
+        }
+
+        Ok(())
+    }
+
+    async fn build(&self, p: &Path) -> Arc<crate::RocksStore> {
+        crate::RocksStore::new(p).await.unwrap()
+    }
+}
+
+#[tokio::test]
+async fn test_load_every_schema_version() -> anyhow::Result<()> {
+    let suite = compat::testing::SchemaSuite { builder: SchemaBuilder };
+
+    suite.test_all().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_record_schema_version() -> anyhow::Result<()> {
+    let td = tempfile::TempDir::new()?;
+
+    {
+        let _store = crate::RocksStore::new(td.path()).await?;
+    }
+
+    let db = open_db(td.path());
+    let got = db.get_cf(db.cf_handle("meta").unwrap(), "schema_version")?;
+    let got = got.map(|x| serde_json::from_slice::<SchemaVersion>(&x)).transpose()?;
+
+    assert_eq!(Some(SchemaVersion::CURRENT), got);
+
+    Ok(())
+}
+
+/// An upgrade interrupted after a batch of logs is written resumes from the next log.
+#[tokio::test]
+async fn test_resume_interrupted_upgrade() -> anyhow::Result<()> {
+    let td = tempfile::TempDir::new()?;
+
+    let fixtures = compat::testing::fixtures();
+    let (v07, v08) = (&fixtures[0], &fixtures[1]);
+
+    SchemaBuilder.write_fixture(td.path(), v07).await?;
+
+    {
+        let db = open_db(td.path());
+
+        // The first batch, log 2, is written before a crash.
+        let logs = db.cf_handle("logs").unwrap();
+        let meta = db.cf_handle("meta").unwrap();
+
+        db.put_cf(logs, id_to_bin(2), serde_json::to_vec(&v08.logs[0])?)?;
+        db.put_cf(meta, "schema_upgrading_logs", serde_json::to_vec(&3u64)?)?;
+    }
+
+    {
+        let _store = crate::RocksStore::new(td.path()).await?;
+    }
+
+    let db = open_db(td.path());
+    let meta = db.cf_handle("meta").unwrap();
+    let logs = db.cf_handle("logs").unwrap();
+
+    assert!(db.get_cf(meta, "schema_upgrading_logs")?.is_none());
+
+    for ent in &v08.logs {
+        let got = db.get_cf(logs, id_to_bin(Fixture::log_index(ent)))?.unwrap();
+        assert_eq!(ent, &serde_json::from_slice::<Value>(&got)?);
+    }
+
+    Ok(())
+}
@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use openraft::testing::sample_key_value;
 use openraft::testing::StoreBuilder;
 use openraft::testing::Suite;
 use openraft::StorageError;
@@ -8,6 +9,7 @@ use tempfile::TempDir;
 
 use crate::Config;
 use crate::RocksNodeId;
+use crate::RocksRequest;
 use crate::RocksStore;
 
 struct RocksBuilder {}
@@ -15,9 +17,14 @@ struct RocksBuilder {}
 impl StoreBuilder<Config, Arc<RocksStore>, TempDir> for RocksBuilder {
     async fn build(&self) -> Result<(TempDir, Arc<RocksStore>), StorageError<RocksNodeId>> {
         let td = tempfile::TempDir::new().expect("couldn't create temp dir");
-        let store = RocksStore::new(td.path()).await;
+        let store = RocksStore::new(td.path()).await?;
         Ok((td, store))
     }
+
+    fn sample_app_data(&self, index: u64) -> Option<RocksRequest> {
+        let (key, value) = sample_key_value(index);
+        Some(RocksRequest::Set { key, value })
+    }
 }
 
 #[test]
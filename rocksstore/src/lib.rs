@@ -3,13 +3,14 @@
 
 #[cfg(test)] mod test;
 
-use std::collections::BTreeMap;
 use std::error::Error;
 use std::fmt::Debug;
-use std::io::Cursor;
+use std::io::SeekFrom;
 use std::ops::RangeBounds;
 use std::path::Path;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::Mutex;
 
 use async_std::sync::RwLock;
 use byteorder::BigEndian;
@@ -36,9 +37,17 @@ use rocksdb::ColumnFamily;
 use rocksdb::ColumnFamilyDescriptor;
 use rocksdb::Direction;
 use rocksdb::Options;
+use rocksdb::SstFileWriter;
+use rocksdb::WriteBatch;
 use rocksdb::DB;
 use serde::Deserialize;
 use serde::Serialize;
+use tokio::fs::File;
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncSeekExt;
+use tokio::io::AsyncWriteExt;
+
+pub use openraft_codec::Codec;
 
 pub type RocksNodeId = u64;
 
@@ -71,58 +80,20 @@ pub struct RocksResponse {
     pub value: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct RocksSnapshot {
-    pub meta: SnapshotMeta<RocksNodeId, BasicNode>,
-
-    /// The data of the state machine at the time of this snapshot.
-    pub data: Vec<u8>,
-}
-
 /**
  * Here defines a state machine of the raft, this state represents a copy of the data
- * between each node. Note that we are using `serde` to serialize the `data`, which has
- * a implementation to be serialized. Note that for this test we set both the key and
- * value as String, but you could set any type of value that has the serialization impl.
+ * between each node.
+ *
+ * A snapshot of the state machine is an SST file that contains every key of the application
+ * data. The last applied log id and the last membership are carried by the `SnapshotMeta`.
  */
-#[derive(Serialize, Deserialize, Debug, Default, Clone)]
-pub struct SerializableRocksStateMachine {
-    pub last_applied_log: Option<LogId<RocksNodeId>>,
-
-    pub last_membership: StoredMembership<RocksNodeId, BasicNode>,
-
-    /// Application data.
-    pub data: BTreeMap<String, String>,
-}
-
-impl From<&RocksStateMachine> for SerializableRocksStateMachine {
-    fn from(state: &RocksStateMachine) -> Self {
-        let mut data = BTreeMap::new();
-
-        let it = state.db.iterator_cf(state.cf_sm_data(), rocksdb::IteratorMode::Start);
-
-        for item in it {
-            let (key, value) = item.expect("invalid kv record");
-
-            let key: &[u8] = &key;
-            let value: &[u8] = &value;
-            data.insert(
-                String::from_utf8(key.to_vec()).expect("invalid key"),
-                String::from_utf8(value.to_vec()).expect("invalid data"),
-            );
-        }
-        Self {
-            last_applied_log: state.get_last_applied_log().expect("last_applied_log"),
-            last_membership: state.get_last_membership().expect("last_membership"),
-            data,
-        }
-    }
-}
-
 #[derive(Debug, Clone)]
 pub struct RocksStateMachine {
     /// Application data.
     pub db: Arc<DB>,
+
+    /// The codec to encode state machine meta data.
+    codec: Codec,
 }
 
 fn sm_r_err<E: Error + 'static>(e: E) -> StorageError<RocksNodeId> {
@@ -144,7 +115,7 @@ impl RocksStateMachine {
     fn get_last_membership(&self) -> StorageResult<StoredMembership<RocksNodeId, BasicNode>> {
         self.db.get_cf(self.cf_sm_meta(), "last_membership".as_bytes()).map_err(sm_r_err).and_then(|value| {
             value
-                .map(|v| serde_json::from_slice(&v).map_err(sm_r_err))
+                .map(|v| Codec::decode(&v).map_err(sm_r_err))
                 .unwrap_or_else(|| Ok(StoredMembership::default()))
         })
     }
@@ -154,7 +125,7 @@ impl RocksStateMachine {
             .put_cf(
                 self.cf_sm_meta(),
                 "last_membership".as_bytes(),
-                serde_json::to_vec(&membership).map_err(sm_w_err)?,
+                self.codec.encode(&membership).map_err(sm_w_err)?,
             )
             .map_err(sm_w_err)
     }
@@ -163,7 +134,7 @@ impl RocksStateMachine {
         self.db
             .get_cf(self.cf_sm_meta(), "last_applied_log".as_bytes())
             .map_err(sm_r_err)
-            .and_then(|value| value.map(|v| serde_json::from_slice(&v).map_err(sm_r_err)).transpose())
+            .and_then(|value| value.map(|v| Codec::decode(&v).map_err(sm_r_err)).transpose())
     }
 
     fn set_last_applied_log(&self, log_id: LogId<RocksNodeId>) -> StorageResult<()> {
@@ -171,29 +142,116 @@ impl RocksStateMachine {
             .put_cf(
                 self.cf_sm_meta(),
                 "last_applied_log".as_bytes(),
-                serde_json::to_vec(&log_id).map_err(sm_w_err)?,
+                self.codec.encode(&log_id).map_err(sm_w_err)?,
             )
             .map_err(sm_w_err)
     }
 
-    fn from_serializable(sm: SerializableRocksStateMachine, db: Arc<DB>) -> StorageResult<Self> {
-        let r = Self { db };
+    /// Write all the application data into an SST file at `path`.
+    ///
+    /// An SST file can not be empty, thus an empty file is created if there is no data.
+    fn write_sst(&self, path: &Path) -> StorageResult<()> {
+        let mut it = self.db.iterator_cf(self.cf_sm_data(), rocksdb::IteratorMode::Start).peekable();
 
-        for (key, value) in sm.data {
-            r.db.put_cf(r.cf_sm_data(), key.as_bytes(), value.as_bytes()).map_err(sm_w_err)?;
+        if it.peek().is_none() {
+            std::fs::File::create(path).map_err(sm_r_err)?;
+            return Ok(());
         }
 
-        if let Some(log_id) = sm.last_applied_log {
-            r.set_last_applied_log(log_id)?;
+        let opts = Options::default();
+        let mut writer = SstFileWriter::create(&opts);
+        writer.open(path).map_err(sm_r_err)?;
+
+        for item in it {
+            let (key, value) = item.map_err(sm_r_err)?;
+            writer.put(key, value).map_err(sm_r_err)?;
         }
 
-        r.set_last_membership(sm.last_membership)?;
+        writer.finish().map_err(sm_r_err)?;
+        Ok(())
+    }
 
-        Ok(r)
+    /// Replace all the application data with the content of a snapshot file written by
+    /// `write_sst()`, and reset the meta data of the state machine to the snapshot meta.
+    ///
+    /// `path` is a file in `snapshot_dir`. It has to be kept until the ingestion is done.
+    fn ingest_sst(
+        &self,
+        snapshot_dir: &Path,
+        path: &Path,
+        meta: &SnapshotMeta<RocksNodeId, BasicNode>,
+    ) -> StorageResult<()> {
+        self.begin_ingesting_sst(path, meta)?;
+        self.finish_ingesting_sst(snapshot_dir)
     }
 
-    fn new(db: Arc<DB>) -> RocksStateMachine {
-        Self { db }
+    /// Remove all the application data and save the snapshot meta in one atomic write, along with
+    /// the name of the file to ingest.
+    ///
+    /// If the node crashes before the file is ingested, the ingestion is finished when the store
+    /// is reopened. Thus the state machine never ends up empty with a last applied log id that
+    /// refers to the old data.
+    fn begin_ingesting_sst(&self, path: &Path, meta: &SnapshotMeta<RocksNodeId, BasicNode>) -> StorageResult<()> {
+        let mut batch = WriteBatch::default();
+
+        let last = self.db.iterator_cf(self.cf_sm_data(), rocksdb::IteratorMode::End).next();
+        if let Some(res) = last {
+            let (last_key, _) = res.map_err(sm_r_err)?;
+
+            // The upper bound is exclusive: make it greater than the last key.
+            let mut end = last_key.to_vec();
+            end.push(0);
+            batch.delete_range_cf(self.cf_sm_data(), Vec::new(), end);
+        }
+
+        match meta.last_log_id {
+            Some(log_id) => batch.put_cf(
+                self.cf_sm_meta(),
+                "last_applied_log".as_bytes(),
+                self.codec.encode(&log_id).map_err(sm_w_err)?,
+            ),
+            None => batch.delete_cf(self.cf_sm_meta(), "last_applied_log".as_bytes()),
+        }
+        batch.put_cf(
+            self.cf_sm_meta(),
+            "last_membership".as_bytes(),
+            self.codec.encode(&meta.last_membership).map_err(sm_w_err)?,
+        );
+
+        let file_name = path.file_name().and_then(|x| x.to_str()).expect("snapshot file name is valid utf8");
+        batch.put_cf(self.cf_sm_meta(), INGESTING_SNAPSHOT.as_bytes(), file_name.as_bytes());
+
+        self.db.write(batch).map_err(sm_w_err)?;
+        self.db.flush_wal(true).map_err(sm_w_err)?;
+
+        Ok(())
+    }
+
+    /// Ingest the snapshot file saved by `begin_ingesting_sst()`, if there is one.
+    ///
+    /// It is idempotent: ingesting a file again overrides the keys with the same values.
+    fn finish_ingesting_sst(&self, snapshot_dir: &Path) -> StorageResult<()> {
+        let file_name = self.db.get_cf(self.cf_sm_meta(), INGESTING_SNAPSHOT.as_bytes()).map_err(sm_r_err)?;
+        let file_name = match file_name {
+            None => return Ok(()),
+            Some(x) => String::from_utf8(x).map_err(sm_r_err)?,
+        };
+
+        let path = snapshot_dir.join(file_name);
+        tracing::info!("ingest snapshot file: {}", path.display());
+
+        let size = std::fs::metadata(&path).map_err(sm_r_err)?.len();
+        if size > 0 {
+            self.db.ingest_external_file_cf(self.cf_sm_data(), vec![&path]).map_err(sm_w_err)?;
+        }
+
+        self.db.delete_cf(self.cf_sm_meta(), INGESTING_SNAPSHOT.as_bytes()).map_err(sm_w_err)?;
+
+        Ok(())
+    }
+
+    fn new(db: Arc<DB>, codec: Codec) -> RocksStateMachine {
+        Self { db, codec }
     }
 
     fn insert(&self, key: String, value: String) -> StorageResult<()> {
@@ -215,6 +273,15 @@ impl RocksStateMachine {
 pub struct RocksStore {
     db: Arc<DB>,
 
+    /// The codec to encode new values. Values encoded with other codecs are still readable.
+    codec: Codec,
+
+    /// The directory of snapshot files.
+    snapshot_dir: PathBuf,
+
+    /// Serializes replacing the current snapshot and removing the files of the old ones.
+    snapshot_switch: Mutex<()>,
+
     /// The Raft state machine.
     pub state_machine: RwLock<RocksStateMachine>,
 }
@@ -240,8 +307,8 @@ mod meta {
     use openraft::ErrorSubject;
     use openraft::LogId;
 
+    use crate::BasicNode;
     use crate::RocksNodeId;
-    use crate::RocksSnapshot;
 
     /// Defines metadata key and value
     pub(crate) trait StoreMeta {
@@ -258,7 +325,11 @@ mod meta {
     pub(crate) struct LastPurged {}
     pub(crate) struct SnapshotIndex {}
     pub(crate) struct Vote {}
+    pub(crate) struct Committed {}
+    pub(crate) struct RemovedBy {}
+    pub(crate) struct MembershipLogIds {}
     pub(crate) struct Snapshot {}
+    pub(crate) struct ReceivingSnapshot {}
 
     impl StoreMeta for LastPurged {
         const KEY: &'static str = "last_purged_log_id";
@@ -284,12 +355,52 @@ mod meta {
             ErrorSubject::Vote
         }
     }
+    /// The log ids of the last membership entries in the log, in ascending order.
+    impl StoreMeta for MembershipLogIds {
+        const KEY: &'static str = "membership_log_ids";
+        type Value = Vec<LogId<u64>>;
+
+        fn subject(_v: Option<&Self::Value>) -> ErrorSubject<RocksNodeId> {
+            ErrorSubject::Logs
+        }
+    }
+    impl StoreMeta for Committed {
+        const KEY: &'static str = "committed";
+        type Value = Option<LogId<u64>>;
+
+        fn subject(_v: Option<&Self::Value>) -> ErrorSubject<RocksNodeId> {
+            ErrorSubject::Store
+        }
+    }
+    /// The log id of the membership that removes this node from the cluster.
+    impl StoreMeta for RemovedBy {
+        const KEY: &'static str = "removed_by";
+        type Value = Option<LogId<u64>>;
+
+        fn subject(_v: Option<&Self::Value>) -> ErrorSubject<RocksNodeId> {
+            ErrorSubject::Store
+        }
+    }
+    /// The meta of the current snapshot, the data is stored in a file in the snapshot dir.
     impl StoreMeta for Snapshot {
-        const KEY: &'static str = "snapshot";
-        type Value = RocksSnapshot;
+        const KEY: &'static str = "snapshot_meta";
+        type Value = openraft::SnapshotMeta<RocksNodeId, BasicNode>;
 
         fn subject(v: Option<&Self::Value>) -> ErrorSubject<RocksNodeId> {
-            ErrorSubject::Snapshot(v.unwrap().meta.signature())
+            match v {
+                Some(meta) => ErrorSubject::Snapshot(meta.signature()),
+                None => ErrorSubject::Store,
+            }
+        }
+    }
+    /// The id of the snapshot whose data is in the receiving file, if it is known, and the length
+    /// of the data that is synced to the file.
+    impl StoreMeta for ReceivingSnapshot {
+        const KEY: &'static str = "receiving_snapshot";
+        type Value = Option<(openraft::SnapshotId, u64)>;
+
+        fn subject(_v: Option<&Self::Value>) -> ErrorSubject<RocksNodeId> {
+            ErrorSubject::Store
         }
     }
 }
@@ -315,7 +426,7 @@ impl RocksStore {
         let t = match v {
             None => None,
             Some(bytes) => Some(
-                serde_json::from_slice(&bytes)
+                Codec::decode(&bytes)
                     .map_err(|e| StorageIOError::new(M::subject(None), ErrorVerb::Read, AnyError::new(&e)))?,
             ),
         };
@@ -324,17 +435,157 @@ impl RocksStore {
 
     /// Save a store metadata.
     fn put_meta<M: meta::StoreMeta>(&self, value: &M::Value) -> Result<(), StorageError<RocksNodeId>> {
-        let json_value = serde_json::to_vec(value)
+        let encoded = self
+            .codec
+            .encode(value)
             .map_err(|e| StorageIOError::new(M::subject(Some(value)), ErrorVerb::Write, AnyError::new(&e)))?;
 
         self.db
-            .put_cf(self.cf_meta(), M::KEY, json_value)
+            .put_cf(self.cf_meta(), M::KEY, encoded)
             .map_err(|e| StorageIOError::new(M::subject(Some(value)), ErrorVerb::Write, AnyError::new(&e)))?;
 
         Ok(())
     }
+
+    /// Build the membership log ids by scanning the log, if they are not saved.
+    ///
+    /// It happens only once, when a db written by an older version is opened.
+    fn init_membership_log_ids(&self) -> StorageResult<()> {
+        if self.get_meta::<meta::MembershipLogIds>()?.is_some() {
+            return Ok(());
+        }
+
+        let mut ids = vec![];
+        for item_res in self.db.iterator_cf(self.cf_logs(), rocksdb::IteratorMode::Start) {
+            let (_, val) = item_res.map_err(read_logs_err)?;
+            let entry: Entry<Config> = Codec::decode(&val).map_err(read_logs_err)?;
+            if let EntryPayload::Membership(_) = entry.payload {
+                ids.push(entry.log_id);
+            }
+        }
+        trim_membership_log_ids(&mut ids);
+
+        self.put_meta::<meta::MembershipLogIds>(&ids)
+    }
+
+    /// Update the saved membership log ids with `f`.
+    fn update_membership_log_ids(&self, f: impl FnOnce(&mut Vec<LogId<RocksNodeId>>)) -> StorageResult<()> {
+        let mut ids = self.get_meta::<meta::MembershipLogIds>()?.unwrap_or_default();
+        f(&mut ids);
+        trim_membership_log_ids(&mut ids);
+        self.put_meta::<meta::MembershipLogIds>(&ids)
+    }
+
+    /// The path of the file of a snapshot.
+    fn snapshot_path(&self, snapshot_id: &str) -> PathBuf {
+        // The snapshot id may be built by another node: hex encode it to make a safe file name, that
+        // is distinct for every id.
+        let name: String = snapshot_id.bytes().map(|b| format!("{:02x}", b)).collect();
+        self.snapshot_dir.join(format!("{}{}.sst", SNAPSHOT_FILE_PREFIX, name))
+    }
+
+    /// The path of the file of the snapshot being received.
+    fn receiving_snapshot_path(&self) -> PathBuf {
+        self.snapshot_dir.join("receiving.sst")
+    }
+
+    /// Move a finished snapshot file to its final path and make it the current snapshot, if it is
+    /// not older than the current one, and open it.
+    ///
+    /// The file of a snapshot older than the current one is removed once it is opened.
+    fn finish_building_snapshot(
+        &self,
+        building: &Path,
+        meta: &SnapshotMeta<RocksNodeId, BasicNode>,
+    ) -> StorageResult<std::fs::File> {
+        let _switch = self.snapshot_switch.lock().unwrap();
+
+        let path = self.snapshot_path(&meta.snapshot_id);
+        std::fs::rename(building, &path).map_err(|e| StorageIOError::write_snapshot(meta.signature(), &e))?;
+
+        let f = std::fs::File::open(&path).map_err(|e| StorageIOError::read_snapshot(meta.signature(), &e))?;
+
+        let current = self.get_meta::<meta::Snapshot>()?;
+        if current.map(|c| c.last_log_id > meta.last_log_id).unwrap_or(false) {
+            tracing::info!("a newer snapshot is installed, do not replace it with: {:?}", meta);
+            std::fs::remove_file(&path).map_err(|e| StorageIOError::write_snapshot(meta.signature(), &e))?;
+            return Ok(f);
+        }
+
+        self.set_current_snapshot(meta)?;
+        Ok(f)
+    }
+
+    /// Move the received snapshot file to its final path, replace the state machine with it and
+    /// make it the current snapshot.
+    fn finish_receiving_snapshot(
+        &self,
+        sm: &RocksStateMachine,
+        meta: &SnapshotMeta<RocksNodeId, BasicNode>,
+    ) -> StorageResult<()> {
+        let _switch = self.snapshot_switch.lock().unwrap();
+
+        let path = self.snapshot_path(&meta.snapshot_id);
+        std::fs::rename(self.receiving_snapshot_path(), &path)
+            .map_err(|e| StorageIOError::write_snapshot(meta.signature(), &e))?;
+        self.put_meta::<meta::ReceivingSnapshot>(&None)?;
+
+        sm.ingest_sst(&self.snapshot_dir, &path, meta)?;
+        self.db.flush_wal(true).map_err(sm_w_err)?;
+
+        self.set_current_snapshot(meta)
+    }
+
+    /// Save the meta of the current snapshot and remove the files of all the other snapshots.
+    ///
+    /// The caller must hold `snapshot_switch`.
+    fn set_current_snapshot(&self, meta: &SnapshotMeta<RocksNodeId, BasicNode>) -> StorageResult<()> {
+        self.put_meta::<meta::Snapshot>(meta)?;
+
+        let current = self.snapshot_path(&meta.snapshot_id);
+
+        let dir = std::fs::read_dir(&self.snapshot_dir).map_err(|e| StorageIOError::write(&e))?;
+        for ent in dir {
+            let path = ent.map_err(|e| StorageIOError::write(&e))?.path();
+
+            let is_snapshot = path
+                .file_name()
+                .and_then(|x| x.to_str())
+                .map(|x| x.starts_with(SNAPSHOT_FILE_PREFIX))
+                .unwrap_or(false);
+
+            // Snapshots being built or received are not touched.
+            if !is_snapshot || path == current {
+                continue;
+            }
+
+            tracing::info!("remove old snapshot file: {}", path.display());
+            std::fs::remove_file(&path).map_err(|e| StorageIOError::write(&e))?;
+        }
+
+        Ok(())
+    }
 }
 
+/// The number of the last membership log ids to keep.
+///
+/// Raft uses only the last two membership logs. One more is kept so that the last two are still
+/// known after an uncommitted membership log is deleted by `delete_conflict_logs_since()`.
+const MEMBERSHIP_LOG_IDS_TO_KEEP: usize = 3;
+
+/// Remove the membership log ids that are older than the last `MEMBERSHIP_LOG_IDS_TO_KEEP`.
+fn trim_membership_log_ids(ids: &mut Vec<LogId<RocksNodeId>>) {
+    if ids.len() > MEMBERSHIP_LOG_IDS_TO_KEEP {
+        ids.drain(..ids.len() - MEMBERSHIP_LOG_IDS_TO_KEEP);
+    }
+}
+
+/// The file name prefix of a finished snapshot.
+const SNAPSHOT_FILE_PREFIX: &str = "snapshot-";
+
+/// The key in `sm_meta` of the name of the snapshot file that is being ingested.
+const INGESTING_SNAPSHOT: &str = "ingesting_snapshot";
+
 #[async_trait]
 impl RaftLogReader<Config> for Arc<RocksStore> {
     async fn get_log_state(&mut self) -> StorageResult<LogState<Config>> {
@@ -344,7 +595,7 @@ impl RaftLogReader<Config> for Arc<RocksStore> {
             None => None,
             Some(res) => {
                 let (_log_index, entry_bytes) = res.map_err(read_logs_err)?;
-                let ent = serde_json::from_slice::<Entry<Config>>(&entry_bytes).map_err(read_logs_err)?;
+                let ent = Codec::decode::<Entry<Config>>(&entry_bytes).map_err(read_logs_err)?;
                 Some(ent.log_id)
             }
         };
@@ -383,7 +634,7 @@ impl RaftLogReader<Config> for Arc<RocksStore> {
                 break;
             }
 
-            let entry: Entry<_> = serde_json::from_slice(&val).map_err(read_logs_err)?;
+            let entry: Entry<_> = Codec::decode(&val).map_err(read_logs_err)?;
 
             assert_eq!(id, entry.log_id.index);
 
@@ -394,28 +645,27 @@ impl RaftLogReader<Config> for Arc<RocksStore> {
 }
 
 #[async_trait]
-impl RaftSnapshotBuilder<Config, Cursor<Vec<u8>>> for Arc<RocksStore> {
+impl RaftSnapshotBuilder<Config, File> for Arc<RocksStore> {
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn build_snapshot(
-        &mut self,
-    ) -> Result<Snapshot<RocksNodeId, BasicNode, Cursor<Vec<u8>>>, StorageError<RocksNodeId>> {
-        let data;
+    async fn build_snapshot(&mut self) -> Result<Snapshot<RocksNodeId, BasicNode, File>, StorageError<RocksNodeId>> {
+        // TODO: we probably want this to be atomic.
+        let snapshot_idx: u64 = self.get_meta::<meta::SnapshotIndex>()?.unwrap_or_default() + 1;
+        self.put_meta::<meta::SnapshotIndex>(&snapshot_idx)?;
+
+        let building = self.snapshot_dir.join(format!("building-{}.sst", snapshot_idx));
+
         let last_applied_log;
         let last_membership;
 
         {
-            // Serialize the data of the state machine.
-            let state_machine = SerializableRocksStateMachine::from(&*self.state_machine.read().await);
-            data = serde_json::to_vec(&state_machine).map_err(|e| StorageIOError::read_state_machine(&e))?;
+            // Write the data of the state machine to a file, without loading it into memory.
+            let state_machine = self.state_machine.read().await;
+            state_machine.write_sst(&building)?;
 
-            last_applied_log = state_machine.last_applied_log;
-            last_membership = state_machine.last_membership;
+            last_applied_log = state_machine.get_last_applied_log()?;
+            last_membership = state_machine.get_last_membership()?;
         }
 
-        // TODO: we probably want this to be atomic.
-        let snapshot_idx: u64 = self.get_meta::<meta::SnapshotIndex>()?.unwrap_or_default() + 1;
-        self.put_meta::<meta::SnapshotIndex>(&snapshot_idx)?;
-
         let snapshot_id = if let Some(last) = last_applied_log {
             format!("{}-{}-{}", last.leader_id, last.index, snapshot_idx)
         } else {
@@ -428,23 +678,18 @@ impl RaftSnapshotBuilder<Config, Cursor<Vec<u8>>> for Arc<RocksStore> {
             snapshot_id,
         };
 
-        let snapshot = RocksSnapshot {
-            meta: meta.clone(),
-            data: data.clone(),
-        };
-
-        self.put_meta::<meta::Snapshot>(&snapshot)?;
+        let f = self.finish_building_snapshot(&building, &meta)?;
 
         Ok(Snapshot {
             meta,
-            snapshot: Box::new(Cursor::new(data)),
+            snapshot: Box::new(File::from_std(f)),
         })
     }
 }
 
 #[async_trait]
 impl RaftStorage<Config> for Arc<RocksStore> {
-    type SnapshotData = Cursor<Vec<u8>>;
+    type SnapshotData = File;
     type LogReader = Self;
     type SnapshotBuilder = Self;
 
@@ -457,19 +702,59 @@ impl RaftStorage<Config> for Arc<RocksStore> {
         self.get_meta::<meta::Vote>()
     }
 
+    async fn save_committed(&mut self, committed: Option<LogId<RocksNodeId>>) -> Result<(), StorageError<RocksNodeId>> {
+        // It does not have to be flushed: a lost committed log id only delays applying.
+        self.put_meta::<meta::Committed>(&committed)
+    }
+
+    async fn read_committed(&mut self) -> Result<Option<LogId<RocksNodeId>>, StorageError<RocksNodeId>> {
+        Ok(self.get_meta::<meta::Committed>()?.flatten())
+    }
+
+    async fn save_removed_by(
+        &mut self,
+        removed_by: Option<LogId<RocksNodeId>>,
+    ) -> Result<(), StorageError<RocksNodeId>> {
+        self.put_meta::<meta::RemovedBy>(&removed_by)
+    }
+
+    async fn read_removed_by(&mut self) -> Result<Option<LogId<RocksNodeId>>, StorageError<RocksNodeId>> {
+        Ok(self.get_meta::<meta::RemovedBy>()?.flatten())
+    }
+
+    async fn read_membership_log_ids(&mut self) -> Result<Option<Vec<LogId<RocksNodeId>>>, StorageError<RocksNodeId>> {
+        self.get_meta::<meta::MembershipLogIds>()
+    }
+
     #[tracing::instrument(level = "trace", skip(self, entries))]
     async fn append_to_log(&mut self, entries: &[Entry<Config>]) -> StorageResult<()> {
+        let mut ids = self.get_meta::<meta::MembershipLogIds>()?.unwrap_or_default();
+
+        // Write the logs and the membership log ids in one batch, so that they are always consistent.
+        let mut batch = WriteBatch::default();
         for entry in entries {
             let id = id_to_bin(entry.log_id.index);
             assert_eq!(bin_to_id(&id), entry.log_id.index);
-            self.db
-                .put_cf(
-                    self.cf_logs(),
-                    id,
-                    serde_json::to_vec(entry).map_err(|e| StorageIOError::write_logs(&e))?,
-                )
-                .map_err(|e| StorageIOError::write_logs(&e))?;
+            batch.put_cf(
+                self.cf_logs(),
+                id,
+                self.codec.encode(entry).map_err(|e| StorageIOError::write_logs(&e))?,
+            );
+
+            ids.retain(|x| x.index != entry.log_id.index);
+            if let EntryPayload::Membership(_) = entry.payload {
+                ids.push(entry.log_id);
+            }
         }
+
+        trim_membership_log_ids(&mut ids);
+        batch.put_cf(
+            self.cf_meta(),
+            <meta::MembershipLogIds as meta::StoreMeta>::KEY,
+            self.codec.encode(&ids).map_err(|e| StorageIOError::write_logs(&e))?,
+        );
+
+        self.db.write(batch).map_err(|e| StorageIOError::write_logs(&e))?;
         Ok(())
     }
 
@@ -479,9 +764,9 @@ impl RaftStorage<Config> for Arc<RocksStore> {
 
         let from = id_to_bin(log_id.index);
         let to = id_to_bin(0xff_ff_ff_ff_ff_ff_ff_ff);
-        self.db
-            .delete_range_cf(self.cf_logs(), &from, &to)
-            .map_err(|e| StorageIOError::write_logs(&e).into())
+        self.db.delete_range_cf(self.cf_logs(), &from, &to).map_err(|e| StorageIOError::write_logs(&e))?;
+
+        self.update_membership_log_ids(|ids| ids.retain(|x| x.index < log_id.index))
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
@@ -492,9 +777,9 @@ impl RaftStorage<Config> for Arc<RocksStore> {
 
         let from = id_to_bin(0);
         let to = id_to_bin(log_id.index + 1);
-        self.db
-            .delete_range_cf(self.cf_logs(), &from, &to)
-            .map_err(|e| StorageIOError::write_logs(&e).into())
+        self.db.delete_range_cf(self.cf_logs(), &from, &to).map_err(|e| StorageIOError::write_logs(&e))?;
+
+        self.update_membership_log_ids(|ids| ids.retain(|x| x.index > log_id.index))
     }
 
     async fn last_applied_state(
@@ -543,7 +828,65 @@ impl RaftStorage<Config> for Arc<RocksStore> {
 
     #[tracing::instrument(level = "trace", skip(self))]
     async fn begin_receiving_snapshot(&mut self) -> Result<Box<Self::SnapshotData>, StorageError<RocksNodeId>> {
-        Ok(Box::new(Cursor::new(Vec::new())))
+        // The data in the receiving file is about to be discarded: it belongs to no snapshot.
+        self.put_meta::<meta::ReceivingSnapshot>(&None)?;
+
+        // Only one snapshot is received at a time: a new one overrides the unfinished one.
+        let f = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(self.receiving_snapshot_path())
+            .await
+            .map_err(|e| StorageIOError::write(&e))?;
+
+        Ok(Box::new(f))
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn resume_receiving_snapshot(
+        &mut self,
+        meta: &SnapshotMeta<RocksNodeId, BasicNode>,
+    ) -> Result<(Box<Self::SnapshotData>, u64), StorageError<RocksNodeId>> {
+        let receiving = self.get_meta::<meta::ReceivingSnapshot>()?.flatten();
+
+        let received = match receiving {
+            Some((id, received)) if id == meta.snapshot_id => received,
+            _ => {
+                let f = self.begin_receiving_snapshot().await?;
+                self.put_meta::<meta::ReceivingSnapshot>(&Some((meta.snapshot_id.clone(), 0)))?;
+                return Ok((f, 0));
+            }
+        };
+
+        // Continue writing after the data that is synced before the restart. The data after it
+        // may be partially written, and is discarded.
+        let mut f = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(self.receiving_snapshot_path())
+            .await
+            .map_err(|e| StorageIOError::write_snapshot(meta.signature(), &e))?;
+
+        let w_err = |e: std::io::Error| StorageIOError::write_snapshot(meta.signature(), &e);
+        f.set_len(received).await.map_err(w_err)?;
+        f.seek(SeekFrom::Start(received)).await.map_err(w_err)?;
+        tracing::info!(received, "resume receiving snapshot: {:?}", meta);
+
+        Ok((Box::new(f), received))
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, snapshot))]
+    async fn save_received_snapshot(
+        &mut self,
+        meta: &SnapshotMeta<RocksNodeId, BasicNode>,
+        snapshot: &mut Self::SnapshotData,
+        received: u64,
+    ) -> Result<(), StorageError<RocksNodeId>> {
+        snapshot.sync_data().await.map_err(|e| StorageIOError::write_snapshot(meta.signature(), &e))?;
+        self.put_meta::<meta::ReceivingSnapshot>(&Some((meta.snapshot_id.clone(), received)))
     }
 
     #[tracing::instrument(level = "trace", skip(self, snapshot))]
@@ -552,25 +895,16 @@ impl RaftStorage<Config> for Arc<RocksStore> {
         meta: &SnapshotMeta<RocksNodeId, BasicNode>,
         snapshot: Box<Self::SnapshotData>,
     ) -> Result<(), StorageError<RocksNodeId>> {
-        tracing::info!(
-            { snapshot_size = snapshot.get_ref().len() },
-            "decoding snapshot for installation"
-        );
+        let mut snapshot = snapshot;
+        snapshot.flush().await.map_err(|e| StorageIOError::write_snapshot(meta.signature(), &e))?;
+        snapshot.sync_all().await.map_err(|e| StorageIOError::write_snapshot(meta.signature(), &e))?;
+        drop(snapshot);
 
-        let new_snapshot = RocksSnapshot {
-            meta: meta.clone(),
-            data: snapshot.into_inner(),
-        };
+        tracing::info!("ingesting snapshot file for installation: {:?}", meta);
 
         // Update the state machine.
-        {
-            let updated_state_machine: SerializableRocksStateMachine = serde_json::from_slice(&new_snapshot.data)
-                .map_err(|e| StorageIOError::read_snapshot(new_snapshot.meta.signature(), &e))?;
-            let mut state_machine = self.state_machine.write().await;
-            *state_machine = RocksStateMachine::from_serializable(updated_state_machine, self.db.clone())?;
-        }
-
-        self.put_meta::<meta::Snapshot>(&new_snapshot)?;
+        let state_machine = self.state_machine.write().await;
+        self.finish_receiving_snapshot(&state_machine, meta)?;
 
         Ok(())
     }
@@ -582,11 +916,12 @@ impl RaftStorage<Config> for Arc<RocksStore> {
         let curr_snap = self.get_meta::<meta::Snapshot>()?;
 
         match curr_snap {
-            Some(snapshot) => {
-                let data = snapshot.data.clone();
+            Some(meta) => {
+                let path = self.snapshot_path(&meta.snapshot_id);
+                let f = File::open(&path).await.map_err(|e| StorageIOError::read_snapshot(meta.signature(), &e))?;
                 Ok(Some(Snapshot {
-                    meta: snapshot.meta,
-                    snapshot: Box::new(Cursor::new(data)),
+                    meta,
+                    snapshot: Box::new(f),
                 }))
             }
             None => Ok(None),
@@ -603,7 +938,20 @@ impl RaftStorage<Config> for Arc<RocksStore> {
 }
 
 impl RocksStore {
+    /// Open a store that encodes values with the default [`Codec`].
     pub async fn new<P: AsRef<Path>>(db_path: P) -> Arc<RocksStore> {
+        Self::new_with_codec(db_path, Codec::default()).await
+    }
+
+    /// Open a store that encodes new values with `codec`.
+    ///
+    /// Values already in the db that are encoded with another codec are still readable. They are
+    /// re-encoded when they are written again, or at once by [`RocksStore::upgrade_encoding`].
+    ///
+    /// Snapshot files are stored in the `snapshots` sub directory of `db_path`.
+    pub async fn new_with_codec<P: AsRef<Path>>(db_path: P, codec: Codec) -> Arc<RocksStore> {
+        let snapshot_dir = db_path.as_ref().join("snapshots");
+
         let mut db_opts = Options::default();
         db_opts.create_missing_column_families(true);
         db_opts.create_if_missing(true);
@@ -615,9 +963,102 @@ impl RocksStore {
 
         let db = DB::open_cf_descriptors(&db_opts, db_path, vec![meta, sm_meta, sm_data, logs]).unwrap();
 
+        std::fs::create_dir_all(&snapshot_dir).unwrap();
+
         let db = Arc::new(db);
-        let state_machine = RwLock::new(RocksStateMachine::new(db.clone()));
-        Arc::new(RocksStore { db, state_machine })
+
+        let sm = RocksStateMachine::new(db.clone(), codec);
+        // Finish the snapshot installation interrupted by a crash.
+        sm.finish_ingesting_sst(&snapshot_dir).unwrap();
+
+        let state_machine = RwLock::new(sm);
+        let store = RocksStore {
+            db,
+            codec,
+            snapshot_dir,
+            snapshot_switch: Mutex::new(()),
+            state_machine,
+        };
+
+        store.init_membership_log_ids().unwrap();
+
+        Arc::new(store)
+    }
+
+    /// Re-encode every stored value that is not encoded with the codec of this store, including
+    /// legacy JSON values without a format byte.
+    ///
+    /// It returns the number of re-encoded values.
+    pub async fn upgrade_encoding(&self) -> StorageResult<u64> {
+        let mut n = 0;
+
+        n += self.upgrade_meta::<meta::LastPurged>()?;
+        n += self.upgrade_meta::<meta::SnapshotIndex>()?;
+        n += self.upgrade_meta::<meta::Vote>()?;
+        n += self.upgrade_meta::<meta::Committed>()?;
+        n += self.upgrade_meta::<meta::MembershipLogIds>()?;
+        n += self.upgrade_meta::<meta::Snapshot>()?;
+
+        let it = self.db.iterator_cf(self.cf_logs(), rocksdb::IteratorMode::Start);
+        for item_res in it {
+            let (id, val) = item_res.map_err(read_logs_err)?;
+            if Codec::of(&val) == Some(self.codec) {
+                continue;
+            }
+
+            let entry: Entry<Config> = Codec::decode(&val).map_err(read_logs_err)?;
+            let encoded = self.codec.encode(&entry).map_err(|e| StorageIOError::write_logs(&e))?;
+            self.db.put_cf(self.cf_logs(), id, encoded).map_err(|e| StorageIOError::write_logs(&e))?;
+            n += 1;
+        }
+
+        {
+            let sm = self.state_machine.write().await;
+
+            let sm_meta = [
+                sm.db.get_cf(sm.cf_sm_meta(), "last_membership".as_bytes()).map_err(sm_r_err)?,
+                sm.db.get_cf(sm.cf_sm_meta(), "last_applied_log".as_bytes()).map_err(sm_r_err)?,
+            ];
+
+            if let Some(v) = &sm_meta[0] {
+                if Codec::of(v) != Some(self.codec) {
+                    sm.set_last_membership(sm.get_last_membership()?)?;
+                    n += 1;
+                }
+            }
+
+            if let Some(v) = &sm_meta[1] {
+                if Codec::of(v) != Some(self.codec) {
+                    if let Some(log_id) = sm.get_last_applied_log()? {
+                        sm.set_last_applied_log(log_id)?;
+                        n += 1;
+                    }
+                }
+            }
+        }
+
+        self.db.flush_wal(true).map_err(|e| StorageIOError::write(&e))?;
+
+        tracing::info!("re-encoded {} values with {:?}", n, self.codec);
+        Ok(n)
+    }
+
+    /// Re-encode a store metadata if it is not encoded with the codec of this store.
+    fn upgrade_meta<M: meta::StoreMeta>(&self) -> StorageResult<u64> {
+        let v = self
+            .db
+            .get_cf(self.cf_meta(), M::KEY)
+            .map_err(|e| StorageIOError::new(M::subject(None), ErrorVerb::Read, AnyError::new(&e)))?;
+
+        match v {
+            Some(bytes) if Codec::of(&bytes) != Some(self.codec) => {
+                if let Some(value) = self.get_meta::<M>()? {
+                    self.put_meta::<M>(&value)?;
+                }
+                Ok(1)
+            }
+            _ => Ok(0),
+        }
     }
 }
 
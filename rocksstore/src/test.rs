@@ -1,23 +1,62 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
+use maplit::btreeset;
+use openraft::error::InstallSnapshotError;
+use openraft::error::NetworkError;
+use openraft::error::RPCError;
+use openraft::error::RaftError;
+use openraft::raft::AppendEntriesRequest;
+use openraft::raft::AppendEntriesResponse;
+use openraft::raft::InstallSnapshotRequest;
+use openraft::raft::InstallSnapshotResponse;
+use openraft::raft::VoteRequest;
+use openraft::raft::VoteResponse;
+use openraft::testing::sample_key_value;
+use openraft::testing::ReopenableStoreBuilder;
 use openraft::testing::StoreBuilder;
 use openraft::testing::Suite;
+use openraft::AnyError;
+use openraft::BasicNode;
+use openraft::CommittedLeaderId;
+use openraft::Entry;
+use openraft::EntryPayload;
+use openraft::LogId;
+use openraft::Membership;
+use openraft::Raft;
+use openraft::RaftLogReader;
+use openraft::RaftNetwork;
+use openraft::RaftNetworkFactory;
+use openraft::RaftSnapshotBuilder;
+use openraft::RaftStorage;
 use openraft::StorageError;
+use openraft::Vote;
 use tempfile::TempDir;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
 
+use crate::Codec;
 use crate::Config;
 use crate::RocksNodeId;
+use crate::RocksRequest;
 use crate::RocksStore;
 
-struct RocksBuilder {}
+struct RocksBuilder {
+    codec: Codec,
+}
 #[async_trait]
 impl StoreBuilder<Config, Arc<RocksStore>, TempDir> for RocksBuilder {
     async fn build(&self) -> Result<(TempDir, Arc<RocksStore>), StorageError<RocksNodeId>> {
         let td = tempfile::TempDir::new().expect("couldn't create temp dir");
-        let store = RocksStore::new(td.path()).await;
+        let store = RocksStore::new_with_codec(td.path(), self.codec).await;
         Ok((td, store))
     }
+
+    fn sample_app_data(&self, index: u64) -> Option<RocksRequest> {
+        let (key, value) = sample_key_value(index);
+        Some(RocksRequest::Set { key, value })
+    }
 }
 /// To customize a builder:
 ///
@@ -40,8 +79,349 @@ impl StoreBuilder<Config, Arc<RocksStore>, TempDir> for RocksBuilder {
 ///     Suite::test_all(MemStoreBuilder {})
 /// }
 /// ```
+#[async_trait]
+impl ReopenableStoreBuilder<Config, Arc<RocksStore>, TempDir> for RocksBuilder {
+    async fn reopen(&self, td: &TempDir, store: Arc<RocksStore>) -> Result<Arc<RocksStore>, StorageError<RocksNodeId>> {
+        // The db is closed when the last reference to it is dropped.
+        drop(store);
+        Ok(RocksStore::new_with_codec(td.path(), self.codec).await)
+    }
+}
+
+#[test]
+pub fn test_rocks_store_restart() -> Result<(), StorageError<RocksNodeId>> {
+    Suite::test_restart(&RocksBuilder { codec: Codec::Bincode })
+}
+
 #[test]
 pub fn test_rocks_store() -> Result<(), StorageError<RocksNodeId>> {
-    Suite::test_all(RocksBuilder {})?;
+    Suite::test_all(RocksBuilder { codec: Codec::Bincode })?;
+    Ok(())
+}
+
+#[test]
+pub fn test_rocks_store_json() -> Result<(), StorageError<RocksNodeId>> {
+    Suite::test_all(RocksBuilder { codec: Codec::Json })?;
+    Ok(())
+}
+
+/// Values written as plain JSON by an older version are readable, and are re-encoded by
+/// `upgrade_encoding()`.
+#[async_std::test]
+async fn test_read_and_upgrade_legacy_json() -> Result<(), StorageError<RocksNodeId>> {
+    let td = tempfile::TempDir::new().expect("couldn't create temp dir");
+    let mut store = RocksStore::new(td.path()).await;
+
+    let vote = Vote::new(3, 1);
+    let entry = Entry::<Config> {
+        log_id: LogId::new(CommittedLeaderId::new(3, 1), 5),
+        payload: EntryPayload::Blank,
+    };
+
+    store.db.put_cf(store.cf_meta(), "vote", serde_json::to_vec(&vote).unwrap()).unwrap();
+    store.db.put_cf(store.cf_logs(), 5u64.to_be_bytes(), serde_json::to_vec(&entry).unwrap()).unwrap();
+
+    assert_eq!(Some(vote), store.read_vote().await?);
+    assert_eq!(Some(entry.log_id), store.get_log_state().await?.last_log_id);
+
+    assert_eq!(2, store.upgrade_encoding().await?);
+    assert_eq!(0, store.upgrade_encoding().await?, "nothing to upgrade");
+
+    let raw_vote = store.db.get_cf(store.cf_meta(), "vote").unwrap().unwrap();
+    assert_eq!(Some(Codec::Bincode), Codec::of(&raw_vote));
+
+    assert_eq!(Some(vote), store.read_vote().await?);
+    assert_eq!(Some(entry.log_id), store.get_log_state().await?.last_log_id);
+
+    Ok(())
+}
+
+/// A snapshot is built into a file, and is installed on another store by ingesting the received
+/// file. Files of old snapshots are removed.
+#[async_std::test]
+async fn test_file_snapshot_build_and_install() -> Result<(), StorageError<RocksNodeId>> {
+    let td_a = tempfile::TempDir::new().expect("couldn't create temp dir");
+    let td_b = tempfile::TempDir::new().expect("couldn't create temp dir");
+    let mut a = RocksStore::new(td_a.path()).await;
+    let mut b = RocksStore::new(td_b.path()).await;
+
+    let set = |index: u64, key: &str| Entry::<Config> {
+        log_id: LogId::new(CommittedLeaderId::new(1, 1), index),
+        payload: EntryPayload::Normal(RocksRequest::Set {
+            key: key.to_string(),
+            value: format!("v{}", index),
+        }),
+    };
+
+    a.apply_to_state_machine(&[set(1, "x"), set(2, "y")]).await?;
+    a.get_snapshot_builder().await.build_snapshot().await?;
+
+    a.apply_to_state_machine(&[set(3, "x")]).await?;
+    let snap = a.get_snapshot_builder().await.build_snapshot().await?;
+
+    let snapshot_files = |dir: &std::path::Path| std::fs::read_dir(dir.join("snapshots")).unwrap().count();
+    assert_eq!(1, snapshot_files(td_a.path()), "the old snapshot is removed");
+
+    tracing::info!("--- install on another store");
+    {
+        b.apply_to_state_machine(&[set(1, "z")]).await?;
+
+        let mut src = a.get_current_snapshot().await?.unwrap().snapshot;
+        let mut dst = b.begin_receiving_snapshot().await?;
+        tokio::io::copy(&mut src, &mut dst).await.unwrap();
+
+        b.install_snapshot(&snap.meta, dst).await?;
+
+        let sm = b.state_machine.read().await;
+        assert_eq!(Some("v3".to_string()), sm.get("x")?);
+        assert_eq!(Some("v2".to_string()), sm.get("y")?);
+        assert_eq!(None, sm.get("z")?, "data not in snapshot is removed");
+        assert_eq!(Some(LogId::new(CommittedLeaderId::new(1, 1), 3)), sm.get_last_applied_log()?);
+    }
+
+    let current = b.get_current_snapshot().await?.unwrap();
+    assert_eq!(snap.meta, current.meta);
+
+    Ok(())
+}
+
+/// The synced data of a snapshot being received is kept across a restart, and receiving it is
+/// resumed from the end of it.
+#[async_std::test]
+async fn test_resume_receiving_snapshot_after_restart() -> Result<(), StorageError<RocksNodeId>> {
+    let td_a = tempfile::TempDir::new().expect("couldn't create temp dir");
+    let td_b = tempfile::TempDir::new().expect("couldn't create temp dir");
+    let mut a = RocksStore::new(td_a.path()).await;
+    let mut b = RocksStore::new(td_b.path()).await;
+
+    let set = |index: u64, key: &str| Entry::<Config> {
+        log_id: LogId::new(CommittedLeaderId::new(1, 1), index),
+        payload: EntryPayload::Normal(RocksRequest::Set {
+            key: key.to_string(),
+            value: format!("v{}", index),
+        }),
+    };
+
+    a.apply_to_state_machine(&[set(1, "x"), set(2, "y")]).await?;
+    let mut snap = a.get_snapshot_builder().await.build_snapshot().await?;
+
+    let mut data = vec![];
+    snap.snapshot.read_to_end(&mut data).await.unwrap();
+    let half = data.len() / 2;
+
+    tracing::info!("--- receive half of the snapshot, then restart");
+    {
+        let (mut dst, received) = b.resume_receiving_snapshot(&snap.meta).await?;
+        assert_eq!(0, received);
+
+        dst.write_all(&data[..half]).await.unwrap();
+        b.save_received_snapshot(&snap.meta, &mut dst, half as u64).await?;
+
+        // Not saved: discarded after restart.
+        dst.write_all(&data[half..]).await.unwrap();
+        dst.flush().await.unwrap();
+    }
+    drop(b);
+
+    let mut b = RocksStore::new(td_b.path()).await;
+
+    tracing::info!("--- resume receiving the rest");
+    {
+        let (mut dst, received) = b.resume_receiving_snapshot(&snap.meta).await?;
+        assert_eq!(half as u64, received);
+
+        dst.write_all(&data[half..]).await.unwrap();
+        b.install_snapshot(&snap.meta, dst).await?;
+
+        let sm = b.state_machine.read().await;
+        assert_eq!(Some("v1".to_string()), sm.get("x")?);
+        assert_eq!(Some("v2".to_string()), sm.get("y")?);
+    }
+
+    tracing::info!("--- the received data is discarded when receiving another snapshot");
+    {
+        let (mut dst, received) = b.resume_receiving_snapshot(&snap.meta).await?;
+        assert_eq!(0, received, "the installed snapshot is not being received");
+        dst.write_all(&data[..half]).await.unwrap();
+        dst.flush().await.unwrap();
+
+        let mut other = snap.meta.clone();
+        other.snapshot_id = "other".to_string();
+        let (_dst, received) = b.resume_receiving_snapshot(&other).await?;
+        assert_eq!(0, received);
+    }
+
+    Ok(())
+}
+
+/// If the node crashes after the old data is removed but before the snapshot file is ingested,
+/// the ingestion is finished when the store is reopened.
+#[async_std::test]
+async fn test_finish_ingesting_snapshot_after_crash() -> Result<(), StorageError<RocksNodeId>> {
+    let td = tempfile::TempDir::new().expect("couldn't create temp dir");
+    let mut store = RocksStore::new(td.path()).await;
+
+    let set = |index: u64, key: &str| Entry::<Config> {
+        log_id: LogId::new(CommittedLeaderId::new(1, 1), index),
+        payload: EntryPayload::Normal(RocksRequest::Set {
+            key: key.to_string(),
+            value: format!("v{}", index),
+        }),
+    };
+
+    store.apply_to_state_machine(&[set(1, "x"), set(2, "y")]).await?;
+    let snap = store.get_snapshot_builder().await.build_snapshot().await?;
+    store.apply_to_state_machine(&[set(3, "z")]).await?;
+
+    tracing::info!("--- crash after removing the old data");
+    {
+        let path = store.snapshot_path(&snap.meta.snapshot_id);
+        let sm = store.state_machine.read().await;
+        sm.begin_ingesting_sst(&path, &snap.meta)?;
+        assert_eq!(None, sm.get("x")?);
+    }
+    drop(store);
+
+    let store = RocksStore::new(td.path()).await;
+    let sm = store.state_machine.read().await;
+    assert_eq!(Some("v1".to_string()), sm.get("x")?);
+    assert_eq!(Some("v2".to_string()), sm.get("y")?);
+    assert_eq!(None, sm.get("z")?);
+    assert_eq!(snap.meta.last_log_id, sm.get_last_applied_log()?);
+
+    Ok(())
+}
+
+/// Distinct snapshot ids are stored in distinct files, and only the file of the current snapshot
+/// is kept.
+#[async_std::test]
+async fn test_snapshot_files() -> Result<(), StorageError<RocksNodeId>> {
+    let td = tempfile::TempDir::new().expect("couldn't create temp dir");
+    let mut store = RocksStore::new(td.path()).await;
+
+    assert_ne!(store.snapshot_path("a/b"), store.snapshot_path("a_b"));
+    assert_ne!(store.snapshot_path("a-b"), store.snapshot_path("a_b"));
+
+    let set = |index: u64, key: &str| Entry::<Config> {
+        log_id: LogId::new(CommittedLeaderId::new(1, 1), index),
+        payload: EntryPayload::Normal(RocksRequest::Set {
+            key: key.to_string(),
+            value: format!("v{}", index),
+        }),
+    };
+
+    store.apply_to_state_machine(&[set(1, "x")]).await?;
+    let first = store.get_snapshot_builder().await.build_snapshot().await?;
+    store.apply_to_state_machine(&[set(2, "y")]).await?;
+    let second = store.get_snapshot_builder().await.build_snapshot().await?;
+
+    assert!(!store.snapshot_path(&first.meta.snapshot_id).exists());
+    assert!(store.snapshot_path(&second.meta.snapshot_id).exists());
+
+    Ok(())
+}
+
+/// A network that can not reach any node.
+struct Isolated {}
+
+#[async_trait]
+impl RaftNetworkFactory<Config> for Isolated {
+    type Network = Isolated;
+
+    async fn new_client(&mut self, _target: RocksNodeId, _node: &BasicNode) -> Self::Network {
+        Isolated {}
+    }
+}
+
+#[async_trait]
+impl RaftNetwork<Config> for Isolated {
+    async fn send_append_entries(
+        &mut self,
+        _rpc: AppendEntriesRequest<Config>,
+    ) -> Result<AppendEntriesResponse<RocksNodeId>, RPCError<RocksNodeId, BasicNode, RaftError<RocksNodeId>>> {
+        Err(RPCError::Network(NetworkError::from(AnyError::error("isolated"))))
+    }
+
+    async fn send_install_snapshot(
+        &mut self,
+        _rpc: InstallSnapshotRequest<Config>,
+    ) -> Result<
+        InstallSnapshotResponse<RocksNodeId>,
+        RPCError<RocksNodeId, BasicNode, RaftError<RocksNodeId, InstallSnapshotError>>,
+    > {
+        Err(RPCError::Network(NetworkError::from(AnyError::error("isolated"))))
+    }
+
+    async fn send_vote(
+        &mut self,
+        _rpc: VoteRequest<RocksNodeId>,
+    ) -> Result<VoteResponse<RocksNodeId>, RPCError<RocksNodeId, BasicNode, RaftError<RocksNodeId>>> {
+        Err(RPCError::Network(NetworkError::from(AnyError::error("isolated"))))
+    }
+}
+
+/// A restarted follower applies the logs that are committed but not yet applied, without a leader.
+///
+/// - Write logs to a store and save the committed log id, but apply only the first log.
+/// - Reopen the store and start an isolated raft node with it.
+/// - The node applies the committed logs at startup.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_restart_applies_committed() -> anyhow::Result<()> {
+    let td = tempfile::TempDir::new().expect("couldn't create temp dir");
+
+    let ent = |index: u64, payload: EntryPayload<Config>| Entry::<Config> {
+        log_id: LogId::new(CommittedLeaderId::new(1, 0), index),
+        payload,
+    };
+    let set = |index: u64, key: &str| {
+        ent(
+            index,
+            EntryPayload::Normal(RocksRequest::Set {
+                key: key.to_string(),
+                value: format!("v{}", index),
+            }),
+        )
+    };
+
+    tracing::info!("--- logs are committed but only the first is applied");
+    {
+        let mut store = RocksStore::new(td.path()).await;
+
+        let membership = ent(
+            1,
+            EntryPayload::Membership(Membership::new(vec![btreeset! {0,1,2}], None)),
+        );
+
+        store.save_vote(&Vote::new_committed(1, 0)).await?;
+        store.append_to_log(&[membership.clone(), set(2, "x"), set(3, "y")]).await?;
+        store.apply_to_state_machine(&[membership]).await?;
+        store.save_committed(Some(LogId::new(CommittedLeaderId::new(1, 0), 3))).await?;
+    }
+
+    tracing::info!("--- restart an isolated node");
+    let store = RocksStore::new(td.path()).await;
+
+    let config = openraft::Config {
+        enable_heartbeat: false,
+        election_timeout_min: 3_000,
+        election_timeout_max: 4_000,
+        ..Default::default()
+    }
+    .validate()?;
+
+    let raft = Raft::new(1, Arc::new(config), Isolated {}, store.clone()).await?;
+
+    raft.wait(Some(Duration::from_millis(1_000)))
+        .log(Some(3), "applied committed logs at startup")
+        .await?;
+
+    {
+        let sm = store.state_machine.read().await;
+        assert_eq!(Some("v2".to_string()), sm.get("x")?);
+        assert_eq!(Some("v3".to_string()), sm.get("y")?);
+    }
+
+    raft.shutdown().await?;
+
     Ok(())
 }
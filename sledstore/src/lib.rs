@@ -35,6 +35,8 @@ use serde::Deserialize;
 use serde::Serialize;
 use sled::Transactional;
 
+pub use openraft_codec::Codec;
+
 pub type ExampleNodeId = u64;
 
 openraft::declare_raft_types!(
@@ -94,6 +96,9 @@ pub struct SerializableExampleStateMachine {
 pub struct ExampleStateMachine {
     /// Application data.
     pub db: Arc<sled::Db>,
+
+    /// The codec to encode state machine meta data.
+    codec: Codec,
 }
 
 impl From<&ExampleStateMachine> for SerializableExampleStateMachine {
@@ -158,7 +163,7 @@ impl ExampleStateMachine {
         let ivec = state_machine.get(b"last_membership").map_err(read_sm_err)?;
 
         let m = if let Some(ivec) = ivec {
-            serde_json::from_slice(&ivec).map_err(read_sm_err)?
+            Codec::decode(&ivec).map_err(read_sm_err)?
         } else {
             StoredMembership::default()
         };
@@ -166,7 +171,7 @@ impl ExampleStateMachine {
         Ok(m)
     }
     async fn set_last_membership(&self, membership: StoredMembership<ExampleNodeId, BasicNode>) -> StorageResult<()> {
-        let value = serde_json::to_vec(&membership).map_err(write_sm_err)?;
+        let value = self.codec.encode(&membership).map_err(write_sm_err)?;
         let state_machine = state_machine(&self.db);
         state_machine.insert(b"last_membership", value).map_err(write_err)?;
 
@@ -178,7 +183,7 @@ impl ExampleStateMachine {
         tx_state_machine: &sled::transaction::TransactionalTree,
         membership: StoredMembership<ExampleNodeId, BasicNode>,
     ) -> Result<(), sled::transaction::ConflictableTransactionError<AnyError>> {
-        let value = serde_json::to_vec(&membership).map_err(conflictable_txn_err)?;
+        let value = self.codec.encode(&membership).map_err(conflictable_txn_err)?;
         tx_state_machine.insert(b"last_membership", value).map_err(conflictable_txn_err)?;
         Ok(())
     }
@@ -186,14 +191,14 @@ impl ExampleStateMachine {
         let state_machine = state_machine(&self.db);
         let ivec = state_machine.get(b"last_applied_log").map_err(read_logs_err)?;
         let last_applied = if let Some(ivec) = ivec {
-            serde_json::from_slice(&ivec).map_err(read_sm_err)?
+            Codec::decode(&ivec).map_err(read_sm_err)?
         } else {
             None
         };
         Ok(last_applied)
     }
     async fn set_last_applied_log(&self, log_id: LogId<ExampleNodeId>) -> StorageResult<()> {
-        let value = serde_json::to_vec(&log_id).map_err(write_sm_err)?;
+        let value = self.codec.encode(&log_id).map_err(write_sm_err)?;
         let state_machine = state_machine(&self.db);
         state_machine.insert(b"last_applied_log", value).map_err(read_logs_err)?;
 
@@ -205,11 +210,15 @@ impl ExampleStateMachine {
         tx_state_machine: &sled::transaction::TransactionalTree,
         log_id: LogId<ExampleNodeId>,
     ) -> Result<(), sled::transaction::ConflictableTransactionError<AnyError>> {
-        let value = serde_json::to_vec(&log_id).map_err(conflictable_txn_err)?;
+        let value = self.codec.encode(&log_id).map_err(conflictable_txn_err)?;
         tx_state_machine.insert(b"last_applied_log", value).map_err(conflictable_txn_err)?;
         Ok(())
     }
-    async fn from_serializable(sm: SerializableExampleStateMachine, db: Arc<sled::Db>) -> StorageResult<Self> {
+    async fn from_serializable(
+        sm: SerializableExampleStateMachine,
+        db: Arc<sled::Db>,
+        codec: Codec,
+    ) -> StorageResult<Self> {
         let data_tree = data(&db);
         let mut batch = sled::Batch::default();
         for (key, value) in sm.data {
@@ -218,7 +227,7 @@ impl ExampleStateMachine {
         data_tree.apply_batch(batch).map_err(write_sm_err)?;
         data_tree.flush_async().await.map_err(write_snap_err)?;
 
-        let r = Self { db };
+        let r = Self { db, codec };
         if let Some(log_id) = sm.last_applied_log {
             r.set_last_applied_log(log_id).await?;
         }
@@ -227,8 +236,8 @@ impl ExampleStateMachine {
         Ok(r)
     }
 
-    fn new(db: Arc<sled::Db>) -> ExampleStateMachine {
-        Self { db }
+    fn new(db: Arc<sled::Db>, codec: Codec) -> ExampleStateMachine {
+        Self { db, codec }
     }
     fn insert_tx(
         &self,
@@ -268,6 +277,9 @@ impl ExampleStateMachine {
 pub struct SledStore {
     db: Arc<sled::Db>,
 
+    /// The codec to encode new values. Values encoded with other codecs are still readable.
+    codec: Codec,
+
     /// The Raft state machine.
     pub state_machine: RwLock<ExampleStateMachine>,
 }
@@ -293,7 +305,7 @@ impl SledStore {
         let val = store_tree.get(b"last_purged_log_id").map_err(read_err)?;
 
         if let Some(v) = val {
-            let val = serde_json::from_slice(&v).map_err(read_err)?;
+            let val = Codec::decode(&v).map_err(read_err)?;
             Ok(Some(val))
         } else {
             Ok(None)
@@ -302,7 +314,7 @@ impl SledStore {
 
     async fn set_last_purged_(&self, log_id: LogId<u64>) -> StorageResult<()> {
         let store_tree = store(&self.db);
-        let val = serde_json::to_vec(&log_id).unwrap();
+        let val = self.codec.encode(&log_id).map_err(write_err)?;
         store_tree.insert(b"last_purged_log_id", val.as_slice()).map_err(write_snap_err)?;
 
         store_tree.flush_async().await.map_err(write_snap_err)?;
@@ -314,7 +326,7 @@ impl SledStore {
         let ivec = store_tree.get(b"snapshot_index").map_err(read_snap_err)?;
 
         if let Some(v) = ivec {
-            let val = serde_json::from_slice(&v).map_err(read_err)?;
+            let val = Codec::decode(&v).map_err(read_err)?;
             Ok(val)
         } else {
             Ok(0)
@@ -323,7 +335,7 @@ impl SledStore {
 
     async fn set_snapshot_index_(&self, snapshot_index: u64) -> StorageResult<()> {
         let store_tree = store(&self.db);
-        let val = serde_json::to_vec(&snapshot_index).unwrap();
+        let val = self.codec.encode(&snapshot_index).map_err(write_err)?;
         store_tree.insert(b"snapshot_index", val.as_slice()).map_err(write_snap_err)?;
 
         store_tree.flush_async().await.map_err(write_snap_err)?;
@@ -332,7 +344,7 @@ impl SledStore {
 
     async fn set_vote_(&self, vote: &Vote<ExampleNodeId>) -> Result<(), StorageError<ExampleNodeId>> {
         let store_tree = store(&self.db);
-        let val = serde_json::to_vec(vote).unwrap();
+        let val = self.codec.encode(vote).map_err(write_vote_err)?;
         store_tree.insert(b"vote", val).map_err(write_vote_err)?;
 
         store_tree.flush_async().await.map_err(write_vote_err)?;
@@ -349,16 +361,107 @@ impl SledStore {
             return Ok(None);
         };
 
-        let v = serde_json::from_slice(&ivec).map_err(read_vote_err)?;
+        let v = Codec::decode(&ivec).map_err(read_vote_err)?;
         Ok(Some(v))
     }
 
+    fn set_committed_(&self, committed: &Option<LogId<ExampleNodeId>>) -> StorageResult<()> {
+        let store_tree = store(&self.db);
+        let val = self.codec.encode(committed).map_err(write_err)?;
+        store_tree.insert(b"committed", val).map_err(write_err)?;
+
+        // Not flushed: the committed log id may be lost, it is re-learned from the leader.
+        Ok(())
+    }
+
+    fn get_committed_(&self) -> StorageResult<Option<LogId<ExampleNodeId>>> {
+        let store_tree = store(&self.db);
+        let val = store_tree.get(b"committed").map_err(read_err)?;
+
+        if let Some(v) = val {
+            let committed: Option<LogId<ExampleNodeId>> = Codec::decode(&v).map_err(read_err)?;
+            Ok(committed)
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn set_removed_by_(&self, removed_by: &Option<LogId<ExampleNodeId>>) -> StorageResult<()> {
+        let store_tree = store(&self.db);
+        let val = self.codec.encode(removed_by).map_err(write_err)?;
+        store_tree.insert(b"removed_by", val).map_err(write_err)?;
+
+        store_tree.flush_async().await.map_err(write_err)?;
+        Ok(())
+    }
+
+    fn get_removed_by_(&self) -> StorageResult<Option<LogId<ExampleNodeId>>> {
+        let store_tree = store(&self.db);
+        let val = store_tree.get(b"removed_by").map_err(read_err)?;
+
+        if let Some(v) = val {
+            let removed_by: Option<LogId<ExampleNodeId>> = Codec::decode(&v).map_err(read_err)?;
+            Ok(removed_by)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn get_membership_log_ids_(&self) -> StorageResult<Option<Vec<LogId<ExampleNodeId>>>> {
+        let store_tree = store(&self.db);
+        let val = store_tree.get(b"membership_log_ids").map_err(read_logs_err)?;
+
+        if let Some(v) = val {
+            let ids = Codec::decode(&v).map_err(read_logs_err)?;
+            Ok(Some(ids))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn set_membership_log_ids_(&self, ids: &[LogId<ExampleNodeId>]) -> StorageResult<()> {
+        let store_tree = store(&self.db);
+        let val = self.codec.encode(&ids).map_err(write_logs_err)?;
+        store_tree.insert(b"membership_log_ids", val).map_err(write_logs_err)?;
+
+        store_tree.flush_async().await.map_err(write_logs_err)?;
+        Ok(())
+    }
+
+    /// Update the saved membership log ids with `f`.
+    async fn update_membership_log_ids_(&self, f: impl FnOnce(&mut Vec<LogId<ExampleNodeId>>)) -> StorageResult<()> {
+        let mut ids = self.get_membership_log_ids_()?.unwrap_or_default();
+        f(&mut ids);
+        trim_membership_log_ids(&mut ids);
+        self.set_membership_log_ids_(&ids).await
+    }
+
+    /// Build the membership log ids by scanning the log, if they are not saved.
+    ///
+    /// It happens only once, when a db written by an older version is opened.
+    async fn init_membership_log_ids_(&self) -> StorageResult<()> {
+        if self.get_membership_log_ids_()?.is_some() {
+            return Ok(());
+        }
+
+        let mut ids = vec![];
+        for item_res in logs(&self.db).iter() {
+            let (_, val) = item_res.map_err(read_logs_err)?;
+            let entry: Entry<ExampleTypeConfig> = Codec::decode(&val).map_err(read_logs_err)?;
+            if let EntryPayload::Membership(_) = entry.payload {
+                ids.push(entry.log_id);
+            }
+        }
+
+        self.update_membership_log_ids_(|x| *x = ids).await
+    }
+
     fn get_current_snapshot_(&self) -> StorageResult<Option<ExampleSnapshot>> {
         let store_tree = store(&self.db);
         let ivec = store_tree.get(b"snapshot").map_err(read_snap_err)?;
 
         if let Some(ivec) = ivec {
-            let snap = serde_json::from_slice(&ivec).map_err(read_snap_err)?;
+            let snap = Codec::decode(&ivec).map_err(read_snap_err)?;
             Ok(Some(snap))
         } else {
             Ok(None)
@@ -367,7 +470,7 @@ impl SledStore {
 
     async fn set_current_snapshot_(&self, snap: ExampleSnapshot) -> StorageResult<()> {
         let store_tree = store(&self.db);
-        let val = serde_json::to_vec(&snap).unwrap();
+        let val = self.codec.encode(&snap).map_err(write_snap_err)?;
         let meta = snap.meta.clone();
         store_tree.insert(b"snapshot", val.as_slice()).map_err(|e| StorageError::IO {
             source: StorageIOError::write_snapshot(snap.meta.signature(), &e),
@@ -397,7 +500,7 @@ impl RaftLogReader<ExampleTypeConfig> for Arc<SledStore> {
             });
         };
 
-        let last_ent = serde_json::from_slice::<Entry<ExampleTypeConfig>>(&ent_ivec).map_err(read_logs_err)?;
+        let last_ent = Codec::decode::<Entry<ExampleTypeConfig>>(&ent_ivec).map_err(read_logs_err)?;
         let last_log_id = Some(*last_ent.get_log_id());
 
         let last_log_id = std::cmp::max(last_log_id, last_purged);
@@ -424,7 +527,7 @@ impl RaftLogReader<ExampleTypeConfig> for Arc<SledStore> {
                 let el = el_res.expect("Failed read log entry");
                 let id = el.0;
                 let val = el.1;
-                let entry: StorageResult<Entry<_>> = serde_json::from_slice(&val).map_err(|e| StorageError::IO {
+                let entry: StorageResult<Entry<_>> = Codec::decode(&val).map_err(|e| StorageError::IO {
                     source: StorageIOError::read_logs(&e),
                 });
                 let id = bin_to_id(&id);
@@ -452,7 +555,7 @@ impl RaftSnapshotBuilder<ExampleTypeConfig, Cursor<Vec<u8>>> for Arc<SledStore>
         {
             // Serialize the data of the state machine.
             let state_machine = SerializableExampleStateMachine::from(&*self.state_machine.read().await);
-            data = serde_json::to_vec(&state_machine).map_err(|e| StorageIOError::read_state_machine(&e))?;
+            data = self.codec.encode(&state_machine).map_err(|e| StorageIOError::read_state_machine(&e))?;
 
             last_applied_log = state_machine.last_applied_log;
             last_membership = state_machine.last_membership;
@@ -503,18 +606,58 @@ impl RaftStorage<ExampleTypeConfig> for Arc<SledStore> {
         self.get_vote_()
     }
 
+    async fn save_committed(
+        &mut self,
+        committed: Option<LogId<ExampleNodeId>>,
+    ) -> Result<(), StorageError<ExampleNodeId>> {
+        self.set_committed_(&committed)
+    }
+
+    async fn read_committed(&mut self) -> Result<Option<LogId<ExampleNodeId>>, StorageError<ExampleNodeId>> {
+        self.get_committed_()
+    }
+
+    async fn save_removed_by(
+        &mut self,
+        removed_by: Option<LogId<ExampleNodeId>>,
+    ) -> Result<(), StorageError<ExampleNodeId>> {
+        self.set_removed_by_(&removed_by).await
+    }
+
+    async fn read_removed_by(&mut self) -> Result<Option<LogId<ExampleNodeId>>, StorageError<ExampleNodeId>> {
+        self.get_removed_by_()
+    }
+
+    async fn read_membership_log_ids(
+        &mut self,
+    ) -> Result<Option<Vec<LogId<ExampleNodeId>>>, StorageError<ExampleNodeId>> {
+        self.get_membership_log_ids_()
+    }
+
     async fn get_log_reader(&mut self) -> Self::LogReader {
         self.clone()
     }
 
     #[tracing::instrument(level = "trace", skip(self, entries))]
     async fn append_to_log(&mut self, entries: &[Entry<ExampleTypeConfig>]) -> StorageResult<()> {
+        // The membership log ids are saved before the logs: an id of a log that is not written is
+        // ignored, because it is beyond the last log.
+        self.update_membership_log_ids_(|ids| {
+            for entry in entries {
+                ids.retain(|x| x.index != entry.log_id.index);
+                if let EntryPayload::Membership(_) = entry.payload {
+                    ids.push(entry.log_id);
+                }
+            }
+        })
+        .await?;
+
         let logs_tree = logs(&self.db);
         let mut batch = sled::Batch::default();
         for entry in entries {
             let id = id_to_bin(entry.log_id.index);
             assert_eq!(bin_to_id(&id), entry.log_id.index);
-            let value = serde_json::to_vec(entry).map_err(write_logs_err)?;
+            let value = self.codec.encode(entry).map_err(write_logs_err)?;
             batch.insert(id.as_slice(), value);
         }
         logs_tree.apply_batch(batch).map_err(write_logs_err)?;
@@ -538,6 +681,9 @@ impl RaftStorage<ExampleTypeConfig> for Arc<SledStore> {
         }
         logs_tree.apply_batch(batch_del).map_err(write_logs_err)?;
         logs_tree.flush_async().await.map_err(write_logs_err)?;
+
+        // The membership log ids are removed after the logs, so that no membership log is missed.
+        self.update_membership_log_ids_(|ids| ids.retain(|x| x.index < log_id.index)).await?;
         Ok(())
     }
 
@@ -558,6 +704,8 @@ impl RaftStorage<ExampleTypeConfig> for Arc<SledStore> {
         logs_tree.apply_batch(batch_del).map_err(write_logs_err)?;
 
         logs_tree.flush_async().await.map_err(write_logs_err)?;
+
+        self.update_membership_log_ids_(|ids| ids.retain(|x| x.index > log_id.index)).await?;
         Ok(())
     }
 
@@ -640,10 +788,11 @@ impl RaftStorage<ExampleTypeConfig> for Arc<SledStore> {
 
         // Update the state machine.
         {
-            let updated_state_machine: SerializableExampleStateMachine = serde_json::from_slice(&new_snapshot.data)
+            let updated_state_machine: SerializableExampleStateMachine = Codec::decode(&new_snapshot.data)
                 .map_err(|e| StorageIOError::read_snapshot(new_snapshot.meta.signature(), &e))?;
             let mut state_machine = self.state_machine.write().await;
-            *state_machine = ExampleStateMachine::from_serializable(updated_state_machine, self.db.clone()).await?;
+            *state_machine =
+                ExampleStateMachine::from_serializable(updated_state_machine, self.db.clone(), self.codec).await?;
         }
 
         self.set_current_snapshot_(new_snapshot).await?;
@@ -667,14 +816,101 @@ impl RaftStorage<ExampleTypeConfig> for Arc<SledStore> {
     }
 }
 impl SledStore {
+    /// Open a store that encodes values with the default [`Codec`].
     pub async fn new(db: Arc<sled::Db>) -> Arc<SledStore> {
+        Self::new_with_codec(db, Codec::default()).await
+    }
+
+    /// Open a store that encodes new values with `codec`.
+    ///
+    /// Values already in the db that are encoded with another codec are still readable. They are
+    /// re-encoded when they are written again, or at once by [`SledStore::upgrade_encoding`].
+    pub async fn new_with_codec(db: Arc<sled::Db>, codec: Codec) -> Arc<SledStore> {
         let _store = store(&db);
         let _state_machine = state_machine(&db);
         let _data = data(&db);
         let _logs = logs(&db);
 
-        let state_machine = RwLock::new(ExampleStateMachine::new(db.clone()));
-        Arc::new(SledStore { db, state_machine })
+        let state_machine = RwLock::new(ExampleStateMachine::new(db.clone(), codec));
+        let store = SledStore {
+            db,
+            codec,
+            state_machine,
+        };
+
+        store.init_membership_log_ids_().await.unwrap();
+
+        Arc::new(store)
+    }
+
+    /// Re-encode every stored value that is not encoded with the codec of this store, including
+    /// legacy JSON values without a format byte.
+    ///
+    /// The data of a stored snapshot is left as is: it is decoded according to its own format byte.
+    ///
+    /// It returns the number of re-encoded values.
+    pub async fn upgrade_encoding(&self) -> StorageResult<u64> {
+        let mut n = 0;
+
+        let store_tree = store(&self.db);
+        n += self.upgrade_value::<LogId<ExampleNodeId>>(&store_tree, b"last_purged_log_id")?;
+        n += self.upgrade_value::<u64>(&store_tree, b"snapshot_index")?;
+        n += self.upgrade_value::<Vote<ExampleNodeId>>(&store_tree, b"vote")?;
+        n += self.upgrade_value::<Option<LogId<ExampleNodeId>>>(&store_tree, b"committed")?;
+        n += self.upgrade_value::<Option<LogId<ExampleNodeId>>>(&store_tree, b"removed_by")?;
+        n += self.upgrade_value::<Vec<LogId<ExampleNodeId>>>(&store_tree, b"membership_log_ids")?;
+        n += self.upgrade_value::<ExampleSnapshot>(&store_tree, b"snapshot")?;
+
+        let sm_tree = state_machine(&self.db);
+        n += self.upgrade_value::<StoredMembership<ExampleNodeId, BasicNode>>(&sm_tree, b"last_membership")?;
+        n += self.upgrade_value::<LogId<ExampleNodeId>>(&sm_tree, b"last_applied_log")?;
+
+        let logs_tree = logs(&self.db);
+        let mut batch = sled::Batch::default();
+        for item_res in logs_tree.iter() {
+            let (id, val) = item_res.map_err(read_logs_err)?;
+            if Codec::of(&val) == Some(self.codec) {
+                continue;
+            }
+
+            let entry: Entry<ExampleTypeConfig> = Codec::decode(&val).map_err(read_logs_err)?;
+            batch.insert(id, self.codec.encode(&entry).map_err(write_logs_err)?);
+            n += 1;
+        }
+        logs_tree.apply_batch(batch).map_err(write_logs_err)?;
+
+        self.db.flush_async().await.map_err(write_err)?;
+
+        tracing::info!("re-encoded {} values with {:?}", n, self.codec);
+        Ok(n)
+    }
+
+    /// Re-encode a single value in `tree` if it is not encoded with the codec of this store.
+    fn upgrade_value<T>(&self, tree: &sled::Tree, key: &[u8]) -> StorageResult<u64>
+    where T: Serialize + serde::de::DeserializeOwned {
+        let ivec = tree.get(key).map_err(read_err)?;
+
+        match ivec {
+            Some(v) if Codec::of(&v) != Some(self.codec) => {
+                let value: T = Codec::decode(&v).map_err(read_err)?;
+                tree.insert(key, self.codec.encode(&value).map_err(write_err)?).map_err(write_err)?;
+                Ok(1)
+            }
+            _ => Ok(0),
+        }
+    }
+}
+
+/// The number of the last membership log ids to keep.
+///
+/// Raft uses only the last two membership logs. One more is kept so that the last two are still
+/// known after an uncommitted membership log is deleted by `delete_conflict_logs_since()`.
+const MEMBERSHIP_LOG_IDS_TO_KEEP: usize = 3;
+
+/// Remove the membership log ids that are older than the last `MEMBERSHIP_LOG_IDS_TO_KEEP`.
+fn trim_membership_log_ids(ids: &mut Vec<LogId<ExampleNodeId>>) {
+    if ids.len() > MEMBERSHIP_LOG_IDS_TO_KEEP {
+        ids.drain(..ids.len() - MEMBERSHIP_LOG_IDS_TO_KEEP);
     }
 }
 
@@ -1,20 +1,43 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use openraft::testing::sample_key_value;
+use openraft::testing::ReopenableStoreBuilder;
 use openraft::testing::StoreBuilder;
 use openraft::testing::Suite;
+use openraft::CommittedLeaderId;
+use openraft::Entry;
+use openraft::EntryPayload;
+use openraft::LogId;
+use openraft::RaftLogReader;
+use openraft::RaftStorage;
 use openraft::StorageError;
+use openraft::Vote;
 use tempfile::TempDir;
 
+use crate::Codec;
 use crate::ExampleNodeId;
+use crate::ExampleRequest;
 use crate::ExampleTypeConfig;
 use crate::SledStore;
 
-struct SledBuilder {}
+struct SledBuilder {
+    codec: Codec,
+}
 
 #[test]
 pub fn test_sled_store() -> Result<(), StorageError<ExampleNodeId>> {
-    Suite::test_all(SledBuilder {})
+    Suite::test_all(SledBuilder { codec: Codec::Bincode })
+}
+
+#[test]
+pub fn test_sled_store_json() -> Result<(), StorageError<ExampleNodeId>> {
+    Suite::test_all(SledBuilder { codec: Codec::Json })
+}
+
+#[test]
+pub fn test_sled_store_restart() -> Result<(), StorageError<ExampleNodeId>> {
+    Suite::test_restart(&SledBuilder { codec: Codec::Bincode })
 }
 
 #[async_trait]
@@ -24,8 +47,56 @@ impl StoreBuilder<ExampleTypeConfig, Arc<SledStore>, TempDir> for SledBuilder {
 
         let db: sled::Db = sled::open(td.path()).unwrap();
 
-        let store = SledStore::new(Arc::new(db)).await;
+        let store = SledStore::new_with_codec(Arc::new(db), self.codec).await;
 
         Ok((td, store))
     }
+
+    fn sample_app_data(&self, index: u64) -> Option<ExampleRequest> {
+        let (key, value) = sample_key_value(index);
+        Some(ExampleRequest::Set { key, value })
+    }
+}
+
+#[async_trait]
+impl ReopenableStoreBuilder<ExampleTypeConfig, Arc<SledStore>, TempDir> for SledBuilder {
+    async fn reopen(&self, td: &TempDir, store: Arc<SledStore>) -> Result<Arc<SledStore>, StorageError<ExampleNodeId>> {
+        // The db is closed when the last reference to it is dropped.
+        drop(store);
+
+        let db: sled::Db = sled::open(td.path()).unwrap();
+        Ok(SledStore::new_with_codec(Arc::new(db), self.codec).await)
+    }
+}
+
+/// Values written as plain JSON by an older version are readable, and are re-encoded by
+/// `upgrade_encoding()`.
+#[async_std::test]
+async fn test_read_and_upgrade_legacy_json() -> Result<(), StorageError<ExampleNodeId>> {
+    let td = tempfile::TempDir::new().expect("couldn't create temp dir");
+    let db: sled::Db = sled::open(td.path()).unwrap();
+    let mut store = SledStore::new(Arc::new(db)).await;
+
+    let vote = Vote::new(3, 1);
+    let entry = Entry::<ExampleTypeConfig> {
+        log_id: LogId::new(CommittedLeaderId::new(3, 1), 5),
+        payload: EntryPayload::Blank,
+    };
+
+    crate::store(&store.db).insert(b"vote", serde_json::to_vec(&vote).unwrap()).unwrap();
+    crate::logs(&store.db).insert(crate::id_to_bin(5), serde_json::to_vec(&entry).unwrap()).unwrap();
+
+    assert_eq!(Some(vote), store.read_vote().await?);
+    assert_eq!(Some(entry.log_id), store.get_log_state().await?.last_log_id);
+
+    assert_eq!(2, store.upgrade_encoding().await?);
+    assert_eq!(0, store.upgrade_encoding().await?, "nothing to upgrade");
+
+    let raw_vote = crate::store(&store.db).get(b"vote").unwrap().unwrap();
+    assert_eq!(Some(Codec::Bincode), Codec::of(&raw_vote));
+
+    assert_eq!(Some(vote), store.read_vote().await?);
+    assert_eq!(Some(entry.log_id), store.get_log_state().await?.last_log_id);
+
+    Ok(())
 }
@@ -44,12 +44,12 @@ async fn append_conflicts() -> Result<()> {
 
     tracing::info!("--- case 0: prev_log_id == None, no logs");
 
-    let req = AppendEntriesRequest {
-        vote: Vote::new_committed(1, 2),
-        prev_log_id: None,
-        entries: vec![],
-        leader_commit: Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
-    };
+    let req = AppendEntriesRequest::new(
+        Vote::new_committed(1, 2),
+        None,
+        vec![],
+        Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
+    );
 
     let resp = r0.append_entries(req.clone()).await?;
 
@@ -58,12 +58,12 @@ async fn append_conflicts() -> Result<()> {
 
     tracing::info!("--- case 0: prev_log_id == None, 1 logs");
 
-    let req = AppendEntriesRequest {
-        vote: Vote::new_committed(1, 2),
-        prev_log_id: None,
-        entries: vec![blank(0, 0)],
-        leader_commit: Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
-    };
+    let req = AppendEntriesRequest::new(
+        Vote::new_committed(1, 2),
+        None,
+        vec![blank(0, 0)],
+        Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
+    );
 
     let resp = r0.append_entries(req.clone()).await?;
     assert!(resp.is_success());
@@ -71,12 +71,12 @@ async fn append_conflicts() -> Result<()> {
 
     tracing::info!("--- case 0: prev_log_id == 1-1, 0 logs");
 
-    let req = AppendEntriesRequest {
-        vote: Vote::new_committed(1, 2),
-        prev_log_id: Some(LogId::new(CommittedLeaderId::new(0, 0), 0)),
-        entries: vec![],
-        leader_commit: Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
-    };
+    let req = AppendEntriesRequest::new(
+        Vote::new_committed(1, 2),
+        Some(LogId::new(CommittedLeaderId::new(0, 0), 0)),
+        vec![],
+        Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
+    );
 
     let resp = r0.append_entries(req.clone()).await?;
     assert!(resp.is_success());
@@ -86,13 +86,13 @@ async fn append_conflicts() -> Result<()> {
 
     tracing::info!("--- case 0: prev_log_id.index == 0, ");
 
-    let req = AppendEntriesRequest {
-        vote: Vote::new_committed(1, 2),
-        prev_log_id: Some(LogId::new(CommittedLeaderId::new(0, 0), 0)),
-        entries: vec![blank(1, 1), blank(1, 2), blank(1, 3), blank(1, 4)],
+    let req = AppendEntriesRequest::new(
+        Vote::new_committed(1, 2),
+        Some(LogId::new(CommittedLeaderId::new(0, 0), 0)),
+        vec![blank(1, 1), blank(1, 2), blank(1, 3), blank(1, 4)],
         // this set the last_applied to 2
-        leader_commit: Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
-    };
+        Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
+    );
 
     let resp = r0.append_entries(req.clone()).await?;
     assert!(resp.is_success());
@@ -111,12 +111,12 @@ async fn append_conflicts() -> Result<()> {
     // committed index is 2
     tracing::info!("--- case 1: 0 < prev_log_id.index < commit_index");
 
-    let req = AppendEntriesRequest {
-        vote: Vote::new_committed(1, 2),
-        prev_log_id: Some(LogId::new(CommittedLeaderId::new(1, 0), 1)),
-        entries: vec![blank(1, 2)],
-        leader_commit: Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
-    };
+    let req = AppendEntriesRequest::new(
+        Vote::new_committed(1, 2),
+        Some(LogId::new(CommittedLeaderId::new(1, 0), 1)),
+        vec![blank(1, 2)],
+        Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
+    );
 
     let resp = r0.append_entries(req).await?;
     assert!(resp.is_success());
@@ -126,13 +126,13 @@ async fn append_conflicts() -> Result<()> {
 
     tracing::info!("--- case 2:  prev_log_id.index == last_applied, inconsistent log should be removed");
 
-    let req = AppendEntriesRequest {
-        vote: Vote::new_committed(1, 2),
-        prev_log_id: Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
-        entries: vec![blank(2, 3)],
+    let req = AppendEntriesRequest::new(
+        Vote::new_committed(1, 2),
+        Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
+        vec![blank(2, 3)],
         // this set the last_applied to 2
-        leader_commit: Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
-    };
+        Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
+    );
 
     let resp = r0.append_entries(req).await?;
     assert!(resp.is_success());
@@ -141,12 +141,12 @@ async fn append_conflicts() -> Result<()> {
     check_logs(&mut sto0, vec![0, 1, 1, 2]).await?;
 
     // check last_log_id is updated:
-    let req = AppendEntriesRequest {
-        vote: Vote::new_committed(1, 2),
-        prev_log_id: Some(LogId::new(CommittedLeaderId::new(1, 0), 2000)),
-        entries: vec![],
-        leader_commit: Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
-    };
+    let req = AppendEntriesRequest::new(
+        Vote::new_committed(1, 2),
+        Some(LogId::new(CommittedLeaderId::new(1, 0), 2000)),
+        vec![],
+        Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
+    );
 
     let resp = r0.append_entries(req).await?;
     assert!(!resp.is_success());
@@ -156,12 +156,12 @@ async fn append_conflicts() -> Result<()> {
 
     tracing::info!("--- case 3,4: prev_log_id.index <= last_log_id, prev_log_id mismatch, inconsistent log is removed");
 
-    let req = AppendEntriesRequest {
-        vote: Vote::new_committed(1, 2),
-        prev_log_id: Some(LogId::new(CommittedLeaderId::new(3, 0), 3)),
-        entries: vec![],
-        leader_commit: Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
-    };
+    let req = AppendEntriesRequest::new(
+        Vote::new_committed(1, 2),
+        Some(LogId::new(CommittedLeaderId::new(3, 0), 3)),
+        vec![],
+        Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
+    );
 
     let resp = r0.append_entries(req).await?;
     assert!(!resp.is_success());
@@ -171,12 +171,12 @@ async fn append_conflicts() -> Result<()> {
 
     tracing::info!("--- case 3,4: prev_log_id.index <= last_log_id, prev_log_id matches, inconsistent log is removed");
     // refill logs
-    let req = AppendEntriesRequest {
-        vote: Vote::new_committed(1, 2),
-        prev_log_id: Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
-        entries: vec![blank(2, 3), blank(2, 4), blank(2, 5)],
-        leader_commit: Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
-    };
+    let req = AppendEntriesRequest::new(
+        Vote::new_committed(1, 2),
+        Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
+        vec![blank(2, 3), blank(2, 4), blank(2, 5)],
+        Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
+    );
 
     let resp = r0.append_entries(req).await?;
     assert!(resp.is_success());
@@ -186,12 +186,12 @@ async fn append_conflicts() -> Result<()> {
     check_logs(&mut sto0, vec![0, 1, 1, 2, 2, 2]).await?;
 
     // prev_log_id matches
-    let req = AppendEntriesRequest {
-        vote: Vote::new_committed(1, 2),
-        prev_log_id: Some(LogId::new(CommittedLeaderId::new(2, 0), 3)),
-        entries: vec![blank(3, 4)],
-        leader_commit: Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
-    };
+    let req = AppendEntriesRequest::new(
+        Vote::new_committed(1, 2),
+        Some(LogId::new(CommittedLeaderId::new(2, 0), 3)),
+        vec![blank(3, 4)],
+        Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
+    );
 
     let resp = r0.append_entries(req).await?;
     assert!(resp.is_success());
@@ -202,12 +202,12 @@ async fn append_conflicts() -> Result<()> {
     tracing::info!("--- case 5: last_log_id.index < prev_log_id.index");
 
     // refill logs
-    let req = AppendEntriesRequest {
-        vote: Vote::new_committed(1, 2),
-        prev_log_id: Some(LogId::new(CommittedLeaderId::new(1, 0), 200)),
-        entries: vec![],
-        leader_commit: Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
-    };
+    let req = AppendEntriesRequest::new(
+        Vote::new_committed(1, 2),
+        Some(LogId::new(CommittedLeaderId::new(1, 0), 200)),
+        vec![],
+        Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
+    );
 
     let resp = r0.append_entries(req).await?;
     assert!(!resp.is_success());
@@ -44,10 +44,10 @@ async fn append_updates_membership() -> Result<()> {
 
     tracing::info!("--- append-entries update membership");
     {
-        let req = AppendEntriesRequest {
-            vote: Vote::new_committed(1, 1),
-            prev_log_id: None,
-            entries: vec![
+        let req = AppendEntriesRequest::new(
+            Vote::new_committed(1, 1),
+            None,
+            vec![
                 blank(0, 0),
                 blank(1, 1),
                 Entry {
@@ -61,8 +61,8 @@ async fn append_updates_membership() -> Result<()> {
                 },
                 blank(1, 5),
             ],
-            leader_commit: Some(LogId::new(CommittedLeaderId::new(0, 0), 0)),
-        };
+            Some(LogId::new(CommittedLeaderId::new(0, 0), 0)),
+        );
 
         let resp = r0.append_entries(req.clone()).await?;
         assert!(resp.is_success());
@@ -73,12 +73,12 @@ async fn append_updates_membership() -> Result<()> {
 
     tracing::info!("--- delete inconsistent logs update membership");
     {
-        let req = AppendEntriesRequest {
-            vote: Vote::new_committed(2, 2),
-            prev_log_id: Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
-            entries: vec![blank(2, 3)],
-            leader_commit: Some(LogId::new(CommittedLeaderId::new(0, 0), 0)),
-        };
+        let req = AppendEntriesRequest::new(
+            Vote::new_committed(2, 2),
+            Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
+            vec![blank(2, 3)],
+            Some(LogId::new(CommittedLeaderId::new(0, 0), 0)),
+        );
 
         let resp = r0.append_entries(req.clone()).await?;
         assert!(resp.is_success());
@@ -0,0 +1,103 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::entry::FromAppData;
+use openraft::entry::RaftEntry;
+use openraft::Config;
+use openraft::Entry;
+use openraft_memstore::ClientRequest;
+use openraft_memstore::Config as MemConfig;
+use openraft_memstore::IntoMemClientRequest;
+use tokio::sync::oneshot;
+use tokio::time::sleep;
+use tokio::time::Instant;
+
+use crate::fixtures::init_default_ut_tracing;
+use crate::fixtures::RaftRouter;
+
+/// Heartbeats are sent on schedule while the replication to a follower is blocked.
+///
+/// - Limit the replication rate so that the replication stream of every follower waits for seconds
+///   before sending the next entry.
+/// - The followers keep receiving heartbeats: the leader lease is extended and no election starts,
+///   while the new entry is not yet replicated.
+/// - Remove the limit: the entry is replicated.
+#[async_entry::test(worker_threads = 8, init = "init_default_ut_tracing()", tracing_span = "debug")]
+async fn heartbeat_independent_of_replication() -> Result<()> {
+    let entry_size = Entry::<MemConfig>::from_app_data(ClientRequest::make_request("foo", 1)).size_hint();
+
+    let config = Arc::new(Config::default().validate()?);
+    let mut router = RaftRouter::new(config.clone());
+
+    let mut log_index = router.new_cluster(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let leader = router.get_raft_handle(&0)?;
+    let term = router.get_metrics(&0)?.current_term;
+
+    tracing::info!("--- limit the replication rate: sending an entry takes 4 seconds");
+    {
+        leader.set_replication_target_max_bytes_per_sec(entry_size / 4);
+
+        // The first entry is sent at once, the next one has to wait for 4 seconds.
+        router.client_request(0, "foo", 0).await?;
+        log_index += 1;
+
+        router.wait_for_log(&btreeset! {0,1,2}, Some(log_index), timeout(), "first entry").await?;
+
+        let r = router.clone();
+        tokio::spawn(async move { r.client_request(0, "foo", 1).await });
+    }
+
+    tracing::info!("--- the followers receive heartbeats while the replication is blocked");
+    {
+        let now = Instant::now();
+        sleep(Duration::from_millis(1_000)).await;
+
+        for node_id in [1, 2] {
+            let m = router.get_metrics(&node_id)?;
+            assert_eq!(Some(log_index), m.last_log_index);
+            assert_eq!(term, m.current_term, "node {} does not elect", node_id);
+            assert_eq!(Some(0), m.current_leader);
+
+            // leader lease is extended.
+            let (tx, rx) = oneshot::channel();
+            router.external_request(node_id, move |state, _store, _net| {
+                let _ = tx.send(state.vote_last_modified());
+            });
+            let vote_last_modified = rx.await?;
+            assert!(
+                vote_last_modified > Some(now + Duration::from_millis(500)),
+                "node {} vote last modified: {:?}",
+                node_id,
+                vote_last_modified
+            );
+        }
+
+        let m = router.get_metrics(&0)?;
+        assert_eq!(term, m.current_term);
+        assert_eq!(Some(0), m.current_leader);
+    }
+
+    tracing::info!("--- remove the limit, the entry is replicated");
+    {
+        leader.set_replication_target_max_bytes_per_sec(0);
+        log_index += 1;
+
+        router
+            .wait_for_log(
+                &btreeset! {0,1,2},
+                Some(log_index),
+                Some(Duration::from_millis(5_000)),
+                "second entry",
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(1_000))
+}
@@ -0,0 +1,98 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::entry::FromAppData;
+use openraft::entry::RaftEntry;
+use openraft::Config;
+use openraft::Entry;
+use openraft_memstore::ClientRequest;
+use openraft_memstore::Config as MemConfig;
+use openraft_memstore::IntoMemClientRequest;
+
+use crate::fixtures::init_default_ut_tracing;
+use crate::fixtures::RaftRouter;
+
+/// A learner catches up when the logs are sent in batches of several entries limited by
+/// `max_payload_bytes`.
+#[async_entry::test(worker_threads = 8, init = "init_default_ut_tracing()", tracing_span = "debug")]
+async fn max_payload_bytes() -> Result<()> {
+    catch_up_with_max_payload_bytes(3 * entry_size(), None).await
+}
+
+/// A learner catches up when `max_payload_bytes` is smaller than an entry: every RPC still sends
+/// one entry.
+#[async_entry::test(worker_threads = 8, init = "init_default_ut_tracing()", tracing_span = "debug")]
+async fn max_payload_bytes_smaller_than_entry() -> Result<()> {
+    catch_up_with_max_payload_bytes(1, None).await
+}
+
+/// A learner catches up when one entry among the small ones is larger than `max_payload_bytes`:
+/// it is sent alone in one RPC.
+#[async_entry::test(worker_threads = 8, init = "init_default_ut_tracing()", tracing_span = "debug")]
+async fn max_payload_bytes_with_a_large_entry() -> Result<()> {
+    let max_payload_bytes = 3 * entry_size();
+
+    let large = ClientRequest {
+        client: "foo".to_string(),
+        serial: 1_000,
+        status: "x".repeat(10 * max_payload_bytes as usize),
+    };
+
+    assert!(Entry::<MemConfig>::from_app_data(large.clone()).size_hint() > max_payload_bytes);
+
+    catch_up_with_max_payload_bytes(max_payload_bytes, Some(large)).await
+}
+
+/// The size of an entry of a small request.
+fn entry_size() -> u64 {
+    Entry::<MemConfig>::from_app_data(ClientRequest::make_request("foo", 10)).size_hint()
+}
+
+/// Write logs, with a `large` one in the middle if it is given, add a learner, and wait for it to
+/// catch up.
+async fn catch_up_with_max_payload_bytes(max_payload_bytes: u64, large: Option<ClientRequest>) -> Result<()> {
+    let config = Arc::new(
+        Config {
+            enable_heartbeat: false,
+            max_payload_bytes,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let mut router = RaftRouter::new(config.clone());
+
+    let mut log_index = router.new_cluster(btreeset! {0}, btreeset! {}).await?;
+
+    log_index += router.client_request_many(0, "foo", 10).await?;
+
+    if let Some(large) = large {
+        router.send_client_request(0, large).await?;
+        log_index += 1;
+    }
+
+    log_index += router.client_request_many(0, "foo", 10).await?;
+
+    tracing::info!("--- add a learner");
+    {
+        router.new_raft_node(1).await;
+        router.add_learner(0, 1).await?;
+        log_index += 1;
+
+        router.wait_for_log(&btreeset! {0, 1}, Some(log_index), timeout(), "learner-1 caught up").await?;
+    }
+
+    tracing::info!("--- write more logs");
+    {
+        log_index += router.client_request_many(0, "foo", 20).await?;
+
+        router.wait_for_log(&btreeset! {0, 1}, Some(log_index), timeout(), "learner-1 replicated").await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(3_000))
+}
@@ -0,0 +1,104 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::entry::FromAppData;
+use openraft::entry::RaftEntry;
+use openraft::Config;
+use openraft::Entry;
+use openraft_memstore::ClientRequest;
+use openraft_memstore::Config as MemConfig;
+use openraft_memstore::IntoMemClientRequest;
+use tokio::time::Instant;
+
+use crate::fixtures::init_default_ut_tracing;
+use crate::fixtures::RaftRouter;
+
+/// Replication to a learner is limited by the learner rate limit, which can be changed at runtime.
+///
+/// - Write logs, then add a learner with a rate limit that takes about 1 second to send the logs.
+/// - The time the replication waited is reported in the replication metrics.
+/// - Promote the learner to a voter: it is no longer limited by the learner rate limit.
+/// - Remove the limit at runtime: another learner catches up at once.
+#[async_entry::test(worker_threads = 8, init = "init_default_ut_tracing()", tracing_span = "debug")]
+async fn replication_throttle() -> Result<()> {
+    let entry_size = Entry::<MemConfig>::from_app_data(ClientRequest::make_request("foo", 10)).size_hint();
+
+    let config = Arc::new(
+        Config {
+            enable_heartbeat: false,
+            max_payload_entries: 10,
+            // 20 entries per second: every batch of 10 entries takes 500 ms.
+            replication_learner_max_bytes_per_sec: 20 * entry_size,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let mut router = RaftRouter::new(config.clone());
+
+    let mut log_index = router.new_cluster(btreeset! {0}, btreeset! {}).await?;
+
+    log_index += router.client_request_many(0, "foo", 30).await?;
+
+    tracing::info!("--- add a throttled learner");
+    {
+        let now = Instant::now();
+
+        router.new_raft_node(1).await;
+        router.add_learner(0, 1).await?;
+        log_index += 1;
+
+        router.wait_for_log(&btreeset! {1}, Some(log_index), timeout(), "learner-1 caught up").await?;
+
+        let elapsed = now.elapsed();
+        assert!(elapsed >= Duration::from_millis(900), "elapsed: {:?}", elapsed);
+
+        let m = router.get_metrics(&0)?;
+        let repl = m.replication.unwrap();
+        let throttled = repl.data().replication.get(&1).unwrap().throttled();
+        assert!(throttled >= Duration::from_millis(500), "throttled: {:?}", throttled);
+    }
+
+    tracing::info!("--- promote the learner to a voter, it is not limited any more");
+    {
+        let leader = router.get_raft_handle(&0)?;
+        leader.change_membership(btreeset! {0, 1}, false).await?;
+        log_index += 2;
+
+        let now = Instant::now();
+
+        log_index += router.client_request_many(0, "foo", 30).await?;
+        router.wait_for_log(&btreeset! {0, 1}, Some(log_index), timeout(), "voter-1 replicated").await?;
+
+        let elapsed = now.elapsed();
+        assert!(elapsed < Duration::from_millis(500), "elapsed: {:?}", elapsed);
+    }
+
+    tracing::info!("--- remove the limit at runtime");
+    {
+        let leader = router.get_raft_handle(&0)?;
+        leader.set_replication_learner_max_bytes_per_sec(0);
+
+        let now = Instant::now();
+
+        router.new_raft_node(2).await;
+        router.add_learner(0, 2).await?;
+        log_index += 1;
+
+        router.wait_for_log(&btreeset! {1, 2}, Some(log_index), timeout(), "learner-2 caught up").await?;
+
+        let elapsed = now.elapsed();
+        assert!(elapsed < Duration::from_millis(500), "elapsed: {:?}", elapsed);
+
+        let m = router.get_metrics(&0)?;
+        let repl = m.replication.unwrap();
+        assert_eq!(Duration::ZERO, repl.data().replication.get(&2).unwrap().throttled());
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(3_000))
+}
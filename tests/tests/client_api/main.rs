@@ -10,4 +10,7 @@ mod fixtures;
 
 mod t10_client_writes;
 mod t20_client_reads;
+mod t30_wait_applied;
+mod t40_write_backpressure;
 mod t50_lagging_network_write;
+mod t60_duplicate_write;
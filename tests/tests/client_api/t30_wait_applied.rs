@@ -0,0 +1,145 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::error::LogIdMismatch;
+use openraft::error::WaitAppliedError;
+use openraft::Config;
+use openraft::RaftLogReader;
+use openraft_memstore::ClientRequest;
+use openraft_memstore::IntoMemClientRequest;
+use tokio::time::timeout;
+
+use crate::fixtures::init_default_ut_tracing;
+use crate::fixtures::log_id;
+use crate::fixtures::RaftRouter;
+
+/// A learner waits for a log written through the leader to be applied.
+///
+/// - A learner is woken up when the log is replicated and applied.
+/// - Waiting for a log that is already applied returns.
+/// - Waiting for a log that is never applied times out.
+/// - Waiting for a log whose index holds another log returns `LogIdMismatch`.
+#[async_entry::test(worker_threads = 8, init = "init_default_ut_tracing()", tracing_span = "debug")]
+async fn wait_applied() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            enable_tick: false,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let mut router = RaftRouter::new(config.clone());
+    let log_index = router.new_cluster(btreeset! {0,1,2}, btreeset! {3}).await?;
+
+    let leader = router.get_raft_handle(&0)?;
+    let learner = router.get_raft_handle(&3)?;
+
+    tracing::info!("--- isolate the learner and write a log");
+
+    router.isolate_node(3);
+
+    let resp = leader.client_write(ClientRequest::make_request("foo", 1)).await?;
+    let written = resp.log_id;
+    assert_eq!(log_index + 1, written.index);
+
+    let mut waiting = tokio::spawn({
+        let learner = learner.clone();
+        async move { learner.wait_applied(written, Duration::from_millis(5_000)).await }
+    });
+
+    let res = timeout(Duration::from_millis(500), &mut waiting).await;
+    assert!(res.is_err(), "the learner has not received the log");
+
+    tracing::info!("--- restore the learner, it is woken up when the log is applied");
+
+    router.restore_node(3);
+    leader.trigger_heartbeat().await?;
+
+    waiting.await??;
+
+    tracing::info!("--- an applied log returns");
+
+    learner.wait_applied(written, Duration::from_millis(1_000)).await?;
+
+    tracing::info!("--- a log that is not applied times out");
+
+    let res = learner.wait_applied(log_id(1, 0, written.index + 10), Duration::from_millis(100)).await;
+    let err = res.unwrap_err().into_api_error().unwrap();
+    assert!(matches!(err, WaitAppliedError::Timeout(_)), "got: {:?}", err);
+
+    tracing::info!("--- another log at the index");
+
+    let other = log_id(5, 0, written.index);
+    let res = learner.wait_applied(other, Duration::from_millis(1_000)).await;
+    let err = res.unwrap_err().into_api_error().unwrap();
+    assert_eq!(
+        WaitAppliedError::LogIdMismatch(LogIdMismatch {
+            expect: other,
+            got: Some(written),
+        }),
+        err
+    );
+
+    Ok(())
+}
+
+/// Waiting for a log that is purged.
+///
+/// - The last purged log returns.
+/// - A purged log before it is applied and returns.
+/// - A log with a greater leader id than the last purged one returns `LogIdMismatch`.
+#[async_entry::test(worker_threads = 8, init = "init_default_ut_tracing()", tracing_span = "debug")]
+async fn wait_applied_purged() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            max_in_snapshot_log_to_keep: 0,
+            purge_batch_size: 1,
+            enable_tick: false,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let mut router = RaftRouter::new(config.clone());
+    router.new_cluster(btreeset! {0}, btreeset! {}).await?;
+
+    let leader = router.get_raft_handle(&0)?;
+
+    tracing::info!("--- write 2 logs, build a snapshot and purge them");
+
+    let first = leader.client_write(ClientRequest::make_request("foo", 1)).await?.log_id;
+    let last = leader.client_write(ClientRequest::make_request("foo", 2)).await?.log_id;
+
+    leader.trigger_snapshot().await?;
+    leader.wait(Some(Duration::from_millis(1_000))).snapshot(last, "build snapshot").await?;
+
+    let mut sto = router.get_storage_handle(&0)?;
+    let logs = sto.try_get_log_entries(..).await?;
+    assert!(logs.is_empty(), "logs are purged");
+
+    tracing::info!("--- the last purged log returns");
+
+    leader.wait_applied(last, Duration::from_millis(1_000)).await?;
+
+    tracing::info!("--- a purged log before the last purged one returns");
+
+    leader.wait_applied(first, Duration::from_millis(1_000)).await?;
+
+    tracing::info!("--- a log with a greater leader id is another log");
+
+    let other = log_id(5, 0, first.index);
+    let res = leader.wait_applied(other, Duration::from_millis(1_000)).await;
+    let err = res.unwrap_err().into_api_error().unwrap();
+    assert_eq!(
+        WaitAppliedError::LogIdMismatch(LogIdMismatch {
+            expect: other,
+            got: None,
+        }),
+        err
+    );
+
+    Ok(())
+}
@@ -0,0 +1,205 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::error::Backpressure;
+use openraft::error::ClientWriteError;
+use openraft::Config;
+use openraft_memstore::ClientRequest;
+use openraft_memstore::IntoMemClientRequest;
+use tokio::time::timeout;
+use tokio::time::Instant;
+
+use crate::fixtures::init_default_ut_tracing;
+use crate::fixtures::RaftRouter;
+
+/// A write fails at once when the uncommitted entries on the leader reach the limit.
+///
+/// - Isolate the followers, so that no entry can be committed.
+/// - Fill up the limit with writes, the usage is reported in metrics.
+/// - The next write fails with `Backpressure`.
+#[async_entry::test(worker_threads = 8, init = "init_default_ut_tracing()", tracing_span = "debug")]
+async fn write_backpressure_fail_fast() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            enable_heartbeat: false,
+            enable_elect: false,
+            max_uncommitted_entries: 3,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let mut router = RaftRouter::new(config.clone());
+    router.new_cluster(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let leader = router.get_raft_handle(&0)?;
+
+    router.isolate_node(1);
+    router.isolate_node(2);
+
+    tracing::info!("--- fill up the uncommitted entries");
+    for i in 0..3 {
+        let leader = leader.clone();
+        tokio::spawn(async move { leader.client_write(ClientRequest::make_request("foo", i)).await });
+    }
+
+    router
+        .wait(&0, timeout_ms(1_000))
+        .metrics(|x| x.uncommitted_entries == 3, "3 uncommitted entries")
+        .await?;
+
+    tracing::info!("--- the next write fails");
+    {
+        let res = leader.client_write(ClientRequest::make_request("foo", 3)).await;
+        let err = res.unwrap_err().into_api_error().unwrap();
+
+        assert_eq!(
+            ClientWriteError::Backpressure(Backpressure {
+                uncommitted_entries: 3,
+                uncommitted_bytes: router.get_metrics(&0)?.uncommitted_bytes,
+                max_entries: 3,
+                max_bytes: 0,
+            }),
+            err
+        );
+    }
+
+    Ok(())
+}
+
+/// A write waits for the uncommitted entries on the leader to drop below the limit.
+///
+/// - A write waits when the limit is reached, and is written when the entries are committed.
+/// - A write fails with `Backpressure` if the entries are not committed in time.
+#[async_entry::test(worker_threads = 8, init = "init_default_ut_tracing()", tracing_span = "debug")]
+async fn write_backpressure_wait() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            enable_heartbeat: false,
+            enable_elect: false,
+            max_uncommitted_entries: 3,
+            backpressure_timeout: 1_000,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let mut router = RaftRouter::new(config.clone());
+    let log_index = router.new_cluster(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let leader = router.get_raft_handle(&0)?;
+
+    router.isolate_node(1);
+    router.isolate_node(2);
+
+    tracing::info!("--- fill up the uncommitted entries");
+    for i in 0..3 {
+        let leader = leader.clone();
+        tokio::spawn(async move { leader.client_write(ClientRequest::make_request("foo", i)).await });
+    }
+
+    router
+        .wait(&0, timeout_ms(1_000))
+        .metrics(|x| x.uncommitted_entries == 3, "3 uncommitted entries")
+        .await?;
+
+    tracing::info!("--- a write waits until the entries are committed");
+    {
+        let mut writing = tokio::spawn({
+            let leader = leader.clone();
+            async move { leader.client_write(ClientRequest::make_request("foo", 3)).await }
+        });
+
+        let res = timeout(Duration::from_millis(300), &mut writing).await;
+        assert!(res.is_err(), "the write is waiting");
+
+        router.restore_node(1);
+        router.restore_node(2);
+        leader.trigger_heartbeat().await?;
+
+        let resp = writing.await??;
+        assert_eq!(log_index + 4, resp.log_id.index);
+    }
+
+    tracing::info!("--- a write fails if the entries are not committed in time");
+    {
+        router.isolate_node(1);
+        router.isolate_node(2);
+
+        for i in 4..7 {
+            let leader = leader.clone();
+            tokio::spawn(async move { leader.client_write(ClientRequest::make_request("foo", i)).await });
+        }
+
+        router
+            .wait(&0, timeout_ms(1_000))
+            .metrics(|x| x.uncommitted_entries == 3, "3 uncommitted entries")
+            .await?;
+
+        let res = leader.client_write(ClientRequest::make_request("foo", 7)).await;
+        let err = res.unwrap_err().into_api_error().unwrap();
+        assert!(matches!(err, ClientWriteError::Backpressure(_)), "got: {:?}", err);
+    }
+
+    Ok(())
+}
+
+/// A held write fails when `backpressure_timeout` passes, even if the leader receives no other
+/// message, e.g., the tick is disabled.
+#[async_entry::test(worker_threads = 8, init = "init_default_ut_tracing()", tracing_span = "debug")]
+async fn write_backpressure_timeout_without_tick() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            enable_tick: false,
+            enable_heartbeat: false,
+            enable_elect: false,
+            max_uncommitted_entries: 3,
+            backpressure_timeout: 500,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let mut router = RaftRouter::new(config.clone());
+    router.new_cluster(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let leader = router.get_raft_handle(&0)?;
+
+    router.isolate_node(1);
+    router.isolate_node(2);
+
+    tracing::info!("--- fill up the uncommitted entries");
+    for i in 0..3 {
+        let leader = leader.clone();
+        tokio::spawn(async move { leader.client_write(ClientRequest::make_request("foo", i)).await });
+    }
+
+    router
+        .wait(&0, timeout_ms(1_000))
+        .metrics(|x| x.uncommitted_entries == 3, "3 uncommitted entries")
+        .await?;
+
+    tracing::info!("--- the held write fails in time");
+    {
+        let now = Instant::now();
+
+        let res = timeout(
+            Duration::from_millis(2_000),
+            leader.client_write(ClientRequest::make_request("foo", 3)),
+        )
+        .await?;
+        let err = res.unwrap_err().into_api_error().unwrap();
+        assert!(matches!(err, ClientWriteError::Backpressure(_)), "got: {:?}", err);
+
+        let elapsed = now.elapsed();
+        assert!(elapsed >= Duration::from_millis(450), "elapsed: {:?}", elapsed);
+    }
+
+    Ok(())
+}
+
+fn timeout_ms(ms: u64) -> Option<Duration> {
+    Some(Duration::from_millis(ms))
+}
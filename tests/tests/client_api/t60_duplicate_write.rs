@@ -0,0 +1,73 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::RaftStorageDebug;
+use openraft_memstore::ClientRequest;
+use openraft_memstore::ClientResponse;
+
+use crate::fixtures::init_default_ut_tracing;
+use crate::fixtures::RaftRouter;
+
+/// A write resent by a client is applied only once.
+///
+/// - Resending the last write of a client returns the cached response and does not change the state
+///   machine, even if the resent write carries other data.
+/// - A write with another serial is applied.
+#[async_entry::test(worker_threads = 8, init = "init_default_ut_tracing()", tracing_span = "debug")]
+async fn duplicate_write() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            enable_tick: false,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let mut router = RaftRouter::new(config.clone());
+    let mut log_index = router.new_cluster(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let leader = router.get_raft_handle(&0)?;
+
+    let req = |serial: u64, status: &str| ClientRequest {
+        client: "foo".to_string(),
+        serial,
+        status: status.to_string(),
+    };
+
+    tracing::info!("--- write two requests");
+    {
+        let resp = leader.client_write(req(1, "a")).await?;
+        assert_eq!(ClientResponse(None), resp.data);
+
+        let resp = leader.client_write(req(2, "b")).await?;
+        assert_eq!(ClientResponse(Some("a".to_string())), resp.data);
+        log_index += 2;
+    }
+
+    tracing::info!("--- resend the last request: it is not applied again");
+    {
+        let resp = leader.client_write(req(2, "c")).await?;
+        assert_eq!(ClientResponse(Some("a".to_string())), resp.data, "the cached response");
+        log_index += 1;
+        assert_eq!(log_index, resp.log_id.index, "a duplicate is still replicated");
+    }
+
+    router.wait_for_log(&btreeset! {0,1,2}, Some(log_index), None, "duplicate replicated").await?;
+
+    for id in [0, 1, 2] {
+        let mut sto = router.get_storage_handle(&id)?;
+        let sm = sto.get_state_machine().await;
+        assert_eq!(Some(&"b".to_string()), sm.client_status.get("foo"), "node-{}", id);
+        assert_eq!(2, sm.sessions.get(&"foo".to_string()).unwrap().serial, "node-{}", id);
+    }
+
+    tracing::info!("--- a request with another serial is applied");
+    {
+        let resp = leader.client_write(req(3, "c")).await?;
+        assert_eq!(ClientResponse(Some("b".to_string())), resp.data);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::error::ClientWriteError;
+use openraft::Config;
+use openraft::ServerState;
+use openraft_memstore::ClientRequest;
+use openraft_memstore::IntoMemClientRequest;
+
+use crate::fixtures::init_default_ut_tracing;
+use crate::fixtures::RaftRouter;
+
+/// A leader that can not reach a quorum steps down, with check-quorum enabled.
+///
+/// - Isolate the leader and write to it: the write can not be committed.
+/// - The leader steps down when no quorum responds within the election timeout.
+/// - The pending write fails with `ForwardToLeader`.
+#[async_entry::test(worker_threads = 8, init = "init_default_ut_tracing()", tracing_span = "debug")]
+async fn check_quorum_step_down() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            enable_check_quorum: true,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let mut router = RaftRouter::new(config.clone());
+
+    let log_index = router.new_cluster(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- the leader keeps hearing from a quorum");
+    {
+        tokio::time::sleep(Duration::from_millis(1_000)).await;
+
+        let m = router.get_metrics(&0)?;
+        assert_eq!(ServerState::Leader, m.state);
+        assert_eq!(Some(log_index), m.last_log_index);
+    }
+
+    tracing::info!("--- isolate the leader and write to it");
+
+    router.isolate_node(0);
+
+    let leader = router.get_raft_handle(&0)?;
+    let writing = tokio::spawn({
+        let leader = leader.clone();
+        async move { leader.client_write(ClientRequest::make_request("foo", 1)).await }
+    });
+
+    tracing::info!("--- the leader steps down");
+    {
+        router.wait(&0, timeout()).metrics(|x| x.state != ServerState::Leader, "node-0 steps down").await?;
+
+        let res = writing.await?;
+        let err = res.unwrap_err().into_api_error().unwrap();
+        assert!(
+            matches!(err, ClientWriteError::ForwardToLeader(_)),
+            "expect ForwardToLeader, got: {:?}",
+            err
+        );
+    }
+
+    tracing::info!("--- the other nodes elect a new leader");
+    {
+        router
+            .wait(&1, timeout())
+            .metrics(
+                |x| x.current_leader.is_some() && x.current_leader != Some(0),
+                "node-1 sees a new leader",
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(2_000))
+}
@@ -5,13 +5,10 @@
 #[cfg(feature = "bt")] use std::backtrace::Backtrace;
 use std::collections::BTreeMap;
 use std::collections::BTreeSet;
-use std::collections::HashSet;
 use std::env;
 use std::fmt::Debug;
 use std::marker::PhantomData;
 use std::panic::PanicInfo;
-use std::sync::atomic::AtomicU64;
-use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::Once;
@@ -26,6 +23,7 @@ use openraft::entry::RaftEntry;
 use openraft::error::CheckIsLeaderError;
 use openraft::error::ClientWriteError;
 use openraft::error::InstallSnapshotError;
+use openraft::error::JoinError;
 use openraft::error::NetworkError;
 use openraft::error::RPCError;
 use openraft::error::RaftError;
@@ -34,12 +32,18 @@ use openraft::metrics::Wait;
 use openraft::raft::AppendEntriesRequest;
 use openraft::raft::AppendEntriesResponse;
 use openraft::raft::ClientWriteResponse;
+use openraft::raft::HeartbeatRequest;
+use openraft::raft::HeartbeatResponse;
 use openraft::raft::InstallSnapshotRequest;
 use openraft::raft::InstallSnapshotResponse;
+use openraft::raft::JoinRequest;
+use openraft::raft::JoinResponse;
 use openraft::raft::VoteRequest;
 use openraft::raft::VoteResponse;
 use openraft::storage::RaftLogReader;
 use openraft::storage::RaftStorage;
+use openraft::testing::FaultInjector;
+use openraft::testing::FaultNetworkFactory;
 use openraft::CommittedLeaderId;
 use openraft::Config;
 use openraft::DefensiveCheckBase;
@@ -72,7 +76,8 @@ pub mod logging;
 pub type StoreWithDefensive<C = MemConfig, S = Arc<MemStore>> = StoreExt<C, S>;
 
 /// A concrete Raft type used during testing.
-pub type MemRaft<C = MemConfig, S = Arc<MemStore>> = Raft<C, TypedRaftRouter<C, S>, StoreWithDefensive<C, S>>;
+pub type MemRaft<C = MemConfig, S = Arc<MemStore>> =
+    Raft<C, FaultNetworkFactory<C, TypedRaftRouter<C, S>>, StoreWithDefensive<C, S>>;
 
 pub fn init_default_ut_tracing() {
     static START: Once = Once::new();
@@ -135,6 +140,8 @@ pub fn log_panic(panic: &PanicInfo) {
 }
 
 /// A type which emulates a network transport and implements the `RaftNetworkFactory` trait.
+///
+/// Every node wraps it in a [`FaultNetworkFactory`] to inject network faults.
 pub struct TypedRaftRouter<C: RaftTypeConfig = openraft_memstore::Config, S: RaftStorage<C> = Arc<MemStore>>
 where
     C::D: Debug + IntoMemClientRequest<C::D>,
@@ -147,12 +154,11 @@ where
     #[allow(clippy::type_complexity)]
     routing_table: Arc<Mutex<BTreeMap<C::NodeId, (MemRaft<C, S>, StoreWithDefensive<C, S>)>>>,
 
-    /// Nodes which are isolated can neither send nor receive frames.
-    isolated_nodes: Arc<Mutex<HashSet<C::NodeId>>>,
+    /// Network faults injected into the RPCs between nodes, such as isolated nodes and send delay.
+    faults: FaultInjector<C::NodeId>,
 
-    /// To emulate network delay for sending, in milliseconds.
-    /// 0 means no delay.
-    send_delay: Arc<AtomicU64>,
+    /// The offsets of the InstallSnapshot requests delivered to every node, in delivery order.
+    snapshot_offsets: Arc<Mutex<BTreeMap<C::NodeId, Vec<u64>>>>,
 }
 
 /// Default `RaftRouter` for memstore.
@@ -186,11 +192,14 @@ where
                 self.send_delay
             }
         };
+        let faults = FaultInjector::new();
+        faults.set_latency(Duration::from_millis(0), Duration::from_millis(send_delay));
+
         TypedRaftRouter {
             config: self.config,
             routing_table: Default::default(),
-            isolated_nodes: Default::default(),
-            send_delay: Arc::new(AtomicU64::new(send_delay)),
+            faults,
+            snapshot_offsets: Default::default(),
         }
     }
 }
@@ -205,8 +214,8 @@ where
         Self {
             config: self.config.clone(),
             routing_table: self.routing_table.clone(),
-            isolated_nodes: self.isolated_nodes.clone(),
-            send_delay: self.send_delay.clone(),
+            faults: self.faults.clone(),
+            snapshot_offsets: self.snapshot_offsets.clone(),
         }
     }
 }
@@ -230,20 +239,25 @@ where
         Self::builder(config).build()
     }
 
+    /// Set the max random delay for sending an RPC, in milliseconds. 0 means no delay.
     pub fn network_send_delay(&mut self, ms: u64) {
-        self.send_delay.store(ms, Ordering::Relaxed);
+        self.faults.set_latency(Duration::from_millis(0), Duration::from_millis(ms));
     }
 
-    #[tracing::instrument(level = "debug", skip(self))]
-    async fn rand_send_delay(&self) {
-        let send_delay = self.send_delay.load(Ordering::Relaxed);
-        if send_delay == 0 {
-            return;
-        }
+    /// The fault injector shared by the network of every node, to inject more kinds of faults.
+    pub fn faults(&self) -> &FaultInjector<C::NodeId> {
+        &self.faults
+    }
+
+    /// Returns the offsets of the InstallSnapshot requests delivered to `target`, in order.
+    pub fn snapshot_offsets(&self, target: C::NodeId) -> Vec<u64> {
+        let offsets = self.snapshot_offsets.lock().unwrap();
+        offsets.get(&target).cloned().unwrap_or_default()
+    }
 
-        let r = rand::random::<u64>() % send_delay;
-        let timeout = Duration::from_millis(r);
-        tokio::time::sleep(timeout).await;
+    /// Forget the recorded offsets of the InstallSnapshot requests delivered to every node.
+    pub fn clear_snapshot_offsets(&self) {
+        self.snapshot_offsets.lock().unwrap().clear();
     }
 
     /// Create a cluster: 0 is the initial leader, others are voters and learners
@@ -360,7 +374,8 @@ where
 
     #[tracing::instrument(level = "debug", skip(self, sto))]
     pub async fn new_raft_node_with_sto(&mut self, id: C::NodeId, sto: StoreWithDefensive<C, S>) {
-        let node = Raft::new(id, self.config.clone(), self.clone(), sto.clone()).await.unwrap();
+        let network = FaultNetworkFactory::new(id, self.clone(), self.faults.clone());
+        let node = Raft::new(id, self.config.clone(), network, sto.clone()).await.unwrap();
         let mut rt = self.routing_table.lock().unwrap();
         rt.insert(id, (node, sto));
     }
@@ -372,10 +387,7 @@ where
             rt.remove(&id)
         };
 
-        {
-            let mut isolated = self.isolated_nodes.lock().unwrap();
-            isolated.remove(&id);
-        }
+        self.faults.restore_node(id);
 
         opt_handles
     }
@@ -396,7 +408,7 @@ where
     /// Isolate the network of the specified node.
     #[tracing::instrument(level = "debug", skip(self))]
     pub fn isolate_node(&self, id: C::NodeId) {
-        self.isolated_nodes.lock().unwrap().insert(id);
+        self.faults.isolate_node(id);
     }
 
     /// Get a payload of the latest metrics from each node in the cluster.
@@ -527,10 +539,7 @@ where
 
     /// Get the ID of the current leader.
     pub fn leader(&self) -> Option<C::NodeId> {
-        let isolated = {
-            let isolated = self.isolated_nodes.lock().unwrap();
-            isolated.clone()
-        };
+        let isolated = self.faults.isolated_nodes();
 
         tracing::debug!("router::leader: isolated: {:?}", isolated);
 
@@ -550,8 +559,7 @@ where
     /// Restore the network of the specified node.
     #[tracing::instrument(level = "debug", skip(self))]
     pub fn restore_node(&self, id: C::NodeId) {
-        let mut nodes = self.isolated_nodes.lock().unwrap();
-        nodes.remove(&id);
+        self.faults.restore_node(id);
     }
 
     /// Bring up a new learner and add it to the leader's membership.
@@ -718,10 +726,7 @@ where
     /// log index and last applied log match the given value. Else, the leader's last_log_index
     /// will be used for the assertion.
     pub fn assert_stable_cluster(&self, expected_term: Option<u64>, expected_last_log: Option<u64>) {
-        let isolated = {
-            let x = self.isolated_nodes.lock().unwrap();
-            x.clone()
-        };
+        let isolated = self.faults.isolated_nodes();
         let nodes = self.latest_metrics();
 
         let non_isolated_nodes: Vec<_> = nodes.iter().filter(|node| !isolated.contains(&node.id)).collect();
@@ -921,18 +926,6 @@ where
 
         Ok(())
     }
-
-    #[tracing::instrument(level = "debug", skip(self))]
-    pub fn check_reachable(&self, id: C::NodeId, target: C::NodeId) -> Result<(), NetworkError> {
-        let isolated = self.isolated_nodes.lock().unwrap();
-
-        if isolated.contains(&target) || isolated.contains(&id) {
-            let network_err = NetworkError::new(&AnyError::error(format!("isolated:{} -> {}", id, target)));
-            return Err(network_err);
-        }
-
-        Ok(())
-    }
 }
 
 #[async_trait]
@@ -975,9 +968,6 @@ where
         rpc: AppendEntriesRequest<C>,
     ) -> Result<AppendEntriesResponse<C::NodeId>, RPCError<C::NodeId, C::Node, RaftError<C::NodeId>>> {
         tracing::debug!("append_entries to id={} {}", self.target, rpc.summary());
-        self.owner.check_reachable(rpc.vote.leader_id().voted_for().unwrap(), self.target)?;
-        self.owner.rand_send_delay().await;
-
         let node = self.owner.get_raft_handle(&self.target)?;
 
         let resp = node.append_entries(rpc).await;
@@ -987,6 +977,21 @@ where
         Ok(resp)
     }
 
+    /// Send a heartbeat RPC to the target Raft node.
+    async fn send_heartbeat(
+        &mut self,
+        rpc: HeartbeatRequest<C::NodeId>,
+    ) -> Result<HeartbeatResponse<C::NodeId>, RPCError<C::NodeId, C::Node, RaftError<C::NodeId>>> {
+        tracing::debug!("heartbeat to id={} {}", self.target, rpc.summary());
+        let node = self.owner.get_raft_handle(&self.target)?;
+
+        let resp = node.heartbeat(rpc).await;
+
+        tracing::debug!("heartbeat: recv resp from id={} {:?}", self.target, resp);
+        let resp = resp.map_err(|e| RemoteError::new(self.target, e))?;
+        Ok(resp)
+    }
+
     /// Send an InstallSnapshot RPC to the target Raft node (§7).
     async fn send_install_snapshot(
         &mut self,
@@ -995,11 +1000,13 @@ where
         InstallSnapshotResponse<C::NodeId>,
         RPCError<C::NodeId, C::Node, RaftError<C::NodeId, InstallSnapshotError>>,
     > {
-        self.owner.check_reachable(rpc.vote.leader_id().voted_for().unwrap(), self.target)?;
-        self.owner.rand_send_delay().await;
-
         let node = self.owner.get_raft_handle(&self.target)?;
 
+        {
+            let mut offsets = self.owner.snapshot_offsets.lock().unwrap();
+            offsets.entry(self.target).or_default().push(rpc.offset);
+        }
+
         let resp = node.install_snapshot(rpc).await;
         let resp = resp.map_err(|e| RemoteError::new(self.target, e))?;
         Ok(resp)
@@ -1010,15 +1017,27 @@ where
         &mut self,
         rpc: VoteRequest<C::NodeId>,
     ) -> Result<VoteResponse<C::NodeId>, RPCError<C::NodeId, C::Node, RaftError<C::NodeId>>> {
-        self.owner.check_reachable(rpc.vote.leader_id().voted_for().unwrap(), self.target)?;
-        self.owner.rand_send_delay().await;
-
         let node = self.owner.get_raft_handle(&self.target)?;
 
         let resp = node.vote(rpc).await;
         let resp = resp.map_err(|e| RemoteError::new(self.target, e))?;
         Ok(resp)
     }
+
+    /// Send a Join RPC to the target Raft node.
+    async fn send_join(
+        &mut self,
+        rpc: JoinRequest<C::NodeId, C::Node>,
+    ) -> Result<
+        JoinResponse<C::NodeId, C::Node>,
+        RPCError<C::NodeId, C::Node, RaftError<C::NodeId, JoinError<C::NodeId, C::Node>>>,
+    > {
+        let node = self.owner.get_raft_handle(&self.target)?;
+
+        let resp = node.handle_join(rpc).await;
+        let resp = resp.map_err(|e| RemoteError::new(self.target, e))?;
+        Ok(resp)
+    }
 }
 
 pub enum ValueTest<T> {
@@ -0,0 +1,80 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use maplit::btreeset;
+use openraft::error::Fatal;
+use openraft::Config;
+use openraft::LogIdOptionExt;
+use openraft::RaftStorage;
+
+use crate::fixtures::init_default_ut_tracing;
+use crate::fixtures::RaftRouter;
+
+/// A restarted follower applies the logs that are committed but not yet applied, without a leader.
+///
+/// - Fail applying on node-1, so that it saves the committed log id but quits before applying.
+/// - Restart node-1 while it is isolated from the leader.
+/// - node-1 applies the committed log at startup.
+#[async_entry::test(worker_threads = 8, init = "init_default_ut_tracing()", tracing_span = "debug")]
+async fn follower_restart_applies_committed() -> anyhow::Result<()> {
+    let config = Arc::new(
+        Config {
+            enable_heartbeat: false,
+            election_timeout_min: 3_000,
+            election_timeout_max: 4_000,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let mut router = RaftRouter::new(config.clone());
+
+    tracing::info!("--- bring up cluster of 3 nodes");
+    let mut log_index = router.new_cluster(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- node-1 fails to apply the next committed log");
+    {
+        let sto1 = router.get_storage_handle(&1)?;
+        sto1.fail_apply_to_state_machine_at(1);
+
+        router.client_request_many(0, "foo", 1).await?;
+        log_index += 1;
+
+        router
+            .wait(&1, timeout())
+            .metrics(
+                |m| matches!(m.running_state, Err(Fatal::StorageError(_))),
+                "node-1 quit with storage error",
+            )
+            .await?;
+    }
+
+    tracing::info!("--- restart node-1 while it is isolated");
+    {
+        router.isolate_node(1);
+
+        let (node, sto1) = router.remove_node(1).unwrap();
+        node.shutdown().await?;
+
+        let mut sto1 = sto1.restart();
+
+        let committed = sto1.read_committed().await?;
+        assert_eq!(Some(log_index), committed.index(), "committed log id is saved");
+
+        let (last_applied, _) = sto1.last_applied_state().await?;
+        assert_eq!(Some(log_index - 1), last_applied.index(), "the committed log is not applied");
+
+        router.new_raft_node_with_sto(1, sto1).await;
+    }
+
+    tracing::info!("--- node-1 applies the committed log without a leader");
+    {
+        router.wait(&1, timeout()).log(Some(log_index), "node-1 applied committed log").await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(1_000))
+}
@@ -0,0 +1,197 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use maplit::btreeset;
+use openraft::error::Fatal;
+use openraft::Config;
+use openraft::LogIdOptionExt;
+use openraft::RaftLogReader;
+
+use crate::fixtures::init_default_ut_tracing;
+use crate::fixtures::RaftRouter;
+
+/// A failure of `append_to_log()` shuts down `RaftCore` with `Fatal::StorageError`.
+///
+/// - Inject a failure into the next `append_to_log()` of the leader.
+/// - A client write fails and the leader quits with a storage error.
+/// - Restart the leader on the same store, it works again.
+#[async_entry::test(worker_threads = 8, init = "init_default_ut_tracing()", tracing_span = "debug")]
+async fn append_failure_shutdown_and_restart() -> anyhow::Result<()> {
+    let config = Arc::new(
+        Config {
+            enable_heartbeat: false,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let mut router = RaftRouter::new(config.clone());
+
+    tracing::info!("--- bring up cluster of 1 node");
+    let mut log_index = router.new_cluster(btreeset! {0}, btreeset! {}).await?;
+
+    tracing::info!("--- fail the next append_to_log, RaftCore quits");
+    {
+        let sto = router.get_storage_handle(&0)?;
+        sto.fail_append_to_log_at(1);
+
+        let res = router.client_request(0, "foo", 1).await;
+        assert!(res.is_err(), "client write fails on storage error");
+
+        router
+            .wait(&0, timeout())
+            .metrics(
+                |m| matches!(m.running_state, Err(Fatal::StorageError(_))),
+                "node-0 quit with storage error",
+            )
+            .await?;
+    }
+
+    tracing::info!("--- restart node-0");
+    {
+        let (node, sto) = router.remove_node(0).unwrap();
+        node.shutdown().await?;
+
+        router.new_raft_node_with_sto(0, sto.restart()).await;
+    }
+
+    tracing::info!("--- write to 1 log after restart");
+    {
+        router.client_request_many(0, "foo", 1).await?;
+        log_index += 1;
+
+        router.wait(&0, timeout()).log(Some(log_index), "node-0 works").await?;
+    }
+
+    Ok(())
+}
+
+/// A torn append on a follower leaves a prefix of the entries in its log. The follower recovers
+/// the remaining entries from the leader after restart.
+#[async_entry::test(worker_threads = 8, init = "init_default_ut_tracing()", tracing_span = "debug")]
+async fn torn_append_on_follower() -> anyhow::Result<()> {
+    let config = Arc::new(
+        Config {
+            enable_heartbeat: false,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let mut router = RaftRouter::new(config.clone());
+
+    tracing::info!("--- bring up cluster of 1 voter and 1 learner");
+    let mut log_index = router.new_cluster(btreeset! {0}, btreeset! {1}).await?;
+    let before = log_index;
+
+    tracing::info!("--- isolate node-1 and write 10 logs");
+    {
+        router.isolate_node(1);
+
+        router.client_request_many(0, "foo", 10).await?;
+        log_index += 10;
+
+        router.wait(&0, timeout()).log(Some(log_index), "leader received 10 logs").await?;
+    }
+
+    tracing::info!("--- node-1 persists only 1 entry of the first append and quits");
+    {
+        let sto1 = router.get_storage_handle(&1)?;
+        sto1.fail_append_to_log_torn_at(1, 1);
+
+        router.restore_node(1);
+
+        router
+            .wait(&1, timeout())
+            .metrics(
+                |m| matches!(m.running_state, Err(Fatal::StorageError(_))),
+                "node-1 quit with storage error",
+            )
+            .await?;
+    }
+
+    tracing::info!("--- restart node-1, it catches up with the leader");
+    {
+        let (node, sto1) = router.remove_node(1).unwrap();
+        node.shutdown().await?;
+
+        let mut sto1 = sto1.restart();
+        let log_state = sto1.get_log_state().await?;
+        assert_eq!(Some(before + 1), log_state.last_log_id.index(), "only the prefix is persisted");
+
+        router.new_raft_node_with_sto(1, sto1).await;
+        router.wait(&1, timeout()).log(Some(log_index), "node-1 caught up").await?;
+    }
+
+    Ok(())
+}
+
+/// A crash discards the writes that are not yet flushed.
+///
+/// - Delay writes on the leader, then crash its store while a client write is still in flight.
+/// - The leader quits with a storage error and the in-flight entry is not in the log.
+/// - Restart the leader on the state that survived the crash.
+#[async_entry::test(worker_threads = 8, init = "init_default_ut_tracing()", tracing_span = "debug")]
+async fn crash_discards_unflushed_writes() -> anyhow::Result<()> {
+    let config = Arc::new(
+        Config {
+            enable_heartbeat: false,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+
+    let mut router = RaftRouter::new(config.clone());
+
+    tracing::info!("--- bring up cluster of 1 node");
+    let mut log_index = router.new_cluster(btreeset! {0}, btreeset! {}).await?;
+
+    tracing::info!("--- crash the store while a write is delayed");
+    {
+        let sto = router.get_storage_handle(&0)?;
+        sto.set_write_latency(1_000);
+
+        let r = router.clone();
+        let h = tokio::spawn(async move { r.client_request(0, "foo", 1).await });
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        sto.crash();
+
+        let res = h.await?;
+        assert!(res.is_err(), "in-flight write fails");
+
+        router
+            .wait(&0, timeout())
+            .metrics(
+                |m| matches!(m.running_state, Err(Fatal::StorageError(_))),
+                "node-0 quit with storage error",
+            )
+            .await?;
+    }
+
+    tracing::info!("--- restart node-0, the in-flight entry is lost");
+    {
+        let (node, sto) = router.remove_node(0).unwrap();
+        node.shutdown().await?;
+
+        let mut sto = sto.restart();
+        let log_state = sto.get_log_state().await?;
+        assert_eq!(Some(log_index), log_state.last_log_id.index());
+
+        router.new_raft_node_with_sto(0, sto).await;
+    }
+
+    tracing::info!("--- write to 1 log after restart");
+    {
+        router.client_request_many(0, "foo", 1).await?;
+        log_index += 1;
+
+        router.wait(&0, timeout()).log(Some(log_index), "node-0 works").await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(2_000))
+}
@@ -139,12 +139,12 @@ async fn compaction() -> Result<()> {
         let res = router
             .new_client(1, &())
             .await
-            .send_append_entries(AppendEntriesRequest {
-                vote: Vote::new_committed(1, 0),
-                prev_log_id: Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
-                entries: vec![],
-                leader_commit: Some(LogId::new(CommittedLeaderId::new(0, 0), 0)),
-            })
+            .send_append_entries(AppendEntriesRequest::new(
+                Vote::new_committed(1, 0),
+                Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
+                vec![],
+                Some(LogId::new(CommittedLeaderId::new(0, 0), 0)),
+            ))
             .await?;
 
         tracing::debug!("--- append-entries res: {:?}", res);
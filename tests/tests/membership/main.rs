@@ -14,6 +14,7 @@ mod t10_add_learner;
 mod t12_concurrent_write_and_add_learner;
 mod t15_add_remove_follower;
 mod t16_change_membership_cases;
+mod t17_join;
 mod t20_change_membership;
 mod t25_elect_with_new_config;
 mod t30_commit_joint_config;
@@ -0,0 +1,103 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreemap;
+use maplit::btreeset;
+use openraft::error::JoinError;
+use openraft::Config;
+
+use crate::fixtures::init_default_ut_tracing;
+use crate::fixtures::RaftRouter;
+
+/// A new node joins a cluster through a seed that is not the leader.
+///
+/// - The join request is forwarded to the leader, which adds the node as a learner and promotes it
+///   to a voter.
+/// - Joining again does not change the membership.
+/// - A node joins as a learner.
+/// - Joining through unreachable seeds fails with `NoLeaderReachable`.
+#[async_entry::test(worker_threads = 8, init = "init_default_ut_tracing()", tracing_span = "debug")]
+async fn join() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            enable_heartbeat: false,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let mut router = RaftRouter::new(config.clone());
+
+    let mut log_index = router.new_cluster(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- node-3 joins as a voter through node-1");
+    {
+        router.new_raft_node(3).await;
+        let n3 = router.get_raft_handle(&3)?;
+
+        let resp = n3.join((), btreemap! {1=>()}, true).await?;
+        // add learner, joint config, uniform config
+        log_index += 3;
+
+        let voters = resp.membership.voter_ids().collect::<BTreeSet<_>>();
+        assert_eq!(btreeset! {0,1,2,3}, voters);
+
+        router.wait_for_log(&btreeset! {0,1,2,3}, Some(log_index), timeout(), "node-3 joined").await?;
+    }
+
+    tracing::info!("--- node-3 joins again, nothing changes");
+    {
+        let n3 = router.get_raft_handle(&3)?;
+
+        let resp = n3.join((), btreemap! {2=>()}, true).await?;
+
+        let voters = resp.membership.voter_ids().collect::<BTreeSet<_>>();
+        assert_eq!(btreeset! {0,1,2,3}, voters);
+        assert_eq!(Some(log_index), resp.membership.log_id().map(|x| x.index));
+
+        let m = router.get_metrics(&0)?;
+        assert_eq!(Some(log_index), m.last_log_index, "no log is written");
+    }
+
+    tracing::info!("--- node-4 joins as a learner");
+    {
+        router.new_raft_node(4).await;
+        let n4 = router.get_raft_handle(&4)?;
+
+        let resp = n4.join((), btreemap! {2=>(), 3=>()}, false).await?;
+        log_index += 1;
+
+        let voters = resp.membership.voter_ids().collect::<BTreeSet<_>>();
+        assert_eq!(btreeset! {0,1,2,3}, voters);
+        assert_eq!(
+            btreeset! {4},
+            resp.membership.membership().learner_ids().collect::<BTreeSet<_>>()
+        );
+
+        router.wait_for_log(&btreeset! {0,1,2,3,4}, Some(log_index), timeout(), "node-4 joined").await?;
+    }
+
+    tracing::info!("--- node-5 can not reach any seed");
+    {
+        router.new_raft_node(5).await;
+        let n5 = router.get_raft_handle(&5)?;
+
+        let res = n5.join((), btreemap! {8=>(), 9=>()}, true).await;
+        let err = res.unwrap_err().into_api_error().unwrap();
+
+        match err {
+            JoinError::NoLeaderReachable(e) => {
+                assert_eq!(btreeset! {8,9}, e.tried);
+                assert!(e.last_error.is_some());
+            }
+            _ => panic!("expect NoLeaderReachable, got: {:?}", err),
+        }
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(1_000))
+}
@@ -10,6 +10,8 @@ use crate::fixtures::init_default_ut_tracing;
 use crate::fixtures::RaftRouter;
 
 /// Replication should stop after a **unreachable** follower is removed from membership.
+///
+/// The removed follower learns about its removal when it elects, and stops electing.
 #[async_entry::test(worker_threads = 8, init = "init_default_ut_tracing()", tracing_span = "debug")]
 async fn stop_replication_to_removed_unreachable_follower_network_failure() -> Result<()> {
     let config = Arc::new(
@@ -78,13 +80,27 @@ async fn stop_replication_to_removed_unreachable_follower_network_failure() -> R
             .metrics(
                 |x| {
                     x.last_log_index == Some(node4_log_index)
-                        && (x.state == ServerState::Candidate || x.state == ServerState::Follower)
+                        && (x.state == ServerState::Candidate
+                            || x.state == ServerState::Follower
+                            || x.state == ServerState::Removed)
                 },
                 "node 4 stopped recv log and start to elect",
             )
             .await?;
     }
 
+    tracing::info!("--- node 4 learns that it is removed from a vote response, and stops electing");
+    {
+        let m = router.wait(&4, timeout()).state(ServerState::Removed, "node 4 is removed").await?;
+        let node4_term = m.current_term;
+
+        tokio::time::sleep(Duration::from_millis(1_000)).await;
+
+        let m = router.get_metrics(&4)?;
+        assert_eq!(ServerState::Removed, m.state);
+        assert_eq!(node4_term, m.current_term, "node 4 does not elect any more");
+    }
+
     Ok(())
 }
 
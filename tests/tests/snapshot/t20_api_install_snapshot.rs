@@ -20,6 +20,7 @@ use crate::fixtures::RaftRouter;
 ///
 /// - build a stable single node cluster.
 /// - send install_snapshot request with matched/mismatched id and offset
+/// - the response reports the end of the received data
 #[async_entry::test(worker_threads = 8, init = "init_default_ut_tracing()", tracing_span = "debug")]
 async fn snapshot_arguments() -> Result<()> {
     let config = Arc::new(
@@ -49,9 +50,9 @@ async fn snapshot_arguments() -> Result<()> {
     }
 
     let n = router.remove_node(0).ok_or_else(|| anyhow::anyhow!("node not found"))?;
-    let req0 = InstallSnapshotRequest {
-        vote: Vote::new_committed(1, 0),
-        meta: SnapshotMeta {
+    let req0 = InstallSnapshotRequest::new(
+        Vote::new_committed(1, 0),
+        SnapshotMeta {
             snapshot_id: "ss1".into(),
             last_log_id: Some(LogId {
                 leader_id: CommittedLeaderId::new(1, 0),
@@ -59,10 +60,10 @@ async fn snapshot_arguments() -> Result<()> {
             }),
             last_membership: Default::default(),
         },
-        offset: 0,
-        data: vec![1, 2, 3],
-        done: false,
-    };
+        0,
+        vec![1, 2, 3],
+        false,
+    );
 
     tracing::info!("--- only allow to begin a new session when offset is 0");
     {
@@ -113,6 +114,24 @@ async fn snapshot_arguments() -> Result<()> {
         req.meta.snapshot_id = "ss2".into();
         n.0.install_snapshot(req).await?;
     }
+
+    tracing::info!("-- the response reports the end of the received data");
+    {
+        let mut req = req0.clone();
+        req.offset = 11;
+        req.meta.snapshot_id = "ss2".into();
+        let resp = n.0.install_snapshot(req).await?;
+        assert_eq!(Some(14), resp.offset);
+    }
+
+    tracing::info!("-- resend written data is skipped, and the end of the received data is reported");
+    {
+        let mut req = req0.clone();
+        req.offset = 3;
+        req.meta.snapshot_id = "ss2".into();
+        let resp = n.0.install_snapshot(req).await?;
+        assert_eq!(Some(14), resp.offset);
+    }
     Ok(())
 }
 
@@ -0,0 +1,119 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::CommittedLeaderId;
+use openraft::Config;
+use openraft::LogId;
+use openraft::RaftLogReader;
+use openraft::RaftStorage;
+use tokio::time::sleep;
+
+use crate::fixtures::init_default_ut_tracing;
+use crate::fixtures::RaftRouter;
+
+/// A snapshot transfer that is cut is resumed from the offset the receiver reports.
+///
+/// - Build a snapshot on the leader and add a learner: the snapshot is sent in small chunks over a
+///   slow network.
+/// - Cut the transfer by shutting down both nodes, when part of the snapshot is received.
+/// - Restart both nodes: the leader begins the transfer from offset 0, the learner reports the end
+///   of the data its store kept, and the leader continues from there.
+#[async_entry::test(worker_threads = 8, init = "init_default_ut_tracing()", tracing_span = "debug")]
+async fn resume_snapshot_transfer() -> Result<()> {
+    let config = Arc::new(
+        Config {
+            snapshot_max_chunk_size: 10,
+            max_in_snapshot_log_to_keep: 0,
+            purge_batch_size: 1,
+            enable_heartbeat: false,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let mut router = RaftRouter::new(config.clone());
+
+    let mut log_index = router.new_cluster(btreeset! {0}, btreeset! {}).await?;
+
+    tracing::info!("--- build a snapshot on the leader and purge the logs");
+    let (snapshot_meta, snapshot_size) = {
+        for i in 0..10 {
+            router.client_request(0, &format!("client-{}", i), 1).await?;
+            log_index += 1;
+        }
+
+        let leader = router.get_raft_handle(&0)?;
+        leader.trigger_snapshot().await?;
+        leader
+            .wait(timeout())
+            .snapshot(LogId::new(CommittedLeaderId::new(1, 0), log_index), "build snapshot")
+            .await?;
+
+        let mut sto0 = router.get_storage_handle(&0)?;
+        let logs = sto0.try_get_log_entries(..).await?;
+        assert!(logs.is_empty(), "logs are purged");
+
+        let snapshot = sto0.get_current_snapshot().await?.unwrap();
+        (snapshot.meta, snapshot.snapshot.len() as u64)
+    };
+
+    tracing::info!("--- add learner 1, the snapshot is sent slowly");
+    {
+        router.faults().set_latency(Duration::from_millis(50), Duration::from_millis(0));
+
+        router.new_raft_node(1).await;
+        let leader = router.get_raft_handle(&0)?;
+        leader.add_learner(1, (), false).await?;
+
+        for _ in 0..100 {
+            if router.snapshot_offsets(1).len() >= 5 {
+                break;
+            }
+            sleep(Duration::from_millis(10)).await;
+        }
+        assert!(router.snapshot_offsets(1).len() >= 5, "several chunks are sent");
+    }
+
+    tracing::info!("--- cut the transfer by shutting down both nodes");
+    let (sto0, mut sto1) = {
+        let (node0, sto0) = router.remove_node(0).unwrap();
+        node0.shutdown().await?;
+
+        let (node1, sto1) = router.remove_node(1).unwrap();
+        node1.shutdown().await?;
+
+        (sto0, sto1)
+    };
+
+    let (_data, received) = sto1.resume_receiving_snapshot(&snapshot_meta).await?;
+    assert!(received > 0, "the store of the learner keeps the received data");
+    assert!(received < snapshot_size, "the transfer is not finished");
+
+    tracing::info!("--- restart both nodes, the transfer is resumed");
+    {
+        router.faults().set_latency(Duration::from_millis(0), Duration::from_millis(0));
+        router.clear_snapshot_offsets();
+
+        router.new_raft_node_with_sto(1, sto1).await;
+        router.new_raft_node_with_sto(0, sto0).await;
+
+        router
+            .wait(&1, Some(Duration::from_millis(5_000)))
+            .snapshot(snapshot_meta.last_log_id.unwrap(), "learner installs the snapshot")
+            .await?;
+
+        let offsets = router.snapshot_offsets(1);
+        assert_eq!(0, offsets[0], "the leader begins the transfer from the beginning");
+        assert_eq!(
+            received, offsets[1],
+            "the leader continues from the offset the learner reports"
+        );
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(1_000))
+}
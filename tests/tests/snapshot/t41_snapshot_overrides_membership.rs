@@ -89,15 +89,15 @@ async fn snapshot_overrides_membership() -> Result<()> {
 
         tracing::info!("--- add a membership config log to the learner");
         {
-            let req = AppendEntriesRequest {
-                vote: Vote::new_committed(1, 0),
-                prev_log_id: None,
-                entries: vec![blank(0, 0), Entry {
+            let req = AppendEntriesRequest::new(
+                Vote::new_committed(1, 0),
+                None,
+                vec![blank(0, 0), Entry {
                     log_id: LogId::new(CommittedLeaderId::new(1, 0), 1),
                     payload: EntryPayload::Membership(Membership::new(vec![btreeset! {2,3}], None)),
                 }],
-                leader_commit: Some(LogId::new(CommittedLeaderId::new(0, 0), 0)),
-            };
+                Some(LogId::new(CommittedLeaderId::new(0, 0), 0)),
+            );
             router.new_client(1, &()).await.send_append_entries(req).await?;
 
             tracing::info!("--- check that learner membership is affected");
@@ -87,10 +87,10 @@ async fn snapshot_delete_conflicting_logs() -> Result<()> {
     {
         router.new_raft_node(1).await;
 
-        let req = AppendEntriesRequest {
-            vote: Vote::new_committed(1, 0),
-            prev_log_id: None,
-            entries: vec![
+        let req = AppendEntriesRequest::new(
+            Vote::new_committed(1, 0),
+            None,
+            vec![
                 blank(0, 0),
                 blank(1, 1),
                 // conflict membership will be replaced with membership in snapshot
@@ -112,8 +112,8 @@ async fn snapshot_delete_conflicting_logs() -> Result<()> {
                     payload: EntryPayload::Membership(Membership::new(vec![btreeset! {4,5}], None)),
                 },
             ],
-            leader_commit: Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
-        };
+            Some(LogId::new(CommittedLeaderId::new(1, 0), 2)),
+        );
         router.new_client(1, &()).await.send_append_entries(req).await?;
 
         tracing::info!("--- check that learner membership is affected");
@@ -142,13 +142,13 @@ async fn snapshot_delete_conflicting_logs() -> Result<()> {
             b.build_snapshot().await?
         };
 
-        let req = InstallSnapshotRequest {
-            vote: sto0.read_vote().await?.unwrap(),
-            meta: snap.meta.clone(),
-            offset: 0,
-            data: snap.snapshot.into_inner(),
-            done: true,
-        };
+        let req = InstallSnapshotRequest::new(
+            sto0.read_vote().await?.unwrap(),
+            snap.meta.clone(),
+            0,
+            snap.snapshot.into_inner(),
+            true,
+        );
 
         router.new_client(1, &()).await.send_install_snapshot(req).await?;
 
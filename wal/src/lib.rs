@@ -0,0 +1,372 @@
+#![deny(unused_crate_dependencies)]
+#![deny(unused_qualifications)]
+
+//! A segmented write-ahead-log implementation of the log half of `RaftStorage`.
+//!
+//! [`Wal`] stores raft log entries and the vote in a directory of append-only, checksummed segment
+//! files. [`WalStore`] is a complete `RaftStorage` built on it, with an in-memory key-value state
+//! machine, to show how to use it and to run the `openraft::testing::Suite` against it.
+
+mod record;
+mod segment;
+mod wal;
+
+#[cfg(test)] mod test;
+#[cfg(test)] mod wal_test;
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::io::Cursor;
+use std::ops::RangeBounds;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use openraft::async_trait::async_trait;
+use openraft::storage::LogState;
+use openraft::storage::Snapshot;
+use openraft::BasicNode;
+use openraft::Entry;
+use openraft::EntryPayload;
+use openraft::LogId;
+use openraft::RaftLogReader;
+use openraft::RaftSnapshotBuilder;
+use openraft::RaftStorage;
+use openraft::SnapshotMeta;
+use openraft::StorageError;
+use openraft::StorageIOError;
+use openraft::StoredMembership;
+use openraft::Vote;
+use serde::Deserialize;
+use serde::Serialize;
+
+pub use crate::wal::Wal;
+pub use crate::wal::WalOptions;
+
+pub type ExampleNodeId = u64;
+
+openraft::declare_raft_types!(
+    /// Declare the type configuration for example K/V store.
+    pub ExampleTypeConfig: D = ExampleRequest, R = ExampleResponse, NodeId = ExampleNodeId, Node = BasicNode, Entry = Entry<ExampleTypeConfig>
+);
+
+/// File storing the current snapshot, in the same directory as the log.
+const SNAPSHOT_FILE: &str = "snapshot";
+
+/// File storing the index of the last built snapshot, so that a snapshot id is not reused after
+/// restart.
+const SNAPSHOT_IDX_FILE: &str = "snapshot_idx";
+
+/// The application request type which the `WalStore` works with.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum ExampleRequest {
+    Set { key: String, value: String },
+}
+
+/// The application response type which the `WalStore` works with.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExampleResponse {
+    pub value: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExampleSnapshot {
+    pub meta: SnapshotMeta<ExampleNodeId, BasicNode>,
+
+    /// The data of the state machine at the time of this snapshot.
+    pub data: Vec<u8>,
+}
+
+/// The in-memory state machine of the `WalStore`.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct ExampleStateMachine {
+    pub last_applied_log: Option<LogId<ExampleNodeId>>,
+
+    pub last_membership: StoredMembership<ExampleNodeId, BasicNode>,
+
+    /// Application data.
+    pub data: BTreeMap<String, String>,
+}
+
+/// A `RaftStorage` that stores logs and vote in a [`Wal`], and keeps the state machine in memory.
+///
+/// The state machine is not persisted except as snapshots: after restart it is restored from the
+/// last snapshot, and the logs after it are applied again by raft.
+///
+/// Writes, which `fsync`, run in a blocking thread with [`blocking()`], not in the async runtime.
+pub struct WalStore {
+    dir: PathBuf,
+
+    wal: Arc<Mutex<Wal<ExampleTypeConfig>>>,
+
+    sm: Mutex<ExampleStateMachine>,
+
+    snapshot_idx: Mutex<u64>,
+
+    current_snapshot: Mutex<Option<ExampleSnapshot>>,
+}
+
+impl WalStore {
+    /// Open a store in directory `dir` with the default options.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Arc<WalStore>, StorageError<ExampleNodeId>> {
+        Self::open_with_options(dir, WalOptions::default())
+    }
+
+    pub fn open_with_options(
+        dir: impl AsRef<Path>,
+        options: WalOptions,
+    ) -> Result<Arc<WalStore>, StorageError<ExampleNodeId>> {
+        let dir = dir.as_ref().to_path_buf();
+        let wal = Wal::open(&dir, options)?;
+
+        let current_snapshot: Option<ExampleSnapshot> =
+            wal::read_file(&dir, SNAPSHOT_FILE).map_err(|e| StorageIOError::read(&e))?;
+
+        let sm = match &current_snapshot {
+            None => ExampleStateMachine::default(),
+            Some(s) => serde_json::from_slice(&s.data)
+                .map_err(|e| StorageIOError::read_snapshot(s.meta.signature(), &e))?,
+        };
+
+        let snapshot_idx: Option<u64> =
+            wal::read_file(&dir, SNAPSHOT_IDX_FILE).map_err(|e| StorageIOError::read(&e))?;
+
+        Ok(Arc::new(WalStore {
+            dir,
+            wal: Arc::new(Mutex::new(wal)),
+            sm: Mutex::new(sm),
+            snapshot_idx: Mutex::new(snapshot_idx.unwrap_or_default()),
+            current_snapshot: Mutex::new(current_snapshot),
+        }))
+    }
+
+    /// Returns a copy of the state machine.
+    pub fn state_machine(&self) -> ExampleStateMachine {
+        self.sm.lock().unwrap().clone()
+    }
+
+    /// Persist a snapshot and make it the current one. It blocks.
+    fn save_snapshot(&self, snapshot: ExampleSnapshot) -> Result<(), StorageError<ExampleNodeId>> {
+        let sync = self.wal.lock().unwrap().options().sync;
+        wal::write_file(&self.dir, SNAPSHOT_FILE, &snapshot, sync)
+            .map_err(|e| StorageIOError::write_snapshot(snapshot.meta.signature(), &e))?;
+
+        *self.current_snapshot.lock().unwrap() = Some(snapshot);
+        Ok(())
+    }
+
+    /// Persist and return the index of a new snapshot. It blocks.
+    fn next_snapshot_idx(&self) -> Result<u64, StorageError<ExampleNodeId>> {
+        let sync = self.wal.lock().unwrap().options().sync;
+
+        let mut l = self.snapshot_idx.lock().unwrap();
+        wal::write_file(&self.dir, SNAPSHOT_IDX_FILE, &(*l + 1), sync).map_err(|e| StorageIOError::write(&e))?;
+        *l += 1;
+
+        Ok(*l)
+    }
+}
+
+/// Run `f`, which does blocking IO such as `fsync`, in a thread where blocking is allowed.
+async fn blocking<T, F>(f: F) -> Result<T, StorageError<ExampleNodeId>>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, StorageError<ExampleNodeId>> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f).await.map_err(|e| StorageIOError::write(&e))?
+}
+
+#[async_trait]
+impl RaftLogReader<ExampleTypeConfig> for Arc<WalStore> {
+    async fn get_log_state(&mut self) -> Result<LogState<ExampleTypeConfig>, StorageError<ExampleNodeId>> {
+        Ok(self.wal.lock().unwrap().get_log_state())
+    }
+
+    async fn try_get_log_entries<RB: RangeBounds<u64> + Clone + Debug + Send + Sync>(
+        &mut self,
+        range: RB,
+    ) -> Result<Vec<Entry<ExampleTypeConfig>>, StorageError<ExampleNodeId>> {
+        self.wal.lock().unwrap().try_get_log_entries(range)
+    }
+}
+
+#[async_trait]
+impl RaftSnapshotBuilder<ExampleTypeConfig, Cursor<Vec<u8>>> for Arc<WalStore> {
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn build_snapshot(
+        &mut self,
+    ) -> Result<Snapshot<ExampleNodeId, BasicNode, Cursor<Vec<u8>>>, StorageError<ExampleNodeId>> {
+        let (data, last_applied_log, last_membership) = {
+            let sm = self.sm.lock().unwrap();
+            let data = serde_json::to_vec(&*sm).map_err(|e| StorageIOError::read_state_machine(&e))?;
+            (data, sm.last_applied_log, sm.last_membership.clone())
+        };
+
+        let store = self.clone();
+        let snapshot_idx = blocking(move || store.next_snapshot_idx()).await?;
+
+        let snapshot_id = if let Some(last) = last_applied_log {
+            format!("{}-{}-{}", last.leader_id, last.index, snapshot_idx)
+        } else {
+            format!("--{}", snapshot_idx)
+        };
+
+        let meta = SnapshotMeta {
+            last_log_id: last_applied_log,
+            last_membership,
+            snapshot_id,
+        };
+
+        let store = self.clone();
+        let snapshot = ExampleSnapshot {
+            meta: meta.clone(),
+            data: data.clone(),
+        };
+        blocking(move || store.save_snapshot(snapshot)).await?;
+
+        Ok(Snapshot {
+            meta,
+            snapshot: Box::new(Cursor::new(data)),
+        })
+    }
+}
+
+#[async_trait]
+impl RaftStorage<ExampleTypeConfig> for Arc<WalStore> {
+    type SnapshotData = Cursor<Vec<u8>>;
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn save_vote(&mut self, vote: &Vote<ExampleNodeId>) -> Result<(), StorageError<ExampleNodeId>> {
+        let wal = self.wal.clone();
+        let vote = *vote;
+        blocking(move || wal.lock().unwrap().save_vote(&vote)).await
+    }
+
+    async fn read_vote(&mut self) -> Result<Option<Vote<ExampleNodeId>>, StorageError<ExampleNodeId>> {
+        Ok(self.wal.lock().unwrap().read_vote())
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, entries))]
+    async fn append_to_log(&mut self, entries: &[Entry<ExampleTypeConfig>]) -> Result<(), StorageError<ExampleNodeId>> {
+        let wal = self.wal.clone();
+        let entries = entries.to_vec();
+        blocking(move || wal.lock().unwrap().append(&entries)).await
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn delete_conflict_logs_since(
+        &mut self,
+        log_id: LogId<ExampleNodeId>,
+    ) -> Result<(), StorageError<ExampleNodeId>> {
+        tracing::debug!("delete_log: [{:?}, +oo)", log_id);
+        let wal = self.wal.clone();
+        blocking(move || wal.lock().unwrap().truncate(log_id.index)).await
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn purge_logs_upto(&mut self, log_id: LogId<ExampleNodeId>) -> Result<(), StorageError<ExampleNodeId>> {
+        tracing::debug!("purge_log: [0, {:?}]", log_id);
+        let wal = self.wal.clone();
+        blocking(move || wal.lock().unwrap().purge(log_id)).await
+    }
+
+    async fn last_applied_state(
+        &mut self,
+    ) -> Result<(Option<LogId<ExampleNodeId>>, StoredMembership<ExampleNodeId, BasicNode>), StorageError<ExampleNodeId>>
+    {
+        let sm = self.sm.lock().unwrap();
+        Ok((sm.last_applied_log, sm.last_membership.clone()))
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, entries))]
+    async fn apply_to_state_machine(
+        &mut self,
+        entries: &[Entry<ExampleTypeConfig>],
+    ) -> Result<Vec<ExampleResponse>, StorageError<ExampleNodeId>> {
+        let mut res = Vec::with_capacity(entries.len());
+
+        let mut sm = self.sm.lock().unwrap();
+
+        for entry in entries {
+            tracing::debug!(%entry.log_id, "replicate to sm");
+
+            sm.last_applied_log = Some(entry.log_id);
+
+            match entry.payload {
+                EntryPayload::Blank => res.push(ExampleResponse { value: None }),
+                EntryPayload::Normal(ref req) => match req {
+                    ExampleRequest::Set { key, value } => {
+                        sm.data.insert(key.clone(), value.clone());
+                        res.push(ExampleResponse {
+                            value: Some(value.clone()),
+                        })
+                    }
+                },
+                EntryPayload::Membership(ref mem) => {
+                    sm.last_membership = StoredMembership::new(Some(entry.log_id), mem.clone());
+                    res.push(ExampleResponse { value: None })
+                }
+            };
+        }
+        Ok(res)
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn begin_receiving_snapshot(&mut self) -> Result<Box<Self::SnapshotData>, StorageError<ExampleNodeId>> {
+        Ok(Box::new(Cursor::new(Vec::new())))
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, snapshot))]
+    async fn install_snapshot(
+        &mut self,
+        meta: &SnapshotMeta<ExampleNodeId, BasicNode>,
+        snapshot: Box<Self::SnapshotData>,
+    ) -> Result<(), StorageError<ExampleNodeId>> {
+        tracing::info!(
+            { snapshot_size = snapshot.get_ref().len() },
+            "decoding snapshot for installation"
+        );
+
+        let new_snapshot = ExampleSnapshot {
+            meta: meta.clone(),
+            data: snapshot.into_inner(),
+        };
+
+        let new_sm: ExampleStateMachine = serde_json::from_slice(&new_snapshot.data)
+            .map_err(|e| StorageIOError::read_snapshot(new_snapshot.meta.signature(), &e))?;
+
+        // Persist the snapshot before updating the state machine, so that the state machine is never
+        // ahead of what can be restored after a restart.
+        let store = self.clone();
+        blocking(move || store.save_snapshot(new_snapshot)).await?;
+
+        *self.sm.lock().unwrap() = new_sm;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn get_current_snapshot(
+        &mut self,
+    ) -> Result<Option<Snapshot<ExampleNodeId, BasicNode, Self::SnapshotData>>, StorageError<ExampleNodeId>> {
+        match &*self.current_snapshot.lock().unwrap() {
+            Some(snapshot) => Ok(Some(Snapshot {
+                meta: snapshot.meta.clone(),
+                snapshot: Box::new(Cursor::new(snapshot.data.clone())),
+            })),
+            None => Ok(None),
+        }
+    }
+
+    type LogReader = Self;
+    type SnapshotBuilder = Self;
+
+    async fn get_log_reader(&mut self) -> Self::LogReader {
+        self.clone()
+    }
+
+    async fn get_snapshot_builder(&mut self) -> Self::SnapshotBuilder {
+        self.clone()
+    }
+}
@@ -0,0 +1,75 @@
+//! On-disk layout of a log record.
+//!
+//! A record is a fixed size header followed by the payload:
+//!
+//! ```text
+//! | len: u32 | crc: u32 | index: u64 | payload: [u8; len] |
+//! ```
+//!
+//! All integers are little endian. `crc` is the CRC32 of `index` and `payload`, so that a record
+//! that is only partially written, or is damaged after being written, is detected when it is read.
+
+/// Size of the record header: `len`, `crc` and `index`.
+pub(crate) const HEADER_SIZE: usize = 16;
+
+/// Append a record of log `index` with `payload` to `buf`.
+pub(crate) fn encode(index: u64, payload: &[u8], buf: &mut Vec<u8>) {
+    let index_bytes = index.to_le_bytes();
+
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(&index_bytes);
+    hasher.update(payload);
+
+    buf.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    buf.extend_from_slice(&hasher.finalize().to_le_bytes());
+    buf.extend_from_slice(&index_bytes);
+    buf.extend_from_slice(payload);
+}
+
+/// The result of decoding a record from the start of a buffer.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Decoded<'a> {
+    /// A complete record with a valid checksum. `size` is the number of bytes it occupies.
+    Record { index: u64, payload: &'a [u8], size: usize },
+
+    /// The buffer ends before the record does: the record was not completely written.
+    Torn,
+
+    /// The record is complete but its checksum does not match.
+    Corrupt,
+}
+
+/// The size of the record whose header is at the start of `header`, which must hold at least
+/// [`HEADER_SIZE`] bytes.
+pub(crate) fn record_size(header: &[u8]) -> u64 {
+    let len = u32::from_le_bytes(header[0..4].try_into().unwrap());
+    HEADER_SIZE as u64 + len as u64
+}
+
+/// Decode the record at the start of `buf`.
+pub(crate) fn decode(buf: &[u8]) -> Decoded<'_> {
+    if buf.len() < HEADER_SIZE {
+        return Decoded::Torn;
+    }
+
+    let len = u32::from_le_bytes(buf[0..4].try_into().unwrap()) as usize;
+    let crc = u32::from_le_bytes(buf[4..8].try_into().unwrap());
+    let index = u64::from_le_bytes(buf[8..16].try_into().unwrap());
+
+    let size = HEADER_SIZE + len;
+    if buf.len() < size {
+        return Decoded::Torn;
+    }
+
+    let payload = &buf[HEADER_SIZE..size];
+
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(&buf[8..16]);
+    hasher.update(payload);
+
+    if hasher.finalize() != crc {
+        return Decoded::Corrupt;
+    }
+
+    Decoded::Record { index, payload, size }
+}
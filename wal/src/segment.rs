@@ -0,0 +1,233 @@
+use std::fs;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::BufReader;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::record;
+use crate::record::Decoded;
+
+/// Extension of segment file names.
+pub(crate) const SEGMENT_EXT: &str = "wal";
+
+/// One file of the log, holding the records of a contiguous range of log indexes.
+///
+/// The file is named after the index of its first record, so that the segments sort by file name
+/// in log order.
+pub(crate) struct Segment {
+    path: PathBuf,
+
+    file: File,
+
+    /// The log index of the first record.
+    first_index: u64,
+
+    /// The in-memory index: `offsets[i]` is the file offset of the record of `first_index + i`.
+    offsets: Vec<u64>,
+
+    /// The size of the valid records in the file; new records are written from here.
+    size: u64,
+}
+
+/// The outcome of scanning a segment file when it is opened.
+pub(crate) struct Recovered {
+    pub(crate) segment: Segment,
+
+    /// The number of bytes after the last valid record, which are not a complete valid record.
+    pub(crate) invalid_tail: u64,
+}
+
+impl Segment {
+    pub(crate) fn file_name(first_index: u64) -> String {
+        format!("{:020}.{}", first_index, SEGMENT_EXT)
+    }
+
+    /// Parse the first index from a segment file name, or return `None` if it is not one.
+    pub(crate) fn parse_file_name(name: &str) -> Option<u64> {
+        let stem = name.strip_suffix(SEGMENT_EXT)?.strip_suffix('.')?;
+        if stem.len() != 20 {
+            return None;
+        }
+        stem.parse().ok()
+    }
+
+    /// Create a new empty segment whose first record is of log index `first_index`.
+    pub(crate) fn create(dir: &Path, first_index: u64) -> io::Result<Segment> {
+        let path = dir.join(Self::file_name(first_index));
+        let file = OpenOptions::new().read(true).write(true).create_new(true).open(&path)?;
+
+        Ok(Segment {
+            path,
+            file,
+            first_index,
+            offsets: vec![],
+            size: 0,
+        })
+    }
+
+    /// Open a segment file and rebuild its in-memory index by scanning all records.
+    ///
+    /// The scan reads one record at a time. It stops at the first record that is torn or fails
+    /// the checksum. The bytes from there on are reported as `invalid_tail` and are not part of the
+    /// segment.
+    pub(crate) fn open(path: PathBuf, first_index: u64) -> io::Result<Recovered> {
+        let file = OpenOptions::new().read(true).write(true).open(&path)?;
+        let file_size = file.metadata()?.len();
+
+        let mut offsets = vec![];
+        let mut pos = 0;
+
+        {
+            let mut reader = BufReader::new(&file);
+            let mut buf = vec![0; record::HEADER_SIZE];
+
+            while pos + record::HEADER_SIZE as u64 <= file_size {
+                buf.resize(record::HEADER_SIZE, 0);
+                reader.read_exact(&mut buf)?;
+
+                // A torn record, or a damaged length that would make it end after the file.
+                let size = record::record_size(&buf);
+                if pos + size > file_size {
+                    break;
+                }
+
+                buf.resize(size as usize, 0);
+                reader.read_exact(&mut buf[record::HEADER_SIZE..])?;
+
+                match record::decode(&buf) {
+                    Decoded::Record { index, .. } if index == first_index + offsets.len() as u64 => {
+                        offsets.push(pos);
+                        pos += size;
+                    }
+                    _ => break,
+                }
+            }
+        }
+
+        let segment = Segment {
+            path,
+            file,
+            first_index,
+            offsets,
+            size: pos,
+        };
+
+        Ok(Recovered {
+            segment,
+            invalid_tail: file_size - pos,
+        })
+    }
+
+    pub(crate) fn first_index(&self) -> u64 {
+        self.first_index
+    }
+
+    /// The index the next record appended to this segment must have.
+    pub(crate) fn next_index(&self) -> u64 {
+        self.first_index + self.offsets.len() as u64
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    pub(crate) fn contains(&self, index: u64) -> bool {
+        index >= self.first_index && index < self.next_index()
+    }
+
+    pub(crate) fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Append encoded records, of log indexes from `next_index()` on, with a single write.
+    ///
+    /// `offsets` are the offsets of the records inside `buf`. The data is not synced.
+    pub(crate) fn append(&mut self, buf: &[u8], offsets: &[u64]) -> io::Result<()> {
+        self.file.seek(SeekFrom::Start(self.size))?;
+        self.file.write_all(buf)?;
+
+        self.offsets.extend(offsets.iter().map(|o| self.size + o));
+        self.size += buf.len() as u64;
+
+        Ok(())
+    }
+
+    /// Read the payloads of the records in `[start, end)`, which must be in this segment.
+    pub(crate) fn read(&mut self, start: u64, end: u64) -> io::Result<Vec<Vec<u8>>> {
+        debug_assert!(start >= self.first_index && start <= end && end <= self.next_index());
+
+        if start == end {
+            return Ok(vec![]);
+        }
+
+        let from = self.offset(start);
+        let to = if end == self.next_index() { self.size } else { self.offset(end) };
+
+        let mut buf = vec![0; (to - from) as usize];
+        self.file.seek(SeekFrom::Start(from))?;
+        self.file.read_exact(&mut buf)?;
+
+        let mut payloads = Vec::with_capacity((end - start) as usize);
+        let mut pos = 0;
+
+        for index in start..end {
+            match record::decode(&buf[pos..]) {
+                Decoded::Record {
+                    index: got,
+                    payload,
+                    size,
+                } if got == index => {
+                    payloads.push(payload.to_vec());
+                    pos += size;
+                }
+                res => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("invalid record of index {} in {}: {:?}", index, self.path.display(), res),
+                    ));
+                }
+            }
+        }
+
+        Ok(payloads)
+    }
+
+    /// Remove the records from log `index` on, which must be in this segment, and sync.
+    pub(crate) fn truncate(&mut self, index: u64) -> io::Result<()> {
+        debug_assert!(self.contains(index));
+
+        let new_size = self.offset(index);
+        self.truncate_to(new_size)?;
+        self.offsets.truncate((index - self.first_index) as usize);
+
+        Ok(())
+    }
+
+    /// Cut the file to `size` bytes and sync.
+    pub(crate) fn truncate_to(&mut self, size: u64) -> io::Result<()> {
+        self.file.set_len(size)?;
+        self.file.sync_all()?;
+        self.size = size;
+        Ok(())
+    }
+
+    pub(crate) fn sync(&self) -> io::Result<()> {
+        self.file.sync_data()
+    }
+
+    /// Close and delete the segment file.
+    pub(crate) fn remove(self) -> io::Result<()> {
+        drop(self.file);
+        fs::remove_file(&self.path)
+    }
+
+    fn offset(&self, index: u64) -> u64 {
+        self.offsets[(index - self.first_index) as usize]
+    }
+}
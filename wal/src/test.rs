@@ -0,0 +1,71 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use openraft::testing::sample_key_value;
+use openraft::testing::StoreBuilder;
+use openraft::testing::Suite;
+use openraft::RaftSnapshotBuilder;
+use openraft::RaftStorage;
+use openraft::StorageError;
+use tempfile::TempDir;
+
+use crate::ExampleNodeId;
+use crate::ExampleRequest;
+use crate::ExampleTypeConfig;
+use crate::WalOptions;
+use crate::WalStore;
+
+struct WalBuilder {
+    options: WalOptions,
+}
+
+#[test]
+pub fn test_wal_store() -> Result<(), StorageError<ExampleNodeId>> {
+    Suite::test_all(WalBuilder {
+        options: WalOptions::default(),
+    })
+}
+
+/// Tiny segments, so that every test spans several segments.
+#[test]
+pub fn test_wal_store_small_segments() -> Result<(), StorageError<ExampleNodeId>> {
+    Suite::test_all(WalBuilder {
+        options: WalOptions {
+            segment_size: 256,
+            ..Default::default()
+        },
+    })
+}
+
+/// The snapshot index is restored after restart, so that a snapshot id is not reused.
+#[tokio::test]
+async fn test_snapshot_idx_after_reopen() -> Result<(), StorageError<ExampleNodeId>> {
+    let td = TempDir::new().expect("couldn't create temp dir");
+
+    let first = {
+        let mut store = WalStore::open(td.path())?;
+        store.get_snapshot_builder().await.build_snapshot().await?.meta.snapshot_id
+    };
+
+    let mut store = WalStore::open(td.path())?;
+    let second = store.get_snapshot_builder().await.build_snapshot().await?.meta.snapshot_id;
+
+    assert_eq!("--1", first);
+    assert_eq!("--2", second);
+
+    Ok(())
+}
+
+#[async_trait]
+impl StoreBuilder<ExampleTypeConfig, Arc<WalStore>, TempDir> for WalBuilder {
+    async fn build(&self) -> Result<(TempDir, Arc<WalStore>), StorageError<ExampleNodeId>> {
+        let td = TempDir::new().expect("couldn't create temp dir");
+        let store = WalStore::open_with_options(td.path(), self.options.clone())?;
+        Ok((td, store))
+    }
+
+    fn sample_app_data(&self, index: u64) -> Option<ExampleRequest> {
+        let (key, value) = sample_key_value(index);
+        Some(ExampleRequest::Set { key, value })
+    }
+}
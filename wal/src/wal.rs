@@ -0,0 +1,443 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::marker::PhantomData;
+use std::ops::Bound;
+use std::ops::RangeBounds;
+use std::path::Path;
+use std::path::PathBuf;
+
+use openraft::storage::LogState;
+use openraft::LogId;
+use openraft::RaftLogId;
+use openraft::RaftTypeConfig;
+use openraft::StorageError;
+use openraft::StorageIOError;
+use openraft::Vote;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::record;
+use crate::segment::Segment;
+
+/// File storing the vote.
+const VOTE_FILE: &str = "vote";
+
+/// File storing the last purged log id.
+const PURGED_FILE: &str = "purged";
+
+/// Options of a [`Wal`].
+#[derive(Debug, Clone)]
+pub struct WalOptions {
+    /// A new segment file is started once the current one reaches this size in bytes.
+    ///
+    /// Purging removes whole segments only, so this is also the granularity of reclaiming disk
+    /// space.
+    pub segment_size: u64,
+
+    /// Whether to `fsync` written data before returning from a write.
+    ///
+    /// Disabling it is only safe for testing: a completed write is assumed by raft to be durable.
+    pub sync: bool,
+}
+
+impl Default for WalOptions {
+    fn default() -> Self {
+        Self {
+            segment_size: 64 * 1024 * 1024,
+            sync: true,
+        }
+    }
+}
+
+/// A segmented, checksummed, append-only log of raft entries, plus the vote.
+///
+/// The log is stored in a directory as a sequence of segment files, each holding the records of a
+/// contiguous range of log indexes. An in-memory index maps every log index to its segment and
+/// offset, so that a read costs one `read` per segment it spans.
+///
+/// - Appending writes a batch of entries with one `write` and makes it durable with one `fsync`,
+///   no matter how many entries are in it.
+/// - Deleting conflicting logs truncates the segment containing the first deleted entry and removes
+///   all segments after it.
+/// - Purging removes only segments whose entries are all purged. The remaining purged entries are
+///   hidden by the persisted last purged log id.
+/// - When it is opened, a record at the end of the last segment that is torn by a crash, or fails
+///   its checksum, is discarded. Such a record in any other segment is reported as an error.
+pub struct Wal<C: RaftTypeConfig> {
+    dir: PathBuf,
+
+    options: WalOptions,
+
+    /// Segments by the index of their first record.
+    segments: BTreeMap<u64, Segment>,
+
+    vote: Option<Vote<C::NodeId>>,
+
+    last_purged_log_id: Option<LogId<C::NodeId>>,
+
+    last_log_id: Option<LogId<C::NodeId>>,
+
+    _p: PhantomData<C>,
+}
+
+impl<C> Wal<C>
+where
+    C: RaftTypeConfig,
+    C::Entry: Serialize + DeserializeOwned,
+{
+    /// Open the log in directory `dir`, creating it if it does not exist.
+    pub fn open(dir: impl AsRef<Path>, options: WalOptions) -> Result<Self, StorageError<C::NodeId>> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).map_err(|e| StorageIOError::read_logs(&e))?;
+
+        let vote = read_file(&dir, VOTE_FILE).map_err(|e| StorageIOError::read_vote(&e))?;
+        let last_purged_log_id: Option<LogId<C::NodeId>> =
+            read_file(&dir, PURGED_FILE).map_err(|e| StorageIOError::read_logs(&e))?;
+
+        let mut wal = Self {
+            dir,
+            options,
+            segments: BTreeMap::new(),
+            vote,
+            last_purged_log_id,
+            last_log_id: None,
+            _p: PhantomData,
+        };
+
+        wal.recover()?;
+
+        Ok(wal)
+    }
+
+    /// Load the segments and rebuild the in-memory index.
+    fn recover(&mut self) -> Result<(), StorageError<C::NodeId>> {
+        let mut first_indexes = vec![];
+        for dir_entry in fs::read_dir(&self.dir).map_err(|e| StorageIOError::read_logs(&e))? {
+            let dir_entry = dir_entry.map_err(|e| StorageIOError::read_logs(&e))?;
+            if let Some(first_index) = dir_entry.file_name().to_str().and_then(Segment::parse_file_name) {
+                first_indexes.push(first_index);
+            }
+        }
+        first_indexes.sort_unstable();
+
+        let n = first_indexes.len();
+        for (i, first_index) in first_indexes.into_iter().enumerate() {
+            let is_last = i + 1 == n;
+            let path = self.dir.join(Segment::file_name(first_index));
+
+            let recovered = Segment::open(path.clone(), first_index).map_err(|e| StorageIOError::read_logs(&e))?;
+            let mut segment = recovered.segment;
+
+            if recovered.invalid_tail > 0 {
+                if !is_last {
+                    let e = io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "{} invalid bytes after index {} in non-last segment {}",
+                            recovered.invalid_tail,
+                            segment.next_index(),
+                            path.display()
+                        ),
+                    );
+                    return Err(StorageIOError::read_logs(&e).into());
+                }
+
+                tracing::warn!(
+                    "discard {} bytes of torn or corrupted records after index {} in {}",
+                    recovered.invalid_tail,
+                    segment.next_index(),
+                    path.display()
+                );
+                segment.truncate_to(segment.size()).map_err(|e| StorageIOError::write_logs(&e))?;
+            }
+
+            self.segments.insert(first_index, segment);
+        }
+
+        // Segments left over by a purge that did not complete.
+        self.remove_purged_segments()?;
+
+        let last_index = self.segments.values().rev().find(|s| !s.is_empty()).map(|s| s.next_index() - 1);
+
+        self.last_log_id = match last_index {
+            None => None,
+            Some(last) => {
+                let ents = self.read_entries(last, last + 1)?;
+                ents.last().map(|ent| *ent.get_log_id())
+            }
+        };
+
+        Ok(())
+    }
+
+    pub fn options(&self) -> &WalOptions {
+        &self.options
+    }
+
+    pub fn read_vote(&self) -> Option<Vote<C::NodeId>> {
+        self.vote
+    }
+
+    pub fn save_vote(&mut self, vote: &Vote<C::NodeId>) -> Result<(), StorageError<C::NodeId>> {
+        write_file(&self.dir, VOTE_FILE, vote, self.options.sync).map_err(|e| StorageIOError::write_vote(&e))?;
+        self.vote = Some(*vote);
+        Ok(())
+    }
+
+    pub fn get_log_state(&self) -> LogState<C> {
+        LogState {
+            last_purged_log_id: self.last_purged_log_id,
+            last_log_id: self.last_log_id.or(self.last_purged_log_id),
+        }
+    }
+
+    /// Read the entries in `range`. Entries that are purged or do not exist are skipped.
+    pub fn try_get_log_entries<RB: RangeBounds<u64> + Clone + Debug + Send + Sync>(
+        &mut self,
+        range: RB,
+    ) -> Result<Vec<C::Entry>, StorageError<C::NodeId>> {
+        let first_unpurged = self.last_purged_log_id.map(|x| x.index + 1).unwrap_or(0);
+
+        let start = match range.start_bound() {
+            Bound::Included(&i) => i,
+            Bound::Excluded(&i) => i + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&i) => i.saturating_add(1),
+            Bound::Excluded(&i) => i,
+            Bound::Unbounded => u64::MAX,
+        };
+
+        self.read_entries(start.max(first_unpurged), end)
+    }
+
+    /// Append entries and make them durable.
+    ///
+    /// An entry whose index is not after the last entry replaces it and all entries after it.
+    pub fn append(&mut self, entries: &[C::Entry]) -> Result<(), StorageError<C::NodeId>> {
+        let first = match entries.first() {
+            None => return Ok(()),
+            Some(ent) => ent.get_log_id().index,
+        };
+
+        if self.last_log_id.map(|x| x.index >= first).unwrap_or(false) {
+            self.truncate(first)?;
+        }
+
+        let mut buf = vec![];
+        let mut offsets = vec![];
+        let mut batch_first = first;
+
+        for ent in entries {
+            let index = ent.get_log_id().index;
+
+            // Start a new segment if the current one is full, or the entry does not follow it.
+            let need_new_segment = match self.segments.values().next_back() {
+                None => true,
+                Some(s) => {
+                    let next_index = s.next_index() + offsets.len() as u64;
+                    next_index != index || s.size() + buf.len() as u64 >= self.options.segment_size
+                }
+            };
+
+            if need_new_segment {
+                self.flush_batch(&mut buf, &mut offsets, batch_first)?;
+                self.seal_last_segment()?;
+
+                let segment = Segment::create(&self.dir, index).map_err(|e| StorageIOError::write_logs(&e))?;
+                self.segments.insert(index, segment);
+                self.sync_dir().map_err(|e| StorageIOError::write_logs(&e))?;
+
+                batch_first = index;
+            }
+
+            let payload = bincode::serialize(ent).map_err(|e| StorageIOError::write_logs(&e))?;
+            offsets.push(buf.len() as u64);
+            record::encode(index, &payload, &mut buf);
+        }
+
+        self.flush_batch(&mut buf, &mut offsets, batch_first)?;
+
+        if self.options.sync {
+            if let Some(s) = self.segments.values().next_back() {
+                s.sync().map_err(|e| StorageIOError::write_logs(&e))?;
+            }
+        }
+
+        self.last_log_id = entries.last().map(|ent| *ent.get_log_id());
+
+        Ok(())
+    }
+
+    /// Delete entries since `index`, inclusive.
+    pub fn truncate(&mut self, index: u64) -> Result<(), StorageError<C::NodeId>> {
+        // Remove from the end, so that what is left after a crash is always a prefix of the log.
+        while let Some(mut entry) = self.segments.last_entry() {
+            if entry.get().first_index() >= index {
+                entry.remove().remove().map_err(|e| StorageIOError::write_logs(&e))?;
+                continue;
+            }
+
+            let segment = entry.get_mut();
+            if segment.contains(index) {
+                segment.truncate(index).map_err(|e| StorageIOError::write_logs(&e))?;
+            }
+            break;
+        }
+
+        self.sync_dir().map_err(|e| StorageIOError::write_logs(&e))?;
+
+        self.last_log_id = if index == 0 {
+            None
+        } else {
+            let prev = self.read_entries(index - 1, index)?;
+            prev.last().map(|ent| *ent.get_log_id())
+        };
+
+        Ok(())
+    }
+
+    /// Purge entries upto `log_id`, inclusive.
+    pub fn purge(&mut self, log_id: LogId<C::NodeId>) -> Result<(), StorageError<C::NodeId>> {
+        // Persist the purged log id first: it hides the purged entries even if removing the
+        // segments does not complete.
+        write_file(&self.dir, PURGED_FILE, &log_id, self.options.sync).map_err(|e| StorageIOError::write_logs(&e))?;
+        self.last_purged_log_id = Some(log_id);
+
+        if self.last_log_id.map(|x| x.index <= log_id.index).unwrap_or(false) {
+            self.last_log_id = None;
+        }
+
+        self.remove_purged_segments()
+    }
+
+    /// Remove segments whose records are all purged.
+    fn remove_purged_segments(&mut self) -> Result<(), StorageError<C::NodeId>> {
+        let first_unpurged = match self.last_purged_log_id {
+            None => return Ok(()),
+            Some(x) => x.index + 1,
+        };
+
+        let mut removed = false;
+
+        while let Some(entry) = self.segments.first_entry() {
+            if entry.get().next_index() > first_unpurged {
+                break;
+            }
+            entry.remove().remove().map_err(|e| StorageIOError::write_logs(&e))?;
+            removed = true;
+        }
+
+        if removed {
+            self.sync_dir().map_err(|e| StorageIOError::write_logs(&e))?;
+        }
+
+        Ok(())
+    }
+
+    /// Read entries in `[start, end)` from the segments.
+    fn read_entries(&mut self, start: u64, end: u64) -> Result<Vec<C::Entry>, StorageError<C::NodeId>> {
+        let mut res = vec![];
+
+        if start >= end {
+            return Ok(res);
+        }
+
+        // The segment containing `start` may begin before it.
+        let first_key = self.segments.range(..=start).next_back().map(|(k, _)| *k).unwrap_or(start);
+
+        for segment in self.segments.range_mut(first_key..end).map(|(_, s)| s) {
+            let s = start.max(segment.first_index());
+            let e = end.min(segment.next_index());
+            if s >= e {
+                continue;
+            }
+
+            let payloads = segment.read(s, e).map_err(|e| StorageIOError::read_logs(&e))?;
+            for payload in payloads {
+                let ent: C::Entry = bincode::deserialize(&payload).map_err(|e| StorageIOError::read_logs(&e))?;
+                res.push(ent);
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Append the pending records in `buf` to the last segment.
+    fn flush_batch(
+        &mut self,
+        buf: &mut Vec<u8>,
+        offsets: &mut Vec<u64>,
+        batch_first: u64,
+    ) -> Result<(), StorageError<C::NodeId>> {
+        if buf.is_empty() {
+            return Ok(());
+        }
+
+        let segment = self.segments.values_mut().next_back().unwrap();
+        debug_assert_eq!(batch_first, segment.next_index());
+
+        segment.append(buf, offsets).map_err(|e| StorageIOError::write_logs(&e))?;
+
+        buf.clear();
+        offsets.clear();
+
+        Ok(())
+    }
+
+    /// Sync the last segment before a new one is started after it.
+    fn seal_last_segment(&mut self) -> Result<(), StorageError<C::NodeId>> {
+        if !self.options.sync {
+            return Ok(());
+        }
+
+        if let Some(s) = self.segments.values().next_back() {
+            s.sync().map_err(|e| StorageIOError::write_logs(&e))?;
+        }
+        Ok(())
+    }
+
+    /// Sync the directory so that creating, renaming or removing files in it is durable.
+    fn sync_dir(&self) -> io::Result<()> {
+        if !self.options.sync {
+            return Ok(());
+        }
+        File::open(&self.dir)?.sync_all()
+    }
+}
+
+/// Read a value stored in a small file, or `None` if the file does not exist.
+pub(crate) fn read_file<T: DeserializeOwned>(dir: &Path, name: &str) -> io::Result<Option<T>> {
+    let buf = match fs::read(dir.join(name)) {
+        Ok(buf) => buf,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    let v = bincode::deserialize(&buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(Some(v))
+}
+
+/// Replace a small file atomically: write a temp file, sync it, then rename it over the old one.
+pub(crate) fn write_file<T: Serialize>(dir: &Path, name: &str, v: &T, sync: bool) -> io::Result<()> {
+    let buf = bincode::serialize(v).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    let tmp = dir.join(format!("{}.tmp", name));
+    fs::write(&tmp, buf)?;
+
+    if sync {
+        File::open(&tmp)?.sync_all()?;
+    }
+
+    fs::rename(&tmp, dir.join(name))?;
+
+    if sync {
+        File::open(dir)?.sync_all()?;
+    }
+
+    Ok(())
+}
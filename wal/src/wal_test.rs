@@ -0,0 +1,201 @@
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use openraft::CommittedLeaderId;
+use openraft::Entry;
+use openraft::EntryPayload;
+use openraft::LogId;
+use openraft::StorageError;
+use openraft::Vote;
+use tempfile::TempDir;
+
+use crate::segment::Segment;
+use crate::ExampleNodeId;
+use crate::ExampleTypeConfig;
+use crate::Wal;
+use crate::WalOptions;
+
+fn log_id(term: u64, index: u64) -> LogId<ExampleNodeId> {
+    LogId::new(CommittedLeaderId::new(term, 0), index)
+}
+
+fn blank(term: u64, index: u64) -> Entry<ExampleTypeConfig> {
+    Entry {
+        log_id: log_id(term, index),
+        payload: EntryPayload::Blank,
+    }
+}
+
+fn blanks(term: u64, indexes: impl IntoIterator<Item = u64>) -> Vec<Entry<ExampleTypeConfig>> {
+    indexes.into_iter().map(|i| blank(term, i)).collect()
+}
+
+fn small_segments() -> WalOptions {
+    WalOptions {
+        segment_size: 100,
+        ..Default::default()
+    }
+}
+
+fn open(dir: &Path) -> Result<Wal<ExampleTypeConfig>, StorageError<ExampleNodeId>> {
+    Wal::open(dir, small_segments())
+}
+
+fn read_indexes(wal: &mut Wal<ExampleTypeConfig>) -> Result<Vec<u64>, StorageError<ExampleNodeId>> {
+    let ents = wal.try_get_log_entries(..)?;
+    Ok(ents.iter().map(|e| e.log_id.index).collect())
+}
+
+/// Paths of the segment files, in log order.
+fn segment_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.file_name().and_then(|n| n.to_str()).and_then(Segment::parse_file_name).is_some())
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
+
+#[test]
+fn test_reopen() -> anyhow::Result<()> {
+    let td = TempDir::new()?;
+
+    {
+        let mut wal = open(td.path())?;
+        wal.save_vote(&Vote::new(2, 1))?;
+        wal.append(&blanks(1, 0..10))?;
+        wal.append(&blanks(2, 10..12))?;
+    }
+
+    let mut wal = open(td.path())?;
+    assert_eq!(Some(Vote::new(2, 1)), wal.read_vote());
+    assert_eq!(Some(log_id(2, 11)), wal.get_log_state().last_log_id);
+    assert_eq!((0..12).collect::<Vec<_>>(), read_indexes(&mut wal)?);
+    assert!(segment_files(td.path()).len() > 1, "entries span several segments");
+
+    Ok(())
+}
+
+#[test]
+fn test_truncate_across_segments() -> anyhow::Result<()> {
+    let td = TempDir::new()?;
+
+    {
+        let mut wal = open(td.path())?;
+        wal.append(&blanks(1, 0..10))?;
+        let n = segment_files(td.path()).len();
+
+        wal.truncate(4)?;
+        assert_eq!(Some(log_id(1, 3)), wal.get_log_state().last_log_id);
+        assert_eq!(vec![0, 1, 2, 3], read_indexes(&mut wal)?);
+        assert!(segment_files(td.path()).len() < n, "segments after index 4 are removed");
+
+        wal.append(&blanks(2, 4..6))?;
+
+        // Appending over existing entries replaces them.
+        wal.append(&blanks(3, 5..7))?;
+    }
+
+    let mut wal = open(td.path())?;
+    assert_eq!(Some(log_id(3, 6)), wal.get_log_state().last_log_id);
+    assert_eq!(
+        vec![log_id(1, 3), log_id(2, 4), log_id(3, 5), log_id(3, 6)],
+        wal.try_get_log_entries(3..)?.iter().map(|e| e.log_id).collect::<Vec<_>>()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_purge_removes_whole_segments() -> anyhow::Result<()> {
+    let td = TempDir::new()?;
+
+    {
+        let mut wal = open(td.path())?;
+        wal.append(&blanks(1, 0..10))?;
+        let n = segment_files(td.path()).len();
+
+        wal.purge(log_id(1, 4))?;
+        assert!(segment_files(td.path()).len() < n, "fully purged segments are removed");
+        assert_eq!((5..10).collect::<Vec<_>>(), read_indexes(&mut wal)?);
+
+        // Purge all, the next append starts a new segment.
+        wal.purge(log_id(1, 9))?;
+        assert!(segment_files(td.path()).is_empty());
+        assert_eq!(Some(log_id(1, 9)), wal.get_log_state().last_log_id);
+
+        wal.append(&blanks(1, 10..12))?;
+    }
+
+    let mut wal = open(td.path())?;
+    let st = wal.get_log_state();
+    assert_eq!(Some(log_id(1, 9)), st.last_purged_log_id);
+    assert_eq!(Some(log_id(1, 11)), st.last_log_id);
+    assert_eq!(vec![10, 11], read_indexes(&mut wal)?);
+
+    Ok(())
+}
+
+#[test]
+fn test_recover_discards_torn_tail() -> anyhow::Result<()> {
+    let td = TempDir::new()?;
+
+    {
+        let mut wal = open(td.path())?;
+        wal.append(&blanks(1, 0..5))?;
+    }
+
+    tracing::info!("--- a partially written record");
+    {
+        let last = segment_files(td.path()).pop().unwrap();
+        let mut f = OpenOptions::new().append(true).open(&last)?;
+        f.write_all(&[10, 0, 0, 0, 1, 2, 3])?;
+    }
+
+    {
+        let mut wal = open(td.path())?;
+        assert_eq!(Some(log_id(1, 4)), wal.get_log_state().last_log_id);
+        assert_eq!((0..5).collect::<Vec<_>>(), read_indexes(&mut wal)?);
+
+        wal.append(&blanks(1, 5..6))?;
+    }
+
+    tracing::info!("--- a complete record with a bad checksum");
+    {
+        let last = segment_files(td.path()).pop().unwrap();
+        let mut buf = fs::read(&last)?;
+        let n = buf.len();
+        buf[n - 1] ^= 0xff;
+        fs::write(&last, buf)?;
+    }
+
+    let mut wal = open(td.path())?;
+    assert_eq!(Some(log_id(1, 4)), wal.get_log_state().last_log_id);
+    assert_eq!((0..5).collect::<Vec<_>>(), read_indexes(&mut wal)?);
+
+    Ok(())
+}
+
+#[test]
+fn test_recover_rejects_corrupted_non_tail_segment() -> anyhow::Result<()> {
+    let td = TempDir::new()?;
+
+    {
+        let mut wal = open(td.path())?;
+        wal.append(&blanks(1, 0..10))?;
+    }
+
+    let first = segment_files(td.path()).remove(0);
+    let mut buf = fs::read(&first)?;
+    let n = buf.len();
+    buf[n - 1] ^= 0xff;
+    fs::write(&first, buf)?;
+
+    assert!(open(td.path()).is_err());
+
+    Ok(())
+}